@@ -0,0 +1,211 @@
+/*
+ * Benchmarks of the core operations of the engine : FEN parsing, board accesses, move
+ * generation, make/unmake, perft, Zobrist hashing and static evaluation. They are run with
+ * "cargo bench", and compared by Criterion with the results of the previous run, so they
+ * can be used to measure the impact of low-level changes.
+ */
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use krabnik::internal::*;
+use krabnik::*;
+
+/***********
+ * POSITIONS
+ ***********/
+
+/// Middlegame positions, with many pieces and tactical moves
+const MIDDLEGAME_FENS: [(&str, &str); 3] = [
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    ),
+    (
+        "italian",
+        "r1bq1rk1/ppp1nppp/4n3/3p3Q/3P4/1BP1B3/PP1N2PP/R4RK1 w - - 1 16",
+    ),
+    (
+        "sicilian",
+        "r1b1k2r/2qnbppp/p2ppn2/1p4B1/3NPPP1/2N2Q2/PPP4P/2KR1B1R w kq - 0 11",
+    ),
+];
+
+fn get_position(fen: &str) -> Position {
+    Position::from_fen(fen).expect("Benchmark positions should be valid")
+}
+
+/*************
+ * BENCHMARKS
+ *************/
+
+fn bench_fen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fen");
+    for (name, fen) in [("startpos", STARTING_FEN), MIDDLEGAME_FENS[0]] {
+        group.bench_with_input(BenchmarkId::new("from_fen", name), fen, |b, fen| {
+            b.iter(|| Position::from_fen(black_box(fen)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_board_access(c: &mut Criterion) {
+    let position = get_position(MIDDLEGAME_FENS[0].1);
+    let coords: Vec<Coord> = (0..64).map(|i| Coord::new(i % 8, i / 8)).collect();
+
+    let mut group = c.benchmark_group("board_access");
+
+    // Reading all the squares of each board type
+    let bitboard = position.get_bitboard().clone();
+    group.bench_function("bitboard_get_square", |b| {
+        b.iter(|| {
+            for coord in &coords {
+                black_box(bitboard.get_square(*coord));
+            }
+        })
+    });
+    let mailbox = position.get_mailbox().clone();
+    group.bench_function("mailbox_get_square", |b| {
+        b.iter(|| {
+            for coord in &coords {
+                black_box(mailbox.get_square(*coord));
+            }
+        })
+    });
+
+    // Moving a piece back and forth (e1-f1), on each board type and on the position
+    let (e1, f1) = (Coord::new(4, 0), Coord::new(5, 0));
+    let mut bitboard = position.get_bitboard().clone();
+    bitboard.set_square(PieceCode::ES, f1);
+    group.bench_function("bitboard_set_square", |b| {
+        b.iter(|| {
+            bitboard.set_square(PieceCode::ES, black_box(e1));
+            bitboard.set_square(PieceCode::WK, black_box(f1));
+            bitboard.set_square(PieceCode::ES, black_box(f1));
+            bitboard.set_square(PieceCode::WK, black_box(e1));
+        })
+    });
+    let mut mailbox = position.get_mailbox().clone();
+    group.bench_function("mailbox_set_square", |b| {
+        b.iter(|| {
+            mailbox.set_square(PieceCode::ES, black_box(e1));
+            mailbox.set_square(PieceCode::WK, black_box(f1));
+            mailbox.set_square(PieceCode::ES, black_box(f1));
+            mailbox.set_square(PieceCode::WK, black_box(e1));
+        })
+    });
+    let mut position = position.clone();
+    group.bench_function("position_set_square", |b| {
+        b.iter(|| {
+            position.set_square(PieceCode::ES, black_box(e1));
+            position.set_square(PieceCode::WK, black_box(f1));
+            position.set_square(PieceCode::ES, black_box(f1));
+            position.set_square(PieceCode::WK, black_box(e1));
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_move_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("move_generation");
+    for (name, fen) in MIDDLEGAME_FENS {
+        let mut position = get_position(fen);
+        group.bench_function(BenchmarkId::new("legal", name), |b| {
+            b.iter(|| gen_legal_moves(black_box(&mut position)))
+        });
+        group.bench_function(BenchmarkId::new("pseudolegal", name), |b| {
+            b.iter(|| gen_pseudolegal_moves(black_box(&position)))
+        });
+        group.bench_function(BenchmarkId::new("captures", name), |b| {
+            b.iter(|| gen_captures(black_box(&mut position)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_make_unmake(c: &mut Criterion) {
+    let mut group = c.benchmark_group("make_unmake");
+    for (name, fen) in MIDDLEGAME_FENS {
+        let mut position = get_position(fen);
+        let moves = gen_legal_moves(&mut position);
+        // Each iteration makes and unmakes all the legal moves of the position
+        group.bench_function(BenchmarkId::new("all_moves", name), |b| {
+            b.iter(|| {
+                for mov in moves.iter().copied() {
+                    position.make_move(black_box(mov));
+                    position.unmake_move(mov);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_perft(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perft");
+    group.sample_size(10);
+    let cases = [
+        ("startpos", STARTING_FEN, 4),
+        ("startpos", STARTING_FEN, 5),
+        ("kiwipete", MIDDLEGAME_FENS[0].1, 4),
+    ];
+    for (name, fen, depth) in cases {
+        let mut position = get_position(fen);
+        let id = BenchmarkId::new(name, depth);
+        group.bench_function(id, |b| b.iter(|| perft(&mut position, black_box(depth))));
+    }
+    group.finish();
+}
+
+fn bench_zobrist(c: &mut Criterion) {
+    let mut position = get_position(MIDDLEGAME_FENS[0].1);
+
+    let mut group = c.benchmark_group("zobrist");
+    group.bench_function("full_hash", |b| {
+        b.iter(|| black_box(&position).compute_zobrist_hash())
+    });
+
+    // Incremental update of the hash, maintained by making and unmaking a quiet move
+    // (Ne5-g4)
+    let mov = parse_uci_move(&mut position, "e5g4").unwrap();
+    group.bench_function("move_update", |b| {
+        b.iter(|| {
+            position.make_move(black_box(mov));
+            let hash = black_box(position.get_zobrist_hash());
+            position.unmake_move(mov);
+            hash
+        })
+    });
+    group.finish();
+}
+
+fn bench_evaluation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evaluation");
+    for (name, fen) in MIDDLEGAME_FENS {
+        let position = get_position(fen);
+        group.bench_function(BenchmarkId::new("handcrafted", name), |b| {
+            b.iter(|| evaluate_handcrafted(black_box(&position)))
+        });
+
+        let mut evaluator = Evaluator::new();
+        evaluator.refresh(&position);
+        group.bench_function(BenchmarkId::new("evaluator", name), |b| {
+            b.iter(|| evaluator.evaluate(black_box(&position)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_fen,
+    bench_board_access,
+    bench_move_generation,
+    bench_make_unmake,
+    bench_perft,
+    bench_zobrist,
+    bench_evaluation
+);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+/*
+ * Fuzz target for the FEN parser. Any input must either be rejected with an error, or give
+ * a valid position, whose FEN string parses back to the same position. The moves of the
+ * parsed positions are also played and unplayed, as the validator should only let through
+ * positions the move generator can handle.
+ */
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use krabnik::*;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(fen) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(mut position) = Position::from_fen(fen) else {
+        return;
+    };
+
+    // Serialize -> parse round trip
+    let serialized = position.to_fen();
+    let reparsed = Position::from_fen(&serialized)
+        .unwrap_or_else(|error| panic!("{} doesn't parse back: {}", serialized, error));
+    assert_eq!(reparsed.to_fen(), serialized);
+    assert_eq!(reparsed, position, "{}", serialized);
+    assert_eq!(reparsed.get_zobrist_hash(), position.get_zobrist_hash());
+
+    for mov in gen_legal_moves(&mut position) {
+        let uci = get_move_uci(&position, mov);
+        position.make_move(mov);
+        assert_eq!(position.validate(), Ok(()), "{} after {}", fen, uci);
+        position.unmake_move(mov);
+    }
+    assert_eq!(position, reparsed);
+});
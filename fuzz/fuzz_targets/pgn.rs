@@ -0,0 +1,34 @@
+/*
+ * Fuzz target for the PGN reader. Any input must either be rejected with an error, or give
+ * games that the writer exports to a PGN string which reads back to the same games. The
+ * export may normalize the input (tag order, comment spacing, move numbers, SAN), so the
+ * games read back must have the same main line, result and starting position, and export
+ * to the exact same string.
+ */
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use krabnik::*;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(pgn) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(games) = read_pgn(pgn) else {
+        return;
+    };
+
+    // Serialize -> parse round trip
+    let serialized = write_pgn(&games);
+    let reparsed = read_pgn(&serialized)
+        .unwrap_or_else(|error| panic!("{:?} doesn't read back: {}", serialized, error));
+    assert_eq!(reparsed.len(), games.len());
+    for (game, reparsed_game) in games.iter().zip(&reparsed) {
+        assert_eq!(reparsed_game.get_main_line(), game.get_main_line());
+        assert_eq!(reparsed_game.result, game.result);
+        assert_eq!(reparsed_game.starting_position, game.starting_position);
+    }
+    assert_eq!(write_pgn(&reparsed), serialized);
+});
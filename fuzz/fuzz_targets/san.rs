@@ -0,0 +1,44 @@
+/*
+ * Fuzz target for the SAN parser. The first line of the input is a FEN string (the normal
+ * starting position is used if it is invalid), and the following words are moves in SAN.
+ * Parsing must never panic, and the parsed moves, as well as all the legal moves of the
+ * first and last positions, must go through SAN and UCI notations and back unchanged.
+ */
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use krabnik::*;
+
+/// Check that a legal move round trips through SAN and UCI notations
+fn check_round_trip(position: &mut Position, mov: Move) {
+    let san = get_move_san(position, mov);
+    assert_eq!(parse_san_move(position, &san), Some(mov), "SAN {}", san);
+    let uci = get_move_uci(position, mov);
+    assert_eq!(parse_uci_move(position, &uci), Some(mov), "UCI {}", uci);
+}
+
+fn check_all_round_trips(position: &mut Position) {
+    for mov in gen_legal_moves(position) {
+        check_round_trip(position, mov);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let (fen, moves) = input.split_once('\n').unwrap_or((input, ""));
+    let mut position = Position::from_fen(fen).unwrap_or_default();
+
+    check_all_round_trips(&mut position);
+    for san in moves.split_whitespace() {
+        let Some(mov) = parse_san_move(&mut position, san) else {
+            continue;
+        };
+        check_round_trip(&mut position, mov);
+        position.make_move(mov);
+    }
+    check_all_round_trips(&mut position);
+});
@@ -0,0 +1,41 @@
+/*
+ * Fuzz target for the UCI command parser. Each line of the input is sent to a UCI session,
+ * which must never panic. "go" commands only have their arguments parsed, as starting
+ * searches would make the target slow and non-deterministic, and the options which
+ * allocate memory or read files are skipped.
+ */
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use krabnik::internal::*;
+use krabnik::*;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let mut session = UciSession::new(Vec::new());
+
+    for line in input.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.split_first() {
+            Some((&"go", args)) => {
+                parse_go_arguments(args, Player::White);
+                continue;
+            }
+            Some((&"setoption", _))
+                if line.contains(HASH_OPTION)
+                    || line.contains(EVAL_FILE_OPTION)
+                    || line.contains(SYZYGY_PATH_OPTION) =>
+            {
+                continue;
+            }
+            _ => {}
+        }
+        if !session.handle_command(line) {
+            break;
+        }
+    }
+});
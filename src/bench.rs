@@ -0,0 +1,383 @@
+/*
+ * The bench module implements "krabnik bench [depth] [threads] [hash]", which searches a
+ * fixed list of positions to a fixed depth and reports the total number of nodes and the
+ * search speed.
+ *
+ * With a single thread, the node count only depends on the search and evaluation code
+ * (the transposition table is cleared before each position), so it works as a signature of
+ * the engine : a change that shouldn't affect the search must leave it unchanged. The nodes
+ * per second are used to measure speed improvements.
+ *
+ * "krabnik bench movegen [depth]" measures the board representation and the move generator
+ * alone : the perft speed, and the number of moves made and unmade per second.
+ */
+
+#![allow(dead_code)]
+
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::search::*;
+
+/***********
+* POSITIONS
+************/
+
+/// Positions searched by the bench : openings, middlegames and endgames, including
+/// positions with checks, promotions, en passant captures, mates and a stalemate
+pub const BENCH_FENS: [&str; 50] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "rq3rk1/ppp2ppp/1bnpb3/3N2B1/3NP3/7P/PPPQ1PP1/2KR3R w - - 7 14",
+    "r1bq1r1k/1pp1n1pp/1p1p4/4p2Q/4Pp2/1BNP4/PPP2PPP/3R1RK1 w - - 2 14",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "r1bbk1nr/pp3p1p/2n5/1N4p1/2Np1B2/8/PPP2PPP/2KR1B1R w kq - 0 13",
+    "r1bq1rk1/ppp1nppp/4n3/3p3Q/3P4/1BP1B3/PP1N2PP/R4RK1 w - - 1 16",
+    "4r1k1/r1q2ppp/ppp2n2/4P3/5Rb1/1N1BQ3/PPP3PP/R5K1 w - - 1 17",
+    "2rqkb1r/ppp2p2/2npb1p1/1N1Nn2p/2P1PP2/8/PP2B1PP/R1BQK2R b KQ - 0 11",
+    "r1bq1r1k/b1p1npp1/p2p3p/1p6/3PP3/1B2NN2/PP3PPP/R2Q1RK1 w - - 1 16",
+    "3r1rk1/p5pp/bpp1pp2/8/q1PP1P2/b3P3/P2NQRPP/1R2B1K1 b - - 6 22",
+    "r1q2rk1/2p1bppp/2Pp4/p6b/Q1PNp3/4B3/PP1R1PPP/2K4R w - - 2 18",
+    "4k2r/1pb2ppp/1p2p3/1R1p4/3P4/2r1PN2/P4PPP/1R4K1 b - - 3 22",
+    "3q2k1/pb3p1p/4pbp1/2r5/PpN2N2/1P2P2P/5PP1/Q2R2K1 b - - 4 26",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "3b4/5kp1/1p1p1p1p/pP1PpP1P/P1P1P3/3KN3/8/8 w - - 0 1",
+    "2K5/p7/7P/5pR1/8/5k2/r7/8 w - - 0 1",
+    "8/6pk/1p6/8/PP3p1p/5P2/4KP1q/3Q4 w - - 0 1",
+    "7k/3p2pp/4q3/8/4Q3/5Kp1/P6b/8 w - - 0 1",
+    "8/2p5/8/2kPKp1p/2p4P/2P5/3P4/8 w - - 0 1",
+    "8/1p3pp1/7p/5P1P/2k3P1/8/2K2P2/8 w - - 0 1",
+    "8/pp2r1k1/2p1p3/3pP2p/1P1P1P1P/P5KR/8/8 w - - 0 1",
+    "8/3p4/p1bk3p/Pp6/1Kp1PpPp/2P2P1P/2P5/5B2 b - - 0 1",
+    "5k2/7R/4P2p/5K2/p1r2P1p/8/8/8 b - - 0 1",
+    "6k1/6p1/P6p/r1N5/5p2/7P/1b3PP1/4R1K1 w - - 0 1",
+    "1r3k2/4q3/2Pp3b/3Bp3/2Q2p2/1p1P2P1/1P2KP2/3N4 w - - 0 1",
+    "6k1/4pp1p/3p2p1/P1pPb3/R7/1r2P1PP/3B1P2/6K1 w - - 0 1",
+    "8/3p3B/5p2/5P2/p7/PP5b/k7/6K1 w - - 0 1",
+    "5rk1/q6p/2p3bR/1pPp1rP1/1P1Pp3/P3B1Q1/1K3P2/R7 w - - 93 90",
+    "4rrk1/1p1nq3/p7/2p1P1pp/3P2bp/3Q1Bn1/PPPB4/1K2R1NR w - - 40 21",
+    "r3k2r/3nnpbp/q2pp1p1/p7/Pp1PPPP1/4BNN1/1P5P/R2Q1RK1 w kq - 0 16",
+    "3Qb1k1/1r2ppb1/pN1n2q1/Pp1Pp1Pr/4P2p/4BP2/4B1R1/1R5K b - - 11 40",
+    "4k3/3q1r2/1N2r1b1/3ppN2/2nPP3/1B1R2n1/2R1Q3/3K4 w - - 5 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+    "8/8/8/5N2/8/p7/8/2NK3k w - - 0 1",
+    "8/3k4/8/8/8/4B3/4KB2/2B5 w - - 0 1",
+    "8/8/1P6/5pr1/8/4R3/7k/2K5 w - - 0 1",
+    "8/2p4P/8/kr6/6R1/8/8/1K6 w - - 0 1",
+    "8/8/3P3k/8/1p6/8/1P6/1K3n2 b - - 0 1",
+    "8/R7/2q5/8/6k1/8/1P5p/K6R w - - 0 124",
+    "6k1/3b3r/1p1p4/p1n2p2/1PPNpP1q/P3Q1p1/1R1RB1P1/5K2 b - - 0 1",
+    "r2r1n2/pp2bk2/2p1p2p/3q4/3PN1QP/2P3R1/P4PP1/5RK1 w - - 0 1",
+    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+    "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R w KQkq - 1 5",
+    "rnbqkb1r/ppp1pppp/5n2/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R w KQkq - 2 3",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+    "8/8/8/8/8/6k1/6p1/6K1 w - - 0 1",
+];
+
+/*******
+* BENCH
+********/
+
+/// Default search depth of the bench
+pub const DEFAULT_BENCH_DEPTH: u8 = 5;
+
+/// Errors that can occur while parsing the arguments of the bench command
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum BenchError {
+    InvalidArgument(String),
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BenchError::InvalidArgument(arg) => write!(f, "invalid argument: {}", arg),
+        }
+    }
+}
+
+impl std::error::Error for BenchError {}
+
+/// Settings of the bench command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BenchOptions {
+    pub depth: u8,
+    pub threads: usize,
+    /// Size of the transposition table, in MiB
+    pub hash: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> BenchOptions {
+        BenchOptions {
+            depth: DEFAULT_BENCH_DEPTH,
+            threads: 1,
+            hash: DEFAULT_HASH_SIZE,
+        }
+    }
+}
+
+impl BenchOptions {
+    /// Parse the positional arguments of the bench command : [depth] [threads] [hash]
+    pub fn from_args(args: &[String]) -> Result<BenchOptions, BenchError> {
+        let mut options = BenchOptions::default();
+        let invalid = |arg: &String| BenchError::InvalidArgument(arg.clone());
+
+        if args.len() > 3 {
+            return Err(invalid(&args[3]));
+        }
+        if let Some(arg) = args.first() {
+            options.depth = arg
+                .parse()
+                .ok()
+                .filter(|depth| (1..=MAX_DEPTH).contains(depth))
+                .ok_or_else(|| invalid(arg))?;
+        }
+        if let Some(arg) = args.get(1) {
+            options.threads = arg
+                .parse()
+                .ok()
+                .filter(|threads| (1..=MAX_THREADS).contains(threads))
+                .ok_or_else(|| invalid(arg))?;
+        }
+        if let Some(arg) = args.get(2) {
+            options.hash = arg
+                .parse()
+                .ok()
+                .filter(|hash| (1..=MAX_HASH_SIZE).contains(hash))
+                .ok_or_else(|| invalid(arg))?;
+        }
+        Ok(options)
+    }
+}
+
+/// Totals of a bench run
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BenchReport {
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchReport {
+    pub fn get_nps(&self) -> u64 {
+        (self.nodes as u128 * 1000 / self.elapsed.as_millis().max(1)) as u64
+    }
+}
+
+/// Search each of the positions to the depth of the options. The callback receives the
+/// index of each position and the result of its search.
+pub fn run_bench<F: FnMut(usize, &SearchResult)>(
+    options: &BenchOptions,
+    fens: &[&str],
+    mut callback: F,
+) -> BenchReport {
+    let tt = TranspositionTable::new(options.hash);
+    let stop = AtomicBool::new(false);
+    let limits = SearchLimits {
+        depth: Some(options.depth),
+        ..SearchLimits::default()
+    };
+    let mut evaluator = Evaluator::new();
+
+    let mut nodes = 0;
+    let start = Instant::now();
+    for (index, fen) in fens.iter().enumerate() {
+        let mut position = Position::from_fen(fen).expect("Bench positions should be valid");
+        tt.clear();
+        let context = SearchContext {
+            tt: &tt,
+            stop: &stop,
+            threads: options.threads,
+            game_hashes: &[],
+            tablebase: None,
+        };
+        let result = search_with_callback(&mut position, &mut evaluator, &limits, &context, |_| {});
+        nodes += result.nodes;
+        callback(index, &result);
+    }
+
+    BenchReport {
+        nodes,
+        elapsed: start.elapsed(),
+    }
+}
+
+pub fn run_bench_command(args: &[String]) -> Result<(), BenchError> {
+    if args.first().is_some_and(|arg| arg == "movegen") {
+        return run_movegen_bench_command(&args[1..]);
+    }
+
+    let options = BenchOptions::from_args(args)?;
+    get_kpk_bitbase();
+
+    let report = run_bench(&options, &BENCH_FENS, |index, result| {
+        println!(
+            "Position {}/{}: {} nodes, score {}",
+            index + 1,
+            BENCH_FENS.len(),
+            result.nodes,
+            result.score
+        );
+    });
+
+    println!("===========================");
+    println!("Total time (ms) : {}", report.elapsed.as_millis());
+    println!("Nodes searched  : {}", report.nodes);
+    println!("Nodes/second    : {}", report.get_nps());
+    Ok(())
+}
+
+/*********************
+* MOVE GENERATION BENCH
+**********************/
+
+/// Default perft depth of the move generation bench
+pub const DEFAULT_MOVEGEN_BENCH_DEPTH: u8 = 3;
+
+/// Number of times each legal move of a position is made and unmade
+pub const MAKE_UNMAKE_ITERATIONS: u32 = 10000;
+
+/// Totals of a move generation bench run
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MovegenBenchReport {
+    /// Perft nodes, and time spent counting them
+    pub perft: BenchReport,
+    /// Moves made and unmade, and time spent making and unmaking them
+    pub make_unmake: BenchReport,
+}
+
+/// Run a perft of the given depth on each position, then make and unmake each of their
+/// legal moves the given number of times
+pub fn run_movegen_bench(depth: u8, iterations: u32, fens: &[&str]) -> MovegenBenchReport {
+    let mut positions: Vec<Position> = fens
+        .iter()
+        .map(|fen| Position::from_fen(fen).expect("Bench positions should be valid"))
+        .collect();
+
+    let mut nodes = 0;
+    let start = Instant::now();
+    for position in positions.iter_mut() {
+        nodes += perft(position, depth);
+    }
+    let perft = BenchReport {
+        nodes,
+        elapsed: start.elapsed(),
+    };
+
+    let mut moves_count = 0;
+    let start = Instant::now();
+    for position in positions.iter_mut() {
+        let moves = gen_legal_moves(position);
+        for _ in 0..iterations {
+            for mov in moves.iter().copied() {
+                position.make_move(mov);
+                position.unmake_move(mov);
+            }
+        }
+        moves_count += moves.len() as u64 * iterations as u64;
+    }
+    let make_unmake = BenchReport {
+        nodes: moves_count,
+        elapsed: start.elapsed(),
+    };
+
+    MovegenBenchReport { perft, make_unmake }
+}
+
+fn run_movegen_bench_command(args: &[String]) -> Result<(), BenchError> {
+    if args.len() > 1 {
+        return Err(BenchError::InvalidArgument(args[1].clone()));
+    }
+    let depth = match args.first() {
+        Some(arg) => arg
+            .parse()
+            .ok()
+            .filter(|depth| (1..=MAX_DEPTH).contains(depth))
+            .ok_or_else(|| BenchError::InvalidArgument(arg.clone()))?,
+        None => DEFAULT_MOVEGEN_BENCH_DEPTH,
+    };
+
+    let report = run_movegen_bench(depth, MAKE_UNMAKE_ITERATIONS, &BENCH_FENS);
+
+    println!("===========================");
+    println!(
+        "Perft time (ms)      : {}",
+        report.perft.elapsed.as_millis()
+    );
+    println!("Perft nodes          : {}", report.perft.nodes);
+    println!("Perft nodes/second   : {}", report.perft.get_nps());
+    println!(
+        "Make/unmake time (ms): {}",
+        report.make_unmake.elapsed.as_millis()
+    );
+    println!("Moves made/unmade    : {}", report.make_unmake.nodes);
+    println!("Make/unmake/second   : {}", report.make_unmake.get_nps());
+    Ok(())
+}
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_bench() {
+    // Arguments
+    let args = |args: &[&str]| {
+        BenchOptions::from_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    };
+    assert!(
+        args(&[]) == Ok(BenchOptions::default()),
+        "Failed at assert 0"
+    );
+    assert!(
+        args(&["3", "2", "8"])
+            == Ok(BenchOptions {
+                depth: 3,
+                threads: 2,
+                hash: 8
+            }),
+        "Failed at assert 1"
+    );
+    assert!(args(&["0"]).is_err(), "Failed at assert 2");
+    assert!(args(&["4", "x"]).is_err(), "Failed at assert 3");
+    assert!(args(&["4", "1", "16", "1"]).is_err(), "Failed at assert 4");
+
+    // Positions
+    for fen in BENCH_FENS {
+        assert!(Position::from_fen(fen).is_ok(), "Failed at assert 5");
+    }
+
+    // Single-threaded benches are deterministic
+    let options = BenchOptions {
+        depth: 3,
+        threads: 1,
+        hash: 1,
+    };
+    let fens = &BENCH_FENS[..8];
+    let first = run_bench(&options, fens, |_, _| {});
+    let second = run_bench(&options, fens, |_, _| {});
+    assert!(first.nodes > 0, "Failed at assert 6");
+    assert!(first.nodes == second.nodes, "Failed at assert 7");
+
+    // Several threads still find a move
+    let options = BenchOptions {
+        threads: 2,
+        ..options
+    };
+    run_bench(&options, fens, |_, result| {
+        assert!(result.best_move.is_some(), "Failed at assert 8");
+    });
+
+    // Move generation bench
+    let report = run_movegen_bench(1, 10, &BENCH_FENS[..2]);
+    assert!(report.perft.nodes == 20 + 48, "Failed at assert 9");
+    assert!(report.make_unmake.nodes == 680, "Failed at assert 10");
+}
@@ -5,20 +5,36 @@
  * (see the move_generation module for that).
  *
  * The current state of a game is represented by the Position type, which contains redundant
- * piece and square centric representations of the board, as well as some metadata (such
- * as the current turn, castling info, ...).
+ * piece centric (bitboards) and square centric (64 squares mailbox) representations of the
+ * board, as well as some metadata (such as the current turn, castling info, ...). With the
+ * zerox88 feature, a 0x88 board is also kept in sync, and used to check attacks.
  *
  * This module is the core of the engine, and contains definitions for all fundamental
- * datatypes.
+ * datatypes (including the Square and Bitboard types, to manipulate sets of squares).
+ * Positions can also be loaded from and saved to FEN strings, and to EPD records (FEN with
+ * additional operations, used by test suites), hashed with Zobrist keys, and validated
+ * (consistency of both boards and legality of the placement).
  */
 
+pub mod bitboard;
 pub mod datatypes;
+pub mod epd;
+pub mod fen;
 pub mod misc;
+pub mod square;
 pub mod static_board;
+pub mod validation;
+pub mod zobrist;
 
+pub use bitboard::*;
 pub use datatypes::*;
+pub use epd::*;
+pub use fen::*;
 pub use misc::*;
+pub use square::*;
 pub use static_board::*;
+pub use validation::*;
+pub use zobrist::*;
 
 /******
 * TESTS
@@ -57,45 +73,48 @@ fn test_static_board() {
     assert!(bit_e2 == PieceCode::ES, "Failed at assert 3");
     assert!(bit_e4 == PieceCode::WP, "Failed at assert 4");
 
-    // Zerox88Board
+    // Mailbox
 
-    let mut zerox: Zerox88Board = Zerox88Board::new();
-    let zerox_c8: PieceCode = zerox.get_square(Coord::new(2, 7));
-    let mut zerox_e2: PieceCode = zerox.get_square(Coord::new(4, 1));
-    let mut zerox_e4: PieceCode = zerox.get_square(Coord::new(4, 3));
+    let mut mailbox: Mailbox = Mailbox::new();
+    let mailbox_c8: PieceCode = mailbox.get_square(Coord::new(2, 7));
+    let mut mailbox_e2: PieceCode = mailbox.get_square(Coord::new(4, 1));
+    let mut mailbox_e4: PieceCode = mailbox.get_square(Coord::new(4, 3));
 
-    print!("0x88 board :\n{}", zerox.ascii());
-    println!("0x88 c8 : {}", get_unicode_piece(zerox_c8));
-    println!("0x88 e2 : {}", get_unicode_piece(zerox_e2));
-    println!("0x88 e4 : {}\n", get_unicode_piece(zerox_e4));
+    print!("Mailbox board :\n{}", mailbox.ascii());
+    println!("Mailbox c8 : {}", get_unicode_piece(mailbox_c8));
+    println!("Mailbox e2 : {}", get_unicode_piece(mailbox_e2));
+    println!("Mailbox e4 : {}\n", get_unicode_piece(mailbox_e4));
 
-    assert!(zerox_c8 == PieceCode::BB, "Failed at assert 4");
-    assert!(zerox_e2 == PieceCode::WP, "Failed at assert 5");
-    assert!(zerox_e4 == PieceCode::ES, "Failed at assert 6");
+    assert!(mailbox_c8 == PieceCode::BB, "Failed at assert 4");
+    assert!(mailbox_e2 == PieceCode::WP, "Failed at assert 5");
+    assert!(mailbox_e4 == PieceCode::ES, "Failed at assert 6");
 
-    zerox.set_square(PieceCode::ES, Coord::new(4, 1));
-    zerox.set_square(PieceCode::WP, Coord::new(4, 3));
-    zerox_e2 = zerox.get_square(Coord::new(4, 1));
-    zerox_e4 = zerox.get_square(Coord::new(4, 3));
+    mailbox.set_square(PieceCode::ES, Coord::new(4, 1));
+    mailbox.set_square(PieceCode::WP, Coord::new(4, 3));
+    mailbox_e2 = mailbox.get_square(Coord::new(4, 1));
+    mailbox_e4 = mailbox.get_square(Coord::new(4, 3));
 
-    print!("0x88 board after playing e4 :\n{}", zerox.ascii());
-    println!("0x88 e2 after playing e4 : {}", get_unicode_piece(zerox_e2));
+    print!("Mailbox board after playing e4 :\n{}", mailbox.ascii());
     println!(
-        "0x88 e4 after playing e4 : {}\n",
-        get_unicode_piece(zerox_e4)
+        "Mailbox e2 after playing e4 : {}",
+        get_unicode_piece(mailbox_e2)
+    );
+    println!(
+        "Mailbox e4 after playing e4 : {}\n",
+        get_unicode_piece(mailbox_e4)
     );
 
-    assert!(zerox_e2 == PieceCode::ES, "Failed at assert 7");
-    assert!(zerox_e4 == PieceCode::WP, "Failed at assert 8");
+    assert!(mailbox_e2 == PieceCode::ES, "Failed at assert 7");
+    assert!(mailbox_e4 == PieceCode::WP, "Failed at assert 8");
 
     // Test board copies
     bit.set_square(PieceCode::ES, Coord::new(4, 6));
     bit.set_square(PieceCode::BP, Coord::new(4, 4));
-    zerox.apply_bitboard(&bit);
+    mailbox.apply_bitboard(&bit);
 
-    zerox.set_square(PieceCode::ES, Coord::new(4, 0));
-    zerox.set_square(PieceCode::WK, Coord::new(4, 1));
-    bit.apply_0x88_board(&zerox);
+    mailbox.set_square(PieceCode::ES, Coord::new(4, 0));
+    mailbox.set_square(PieceCode::WK, Coord::new(4, 1));
+    bit.apply_mailbox(&mailbox);
     bit_e2 = bit.get_square(Coord::new(4, 1));
 
     print!("Bit board after copies :\n{}", bit.ascii());
@@ -105,4 +124,591 @@ fn test_static_board() {
 
     // Test the get_square_bitboard func
     assert!(get_square_bitboard(Coord::new(4, 1)) == 0b1 << 63 >> 12);
+
+    // Test the derived boards
+    let e2 = get_square_bitboard(Coord::new(4, 1));
+    let e4 = get_square_bitboard(Coord::new(4, 3));
+    assert!(
+        bit.occupancy_board.count_ones() == 32,
+        "Failed at assert 10"
+    );
+    assert!(
+        bit.color_boards[Player::White as usize] & e2 != 0,
+        "Failed at assert 11"
+    );
+    assert!(bit.piece_type_boards[5] & e2 != 0, "Failed at assert 12");
+    bit.set_square(PieceCode::ES, Coord::new(4, 3));
+    bit.set_square(PieceCode::BQ, Coord::new(4, 3));
+    assert!(
+        bit.color_boards[Player::White as usize] & e4 == 0,
+        "Failed at assert 13"
+    );
+    assert!(
+        bit.color_boards[Player::Black as usize] & e4 != 0,
+        "Failed at assert 14"
+    );
+    assert!(
+        bit.piece_type_boards[0] & e4 == 0 && bit.piece_type_boards[4] & e4 != 0,
+        "Failed at assert 15"
+    );
+    bit.set_square(PieceCode::ES, Coord::new(4, 3));
+    assert!(bit.occupancy_board & e4 == 0, "Failed at assert 16");
+    assert!(
+        bit.get_square(Coord::new(4, 3)) == PieceCode::ES,
+        "Failed at assert 17"
+    );
+
+    // Overwriting a piece removes it from all the boards
+    bit.set_square(PieceCode::WP, Coord::new(4, 3));
+    bit.set_square(PieceCode::BN, Coord::new(4, 3));
+    assert!(
+        bit.main_boards[PieceCode::WP as usize - 1] & e4 == 0
+            && bit.get_square(Coord::new(4, 3)) == PieceCode::BN,
+        "Failed at assert 18"
+    );
+    assert!(
+        bit.color_boards[Player::White as usize] & e4 == 0 && bit.piece_type_boards[0] & e4 == 0,
+        "Failed at assert 19"
+    );
+    bit.debug_check_invariants();
+    bit.reset();
+    assert!(
+        bit.occupancy_board == 0xFFFF00000000FFFF,
+        "Failed at assert 20"
+    );
+}
+
+#[test]
+fn test_fen() {
+    // Starting position
+    let position = Position::from_fen(STARTING_FEN).unwrap();
+    let default_position = Position::new();
+    for i in 0..8 {
+        for j in 0..8 {
+            let coord = Coord::new(j, i);
+            assert!(position.get_square(coord) == default_position.get_square(coord));
+        }
+    }
+    assert!(
+        position.piece_centric_board.main_boards
+            == default_position.piece_centric_board.main_boards,
+        "Failed at assert 0"
+    );
+    assert!(position.to_fen() == STARTING_FEN, "Failed at assert 1");
+
+    // Position after 1. e4, with an en passant square
+    let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+    let position = Position::from_fen(fen).unwrap();
+    print!("Position after 1. e4 :\n{}", position.ascii());
+    assert!(
+        position.get_square(Coord::new(4, 3)) == PieceCode::WP,
+        "Failed at assert 2"
+    );
+    assert!(
+        position.get_square(Coord::new(4, 1)) == PieceCode::ES,
+        "Failed at assert 3"
+    );
+    assert!(position.current_turn == Player::Black, "Failed at assert 4");
+    assert!(
+        position.piece_centric_board.en_passant_board == get_square_bitboard(Coord::new(4, 2)),
+        "Failed at assert 5"
+    );
+    assert!(position.to_fen() == fen, "Failed at assert 6");
+
+    // Partial castling rights, clocks, and missing optional fields
+    let fen = "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 12 40";
+    let position = Position::from_fen(fen).unwrap();
+    assert!(
+        position.kingside_castling_rook == [Some(7), None],
+        "Failed at assert 7"
+    );
+    assert!(
+        position.queenside_castling_rook == [None, Some(0)],
+        "Failed at assert 8"
+    );
+    assert!(position.plys_without_capture == 12, "Failed at assert 9");
+    assert!(position.full_move_number == 40, "Failed at assert 10");
+    assert!(position.to_fen() == fen, "Failed at assert 11");
+
+    let position = Position::from_fen("8/8/8/8/8/8/8/K6k b - -").unwrap();
+    assert!(
+        position.to_fen() == "8/8/8/8/8/8/8/K6k b - - 0 1",
+        "Failed at assert 12"
+    );
+
+    // Invalid strings
+    assert!(Position::from_fen("").is_err(), "Failed at assert 13");
+    assert!(
+        Position::from_fen("8/8/8/8/8/8/8 w - - 0 1").is_err(),
+        "Failed at assert 14"
+    );
+    assert!(
+        Position::from_fen("9/8/8/8/8/8/8/8 w - - 0 1").is_err(),
+        "Failed at assert 15"
+    );
+    assert!(
+        Position::from_fen("8/8/8/8/8/8/8/7X w - - 0 1").is_err(),
+        "Failed at assert 16"
+    );
+    assert!(
+        Position::from_fen("8/8/8/8/8/8/8/8 x - - 0 1").is_err(),
+        "Failed at assert 17"
+    );
+    assert!(
+        Position::from_fen("8/8/8/8/8/8/8/8 w KK - 0 1").is_err(),
+        "Failed at assert 18"
+    );
+    assert!(
+        Position::from_fen("8/8/8/8/8/8/8/8 w - e4 0 1").is_err(),
+        "Failed at assert 19"
+    );
+    assert!(
+        Position::from_fen("8/8/8/8/8/8/8/8 w - - 0 0").is_err(),
+        "Failed at assert 20"
+    );
+    assert!(
+        Position::from_fen("8/8/8/8/8/8/8/8 w - - 0 1 x").is_err(),
+        "Failed at assert 21"
+    );
+
+    // Chess960 castling rights (Shredder-FEN and X-FEN)
+    let position =
+        Position::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9")
+            .unwrap();
+    assert!(position.chess960, "Failed at assert 22");
+    assert!(
+        position.kingside_castling_rook == [Some(7), Some(7)],
+        "Failed at assert 23"
+    );
+    assert!(
+        position.queenside_castling_rook == [Some(5), Some(5)],
+        "Failed at assert 24"
+    );
+    assert!(
+        position.to_fen() == "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9",
+        "Failed at assert 25"
+    );
+
+    // The file is needed when the castling rook is not the outermost one
+    let position = Position::from_fen("1r2k3/8/8/8/8/8/8/RR2K3 w Bb - 0 1").unwrap();
+    assert!(position.chess960, "Failed at assert 26");
+    assert!(
+        position.queenside_castling_rook == [Some(1), Some(1)],
+        "Failed at assert 27"
+    );
+    assert!(
+        position.to_fen() == "1r2k3/8/8/8/8/8/8/RR2K3 w Bq - 0 1",
+        "Failed at assert 28"
+    );
+    assert!(
+        Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w C - 0 1").is_err(),
+        "Failed at assert 29"
+    );
+}
+
+#[test]
+fn test_epd() {
+    let epd = r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";
+r1b1k2r/ppp2ppp/2n5/3q4/8/2P5/P4PPP/R1BQKB1R w KQkq - am Qxd5 Bc4; c0 "quiet; but tricky"; hmvc 4; fmvn 10;
+
+8/8/8/8/8/8/2k5/K1q5 w - - dm 1;"#;
+
+    let records = read_epd(epd).unwrap();
+    assert!(records.len() == 3, "Failed at assert 0");
+
+    // Best move and id
+    let record = &records[0];
+    assert!(record.id() == Some("WAC.001"), "Failed at assert 1");
+    assert!(record.best_moves() == ["Qg6"], "Failed at assert 2");
+    assert!(record.is_solved_by("Qg6"), "Failed at assert 3");
+    assert!(record.is_solved_by("Qg6+"), "Failed at assert 4");
+    assert!(!record.is_solved_by("Nf5"), "Failed at assert 5");
+    assert!(
+        record.position.get_square(Coord::new(6, 2)) == PieceCode::WQ,
+        "Failed at assert 6"
+    );
+
+    // Moves to avoid, quoted comment, and move counters
+    let record = &records[1];
+    assert!(
+        record.avoid_moves() == ["Qxd5", "Bc4"],
+        "Failed at assert 7"
+    );
+    assert!(!record.is_solved_by("Qxd5"), "Failed at assert 8");
+    assert!(record.is_solved_by("Be2"), "Failed at assert 9");
+    assert!(
+        record.comment(0) == Some("quiet; but tricky"),
+        "Failed at assert 10"
+    );
+    assert!(
+        record.position.plys_without_capture == 4,
+        "Failed at assert 11"
+    );
+    assert!(
+        record.position.full_move_number == 10,
+        "Failed at assert 12"
+    );
+    assert!(
+        record.position.queenside_castling_rook == [Some(0), Some(0)],
+        "Failed at assert 13"
+    );
+
+    // Direct mate
+    assert!(records[2].direct_mate() == Some(1), "Failed at assert 14");
+    assert!(records[2].id().is_none(), "Failed at assert 15");
+
+    // Serialization round trip
+    for line in epd.lines().filter(|line| !line.is_empty()) {
+        let record = EpdRecord::from_epd(line).unwrap();
+        assert!(record.to_epd() == line, "Failed at assert 16");
+    }
+
+    // Invalid records
+    assert!(
+        EpdRecord::from_epd("8/8/8/8 w - - bm e4;").is_err(),
+        "Failed at assert 17"
+    );
+    assert!(
+        EpdRecord::from_epd("8/8/8/8/8/8/8/8 w - - bm e4").is_err(),
+        "Failed at assert 18"
+    );
+    assert!(
+        EpdRecord::from_epd("8/8/8/8/8/8/8/8 w - - id \"x;").is_err(),
+        "Failed at assert 19"
+    );
+    assert!(
+        EpdRecord::from_epd("8/8/8/8/8/8/8/8 w - - 1x e4;").is_err(),
+        "Failed at assert 20"
+    );
+    assert!(
+        EpdRecord::from_epd("8/8/8/8/8/8/8/8 w - - hmvc x;").is_err(),
+        "Failed at assert 21"
+    );
+    assert!(
+        read_epd("4k3/8/8/8/8/8/8/4K3 w - -\n\n8/8 w - -")
+            .unwrap_err()
+            .0
+            == 3,
+        "Failed at assert 22"
+    );
+}
+
+#[test]
+fn test_zobrist() {
+    use crate::move_generation::*;
+
+    let start = Position::default();
+    let hash = |fen: &str| Position::from_fen(fen).unwrap().get_zobrist_hash();
+
+    // Same position, different move counters
+    assert!(
+        hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            == start.get_zobrist_hash(),
+        "Failed at assert 0"
+    );
+    assert!(
+        hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 7 12")
+            == start.get_zobrist_hash(),
+        "Failed at assert 1"
+    );
+
+    // Turn, castling rights and en passant square change the hash
+    assert!(
+        hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1")
+            != start.get_zobrist_hash(),
+        "Failed at assert 2"
+    );
+    assert!(
+        hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1") != start.get_zobrist_hash(),
+        "Failed at assert 3"
+    );
+    assert!(
+        hash("rnbqkbnr/pppp1ppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+            != hash("rnbqkbnr/pppp1ppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"),
+        "Failed at assert 4"
+    );
+
+    // Transpositions give the same hash, and unmaking moves restores it
+    let mut position = Position::default();
+    for mov in ["g1f3", "g8f6", "b1c3"] {
+        let mov = parse_uci_move(&mut position, mov).unwrap();
+        position.make_move(mov);
+    }
+    let mut other = Position::default();
+    let mut moves = Vec::new();
+    for mov in ["b1c3", "g8f6", "g1f3"] {
+        let mov = parse_uci_move(&mut other, mov).unwrap();
+        other.make_move(mov);
+        moves.push(mov);
+    }
+    assert!(
+        position.get_zobrist_hash() == other.get_zobrist_hash(),
+        "Failed at assert 5"
+    );
+    for mov in moves.iter().rev() {
+        other.unmake_move(*mov);
+    }
+    assert!(
+        other.get_zobrist_hash() == start.get_zobrist_hash(),
+        "Failed at assert 6"
+    );
+    // The incremental hash matches the one computed from scratch, through castling, en
+    // passant captures and promotions
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let mut position = Position::from_fen(fen).unwrap();
+    let mut moves = Vec::new();
+    for (i, mov) in ["e1g1", "h3g2", "a2a4", "b4a3", "d5e6", "g2f1q"]
+        .iter()
+        .enumerate()
+    {
+        let mov = parse_uci_move(&mut position, mov).unwrap();
+        position.make_move(mov);
+        moves.push(mov);
+        assert!(
+            position.get_zobrist_hash() == position.compute_zobrist_hash(),
+            "Failed at assert 7.{}",
+            i
+        );
+    }
+    for mov in moves.iter().rev() {
+        position.unmake_move(*mov);
+    }
+    assert!(
+        position.get_zobrist_hash() == hash(fen),
+        "Failed at assert 8"
+    );
+}
+
+#[test]
+fn test_validation() {
+    let error = |fen: &str| match Position::from_fen(fen) {
+        Err(FenError::InvalidPosition(error)) => Some(error),
+        _ => None,
+    };
+
+    // Valid positions
+    assert!(Position::default().validate().is_ok(), "Failed at assert 0");
+    assert!(
+        Position::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").is_ok(),
+        "Failed at assert 1"
+    );
+    assert!(
+        Position::from_fen("4k3/8/8/8/8/8/8/4K2r w - - 0 1").is_ok(),
+        "Failed at assert 2"
+    );
+
+    // Kings, pawns and piece counts
+    assert!(
+        error("8/8/8/8/8/8/8/4K3 w - - 0 1")
+            == Some(PositionError::InvalidKingCount(Player::Black)),
+        "Failed at assert 3"
+    );
+    assert!(
+        error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1")
+            == Some(PositionError::InvalidKingCount(Player::White)),
+        "Failed at assert 4"
+    );
+    assert!(
+        error("4k2P/8/8/8/8/8/8/4K3 w - - 0 1")
+            == Some(PositionError::PawnOnBackRank(Coord::new(7, 7))),
+        "Failed at assert 5"
+    );
+    assert!(
+        error("4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1")
+            == Some(PositionError::TooManyPieces(Player::White)),
+        "Failed at assert 6"
+    );
+    assert!(
+        error("3qk3/8/8/8/8/8/PPPPPPPP/QQ2K3 w - - 0 1")
+            == Some(PositionError::TooManyPieces(Player::White)),
+        "Failed at assert 7"
+    );
+    assert!(
+        Position::from_fen("3qk3/8/8/8/8/8/1PPPPPPP/QQ2K3 w - - 0 1").is_ok(),
+        "Failed at assert 8"
+    );
+
+    // Check, castling rights and en passant square
+    assert!(
+        error("4k3/8/8/8/8/8/8/4K2R w - - 0 1").is_none(),
+        "Failed at assert 9"
+    );
+    assert!(
+        error("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1") == Some(PositionError::OpponentInCheck),
+        "Failed at assert 10"
+    );
+    assert!(
+        error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1")
+            == Some(PositionError::InvalidEnPassantSquare(Coord::new(4, 2))),
+        "Failed at assert 11"
+    );
+    assert!(
+        error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 1")
+            == Some(PositionError::InvalidEnPassantSquare(Coord::new(4, 5))),
+        "Failed at assert 12"
+    );
+    let mut position = Position::default();
+    position.kingside_castling_rook[Player::White as usize] = Some(2);
+    assert!(
+        position.validate() == Err(PositionError::InvalidCastlingRights(Player::White)),
+        "Failed at assert 13"
+    );
+    position.kingside_castling_rook[Player::White as usize] = Some(7);
+    position.set_square(PieceCode::ES, Coord::new(7, 0));
+    assert!(
+        position.validate() == Err(PositionError::InvalidCastlingRights(Player::White)),
+        "Failed at assert 14"
+    );
+
+    // Diverging boards
+    let mut position = Position::default();
+    position
+        .piece_centric_board
+        .set_square(PieceCode::ES, Coord::new(4, 1));
+    assert!(
+        position.validate() == Err(PositionError::BoardMismatch(Coord::new(4, 1))),
+        "Failed at assert 15"
+    );
+    let mut position = Position::default();
+    position.piece_centric_board.main_boards[PieceCode::WQ as usize - 1] |=
+        get_square_bitboard(Coord::new(4, 1));
+    assert!(
+        position.validate() == Err(PositionError::CorruptedBitboard),
+        "Failed at assert 16"
+    );
+}
+
+#[test]
+fn test_bitboard() {
+    // Squares and conversions
+    assert!(Square::E4.index() == 28, "Failed at assert 0");
+    assert!(
+        Square::E4.file() == 4 && Square::E4.rank() == 3,
+        "Failed at assert 1"
+    );
+    assert!(Square::E4.to_string() == "e4", "Failed at assert 2");
+    assert!(
+        Square::from_algebraic("h8") == Some(Square::H8),
+        "Failed at assert 3"
+    );
+    for square in Square::ALL {
+        let coord = Coord::from(square);
+        assert!(Square::from(coord) == square, "Failed at assert 4");
+        assert!(
+            square.bitboard().0 == get_square_bitboard(coord),
+            "Failed at assert 5"
+        );
+        assert!(
+            Square::from_0x88(square.to_0x88()) == Some(square),
+            "Failed at assert 6"
+        );
+    }
+    assert!(Square::from_0x88(0x08).is_none(), "Failed at assert 7");
+    assert!(Square::from_0x88(0x80).is_none(), "Failed at assert 8");
+
+    // Constants
+    let files: Bitboard = Bitboard::FILES
+        .into_iter()
+        .fold(Bitboard::EMPTY, |a, b| a | b);
+    let ranks: Bitboard = Bitboard::RANKS
+        .into_iter()
+        .fold(Bitboard::EMPTY, |a, b| a | b);
+    assert!(
+        files == Bitboard::FULL && ranks == Bitboard::FULL,
+        "Failed at assert 9"
+    );
+    assert!(
+        Bitboard::FILE_E.iter().all(|square| square.file() == 4),
+        "Failed at assert 10"
+    );
+    assert!(
+        Bitboard::RANK_2.iter().all(|square| square.rank() == 1),
+        "Failed at assert 11"
+    );
+    assert!(
+        Bitboard::DIAGONAL
+            .iter()
+            .all(|square| square.file() == square.rank()),
+        "Failed at assert 12"
+    );
+    assert!(
+        Bitboard::ANTI_DIAGONAL
+            .iter()
+            .all(|square| square.file() + square.rank() == 7),
+        "Failed at assert 13"
+    );
+    assert!(
+        Bitboard::DARK_SQUARES.contains(Square::A1)
+            && Bitboard::LIGHT_SQUARES.contains(Square::H1)
+            && (Bitboard::DARK_SQUARES & Bitboard::LIGHT_SQUARES).is_empty(),
+        "Failed at assert 14"
+    );
+
+    // Operators, iteration and popping
+    let mut bitboard = Bitboard::from(Square::A1) | Square::E4 | Square::H8;
+    assert!(bitboard.count() == 3, "Failed at assert 15");
+    assert!(
+        bitboard.iter().collect::<Vec<_>>() == vec![Square::A1, Square::E4, Square::H8],
+        "Failed at assert 16"
+    );
+    assert!(
+        (bitboard & Bitboard::RANK_4) == Square::E4.bitboard(),
+        "Failed at assert 17"
+    );
+    assert!(
+        (bitboard ^ Square::E4).count() == 2 && !(!bitboard).contains(Square::H8),
+        "Failed at assert 18"
+    );
+    assert!(
+        bitboard.pop_msb() == Some(Square::A1),
+        "Failed at assert 19"
+    );
+    assert!(
+        bitboard.pop_lsb() == Some(Square::H8),
+        "Failed at assert 20"
+    );
+    assert!(bitboard == Square::E4.bitboard(), "Failed at assert 21");
+    bitboard.clear(Square::E4);
+    assert!(
+        bitboard.pop_msb().is_none() && bitboard.pop_lsb().is_none(),
+        "Failed at assert 22"
+    );
+    let mut file_h = Bitboard::FILE_H;
+    assert!(file_h.pop_msb() == Some(Square::H1), "Failed at assert 23");
+    assert!(file_h.count() == 7, "Failed at assert 24");
+
+    // Shifts, without wrapping around the board
+    let e4 = Square::E4.bitboard();
+    let expected = [
+        Square::E5,
+        Square::E3,
+        Square::F4,
+        Square::D4,
+        Square::F5,
+        Square::D5,
+        Square::F3,
+        Square::D3,
+    ];
+    for (direction, square) in Direction::ALL.into_iter().zip(expected) {
+        assert!(
+            e4.shift(direction) == square.bitboard(),
+            "Failed at assert 25"
+        );
+    }
+    assert!(
+        Bitboard::FILE_H.shift(Direction::East).is_empty()
+            && Bitboard::FILE_H.shift(Direction::NorthEast).is_empty()
+            && Bitboard::FILE_A.shift(Direction::West).is_empty()
+            && Bitboard::FILE_A.shift(Direction::SouthWest).is_empty(),
+        "Failed at assert 26"
+    );
+    assert!(
+        Bitboard::RANK_8.shift(Direction::North).is_empty()
+            && Bitboard::RANK_1.shift(Direction::South).is_empty(),
+        "Failed at assert 27"
+    );
+    assert!(
+        Bitboard::FULL.shift(Direction::NorthWest).count() == 49,
+        "Failed at assert 28"
+    );
 }
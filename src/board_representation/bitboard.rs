@@ -0,0 +1,288 @@
+#![allow(dead_code)]
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use super::square::*;
+
+/**********
+* BITBOARD
+***********/
+
+// Bitboard wraps a u64 with one bit per square, a1 being the MSB and h8 the LSB (the same
+// layout as the boards of BitBoard). Going north (towards the 8th rank) is a right shift
+// by 8, and going east (towards the h file) a right shift by 1.
+
+/// Set of squares stored in a u64
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Bitboard(pub u64);
+
+/// The eight directions a bitboard can be shifted in
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+    ];
+}
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard(u64::MAX);
+
+    pub const FILE_A: Bitboard = Bitboard(0x8080808080808080);
+    pub const FILE_B: Bitboard = Bitboard(0x4040404040404040);
+    pub const FILE_C: Bitboard = Bitboard(0x2020202020202020);
+    pub const FILE_D: Bitboard = Bitboard(0x1010101010101010);
+    pub const FILE_E: Bitboard = Bitboard(0x0808080808080808);
+    pub const FILE_F: Bitboard = Bitboard(0x0404040404040404);
+    pub const FILE_G: Bitboard = Bitboard(0x0202020202020202);
+    pub const FILE_H: Bitboard = Bitboard(0x0101010101010101);
+
+    pub const RANK_1: Bitboard = Bitboard(0xFF00000000000000);
+    pub const RANK_2: Bitboard = Bitboard(0x00FF000000000000);
+    pub const RANK_3: Bitboard = Bitboard(0x0000FF0000000000);
+    pub const RANK_4: Bitboard = Bitboard(0x000000FF00000000);
+    pub const RANK_5: Bitboard = Bitboard(0x00000000FF000000);
+    pub const RANK_6: Bitboard = Bitboard(0x0000000000FF0000);
+    pub const RANK_7: Bitboard = Bitboard(0x000000000000FF00);
+    pub const RANK_8: Bitboard = Bitboard(0x00000000000000FF);
+
+    /// Files, indexed from a to h
+    pub const FILES: [Bitboard; 8] = [
+        Bitboard::FILE_A,
+        Bitboard::FILE_B,
+        Bitboard::FILE_C,
+        Bitboard::FILE_D,
+        Bitboard::FILE_E,
+        Bitboard::FILE_F,
+        Bitboard::FILE_G,
+        Bitboard::FILE_H,
+    ];
+
+    /// Ranks, indexed from 1 to 8
+    pub const RANKS: [Bitboard; 8] = [
+        Bitboard::RANK_1,
+        Bitboard::RANK_2,
+        Bitboard::RANK_3,
+        Bitboard::RANK_4,
+        Bitboard::RANK_5,
+        Bitboard::RANK_6,
+        Bitboard::RANK_7,
+        Bitboard::RANK_8,
+    ];
+
+    /// The a1-h8 diagonal
+    pub const DIAGONAL: Bitboard = Bitboard(0x8040201008040201);
+    /// The a8-h1 anti-diagonal
+    pub const ANTI_DIAGONAL: Bitboard = Bitboard(0x0102040810204080);
+
+    pub const LIGHT_SQUARES: Bitboard = Bitboard(0x55AA55AA55AA55AA);
+    pub const DARK_SQUARES: Bitboard = Bitboard(0xAA55AA55AA55AA55);
+
+    #[inline(always)]
+    pub const fn from_square(square: Square) -> Bitboard {
+        square.bitboard()
+    }
+
+    #[inline(always)]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of squares in the set
+    #[inline(always)]
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    #[inline(always)]
+    pub const fn contains(self, square: Square) -> bool {
+        self.0 & square.bitboard().0 != 0
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, square: Square) {
+        self.0 |= square.bitboard().0;
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self, square: Square) {
+        self.0 &= !square.bitboard().0;
+    }
+
+    /// Square of the most significant bit, which is the one with the lowest index (closest
+    /// to a1)
+    #[inline(always)]
+    pub fn msb(self) -> Option<Square> {
+        (self.0 != 0).then(|| Square::from_index(self.0.leading_zeros() as u8))
+    }
+
+    /// Square of the least significant bit, which is the one with the highest index
+    /// (closest to h8)
+    #[inline(always)]
+    pub fn lsb(self) -> Option<Square> {
+        (self.0 != 0).then(|| Square::from_index(63 - self.0.trailing_zeros() as u8))
+    }
+
+    /// Remove the most significant bit from the set, and return its square
+    #[inline(always)]
+    pub fn pop_msb(&mut self) -> Option<Square> {
+        let square = self.msb()?;
+        self.clear(square);
+        Some(square)
+    }
+
+    /// Remove the least significant bit from the set, and return its square
+    #[inline(always)]
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let square = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+
+    /// Move all the squares one step in a direction. Squares leaving the board are
+    /// dropped, instead of wrapping around to the opposite file.
+    #[inline(always)]
+    pub const fn shift(self, direction: Direction) -> Bitboard {
+        let not_a = !Bitboard::FILE_A.0;
+        let not_h = !Bitboard::FILE_H.0;
+        Bitboard(match direction {
+            Direction::North => self.0 >> 8,
+            Direction::South => self.0 << 8,
+            Direction::East => (self.0 >> 1) & not_a,
+            Direction::West => (self.0 << 1) & not_h,
+            Direction::NorthEast => (self.0 >> 9) & not_a,
+            Direction::NorthWest => (self.0 >> 7) & not_h,
+            Direction::SouthEast => (self.0 << 7) & not_a,
+            Direction::SouthWest => (self.0 << 9) & not_h,
+        })
+    }
+
+    /// Iterate over the squares of the set, from a1 to h8
+    #[inline(always)]
+    pub fn iter(self) -> BitboardIter {
+        BitboardIter(self)
+    }
+}
+
+/// Iterator over the squares of a bitboard, from a1 to h8
+pub struct BitboardIter(Bitboard);
+
+impl Iterator for BitboardIter {
+    type Item = Square;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Square> {
+        self.0.pop_msb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for BitboardIter {}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+
+    fn into_iter(self) -> BitboardIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Bitboard {
+        iter.into_iter().fold(Bitboard::EMPTY, |bitboard, square| {
+            bitboard | square.bitboard()
+        })
+    }
+}
+
+impl From<u64> for Bitboard {
+    fn from(bitboard: u64) -> Bitboard {
+        Bitboard(bitboard)
+    }
+}
+
+impl From<Bitboard> for u64 {
+    fn from(bitboard: Bitboard) -> u64 {
+        bitboard.0
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(square: Square) -> Bitboard {
+        square.bitboard()
+    }
+}
+
+/// Implement a binary operator and its assignment version, with a Bitboard or a Square as
+/// the right operand
+macro_rules! impl_bitboard_operator {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident, $op:tt) => {
+        impl $trait for Bitboard {
+            type Output = Bitboard;
+
+            #[inline(always)]
+            fn $method(self, rhs: Bitboard) -> Bitboard {
+                Bitboard(self.0 $op rhs.0)
+            }
+        }
+
+        impl $trait<Square> for Bitboard {
+            type Output = Bitboard;
+
+            #[inline(always)]
+            fn $method(self, rhs: Square) -> Bitboard {
+                Bitboard(self.0 $op rhs.bitboard().0)
+            }
+        }
+
+        impl $assign_trait for Bitboard {
+            #[inline(always)]
+            fn $assign_method(&mut self, rhs: Bitboard) {
+                self.0 = self.0 $op rhs.0;
+            }
+        }
+
+        impl $assign_trait<Square> for Bitboard {
+            #[inline(always)]
+            fn $assign_method(&mut self, rhs: Square) {
+                self.0 = self.0 $op rhs.bitboard().0;
+            }
+        }
+    };
+}
+
+impl_bitboard_operator!(BitAnd, bitand, BitAndAssign, bitand_assign, &);
+impl_bitboard_operator!(BitOr, bitor, BitOrAssign, bitor_assign, |);
+impl_bitboard_operator!(BitXor, bitxor, BitXorAssign, bitxor_assign, ^);
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    #[inline(always)]
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
@@ -6,6 +6,7 @@
 
 /// Represents either the black or white player
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Player {
     White,
     Black,
@@ -13,6 +14,7 @@ pub enum Player {
 
 /// Represents any piece, or the empty square
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceCode {
     ES, // Empty square
 
@@ -34,56 +36,104 @@ pub enum PieceCode {
 /// Piece centric bitoard representation. One u64 represents a board in row-major ordering
 /// (starting from the a1 square)
 /// See : <https://www.chessprogramming.org/Bitboards>
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct BitBoard {
     /// Main boards for positions of each piece type.
     /// Should be indexed using PieceCode
-    pub main_boards: [u64; 12],
+    pub(crate) main_boards: [u64; 12],
 
     /// Only one en passant bitboard is needed, as it changes every ply
-    pub en_passant_board: u64,
+    pub(crate) en_passant_board: u64,
+
+    /* Derived boards, kept up to date with the main boards */
+    /// Pieces of each player. Should be indexed using Player.
+    pub(crate) color_boards: [u64; 2],
+    /// Pieces of each type, regardless of their color. Should be indexed using PieceCode - 1,
+    /// modulo 6 (pawns, knights, bishops, rooks, queens and kings).
+    pub(crate) piece_type_boards: [u64; 6],
+    /// All the pieces on the board
+    pub(crate) occupancy_board: u64,
+}
+
+/// Square centric 64 squares board representation (mailbox), indexed in row-major ordering
+/// starting from the a1 square (the index of a Square). Its values correspond to the
+/// PieceCode values.
+/// See : <https://www.chessprogramming.org/Mailbox>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mailbox {
+    pub(crate) main_board: [PieceCode; 64],
 }
 
 /// Square centric 0x88 board representation. Its values correspond to the PieceCode values.
+/// It is only kept (in sync with the other boards) with the zerox88 feature, to check
+/// attacks by walking along the attack directions.
 /// See : <https://www.chessprogramming.org/0x88>
-#[derive(Debug)]
+#[cfg(feature = "zerox88")]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Zerox88Board {
-    pub main_board: [PieceCode; 128],
-    pub en_passant_board: [PieceCode; 128],
+    pub(crate) main_board: [PieceCode; 128],
 }
 
 /// This structure contains all the required data to represent a chess position.
 /// This includes pieces positions (in redundant piece and square centric board
-/// representations), as well as additional game state informations such as the current turn,
-/// castling possibilities, ...
-#[derive(Debug)]
+/// representations, both updated incrementally), as well as additional game state
+/// informations such as the current turn, castling possibilities, ...
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Position {
     /* Pieces positions */
     /// Piece-centric bitboard representation
-    pub piece_centric_board: BitBoard,
+    pub(crate) piece_centric_board: BitBoard,
+
+    /// Square-centric mailbox representation
+    pub(crate) square_centric_board: Mailbox,
 
     /// Square-centric 0x88 representation
-    pub square_centric_board: Zerox88Board,
+    #[cfg(feature = "zerox88")]
+    pub(crate) zerox88_board: Zerox88Board,
 
     /* Other game state informations */
-    pub current_turn: Player,
+    pub(crate) current_turn: Player,
 
-    /// Specify if each player can castle on each side.
+    /// Specify the file of the rook each player can castle with on each side, or None if
+    /// castling on that side is not possible anymore. Files are needed (rather than simple
+    /// booleans) for Chess960, where rooks don't necessarily start on the a and h files.
     /// Should be indexed using the Player enum for clarity.
-    pub can_castle_kingside: [bool; 2],
-    pub can_castle_queenside: [bool; 2],
+    pub(crate) kingside_castling_rook: [Option<u8>; 2],
+    pub(crate) queenside_castling_rook: [Option<u8>; 2],
+
+    /// Set for Chess960 (Fischer Random) positions. This only changes how castling rights
+    /// and castling moves are written.
+    pub(crate) chess960: bool,
 
     /// Used for the 50 moves rule
-    pub plys_without_capture: u8,
-    /* TODO Add a way to check for threefold repetitions. This will likely involve
-     * transposition tables. However, a linked list containing all the previous boards could
-     * work at the beginning, albeit quite inefficient. */
+    pub(crate) plys_without_capture: u8,
+
+    /// Number of the current full move, starting at 1 and incremented after Black's move
+    pub(crate) full_move_number: u16,
+
+    /// States that can't be recovered when unmaking moves, saved before making each move
+    /// (see the move_generation module)
+    pub(crate) history: Vec<IrreversibleState>,
+
+    /// Zobrist hash, updated incrementally by set_square and when making moves. Code that
+    /// changes the other fields directly must call update_zobrist_hash.
+    pub(crate) hash: u64,
+}
+
+/// Part of a Position that is lost when making a move, and thus has to be saved to unmake it
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct IrreversibleState {
+    pub(crate) kingside_castling_rook: [Option<u8>; 2],
+    pub(crate) queenside_castling_rook: [Option<u8>; 2],
+    pub(crate) en_passant_board: u64,
+    pub(crate) plys_without_capture: u8,
+    pub(crate) hash: u64,
 }
 
 /// Stores a coordinate in algebraic notation. Files are indexed from 0 to 7 instead of a-h.
 /// Because value checking is done at the structure creation, Coord is safe to use and does
 /// not require any additional checking.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Coord {
     pub f: u8,
     pub r: u8,
@@ -96,7 +146,7 @@ pub struct Coord {
 /// Default trait for bitboard is the normal starting position
 impl Default for BitBoard {
     fn default() -> BitBoard {
-        BitBoard {
+        let mut bitboard = BitBoard {
             // Each binary litteral can be seen as a rank, with the bit shifts specifying
             // which rank we are inserting pieces in.
             main_boards: [
@@ -114,7 +164,12 @@ impl Default for BitBoard {
                 0b00001000,       // Black king
             ],
             en_passant_board: 0,
-        }
+            color_boards: [0; 2],
+            piece_type_boards: [0; 6],
+            occupancy_board: 0,
+        };
+        bitboard.update_derived_boards();
+        bitboard
     }
 }
 
@@ -123,9 +178,54 @@ impl BitBoard {
     pub fn new() -> BitBoard {
         BitBoard::default()
     }
+
+    /// Initialize a bitboard without any piece on it
+    pub fn empty() -> BitBoard {
+        BitBoard {
+            main_boards: [0; 12],
+            en_passant_board: 0,
+            color_boards: [0; 2],
+            piece_type_boards: [0; 6],
+            occupancy_board: 0,
+        }
+    }
+}
+
+/// Default trait for Mailbox is the normal starting position
+impl Default for Mailbox {
+    #[rustfmt::skip]
+    fn default() -> Mailbox {
+        Mailbox {
+            main_board: [
+                PieceCode::WR, PieceCode::WN, PieceCode::WB, PieceCode::WQ, PieceCode::WK, PieceCode::WB, PieceCode::WN, PieceCode::WR,
+                PieceCode::WP, PieceCode::WP, PieceCode::WP, PieceCode::WP, PieceCode::WP, PieceCode::WP, PieceCode::WP, PieceCode::WP,
+                PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES,
+                PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES,
+                PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES,
+                PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES,
+                PieceCode::BP, PieceCode::BP, PieceCode::BP, PieceCode::BP, PieceCode::BP, PieceCode::BP, PieceCode::BP, PieceCode::BP,
+                PieceCode::BR, PieceCode::BN, PieceCode::BB, PieceCode::BQ, PieceCode::BK, PieceCode::BB, PieceCode::BN, PieceCode::BR,
+            ],
+        }
+    }
+}
+
+impl Mailbox {
+    /// Shorthand for default
+    pub fn new() -> Mailbox {
+        Mailbox::default()
+    }
+
+    /// Initialize a mailbox without any piece on it
+    pub fn empty() -> Mailbox {
+        Mailbox {
+            main_board: [PieceCode::ES; 64],
+        }
+    }
 }
 
 /// Default trait for Zerox88Board is the normal starting position
+#[cfg(feature = "zerox88")]
 impl Default for Zerox88Board {
     #[rustfmt::skip]
     fn default() -> Zerox88Board {
@@ -151,31 +251,45 @@ impl Default for Zerox88Board {
                 PieceCode::BR, PieceCode::BN, PieceCode::BB, PieceCode::BQ, PieceCode::BK, PieceCode::BB, PieceCode::BN, PieceCode::BR,
                 PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES,
             ],
-
-            en_passant_board: [PieceCode::ES; 128],
         }
     }
 }
 
+#[cfg(feature = "zerox88")]
 impl Zerox88Board {
     /// Shorthand for default
     pub fn new() -> Zerox88Board {
         Zerox88Board::default()
     }
+
+    /// Initialize a 0x88 board without any piece on it
+    pub fn empty() -> Zerox88Board {
+        Zerox88Board {
+            main_board: [PieceCode::ES; 128],
+        }
+    }
 }
 
 /// Default trait for Position is the normal starting position.
 /// Both piece and square centric boards are initialized.
 impl Default for Position {
     fn default() -> Position {
-        Position {
+        let mut position = Position {
             piece_centric_board: BitBoard::default(),
-            square_centric_board: Zerox88Board::default(),
+            square_centric_board: Mailbox::default(),
+            #[cfg(feature = "zerox88")]
+            zerox88_board: Zerox88Board::default(),
             current_turn: Player::White,
-            can_castle_kingside: [true, true],
-            can_castle_queenside: [true, true],
+            kingside_castling_rook: [Some(7), Some(7)],
+            queenside_castling_rook: [Some(0), Some(0)],
+            chess960: false,
             plys_without_capture: 0,
-        }
+            full_move_number: 1,
+            history: Vec::new(),
+            hash: 0,
+        };
+        position.update_zobrist_hash();
+        position
     }
 }
 
@@ -184,6 +298,54 @@ impl Position {
     pub fn new() -> Position {
         Position::default()
     }
+
+    /// Initialize a position without any piece on the board, and no castling rights
+    pub fn empty() -> Position {
+        Position {
+            piece_centric_board: BitBoard::empty(),
+            square_centric_board: Mailbox::empty(),
+            #[cfg(feature = "zerox88")]
+            zerox88_board: Zerox88Board::empty(),
+            current_turn: Player::White,
+            kingside_castling_rook: [None, None],
+            queenside_castling_rook: [None, None],
+            chess960: false,
+            plys_without_capture: 0,
+            full_move_number: 1,
+            history: Vec::new(),
+            hash: 0,
+        }
+    }
+
+    /// Return the piece-centric bitboard representation of the position
+    pub fn get_bitboard(&self) -> &BitBoard {
+        &self.piece_centric_board
+    }
+
+    /// Return the square-centric mailbox representation of the position
+    pub fn get_mailbox(&self) -> &Mailbox {
+        &self.square_centric_board
+    }
+
+    /// Return the player whose turn it is
+    pub fn get_current_turn(&self) -> Player {
+        self.current_turn
+    }
+
+    /// Check whether the position is a Chess960 (Fischer Random) position
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Return the number of plies since the last capture or pawn move (50 moves rule)
+    pub fn get_plys_without_capture(&self) -> u8 {
+        self.plys_without_capture
+    }
+
+    /// Return the number of the current full move
+    pub fn get_full_move_number(&self) -> u16 {
+        self.full_move_number
+    }
 }
 
 /// Default trait for Coord is the a1 square
@@ -204,4 +366,19 @@ impl Coord {
         );
         Coord { f: file, r: rank }
     }
+
+    /// Initialize from a square name in algebraic notation (such as "e4"). Returns None if
+    /// the string is not a valid square name.
+    pub fn from_algebraic(square: &str) -> Option<Coord> {
+        let bytes = square.as_bytes();
+        if bytes.len() != 2 {
+            return None;
+        }
+
+        let (file, rank) = (bytes[0], bytes[1]);
+        if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) {
+            return None;
+        }
+        Some(Coord::new(file - b'a', rank - b'1'))
+    }
 }
@@ -0,0 +1,295 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use super::datatypes::*;
+use super::fen::*;
+
+/******************
+* EPD SERIALIZATION
+*******************/
+
+// Extended Position Description is a superset of FEN, used to describe test suites. It is
+// made of the first four FEN fields, followed by a list of operations. Each operation is an
+// opcode and its operands, terminated by a semicolon. String operands are double quoted.
+// For instance :
+// r1b1k2r/ppp2ppp/2n5/3q4/8/2P5/P4PPP/R1BQKB1R w KQkq - bm Qe2+; id "WAC.0001";
+// See : <https://www.chessprogramming.org/Extended_Position_Description>
+
+/// Errors that can occur while parsing an EPD record
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EpdError {
+    /// The first four fields don't describe a valid position
+    InvalidFen(FenError),
+    /// A quoted operand is never closed
+    UnterminatedString(String),
+    /// An operation doesn't start with a valid opcode, or isn't terminated by a semicolon
+    InvalidOperation(String),
+    /// The hmvc or fmvn operation doesn't contain a valid number
+    InvalidMoveCounter(String),
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpdError::InvalidFen(error) => write!(f, "{}", error),
+            EpdError::UnterminatedString(s) => write!(f, "unterminated string: {}", s),
+            EpdError::InvalidOperation(s) => write!(f, "invalid operation: {}", s),
+            EpdError::InvalidMoveCounter(s) => write!(f, "invalid move counter: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// A single EPD operation (opcode and its operands)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EpdOperation {
+    pub opcode: String,
+    /// Operands, without the quotes for string operands
+    pub operands: Vec<String>,
+}
+
+/// A position along with its EPD operations
+#[derive(Debug)]
+pub struct EpdRecord {
+    pub position: Position,
+    /// Operations, in the order they were given
+    pub operations: Vec<EpdOperation>,
+}
+
+/// Remove the check, mate and annotation suffixes of a SAN move, so that moves can be
+/// compared no matter how they were written
+fn normalize_san(san: &str) -> &str {
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+/// Check whether the operands of an opcode are strings, and must be written quoted
+fn is_string_opcode(opcode: &str) -> bool {
+    let is_indexed = |prefix: char| {
+        opcode.len() == 2
+            && opcode.starts_with(prefix)
+            && opcode.ends_with(|c: char| c.is_ascii_digit())
+    };
+    matches!(opcode, "id" | "eco" | "nic" | "tcgs" | "tcri" | "tcsi")
+        || is_indexed('c')
+        || is_indexed('v')
+}
+
+/// Split the operations part of an EPD record into operations
+fn parse_operations(operations: &str) -> Result<Vec<EpdOperation>, EpdError> {
+    let mut parsed_operations = Vec::new();
+    let mut chars = operations.chars().peekable();
+
+    loop {
+        // Skip whitespace between operations
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut tokens: Vec<String> = Vec::new();
+        let mut terminated = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => {
+                    terminated = true;
+                    break;
+                }
+                c if c.is_whitespace() => {}
+                '"' => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => operand.push(c),
+                            None => return Err(EpdError::UnterminatedString(operand)),
+                        }
+                    }
+                    tokens.push(operand);
+                }
+                c => {
+                    let mut operand = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || c == ';' {
+                            break;
+                        }
+                        operand.push(c);
+                        chars.next();
+                    }
+                    tokens.push(operand);
+                }
+            }
+        }
+
+        let operation = tokens.join(" ");
+        if !terminated || tokens.is_empty() {
+            return Err(EpdError::InvalidOperation(operation));
+        }
+
+        // Opcodes start with a letter, and are only made of letters, digits and underscores
+        let opcode = tokens.remove(0);
+        let is_valid_opcode = opcode.starts_with(|c: char| c.is_ascii_alphabetic())
+            && opcode
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_opcode {
+            return Err(EpdError::InvalidOperation(operation));
+        }
+
+        parsed_operations.push(EpdOperation {
+            opcode,
+            operands: tokens,
+        });
+    }
+
+    Ok(parsed_operations)
+}
+
+impl EpdRecord {
+    /// Parse a single EPD record. The "hmvc" and "fmvn" operations, if present, are applied
+    /// to the position's move counters.
+    pub fn from_epd(epd: &str) -> Result<EpdRecord, EpdError> {
+        // Split the four position fields from the operations
+        let mut rest = epd.trim_start();
+        let mut fen_fields = Vec::new();
+        for _ in 0..4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fen_fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+
+        let position = Position::from_fen(&fen_fields.join(" ")).map_err(EpdError::InvalidFen)?;
+        let mut record = EpdRecord {
+            position,
+            operations: parse_operations(rest)?,
+        };
+
+        let halfmove_clock = record
+            .get_operand("hmvc")
+            .map(|operand| {
+                operand
+                    .parse()
+                    .map_err(|_| EpdError::InvalidMoveCounter(operand.to_string()))
+            })
+            .transpose()?;
+        let fullmove_number = record
+            .get_operand("fmvn")
+            .map(|operand| {
+                operand
+                    .parse()
+                    .ok()
+                    .filter(|n| *n != 0)
+                    .ok_or_else(|| EpdError::InvalidMoveCounter(operand.to_string()))
+            })
+            .transpose()?;
+
+        if let Some(halfmove_clock) = halfmove_clock {
+            record.position.plys_without_capture = halfmove_clock;
+        }
+        if let Some(fullmove_number) = fullmove_number {
+            record.position.full_move_number = fullmove_number;
+        }
+
+        Ok(record)
+    }
+
+    /// Return the EPD string of the record. Move counters are only written through the
+    /// "hmvc" and "fmvn" operations, if the record contains them.
+    pub fn to_epd(&self) -> String {
+        // Only keep the first four fields of the FEN
+        let fen = self.position.to_fen();
+        let mut epd = fen.split(' ').take(4).collect::<Vec<&str>>().join(" ");
+
+        for operation in &self.operations {
+            epd.push(' ');
+            epd.push_str(&operation.opcode);
+            for operand in &operation.operands {
+                epd.push(' ');
+                if is_string_opcode(&operation.opcode)
+                    || operand.is_empty()
+                    || operand.contains([' ', ';'])
+                {
+                    epd.push_str(&format!("\"{}\"", operand));
+                } else {
+                    epd.push_str(operand);
+                }
+            }
+            epd.push(';');
+        }
+
+        epd
+    }
+
+    /// Return the operands of an operation, if present
+    pub fn get_operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|operation| operation.opcode == opcode)
+            .map(|operation| operation.operands.as_slice())
+    }
+
+    /// Return the first operand of an operation, if present
+    pub fn get_operand(&self, opcode: &str) -> Option<&str> {
+        self.get_operands(opcode)
+            .and_then(|operands| operands.first())
+            .map(String::as_str)
+    }
+
+    /// Position identifier ("id" opcode)
+    pub fn id(&self) -> Option<&str> {
+        self.get_operand("id")
+    }
+
+    /// Best moves in SAN ("bm" opcode)
+    pub fn best_moves(&self) -> &[String] {
+        self.get_operands("bm").unwrap_or(&[])
+    }
+
+    /// Moves to avoid in SAN ("am" opcode)
+    pub fn avoid_moves(&self) -> &[String] {
+        self.get_operands("am").unwrap_or(&[])
+    }
+
+    /// Comment of index n ("c0" to "c9" opcodes)
+    pub fn comment(&self, n: u8) -> Option<&str> {
+        self.get_operand(&format!("c{}", n))
+    }
+
+    /// Number of moves of a direct mate ("dm" opcode)
+    pub fn direct_mate(&self) -> Option<u32> {
+        self.get_operand("dm")
+            .and_then(|operand| operand.parse().ok())
+    }
+
+    /// Check whether a move (in SAN) solves the position : it must be one of the best moves
+    /// (if any are given), and none of the moves to avoid.
+    pub fn is_solved_by(&self, san: &str) -> bool {
+        let san = normalize_san(san);
+        let is_best_move = self.best_moves().is_empty()
+            || self
+                .best_moves()
+                .iter()
+                .any(|best_move| normalize_san(best_move) == san);
+        let is_avoid_move = self
+            .avoid_moves()
+            .iter()
+            .any(|avoid_move| normalize_san(avoid_move) == san);
+
+        is_best_move && !is_avoid_move
+    }
+}
+
+/// Read all the records of an EPD file (one per line). Empty lines are ignored. In case of
+/// error, the line number (starting at 1) is returned along with the error.
+pub fn read_epd(epd: &str) -> Result<Vec<EpdRecord>, (usize, EpdError)> {
+    epd.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| EpdRecord::from_epd(line).map_err(|error| (i + 1, error)))
+        .collect()
+}
@@ -0,0 +1,333 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use super::datatypes::*;
+use super::misc::*;
+use super::static_board::*;
+use super::validation::*;
+
+/******************
+* FEN SERIALIZATION
+*******************/
+
+// Forsyth-Edwards Notation describes a position in a single line of text. It is made of six
+// space separated fields : piece placement, active color, castling rights, en passant
+// target square, halfmove clock and fullmove number.
+// See : <https://www.chessprogramming.org/Forsyth-Edwards_Notation>
+
+/// FEN string of the normal starting position
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Errors that can occur while parsing a FEN string
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum FenError {
+    /// One of the four mandatory fields is missing
+    MissingField(&'static str),
+    /// The piece placement field doesn't describe 8 ranks of 8 squares
+    InvalidPiecePlacement(String),
+    InvalidActiveColor(String),
+    InvalidCastlingRights(String),
+    InvalidEnPassantSquare(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    /// There are more than six fields in the string
+    TrailingCharacters(String),
+    /// The fields are well-formed, but describe an impossible position
+    InvalidPosition(PositionError),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing FEN field: {}", field),
+            FenError::InvalidPiecePlacement(s) => write!(f, "invalid piece placement: {}", s),
+            FenError::InvalidActiveColor(s) => write!(f, "invalid active color: {}", s),
+            FenError::InvalidCastlingRights(s) => write!(f, "invalid castling rights: {}", s),
+            FenError::InvalidEnPassantSquare(s) => write!(f, "invalid en passant square: {}", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock: {}", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number: {}", s),
+            FenError::TrailingCharacters(s) => write!(f, "trailing characters: {}", s),
+            FenError::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Parse the piece placement field, and write the pieces on the position
+fn parse_piece_placement(position: &mut Position, field: &str) -> Result<(), FenError> {
+    let error = || FenError::InvalidPiecePlacement(field.to_string());
+
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(error());
+    }
+
+    // Ranks are given from the 8th to the 1st
+    for (i, rank_str) in ranks.iter().enumerate() {
+        let rank = 7 - i as u8;
+        let mut file: u8 = 0;
+
+        for c in rank_str.chars() {
+            if let Some(skip) = c.to_digit(10) {
+                if skip == 0 || skip > 8 {
+                    return Err(error());
+                }
+                file += skip as u8;
+            } else {
+                let piece_code = PieceCode::from_fen_char(c).ok_or_else(error)?;
+                if file >= 8 {
+                    return Err(error());
+                }
+                position.set_square(piece_code, Coord::new(file, rank));
+                file += 1;
+            }
+
+            if file > 8 {
+                return Err(error());
+            }
+        }
+
+        if file != 8 {
+            return Err(error());
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the castling rights field. Standard ("KQkq"), Shredder-FEN ("HAha", with the
+/// files of the castling rooks) and X-FEN (a mix of both, files being only used when
+/// the castling rook is not the outermost one) notations are supported.
+fn parse_castling_rights(position: &mut Position, field: &str) -> Result<(), FenError> {
+    let error = || FenError::InvalidCastlingRights(field.to_string());
+
+    if field == "-" {
+        return Ok(());
+    }
+    if field.is_empty() {
+        return Err(error());
+    }
+
+    for c in field.chars() {
+        let player = if c.is_ascii_uppercase() {
+            Player::White
+        } else {
+            Player::Black
+        };
+        let (back_rank, rook) = match player {
+            Player::White => (0, PieceCode::WR),
+            Player::Black => (7, PieceCode::BR),
+        };
+
+        // Castling requires the king to be on its back rank
+        let king = position
+            .get_king_coord(player)
+            .filter(|king| king.r == back_rank)
+            .ok_or_else(error)?;
+        let is_rook = |file: &u8| position.get_square(Coord::new(*file, back_rank)) == rook;
+
+        let (rook_file, is_kingside) = match c.to_ascii_lowercase() {
+            // Outermost rook on the corresponding side of the king
+            'k' => ((king.f + 1..8).rev().find(is_rook), true),
+            'q' => ((0..king.f).find(is_rook), false),
+            file @ 'a'..='h' => {
+                let file = file as u8 - b'a';
+                (Some(file).filter(is_rook), file > king.f)
+            }
+            _ => return Err(error()),
+        };
+        let rook_file = rook_file.ok_or_else(error)?;
+
+        let right = if is_kingside {
+            &mut position.kingside_castling_rook[player as usize]
+        } else {
+            &mut position.queenside_castling_rook[player as usize]
+        };
+
+        // The same right can't be given twice
+        if right.is_some() {
+            return Err(error());
+        }
+        *right = Some(rook_file);
+
+        // Kings and rooks out of their normal starting squares mean this is a Chess960
+        // position
+        if king.f != 4 || (rook_file != 0 && rook_file != 7) {
+            position.chess960 = true;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the castling rights of a player. Standard notation is used for normal positions,
+/// and X-FEN notation for Chess960 ones.
+fn write_castling_rights(position: &Position, player: Player, fen: &mut String) {
+    let (back_rank, rook) = match player {
+        Player::White => (0, PieceCode::WR),
+        Player::Black => (7, PieceCode::BR),
+    };
+
+    let rights = [
+        (position.kingside_castling_rook[player as usize], 'K'),
+        (position.queenside_castling_rook[player as usize], 'Q'),
+    ];
+
+    for (rook_file, standard_char) in rights {
+        let rook_file = match rook_file {
+            Some(rook_file) => rook_file,
+            None => continue,
+        };
+
+        // With X-FEN, the file is only needed if another rook stands between the castling
+        // rook and the corner
+        let mut outer_files = if standard_char == 'K' {
+            rook_file + 1..8
+        } else {
+            0..rook_file
+        };
+        let is_outermost =
+            outer_files.all(|file| position.get_square(Coord::new(file, back_rank)) != rook);
+
+        let c = if !position.chess960 || is_outermost {
+            standard_char
+        } else {
+            (b'A' + rook_file) as char
+        };
+        fen.push(match player {
+            Player::White => c,
+            Player::Black => c.to_ascii_lowercase(),
+        });
+    }
+}
+
+/// Parse the en passant target square field ("-" or a square on the 3rd/6th rank)
+fn parse_en_passant_square(position: &mut Position, field: &str) -> Result<(), FenError> {
+    if field == "-" {
+        return Ok(());
+    }
+
+    let coord = Coord::from_algebraic(field)
+        .filter(|coord| coord.r == 2 || coord.r == 5)
+        .ok_or_else(|| FenError::InvalidEnPassantSquare(field.to_string()))?;
+
+    position.set_en_passant_square(Some(coord));
+
+    Ok(())
+}
+
+impl Position {
+    /// Initialize a position from a FEN string. The halfmove clock and fullmove number
+    /// fields are optional, and respectively default to 0 and 1.
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let mut position = Position::empty();
+        let mut fields = fen.split_whitespace();
+
+        let placement = fields
+            .next()
+            .ok_or(FenError::MissingField("piece placement"))?;
+        parse_piece_placement(&mut position, placement)?;
+
+        let active_color = fields
+            .next()
+            .ok_or(FenError::MissingField("active color"))?;
+        position.current_turn = match active_color {
+            "w" => Player::White,
+            "b" => Player::Black,
+            _ => return Err(FenError::InvalidActiveColor(active_color.to_string())),
+        };
+
+        let castling = fields
+            .next()
+            .ok_or(FenError::MissingField("castling rights"))?;
+        parse_castling_rights(&mut position, castling)?;
+
+        let en_passant = fields
+            .next()
+            .ok_or(FenError::MissingField("en passant square"))?;
+        parse_en_passant_square(&mut position, en_passant)?;
+
+        if let Some(halfmove_clock) = fields.next() {
+            position.plys_without_capture = halfmove_clock
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(halfmove_clock.to_string()))?;
+        }
+
+        if let Some(fullmove_number) = fields.next() {
+            position.full_move_number = fullmove_number
+                .parse()
+                .ok()
+                .filter(|n| *n != 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(fullmove_number.to_string()))?;
+        }
+
+        let trailing: Vec<&str> = fields.collect();
+        if !trailing.is_empty() {
+            return Err(FenError::TrailingCharacters(trailing.join(" ")));
+        }
+
+        position.update_zobrist_hash();
+        position.validate().map_err(FenError::InvalidPosition)?;
+        Ok(position)
+    }
+
+    /// Return the FEN string describing the position
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        // Piece placement, from the 8th rank to the 1st
+        for i in (0..8u8).rev() {
+            let mut empty_squares = 0;
+            for j in 0..8u8 {
+                let piece_code = self.get_square(Coord::new(j, i));
+                if piece_code == PieceCode::ES {
+                    empty_squares += 1;
+                    continue;
+                }
+                if empty_squares != 0 {
+                    fen.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                fen.push(get_fen_piece(piece_code));
+            }
+            if empty_squares != 0 {
+                fen.push_str(&empty_squares.to_string());
+            }
+            if i != 0 {
+                fen.push('/');
+            }
+        }
+
+        // Active color
+        fen.push_str(match self.current_turn {
+            Player::White => " w ",
+            Player::Black => " b ",
+        });
+
+        // Castling rights
+        let castling_len = fen.len();
+        write_castling_rights(self, Player::White, &mut fen);
+        write_castling_rights(self, Player::Black, &mut fen);
+        if fen.len() == castling_len {
+            fen.push('-');
+        }
+
+        // En passant target square
+        fen.push(' ');
+        match self.get_en_passant_square() {
+            Some(coord) => fen.push_str(&get_algebraic_square(coord)),
+            None => fen.push('-'),
+        }
+
+        // Halfmove clock and fullmove number
+        fen.push_str(&format!(
+            " {} {}",
+            self.plys_without_capture, self.full_move_number
+        ));
+
+        fen
+    }
+}
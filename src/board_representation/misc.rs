@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
+use super::bitboard::*;
 use super::datatypes::*;
-use super::static_board::*;
 
 /*************************
 * MISC METHODS & FUNCTIONS
@@ -21,6 +21,20 @@ pub fn get_square_bitboard(coord: Coord) -> u64 {
     a1_bitboard!() >> (coord.r << 3) >> coord.f
 }
 
+/// From a Coord, get the index of the square in a Mailbox
+#[inline(always)]
+pub fn get_mailbox_index(coord: Coord) -> usize {
+    ((coord.r << 3) + coord.f) as usize
+}
+
+/// From a Coord, get the name of the square in algebraic notation (such as "e4")
+pub fn get_algebraic_square(coord: Coord) -> String {
+    let mut square = String::with_capacity(2);
+    square.push((b'a' + coord.f) as char);
+    square.push((b'1' + coord.r) as char);
+    square
+}
+
 pub fn invert_player(player: &Player) -> Player {
     match player {
         Player::White => Player::Black,
@@ -29,91 +43,208 @@ pub fn invert_player(player: &Player) -> Player {
 }
 
 impl BitBoard {
-    /// Copy content of a 0x88 board onto the bitboard
-    pub fn apply_0x88_board(&mut self, zerox88_board: &Zerox88Board) {
-        // Clean all bitboards
-        for i in 0..12 {
-            self.main_boards[i] = 0;
+    /// Copy content of a mailbox onto the bitboard. The en passant board is left unchanged.
+    pub fn apply_mailbox(&mut self, mailbox: &Mailbox) {
+        self.main_boards = [0; 12];
+        let mut mask: u64 = a1_bitboard!();
+        for piece_code in mailbox.main_board {
+            if piece_code != PieceCode::ES {
+                self.main_boards[piece_code as usize - 1] |= mask;
+            }
+            mask >>= 1;
         }
-        self.en_passant_board = 0;
+        self.update_derived_boards();
+    }
 
-        // Copy pieces
-        let mut mask: u64 = a1_bitboard!();
-        for i in 0..8 {
-            for j in 0..8 {
-                // Fill main boards
-                let piece_code = zerox88_board.get_square(Coord::new(j, i));
-                if piece_code != PieceCode::ES {
-                    self.main_boards[piece_code as usize - 1] |= mask;
-                }
+    /// Put a piece on an empty square
+    #[inline(always)]
+    pub fn add_piece(&mut self, piece_code: PieceCode, coord: Coord) {
+        let mask = get_square_bitboard(coord);
+        let i = piece_code as usize - 1;
+        self.main_boards[i] |= mask;
+        self.color_boards[i / 6] |= mask;
+        self.piece_type_boards[i % 6] |= mask;
+        self.occupancy_board |= mask;
+        self.debug_check_invariants();
+    }
 
-                // Fill en passant board
-                let en_passant_piece_code = zerox88_board.en_passant_board[((i << 4) + j) as usize];
-                if en_passant_piece_code != PieceCode::ES {
-                    self.en_passant_board |= mask;
-                }
+    /// Remove a piece, which must be standing on the square
+    #[inline(always)]
+    pub fn remove_piece(&mut self, piece_code: PieceCode, coord: Coord) {
+        let mask = !get_square_bitboard(coord);
+        let i = piece_code as usize - 1;
+        self.main_boards[i] &= mask;
+        self.color_boards[i / 6] &= mask;
+        self.piece_type_boards[i % 6] &= mask;
+        self.occupancy_board &= mask;
+        self.debug_check_invariants();
+    }
 
-                mask >>= 1;
-            }
+    /// Recompute the color, piece type and occupancy boards from the main boards
+    pub fn update_derived_boards(&mut self) {
+        self.color_boards = [0; 2];
+        self.piece_type_boards = [0; 6];
+        for (i, board) in self.main_boards.iter().enumerate() {
+            self.color_boards[i / 6] |= board;
+            self.piece_type_boards[i % 6] |= board;
         }
+        self.occupancy_board = self.color_boards[0] | self.color_boards[1];
+    }
+
+    /// Check that the derived boards match the main boards, and that no square is occupied
+    /// by two pieces
+    pub fn is_consistent(&self) -> bool {
+        let mut recomputed = self.clone();
+        recomputed.update_derived_boards();
+        let piece_count: u32 = self
+            .main_boards
+            .iter()
+            .map(|board| board.count_ones())
+            .sum();
+
+        recomputed.color_boards == self.color_boards
+            && recomputed.piece_type_boards == self.piece_type_boards
+            && recomputed.occupancy_board == self.occupancy_board
+            && piece_count == self.occupancy_board.count_ones()
+    }
+
+    /// Panic if the bitboard is not consistent (in debug builds only)
+    #[inline(always)]
+    pub fn debug_check_invariants(&self) {
+        debug_assert!(
+            self.is_consistent(),
+            "Derived bitboards are out of sync with the main boards"
+        );
     }
 }
 
-impl Zerox88Board {
-    /// Copy content of a bitboard onto the 0x88 board
-    pub fn apply_bitboard(&mut self, bitboard: &BitBoard) {
-        // Clean boards
-        for i in 0..128 {
-            self.main_board[i] = PieceCode::ES;
-            self.en_passant_board[i] = PieceCode::ES;
-        }
+impl BitBoard {
+    /// Return the total number of pieces on the board (kings included)
+    pub fn piece_count(&self) -> u32 {
+        self.occupancy_board.count_ones()
+    }
 
-        // Copy pieces
-        let mut mask: u64 = a1_bitboard!();
-        for i in 0..8 {
-            for j in 0..8 {
-                // Fill main board
-                for k in 0..12 {
-                    let filtered_board = bitboard.main_boards[k] & mask;
-                    if filtered_board != 0 {
-                        self.main_board[(i << 4) + j] = PieceCode::from_usize(k + 1);
-                    }
-                }
+    /// Return the material signature of the board, White pieces first, in the order
+    /// K, Q, R, B, N, P (for instance "KRPvKR")
+    pub fn material_signature(&self) -> String {
+        let mut signature = String::new();
+
+        for player in [Player::White, Player::Black] {
+            if player == Player::Black {
+                signature.push('v');
+            }
 
-                // Fill en passant board
-                let filtered_en_passant_board = bitboard.en_passant_board & mask;
-                if filtered_en_passant_board != 0 {
-                    // We'll put a WP by default, but any PieceCode (!= ES) is fine
-                    self.en_passant_board[(i << 4) + j] = PieceCode::WP;
+            // Pieces are iterated from the king down to the pawn
+            let offset = 6 * player as usize;
+            for i in (0..6).rev() {
+                let piece_code = PieceCode::from_usize(offset + i + 1);
+                let piece = get_fen_piece(piece_code).to_ascii_uppercase();
+                for _ in 0..self.main_boards[offset + i].count_ones() {
+                    signature.push(piece);
                 }
+            }
+        }
+
+        signature
+    }
+}
 
-                mask = mask >> 1;
+impl Mailbox {
+    /// Copy content of a bitboard onto the mailbox
+    pub fn apply_bitboard(&mut self, bitboard: &BitBoard) {
+        self.main_board = [PieceCode::ES; 64];
+        for (i, board) in bitboard.main_boards.iter().enumerate() {
+            for square in Bitboard(*board).iter() {
+                self.main_board[square.index() as usize] = PieceCode::from_usize(i + 1);
             }
         }
     }
 }
 
+#[cfg(feature = "zerox88")]
+impl Zerox88Board {
+    /// Copy content of a mailbox onto the 0x88 board
+    pub fn apply_mailbox(&mut self, mailbox: &Mailbox) {
+        self.main_board = [PieceCode::ES; 128];
+        for (index, piece_code) in mailbox.main_board.iter().enumerate() {
+            self.main_board[((index >> 3) << 4) + (index & 7)] = *piece_code;
+        }
+    }
+}
+
 impl Position {
-    /// Copy content of a 0x88 board onto the Position
-    pub fn apply_0x88_board(&mut self, zerox88_board: &Zerox88Board) {
-        self.piece_centric_board.apply_0x88_board(zerox88_board);
+    /// Copy content of a mailbox onto the Position
+    pub fn apply_mailbox(&mut self, mailbox: &Mailbox) {
+        self.square_centric_board = mailbox.clone();
+        self.piece_centric_board.apply_mailbox(mailbox);
+        #[cfg(feature = "zerox88")]
+        self.zerox88_board.apply_mailbox(mailbox);
+        self.update_zobrist_hash();
     }
 
-    /// Copy content of a bitboard onto the Position
+    /// Copy content of a bitboard onto the Position. The en passant square is left
+    /// unchanged.
     pub fn apply_bitboard(&mut self, bitboard: &BitBoard) {
         self.square_centric_board.apply_bitboard(bitboard);
+        self.apply_own_mailbox();
     }
 
-    /// Copy content of the Position's own 0x88 board onto its own bitboard
-    pub fn apply_own_0x88_board(&mut self) {
+    /// Copy content of the Position's own mailbox onto its other boards
+    pub fn apply_own_mailbox(&mut self) {
         self.piece_centric_board
-            .apply_0x88_board(&self.square_centric_board);
+            .apply_mailbox(&self.square_centric_board);
+        #[cfg(feature = "zerox88")]
+        self.zerox88_board.apply_mailbox(&self.square_centric_board);
+        self.update_zobrist_hash();
     }
 
-    /// Copy content of the Position's own bitboard onto its own 0x88 board
+    /// Copy content of the Position's own bitboard onto its other boards
     pub fn apply_own_bitboard(&mut self) {
         self.square_centric_board
             .apply_bitboard(&self.piece_centric_board);
+        #[cfg(feature = "zerox88")]
+        self.zerox88_board.apply_mailbox(&self.square_centric_board);
+        self.update_zobrist_hash();
+    }
+
+    /// Return the en passant target square, if any
+    pub fn get_en_passant_square(&self) -> Option<Coord> {
+        let en_passant_board = self.piece_centric_board.en_passant_board;
+        if en_passant_board == 0 {
+            return None;
+        }
+        let index = en_passant_board.leading_zeros() as u8;
+        Some(Coord::new(index & 7, index >> 3))
+    }
+
+    /// Set (or clear) the en passant target square. Like the other fields of the position,
+    /// the Zobrist hash isn't updated.
+    pub fn set_en_passant_square(&mut self, coord: Option<Coord>) {
+        self.piece_centric_board.en_passant_board = coord.map_or(0, get_square_bitboard);
+    }
+
+    /// Return the square of the player's king (or None if there is no king on the board)
+    pub fn get_king_coord(&self, player: Player) -> Option<Coord> {
+        let king_board = self.piece_centric_board.main_boards[6 * player as usize + 5];
+        if king_board == 0 {
+            return None;
+        }
+        let index = king_board.leading_zeros() as u8;
+        Some(Coord::new(index & 7, index >> 3))
+    }
+
+    /// Check whether neither side has enough material to mate (no pawn, rook or queen, and
+    /// at most one minor piece on the board)
+    pub fn is_insufficient_material(&self) -> bool {
+        let boards = &self.piece_centric_board.main_boards;
+        let major_pieces_and_pawns = [0, 3, 4, 6, 9, 10]
+            .iter()
+            .fold(0, |board, i| board | boards[*i]);
+        let minor_pieces = [1, 2, 7, 8]
+            .iter()
+            .map(|i| boards[*i].count_ones())
+            .sum::<u32>();
+        major_pieces_and_pawns == 0 && minor_pieces <= 1
     }
 }
 
@@ -176,3 +307,47 @@ pub fn get_unicode_piece(piece_code: PieceCode) -> char {
         PieceCode::BK => '♚',
     }
 }
+
+/// From a piece ID, return its FEN character (uppercase for White, lowercase for Black)
+pub fn get_fen_piece(piece_code: PieceCode) -> char {
+    match piece_code {
+        PieceCode::ES => '.',
+
+        PieceCode::WP => 'P',
+        PieceCode::WN => 'N',
+        PieceCode::WB => 'B',
+        PieceCode::WR => 'R',
+        PieceCode::WQ => 'Q',
+        PieceCode::WK => 'K',
+
+        PieceCode::BP => 'p',
+        PieceCode::BN => 'n',
+        PieceCode::BB => 'b',
+        PieceCode::BR => 'r',
+        PieceCode::BQ => 'q',
+        PieceCode::BK => 'k',
+    }
+}
+
+impl PieceCode {
+    /// From a FEN character, return the corresponding piece ID (if any)
+    pub fn from_fen_char(c: char) -> Option<PieceCode> {
+        match c {
+            'P' => Some(PieceCode::WP),
+            'N' => Some(PieceCode::WN),
+            'B' => Some(PieceCode::WB),
+            'R' => Some(PieceCode::WR),
+            'Q' => Some(PieceCode::WQ),
+            'K' => Some(PieceCode::WK),
+
+            'p' => Some(PieceCode::BP),
+            'n' => Some(PieceCode::BN),
+            'b' => Some(PieceCode::BB),
+            'r' => Some(PieceCode::BR),
+            'q' => Some(PieceCode::BQ),
+            'k' => Some(PieceCode::BK),
+
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use super::bitboard::*;
+use super::datatypes::*;
+use super::misc::*;
+
+/********
+* SQUARE
+*********/
+
+// Squares are indexed from 0 (a1) to 63 (h8) in row-major order, which is also the number
+// of leading zeros of their u64 bitboard (as a1 is the MSB), and the index used by the
+// lookup tables.
+
+/// Represents one of the 64 squares of the board
+#[rustfmt::skip]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Square {
+    A1, B1, C1, D1, E1, F1, G1, H1,
+    A2, B2, C2, D2, E2, F2, G2, H2,
+    A3, B3, C3, D3, E3, F3, G3, H3,
+    A4, B4, C4, D4, E4, F4, G4, H4,
+    A5, B5, C5, D5, E5, F5, G5, H5,
+    A6, B6, C6, D6, E6, F6, G6, H6,
+    A7, B7, C7, D7, E7, F7, G7, H7,
+    A8, B8, C8, D8, E8, F8, G8, H8,
+}
+
+impl Square {
+    /// All the squares, ordered by index
+    #[rustfmt::skip]
+    pub const ALL: [Square; 64] = [
+        Square::A1, Square::B1, Square::C1, Square::D1, Square::E1, Square::F1, Square::G1, Square::H1,
+        Square::A2, Square::B2, Square::C2, Square::D2, Square::E2, Square::F2, Square::G2, Square::H2,
+        Square::A3, Square::B3, Square::C3, Square::D3, Square::E3, Square::F3, Square::G3, Square::H3,
+        Square::A4, Square::B4, Square::C4, Square::D4, Square::E4, Square::F4, Square::G4, Square::H4,
+        Square::A5, Square::B5, Square::C5, Square::D5, Square::E5, Square::F5, Square::G5, Square::H5,
+        Square::A6, Square::B6, Square::C6, Square::D6, Square::E6, Square::F6, Square::G6, Square::H6,
+        Square::A7, Square::B7, Square::C7, Square::D7, Square::E7, Square::F7, Square::G7, Square::H7,
+        Square::A8, Square::B8, Square::C8, Square::D8, Square::E8, Square::F8, Square::G8, Square::H8,
+    ];
+
+    /// Get a square from its index (0 for a1, 63 for h8). Panics if the index is out of
+    /// bounds.
+    #[inline(always)]
+    pub const fn from_index(index: u8) -> Square {
+        Square::ALL[index as usize]
+    }
+
+    /// Get a square from its file and rank (both from 0 to 7)
+    #[inline(always)]
+    pub const fn from_file_rank(file: u8, rank: u8) -> Square {
+        assert!(
+            file < 8 && rank < 8,
+            "Tried to build an out of bounds square"
+        );
+        Square::from_index(rank * 8 + file)
+    }
+
+    /// Get a square from a 0x88 index, or None if the index is off the board
+    #[inline(always)]
+    pub const fn from_0x88(index: usize) -> Option<Square> {
+        if index >= 128 || index & 0x88 != 0 {
+            return None;
+        }
+        Some(Square::from_index(((index >> 4) * 8 + (index & 7)) as u8))
+    }
+
+    /// Initialize from a square name in algebraic notation (such as "e4")
+    pub fn from_algebraic(square: &str) -> Option<Square> {
+        Coord::from_algebraic(square).map(Square::from)
+    }
+
+    #[inline(always)]
+    pub const fn index(self) -> u8 {
+        self as u8
+    }
+
+    #[inline(always)]
+    pub const fn file(self) -> u8 {
+        self as u8 & 7
+    }
+
+    #[inline(always)]
+    pub const fn rank(self) -> u8 {
+        self as u8 >> 3
+    }
+
+    /// Index of the square on a 0x88 board
+    #[inline(always)]
+    pub const fn to_0x88(self) -> usize {
+        ((self.rank() << 4) + self.file()) as usize
+    }
+
+    #[inline(always)]
+    pub fn to_coord(self) -> Coord {
+        Coord::new(self.file(), self.rank())
+    }
+
+    /// Bitboard containing only this square
+    #[inline(always)]
+    pub const fn bitboard(self) -> Bitboard {
+        Bitboard(a1_bitboard!() >> self as u8)
+    }
+}
+
+impl From<Coord> for Square {
+    fn from(coord: Coord) -> Square {
+        Square::from_file_rank(coord.f, coord.r)
+    }
+}
+
+impl From<Square> for Coord {
+    fn from(square: Square) -> Coord {
+        square.to_coord()
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", get_algebraic_square(self.to_coord()))
+    }
+}
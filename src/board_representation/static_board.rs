@@ -2,6 +2,7 @@
 
 use super::datatypes::*;
 use super::misc::*;
+use super::zobrist::*;
 
 /******************
 * STATICBOARD TRAIT
@@ -19,23 +20,47 @@ pub trait StaticBoard {
 
     /// Return an ASCII (Unicode) representation of the board
     fn ascii(&self) -> String {
+        self.ascii_highlighted(false, &[])
+    }
+
+    /// Return an ASCII (Unicode) representation of the board, seen from Black's side if
+    /// flipped. Highlighted squares (such as the squares of the last move) are printed in
+    /// reverse video using ANSI escape codes.
+    fn ascii_highlighted(&self, flipped: bool, highlighted: &[Coord]) -> String {
         let mut board_string: String = String::new();
+        let ranks: Vec<u8> = if flipped {
+            (0..8).collect()
+        } else {
+            (0..8).rev().collect()
+        };
+        let files: Vec<u8> = if flipped {
+            (0..8).rev().collect()
+        } else {
+            (0..8).collect()
+        };
 
-        // Iterate over ranks in reverse order
-        for i in (0..8u8).rev() {
+        for i in ranks {
             // Print the rank number
             board_string.push((i + 49) as char);
             board_string.push(' ');
 
-            // Iterate over files
-            for j in 0..8u8 {
+            for j in files.iter().copied() {
                 board_string.push(' ');
-                let piece_symbol: char = get_unicode_piece(self.get_square(Coord::new(j, i)));
-                board_string.push(piece_symbol);
+                let coord = Coord::new(j, i);
+                let piece_symbol: char = get_unicode_piece(self.get_square(coord));
+                if highlighted.contains(&coord) {
+                    board_string.push_str(&format!("\x1b[7m{}\x1b[0m", piece_symbol));
+                } else {
+                    board_string.push(piece_symbol);
+                }
             }
             board_string.push('\n');
         }
-        board_string.push_str("   a b c d e f g h\n");
+        board_string.push_str(if flipped {
+            "   h g f e d c b a\n"
+        } else {
+            "   a b c d e f g h\n"
+        });
 
         board_string
     }
@@ -58,10 +83,14 @@ impl StaticBoard for BitBoard {
             0b00001000,       // Black king
         ];
         self.en_passant_board = 0;
+        self.update_derived_boards();
     }
 
     fn get_square(&self, coord: Coord) -> PieceCode {
         let mask: u64 = 0b10000000 << ((7 - coord.r) << 3) >> coord.f;
+        if self.occupancy_board & mask == 0 {
+            return PieceCode::ES;
+        }
 
         for i in 0..12 {
             let piece: u64 = self.main_boards[i] & mask;
@@ -73,25 +102,34 @@ impl StaticBoard for BitBoard {
     }
 
     fn set_square(&mut self, piece_code: PieceCode, coord: Coord) {
-        let mask: u64 = 0b10000000 << ((7 - coord.r) << 3) >> coord.f;
-
+        // The piece previously standing on the square (if any) has to be found to be
+        // removed from its piece board and the derived boards
+        let previous_code = self.get_square(coord);
+        if previous_code != PieceCode::ES {
+            self.remove_piece(previous_code, coord);
+        }
         if piece_code != PieceCode::ES {
-            self.main_boards[piece_code as usize - 1] |= mask;
-        } else {
-            // We're emptying a square, this is a special case, as we will need to find
-            // the piece board we will be overwriting
-            for i in 0..12 {
-                let piece: u64 = self.main_boards[i] & mask;
-                if piece != 0 {
-                    // We found the correct piece board
-                    self.main_boards[i] &= !mask;
-                    return;
-                }
-            }
+            self.add_piece(piece_code, coord);
         }
+        self.debug_check_invariants();
+    }
+}
+
+impl StaticBoard for Mailbox {
+    fn reset(&mut self) {
+        *self = Mailbox::default();
+    }
+
+    fn get_square(&self, coord: Coord) -> PieceCode {
+        self.main_board[get_mailbox_index(coord)]
+    }
+
+    fn set_square(&mut self, piece_code: PieceCode, coord: Coord) {
+        self.main_board[get_mailbox_index(coord)] = piece_code;
     }
 }
 
+#[cfg(feature = "zerox88")]
 impl StaticBoard for Zerox88Board {
     #[rustfmt::skip]
     fn reset(&mut self) {
@@ -115,8 +153,6 @@ impl StaticBoard for Zerox88Board {
             PieceCode::BR, PieceCode::BN, PieceCode::BB, PieceCode::BQ, PieceCode::BK, PieceCode::BB, PieceCode::BN, PieceCode::BR,
             PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES, PieceCode::ES,
         ];
-
-        self.en_passant_board = [PieceCode::ES; 128];
     }
 
     fn get_square(&self, coord: Coord) -> PieceCode {
@@ -130,15 +166,21 @@ impl StaticBoard for Zerox88Board {
 
 impl StaticBoard for Position {
     fn reset(&mut self) {
-        // Reset the two boards
+        // Reset the boards
         self.piece_centric_board.reset();
         self.square_centric_board.reset();
+        #[cfg(feature = "zerox88")]
+        self.zerox88_board.reset();
 
         // Reset the position "metadata"
         self.current_turn = Player::White;
-        self.can_castle_kingside = [true, true];
-        self.can_castle_queenside = [true, true];
+        self.kingside_castling_rook = [Some(7), Some(7)];
+        self.queenside_castling_rook = [Some(0), Some(0)];
+        self.chess960 = false;
         self.plys_without_capture = 0;
+        self.full_move_number = 1;
+        self.history.clear();
+        self.update_zobrist_hash();
     }
 
     fn get_square(&self, coord: Coord) -> PieceCode {
@@ -147,8 +189,22 @@ impl StaticBoard for Position {
     }
 
     fn set_square(&mut self, piece_code: PieceCode, coord: Coord) {
-        self.piece_centric_board.set_square(piece_code, coord);
-        self.square_centric_board.set_square(piece_code, coord);
+        // The mailbox gives the piece standing on the square, so the bitboard doesn't have
+        // to look for it
+        let index = get_mailbox_index(coord);
+        let previous_code = self.square_centric_board.main_board[index];
+        if previous_code != PieceCode::ES {
+            self.piece_centric_board.remove_piece(previous_code, coord);
+        }
+        if piece_code != PieceCode::ES {
+            self.piece_centric_board.add_piece(piece_code, coord);
+        }
+        self.square_centric_board.main_board[index] = piece_code;
+        self.hash ^=
+            get_piece_zobrist_key(previous_code, coord) ^ get_piece_zobrist_key(piece_code, coord);
+
+        #[cfg(feature = "zerox88")]
+        self.zerox88_board.set_square(piece_code, coord);
     }
 
     fn ascii(&self) -> String {
@@ -0,0 +1,241 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use super::datatypes::*;
+use super::misc::*;
+use super::static_board::*;
+
+/*********************
+* POSITION VALIDATION
+**********************/
+
+// A Position stores its pieces twice (in a bitboard and in a mailbox, plus a 0x88 board with
+// the zerox88 feature), and nothing prevents them from diverging, or a FEN string from
+// describing an impossible position. The validator checks that the boards agree, and that
+// the position could occur in a game (as far as it can be checked without knowing the moves
+// that led to it).
+
+/// Reasons why a position is invalid
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PositionError {
+    /// The bitboard and the square-centric boards don't contain the same piece on a square
+    BoardMismatch(Coord),
+    /// The derived bitboards (occupancy, colors and piece types) don't match the piece
+    /// boards, or a square is set in several piece boards
+    CorruptedBitboard,
+    /// A player doesn't have exactly one king
+    InvalidKingCount(Player),
+    /// A pawn stands on the first or last rank
+    PawnOnBackRank(Coord),
+    /// The player who just moved is in check
+    OpponentInCheck,
+    /// A castling right doesn't match the placement of the king and the rook
+    InvalidCastlingRights(Player),
+    /// The en passant square doesn't follow a double pawn push of the opponent
+    InvalidEnPassantSquare(Coord),
+    /// A player has more pieces than possible (more than 16 pieces, more than 8 pawns, or
+    /// more promoted pieces than missing pawns)
+    TooManyPieces(Player),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let player_name = |player: &Player| match player {
+            Player::White => "white",
+            Player::Black => "black",
+        };
+        match self {
+            PositionError::BoardMismatch(coord) => {
+                write!(f, "board mismatch on {}", get_algebraic_square(*coord))
+            }
+            PositionError::CorruptedBitboard => write!(f, "corrupted bitboard"),
+            PositionError::InvalidKingCount(player) => {
+                write!(f, "{} doesn't have exactly one king", player_name(player))
+            }
+            PositionError::PawnOnBackRank(coord) => {
+                write!(f, "pawn on back rank: {}", get_algebraic_square(*coord))
+            }
+            PositionError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            PositionError::InvalidCastlingRights(player) => {
+                write!(f, "invalid castling rights for {}", player_name(player))
+            }
+            PositionError::InvalidEnPassantSquare(coord) => {
+                write!(
+                    f,
+                    "invalid en passant square: {}",
+                    get_algebraic_square(*coord)
+                )
+            }
+            PositionError::TooManyPieces(player) => {
+                write!(f, "too many pieces for {}", player_name(player))
+            }
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+impl Position {
+    /// Check that the position is consistent and could be reached in a game
+    pub fn validate(&self) -> Result<(), PositionError> {
+        self.validate_placement()?;
+
+        let opponent = invert_player(&self.current_turn);
+        if let Some(king) = self.get_king_coord(opponent) {
+            if self.is_square_attacked(king, self.current_turn) {
+                return Err(PositionError::OpponentInCheck);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check (in debug builds only) a position reached by making or unmaking a move, and its
+    /// incremental Zobrist hash. As moves only have to be pseudo-legal, the player who just
+    /// moved may be in check.
+    #[inline(always)]
+    pub fn debug_validate(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(error) = self.validate_placement() {
+            panic!("Invalid position ({}): {}", error, self.to_fen());
+        }
+        #[cfg(debug_assertions)]
+        if self.get_zobrist_hash() != self.compute_zobrist_hash() {
+            panic!("Outdated Zobrist hash: {}", self.to_fen());
+        }
+    }
+
+    /// Run all the checks of validate, except the one on the player who just moved
+    fn validate_placement(&self) -> Result<(), PositionError> {
+        self.validate_boards()?;
+        self.validate_pieces()?;
+        self.validate_castling_rights()?;
+        self.validate_en_passant_square()
+    }
+
+    /// Check that all the boards agree, and that the bitboard is internally consistent
+    fn validate_boards(&self) -> Result<(), PositionError> {
+        let bitboard = &self.piece_centric_board;
+        if !bitboard.is_consistent() || bitboard.en_passant_board.count_ones() > 1 {
+            return Err(PositionError::CorruptedBitboard);
+        }
+
+        // As the bitboard is consistent, a square holds a piece on both boards if it is set in
+        // the corresponding piece board
+        let mut mask = a1_bitboard!();
+        for (index, piece_code) in self.square_centric_board.main_board.iter().enumerate() {
+            let board = match piece_code {
+                PieceCode::ES => !bitboard.occupancy_board,
+                piece_code => bitboard.main_boards[*piece_code as usize - 1],
+            };
+            #[cfg(feature = "zerox88")]
+            let board = match self.zerox88_board.main_board[((index >> 3) << 4) + (index & 7)] {
+                zerox88_code if zerox88_code == *piece_code => board,
+                _ => 0,
+            };
+            if board & mask == 0 {
+                return Err(PositionError::BoardMismatch(Coord::new(
+                    index as u8 & 7,
+                    index as u8 >> 3,
+                )));
+            }
+            mask >>= 1;
+        }
+
+        Ok(())
+    }
+
+    /// Check the number of pieces of each player, and the placement of the pawns
+    fn validate_pieces(&self) -> Result<(), PositionError> {
+        let bitboard = &self.piece_centric_board;
+
+        for player in [Player::White, Player::Black] {
+            let offset = 6 * player as usize;
+            let count = |i: usize| bitboard.main_boards[offset + i].count_ones();
+
+            if count(5) != 1 {
+                return Err(PositionError::InvalidKingCount(player));
+            }
+
+            // Each piece beyond the initial ones must come from a promoted pawn
+            let promoted_pieces = count(4).saturating_sub(1)
+                + count(3).saturating_sub(2)
+                + count(2).saturating_sub(2)
+                + count(1).saturating_sub(2);
+            if bitboard.color_boards[player as usize].count_ones() > 16
+                || count(0) + promoted_pieces > 8
+            {
+                return Err(PositionError::TooManyPieces(player));
+            }
+        }
+
+        // First and last ranks
+        let back_ranks = 0xFF000000000000FF;
+        let misplaced_pawns = bitboard.piece_type_boards[0] & back_ranks;
+        if misplaced_pawns != 0 {
+            let index = misplaced_pawns.leading_zeros() as u8;
+            return Err(PositionError::PawnOnBackRank(Coord::new(
+                index & 7,
+                index >> 3,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check that the king and the rooks of each castling right are on their back rank,
+    /// with kingside rooks on the king's right and queenside rooks on its left
+    fn validate_castling_rights(&self) -> Result<(), PositionError> {
+        for player in [Player::White, Player::Black] {
+            let (back_rank, rook) = match player {
+                Player::White => (0, PieceCode::WR),
+                Player::Black => (7, PieceCode::BR),
+            };
+            let kingside_rook = self.kingside_castling_rook[player as usize];
+            let queenside_rook = self.queenside_castling_rook[player as usize];
+            if kingside_rook.is_none() && queenside_rook.is_none() {
+                continue;
+            }
+
+            let error = PositionError::InvalidCastlingRights(player);
+            let king = self
+                .get_king_coord(player)
+                .filter(|king| king.r == back_rank)
+                .ok_or(error.clone())?;
+            let is_rook = |file: u8| self.get_square(Coord::new(file, back_rank)) == rook;
+
+            if kingside_rook.is_some_and(|file| file <= king.f || !is_rook(file))
+                || queenside_rook.is_some_and(|file| file >= king.f || !is_rook(file))
+            {
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the en passant square is right behind a pawn of the opponent that could
+    /// just have been pushed by two squares
+    fn validate_en_passant_square(&self) -> Result<(), PositionError> {
+        let Some(coord) = self.get_en_passant_square() else {
+            return Ok(());
+        };
+
+        // Rank of the en passant square, and direction of the opponent pawns
+        let (rank, direction, pawn) = match self.current_turn {
+            Player::White => (5i8, -1, PieceCode::BP),
+            Player::Black => (2, 1, PieceCode::WP),
+        };
+        let is_plausible = coord.r as i8 == rank
+            && self.get_square(coord) == PieceCode::ES
+            && self.get_square(Coord::new(coord.f, (rank - direction) as u8)) == PieceCode::ES
+            && self.get_square(Coord::new(coord.f, (rank + direction) as u8)) == pawn;
+
+        if !is_plausible {
+            return Err(PositionError::InvalidEnPassantSquare(coord));
+        }
+        Ok(())
+    }
+}
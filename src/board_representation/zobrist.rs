@@ -0,0 +1,150 @@
+#![allow(dead_code)]
+
+use super::bitboard::*;
+use super::datatypes::*;
+
+/*****************
+* ZOBRIST HASHING
+******************/
+
+// A Zobrist hash is the XOR of random keys, one for each feature of the position : every
+// piece on its square, the side to move, the castling rights and the en passant file.
+// Identical positions always get the same hash, and different positions almost never do,
+// which makes it suitable to index the transposition table and detect repetitions.
+// The keys are generated at compile time with a fixed seed, so hashes are reproducible.
+// The hash is stored in the position and updated incrementally : set_square XORs the keys
+// of the pieces leaving and entering a square, and making a move XORs the keys of the side
+// to move, the castling rights and the en passant file.
+// See : <https://www.chessprogramming.org/Zobrist_Hashing>
+
+/// Random keys of every feature of a position
+pub struct ZobristKeys {
+    /// Indexed by PieceCode - 1 (like the main boards) and square (rank * 8 + file)
+    pub pieces: [[u64; 64]; 12],
+    pub black_to_move: u64,
+    /// Indexed by player, side (kingside then queenside) and file of the castling rook
+    pub castling: [[[u64; 8]; 2]; 2],
+    /// Indexed by the file of the en passant square
+    pub en_passant: [u64; 8],
+}
+
+/// SplitMix64 step, returning the new state and the generated number
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_zobrist_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[0; 64]; 12],
+        black_to_move: 0,
+        castling: [[[0; 8]; 2]; 2],
+        en_passant: [0; 8],
+    };
+    let mut state = 0x4B7261626E696B; // "Krabnik"
+    let mut key;
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            (state, key) = split_mix(state);
+            keys.pieces[piece][square] = key;
+            square += 1;
+        }
+        piece += 1;
+    }
+
+    (state, key) = split_mix(state);
+    keys.black_to_move = key;
+
+    let mut player = 0;
+    while player < 2 {
+        let mut side = 0;
+        while side < 2 {
+            let mut file = 0;
+            while file < 8 {
+                (state, key) = split_mix(state);
+                keys.castling[player][side][file] = key;
+                file += 1;
+            }
+            side += 1;
+        }
+        player += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        (state, key) = split_mix(state);
+        keys.en_passant[file] = key;
+        file += 1;
+    }
+
+    keys
+}
+
+pub static ZOBRIST_KEYS: ZobristKeys = generate_zobrist_keys();
+
+/// Return the key of a piece standing on a square (0 for empty squares)
+#[inline(always)]
+pub fn get_piece_zobrist_key(piece_code: PieceCode, coord: Coord) -> u64 {
+    match piece_code {
+        PieceCode::ES => 0,
+        _ => ZOBRIST_KEYS.pieces[piece_code as usize - 1][(coord.r * 8 + coord.f) as usize],
+    }
+}
+
+impl Position {
+    /// Return the Zobrist hash of the position
+    #[inline(always)]
+    pub fn get_zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Recompute the stored hash from scratch, after changing the fields of the position
+    /// directly
+    pub fn update_zobrist_hash(&mut self) {
+        self.hash = self.compute_zobrist_hash();
+    }
+
+    /// Return the XOR of the keys of the castling rights and the en passant file
+    pub(crate) fn get_state_zobrist_key(&self) -> u64 {
+        let mut key = 0;
+        for player in 0..2 {
+            let sides = [
+                self.kingside_castling_rook[player],
+                self.queenside_castling_rook[player],
+            ];
+            for (side, rook_file) in sides.iter().enumerate() {
+                if let Some(file) = rook_file {
+                    key ^= ZOBRIST_KEYS.castling[player][side][*file as usize];
+                }
+            }
+        }
+
+        if let Some(coord) = self.get_en_passant_square() {
+            key ^= ZOBRIST_KEYS.en_passant[coord.f as usize];
+        }
+        key
+    }
+
+    /// Compute the Zobrist hash of the position from scratch
+    pub fn compute_zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (piece, board) in self.piece_centric_board.main_boards.iter().enumerate() {
+            for square in Bitboard(*board).iter() {
+                hash ^= ZOBRIST_KEYS.pieces[piece][square.index() as usize];
+            }
+        }
+
+        if self.current_turn == Player::Black {
+            hash ^= ZOBRIST_KEYS.black_to_move;
+        }
+
+        hash ^ self.get_state_zobrist_key()
+    }
+}
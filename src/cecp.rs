@@ -0,0 +1,556 @@
+/*
+ * The cecp module implements the Chess Engine Communication Protocol (version 2), the text
+ * protocol of XBoard/WinBoard, as an alternative to UCI. The protocol is detected from the
+ * first command received by the engine ("xboard" for CECP).
+ *
+ * Unlike UCI, the engine keeps track of the game itself : it receives the moves one by one,
+ * and decides on its own when to think (when it is its turn, out of force mode). Searches
+ * run on their own thread, like with UCI, and use the same search backend.
+ * See : <https://www.gnu.org/software/xboard/engine-intf.html>
+ */
+
+#![allow(dead_code)]
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::search::*;
+use crate::uci::{get_pv_uci, send_line, ENGINE_NAME};
+
+/*********
+* SESSION
+**********/
+
+/// Mate scores sent in thinking output are 100000 + the number of moves to mate
+const CECP_MATE_SCORE: i32 = 100000;
+
+/// State of the engine between CECP commands
+pub struct CecpSession<W: Write + Send + 'static> {
+    output: Arc<Mutex<W>>,
+    start: Position,
+    position: Position,
+    /// Moves played since the start position, for undo and remove
+    moves: Vec<Move>,
+    /// Hashes of the positions before each move, for repetitions
+    hashes: Vec<u64>,
+    /// Side played by the engine, or None in force mode
+    engine_side: Option<Player>,
+    analyzing: bool,
+    /// Send thinking output
+    post: bool,
+    /// Time control : moves per session (0 if the base time is for the whole game), and
+    /// increment
+    moves_per_session: u32,
+    increment: Duration,
+    /// Exact time per move (st command)
+    move_time: Option<Duration>,
+    /// Depth limit (sd command)
+    depth_limit: Option<u8>,
+    engine_clock: Option<Duration>,
+    opponent_clock: Option<Duration>,
+    tt: Arc<TranspositionTable>,
+    /// Moved to the search thread while a search is running
+    evaluator: Option<Evaluator>,
+    /// Returns the move played by the search, if any
+    search_thread: Option<JoinHandle<(Evaluator, Option<Move>)>>,
+    stop: Arc<AtomicBool>,
+    /// Set (while holding the output) when the move of the running search must be discarded
+    cancel: Arc<AtomicBool>,
+}
+
+/// Format a search score for thinking output, in centipawns or 100000 + moves to mate
+pub fn get_cecp_score(score: i32) -> i32 {
+    if is_mate_score(score) {
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        score.signum() * (CECP_MATE_SCORE + moves)
+    } else {
+        score
+    }
+}
+
+/// Return the result command to send if the game is over by the rules of chess
+pub fn get_game_result(position: &mut Position, hashes: &[u64]) -> Option<&'static str> {
+    let hash = position.get_zobrist_hash();
+    if gen_legal_moves(position).is_empty() {
+        return Some(match position.current_turn {
+            _ if !is_in_check(position, position.current_turn) => "1/2-1/2 {Stalemate}",
+            Player::White => "0-1 {Black mates}",
+            Player::Black => "1-0 {White mates}",
+        });
+    }
+    if hashes.iter().filter(|previous| **previous == hash).count() >= 2 {
+        return Some("1/2-1/2 {Draw by repetition}");
+    }
+    if position.plys_without_capture >= 100 {
+        return Some("1/2-1/2 {Fifty move rule}");
+    }
+    if position.is_insufficient_material() {
+        return Some("1/2-1/2 {Insufficient material}");
+    }
+    None
+}
+
+/// Parse a time of the level command, in minutes ("5") or minutes and seconds ("0:30")
+fn parse_level_time(time: &str) -> Option<Duration> {
+    let (minutes, seconds) = match time.split_once(':') {
+        Some((minutes, seconds)) => (minutes.parse::<u64>().ok()?, seconds.parse::<u64>().ok()?),
+        None => (time.parse::<u64>().ok()?, 0),
+    };
+    Some(Duration::from_secs(minutes * 60 + seconds))
+}
+
+impl<W: Write + Send + 'static> CecpSession<W> {
+    pub fn new(output: W) -> CecpSession<W> {
+        CecpSession {
+            output: Arc::new(Mutex::new(output)),
+            start: Position::new(),
+            position: Position::new(),
+            moves: Vec::new(),
+            hashes: Vec::new(),
+            engine_side: Some(Player::Black),
+            analyzing: false,
+            post: false,
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            move_time: None,
+            depth_limit: None,
+            engine_clock: None,
+            opponent_clock: None,
+            tt: Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)),
+            evaluator: Some(Evaluator::new()),
+            search_thread: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Return the writer the session outputs to
+    pub fn get_output(&self) -> &Arc<Mutex<W>> {
+        &self.output
+    }
+
+    fn send(&self, line: &str) {
+        send_line(&self.output, line);
+    }
+
+    fn make_move(&mut self, mov: Move) {
+        self.hashes.push(self.position.get_zobrist_hash());
+        self.position.make_move(mov);
+        self.moves.push(mov);
+    }
+
+    fn undo_move(&mut self) {
+        if let Some(mov) = self.moves.pop() {
+            self.position.unmake_move(mov);
+            self.hashes.pop();
+        }
+    }
+
+    /// Wait for the running search (if any) to finish, get the evaluator back, and record
+    /// the move it played
+    pub fn wait_for_search(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            let (evaluator, mov) = search_thread.join().expect("Search thread panicked");
+            self.evaluator = Some(evaluator);
+            if let Some(mov) = mov {
+                self.make_move(mov);
+            }
+        }
+    }
+
+    /// Stop the running search, discarding its move if it wasn't sent yet
+    fn cancel_search(&mut self) {
+        {
+            let _output = self.output.lock().unwrap();
+            self.cancel.store(true, Ordering::Relaxed);
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        self.wait_for_search();
+    }
+
+    /// Search limits of the engine's next move
+    fn get_limits(&self) -> SearchLimits {
+        let moves_to_go = if self.moves_per_session > 0 {
+            let moves_played = self.position.full_move_number - self.start.full_move_number;
+            Some(self.moves_per_session - moves_played as u32 % self.moves_per_session)
+        } else {
+            None
+        };
+        SearchLimits {
+            depth: self.depth_limit,
+            nodes: None,
+            time: self.move_time.or_else(|| {
+                self.engine_clock
+                    .map(|clock| get_time_limit(clock, self.increment, moves_to_go))
+            }),
+        }
+    }
+
+    /// Start a search on its own thread. Analysis searches have no limit and never play
+    /// their move.
+    fn start_search(&mut self, analysis: bool) {
+        let limits = if analysis {
+            SearchLimits::default()
+        } else {
+            self.get_limits()
+        };
+        let post = self.post || analysis;
+        let output = self.output.clone();
+        let stop = self.stop.clone();
+        let cancel = self.cancel.clone();
+        let tt = self.tt.clone();
+        let mut hashes = self.hashes.clone();
+        let root = self.position.clone();
+        let mut evaluator = self.evaluator.take().expect("No search should be running");
+        stop.store(false, Ordering::Relaxed);
+        cancel.store(false, Ordering::Relaxed);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let start = Instant::now();
+            let mut position = root.clone();
+            let context = SearchContext {
+                tt: &tt,
+                stop: &stop,
+                threads: 1,
+                game_hashes: &hashes,
+                tablebase: None,
+            };
+            let result =
+                search_with_callback(&mut position, &mut evaluator, &limits, &context, |result| {
+                    if post {
+                        send_line(
+                            &output,
+                            &format!(
+                                "{} {} {} {} {}",
+                                result.depth,
+                                get_cecp_score(result.score),
+                                start.elapsed().as_millis() / 10,
+                                result.nodes,
+                                get_pv_uci(&root, &result.pv)
+                            ),
+                        );
+                    }
+                });
+
+            let Some(mov) = result.best_move.filter(|_| !analysis) else {
+                return (evaluator, None);
+            };
+            // The output stays locked until the move is sent, so that a cancellation
+            // either happens before (and the move is discarded) or after it
+            let mut output = output.lock().unwrap();
+            if cancel.load(Ordering::Relaxed) {
+                return (evaluator, None);
+            }
+            let _ = writeln!(output, "move {}", get_move_uci(&root, mov));
+            hashes.push(position.get_zobrist_hash());
+            position.make_move(mov);
+            if let Some(result) = get_game_result(&mut position, &hashes) {
+                let _ = writeln!(output, "{}", result);
+            }
+            let _ = output.flush();
+            (evaluator, Some(mov))
+        }));
+    }
+
+    /// Think if it is the engine's turn, or restart the analysis in analyze mode
+    fn think(&mut self) {
+        if self.analyzing {
+            self.start_search(true);
+            return;
+        }
+        if self.engine_side != Some(self.position.current_turn) {
+            return;
+        }
+        if let Some(result) = get_game_result(&mut self.position, &self.hashes) {
+            self.send(result);
+            return;
+        }
+        self.start_search(false);
+    }
+
+    fn send_features(&self) {
+        self.send(&format!(
+            "feature myname=\"{} {}\" ping=1 setboard=1 usermove=1 san=0 time=1 draw=0 \
+             sigint=0 sigterm=0 reuse=1 analyze=1 colors=0 variants=\"normal\" done=1",
+            ENGINE_NAME,
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+
+    /// Play a move of the opponent, in coordinate notation (or SAN)
+    fn user_move(&mut self, mov: &str) {
+        let parsed = parse_uci_move(&mut self.position, mov)
+            .or_else(|| parse_san_move(&mut self.position, mov));
+        match parsed {
+            Some(parsed) => {
+                self.make_move(parsed);
+                self.think();
+            }
+            None => self.send(&format!("Illegal move: {}", mov)),
+        }
+    }
+
+    /// Handle a line of input. Returns false once the engine must quit.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = tokens.split_first() else {
+            return true;
+        };
+        let centiseconds = |value: Option<&&str>| {
+            value
+                .and_then(|value| value.parse::<i64>().ok())
+                .map(|value| Duration::from_millis(value.max(0) as u64 * 10))
+        };
+
+        match *command {
+            "protover" => self.send_features(),
+            "ping" => self.send(&format!("pong {}", args.first().unwrap_or(&""))),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "time" => self.engine_clock = centiseconds(args.first()),
+            "otim" => self.opponent_clock = centiseconds(args.first()),
+            "level" => {
+                if let [moves_per_session, base, increment] = args {
+                    self.moves_per_session = moves_per_session.parse().unwrap_or(0);
+                    let base = parse_level_time(base);
+                    self.engine_clock = base;
+                    self.opponent_clock = base;
+                    self.increment = increment.parse::<f64>().map_or(Duration::ZERO, |seconds| {
+                        Duration::from_secs_f64(seconds.max(0.0))
+                    });
+                    self.move_time = None;
+                }
+            }
+            "st" => {
+                self.move_time = args
+                    .first()
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .map(|seconds| Duration::from_secs_f64(seconds.max(0.001)));
+            }
+            "sd" => {
+                self.depth_limit = args
+                    .first()
+                    .and_then(|depth| depth.parse::<u8>().ok())
+                    .map(|depth| depth.clamp(1, MAX_DEPTH));
+            }
+            // Move now
+            "?" => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.wait_for_search();
+            }
+            "new" => {
+                self.cancel_search();
+                self.start = Position::new();
+                self.position = Position::new();
+                self.moves.clear();
+                self.hashes.clear();
+                self.engine_side = Some(Player::Black);
+                self.depth_limit = None;
+                self.tt.clear();
+                self.think();
+            }
+            "setboard" => {
+                self.cancel_search();
+                match Position::from_fen(&args.join(" ")) {
+                    Ok(position) => {
+                        self.start = position.clone();
+                        self.position = position;
+                        self.moves.clear();
+                        self.hashes.clear();
+                    }
+                    Err(error) => self.send(&format!("tellusererror Illegal position: {}", error)),
+                }
+                self.think();
+            }
+            "usermove" => {
+                self.cancel_search();
+                self.user_move(args.first().unwrap_or(&""));
+            }
+            "go" => {
+                self.cancel_search();
+                self.engine_side = Some(self.position.current_turn);
+                self.think();
+            }
+            "playother" => {
+                self.cancel_search();
+                self.engine_side = Some(invert_player(&self.position.current_turn));
+            }
+            "force" | "result" => {
+                self.cancel_search();
+                self.engine_side = None;
+            }
+            "undo" | "remove" => {
+                self.cancel_search();
+                self.undo_move();
+                if *command == "remove" {
+                    self.undo_move();
+                }
+                if self.analyzing {
+                    self.think();
+                }
+            }
+            "analyze" => {
+                self.cancel_search();
+                self.analyzing = true;
+                self.think();
+            }
+            "exit" => {
+                self.cancel_search();
+                self.analyzing = false;
+            }
+            "quit" => {
+                self.cancel_search();
+                return false;
+            }
+            // Commands of the protocol that don't affect the engine
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" | "rating" | "ics" | "." | "bk" | "hint" | "draw" | "variant" => {}
+            _ => {
+                // Moves may also be sent without the usermove prefix
+                let is_move = parse_uci_move(&mut self.position, command).is_some();
+                if is_move {
+                    self.cancel_search();
+                    self.user_move(command);
+                } else {
+                    self.send(&format!("Error (unknown command): {}", command));
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Run the CECP loop on the standard input and output, until "quit" or the end of the
+/// input. The first line may already have been read (to detect the protocol).
+pub fn run_cecp(first_line: Option<&str>) {
+    let mut session = CecpSession::new(io::stdout());
+    if first_line.is_some_and(|line| !session.handle_command(line)) {
+        return;
+    }
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !session.handle_command(&line) {
+            return;
+        }
+    }
+    session.wait_for_search();
+}
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_cecp() {
+    let mut session = CecpSession::new(Vec::new());
+    let output = session.get_output().clone();
+    let take_output = || String::from_utf8(std::mem::take(&mut *output.lock().unwrap())).unwrap();
+
+    // Handshake
+    session.handle_command("xboard");
+    session.handle_command("protover 2");
+    let lines = take_output();
+    assert!(
+        lines.starts_with("feature myname=\"Krabnik"),
+        "Failed at assert 0"
+    );
+    assert!(
+        lines.contains("usermove=1") && lines.ends_with("done=1\n"),
+        "Failed at assert 1"
+    );
+    session.handle_command("ping 7");
+    assert!(take_output() == "pong 7\n", "Failed at assert 2");
+
+    // Force mode : moves are recorded without answer
+    session.handle_command("new");
+    session.handle_command("force");
+    session.handle_command("usermove e2e4");
+    session.handle_command("e7e5");
+    assert!(session.moves.len() == 2, "Failed at assert 3");
+    session.handle_command("usermove e2e5");
+    assert!(
+        take_output() == "Illegal move: e2e5\n",
+        "Failed at assert 4"
+    );
+    session.handle_command("undo");
+    assert!(
+        session.moves.len() == 1 && session.position.current_turn == Player::Black,
+        "Failed at assert 5"
+    );
+    session.handle_command("remove");
+    assert!(session.moves.is_empty(), "Failed at assert 6");
+
+    // The engine answers moves out of force mode
+    session.handle_command("new");
+    session.handle_command("sd 2");
+    session.handle_command("post");
+    session.handle_command("usermove e2e4");
+    session.wait_for_search();
+    let lines = take_output();
+    assert!(lines.starts_with("1 "), "Failed at assert 7");
+    assert!(lines.contains("\nmove "), "Failed at assert 8");
+    assert!(
+        session.moves.len() == 2 && session.position.current_turn == Player::White,
+        "Failed at assert 9"
+    );
+
+    // go makes the engine play the side to move, and mates are announced
+    session.handle_command("force");
+    session.handle_command("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    session.handle_command("nopost");
+    session.handle_command("go");
+    session.wait_for_search();
+    assert!(
+        take_output() == "move a1a8\n1-0 {White mates}\n",
+        "Failed at assert 10"
+    );
+    session.handle_command("setboard 8/8/8 w - - 0 1");
+    assert!(
+        take_output().starts_with("tellusererror"),
+        "Failed at assert 11"
+    );
+
+    // Analysis is stopped without playing a move
+    session.handle_command("setboard 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+    session.handle_command("analyze");
+    session.handle_command("exit");
+    assert!(
+        session.moves.is_empty() && !take_output().contains("move"),
+        "Failed at assert 12"
+    );
+    assert!(!session.handle_command("quit"), "Failed at assert 13");
+
+    // Time controls
+    session.handle_command("level 40 5 0");
+    session.handle_command("time 3000");
+    assert!(
+        session.get_limits().time
+            == Some(get_time_limit(
+                Duration::from_secs(30),
+                Duration::ZERO,
+                Some(40)
+            )),
+        "Failed at assert 14"
+    );
+    session.handle_command("st 2");
+    assert!(
+        session.get_limits().time == Some(Duration::from_secs(2)),
+        "Failed at assert 15"
+    );
+    assert!(
+        parse_level_time("0:30") == Some(Duration::from_secs(30)),
+        "Failed at assert 16"
+    );
+    assert!(
+        get_cecp_score(MATE_SCORE - 3) == 100002,
+        "Failed at assert 17"
+    );
+}
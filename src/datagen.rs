@@ -0,0 +1,162 @@
+/*
+ * The datagen module generates training data for the evaluation (NNUE training and Texel
+ * tuning) by making the engine play against itself.
+ *
+ * It is run with "krabnik datagen <output>", which plays games from randomized openings
+ * with a fixed node or depth limit on several threads, and writes the quiet positions
+ * along with their search score and the game result, in a text or a binary format (both
+ * are described in the records module). Games only depend on the seed, so the same command
+ * always generates the same data, whatever the number of threads.
+ */
+
+pub mod random;
+pub mod records;
+pub mod self_play;
+
+#[allow(unused_imports)]
+pub use random::*;
+pub use records::*;
+pub use self_play::*;
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_datagen() {
+    use crate::board_representation::*;
+    use crate::evaluation::*;
+    use crate::move_generation::*;
+    use crate::pgn::*;
+    use crate::search::*;
+
+    // Text format
+    let line = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 | -25 | 0.5";
+    let record = DataRecord::from_text(line).unwrap();
+    assert!(record.score == -25, "Failed at assert 0");
+    assert!(record.result == GameResult::Draw, "Failed at assert 1");
+    assert!(record.to_text() == line, "Failed at assert 2");
+    assert!(
+        DataRecord::from_text("8/8/8/8/8/8/8/8 w - - 0 1 | 0").is_err(),
+        "Failed at assert 3"
+    );
+
+    // Binary format round trips, including en passant and Chess960 castling rights
+    for fen in [
+        STARTING_FEN,
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "1r2k3/8/8/8/8/8/8/RR2K3 w Bq - 12 40",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9",
+    ] {
+        let record = DataRecord {
+            position: Position::from_fen(fen).unwrap(),
+            score: 1234,
+            result: GameResult::BlackWins,
+        };
+        let bytes = record.to_binary();
+        let decoded = DataRecord::from_binary(&bytes).unwrap();
+        assert!(decoded.position.to_fen() == fen, "Failed at assert 4");
+        assert!(decoded.score == 1234, "Failed at assert 5");
+        assert!(
+            decoded.result == GameResult::BlackWins,
+            "Failed at assert 6"
+        );
+    }
+    assert!(
+        DataRecord::from_binary(&[0xff; BINARY_RECORD_SIZE]).is_err(),
+        "Failed at assert 7"
+    );
+    assert!(
+        read_binary_records(&[0; BINARY_RECORD_SIZE + 1]).is_err(),
+        "Failed at assert 8"
+    );
+
+    // Generation is reproducible from the seed, whatever the number of threads
+    let options = DatagenOptions {
+        games: 3,
+        threads: 1,
+        seed: 42,
+        limits: SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        },
+        max_plies: 40,
+        ..DatagenOptions::default()
+    };
+    let generate = |options: &DatagenOptions| {
+        let mut lines = Vec::new();
+        generate_data(options, &[], None, |_, records| {
+            lines.extend(records.iter().map(DataRecord::to_text))
+        })
+        .unwrap();
+        lines
+    };
+    let lines = generate(&options);
+    assert!(!lines.is_empty(), "Failed at assert 9");
+    assert!(
+        lines
+            == generate(&DatagenOptions {
+                threads: 3,
+                ..options.clone()
+            }),
+        "Failed at assert 10"
+    );
+    assert!(
+        lines
+            != generate(&DatagenOptions {
+                seed: 43,
+                ..options.clone()
+            }),
+        "Failed at assert 11"
+    );
+
+    // Noisy positions are filtered out
+    for line in &lines {
+        let record = DataRecord::from_text(line).unwrap();
+        assert!(
+            !is_in_check(&record.position, record.position.current_turn),
+            "Failed at assert 12"
+        );
+    }
+
+    // Command line
+    let args: Vec<String> = [
+        "data.bin", "--format", "binary", "--depth", "6", "--seed", "7",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let options = DatagenOptions::from_args(&args).unwrap();
+    assert!(options.format == DataFormat::Binary, "Failed at assert 13");
+    assert!(
+        options.limits
+            == SearchLimits {
+                depth: Some(6),
+                ..SearchLimits::default()
+            },
+        "Failed at assert 14"
+    );
+    assert!(options.seed == 7, "Failed at assert 15");
+    assert!(
+        DatagenOptions::from_args(&["--games".to_string()]).is_err(),
+        "Failed at assert 16"
+    );
+
+    // Books must leave a game to play
+    let stalemate = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1";
+    assert!(
+        read_book(&format!("{}\n{}\n", STARTING_FEN, stalemate)).is_err(),
+        "Failed at assert 17"
+    );
+    let book = [Position::from_fen(stalemate).unwrap()];
+    let mut rng = Rng::for_game(options.seed, 0);
+    assert!(
+        play_self_play_game(&book, &options, &mut Evaluator::new(), &mut rng)
+            .is_err_and(|error| error == DatagenError::NoOpening),
+        "Failed at assert 18"
+    );
+    assert!(
+        generate_data(&options, &book, None, |_, _| {}) == Err(DatagenError::NoOpening),
+        "Failed at assert 19"
+    );
+}
@@ -0,0 +1,41 @@
+#![allow(dead_code)]
+
+/***************
+* RANDOM NUMBERS
+****************/
+
+// Data generation has to be reproducible from a seed, so it uses its own small generator
+// rather than a system source of randomness.
+// See : <https://prng.di.unimi.it/splitmix64.c>
+
+/// SplitMix64 pseudo-random number generator
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Initialize the generator of one game, so that each game only depends on the seed and
+    /// its index (and not on the thread that plays it)
+    pub fn for_game(seed: u64, game_index: u64) -> Rng {
+        let mut rng = Rng::new(seed ^ game_index.wrapping_mul(0xd1b54a32d192ed03));
+        Rng::new(rng.next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Return a number in [0, n). n must not be 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
@@ -0,0 +1,242 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use crate::board_representation::*;
+use crate::pgn::*;
+
+/*********
+* RECORDS
+**********/
+
+// Each generated position is stored with its search score and the result of its game, in
+// one of two formats.
+//
+// Text format, one record per line :
+//     <FEN> | <score> | <result>
+// where the score is in centipawns from White's point of view, and the result is 1.0 for a
+// White win, 0.5 for a draw and 0.0 for a Black win. For instance :
+//     rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 | 32 | 0.5
+//
+// Binary format, fixed size records of 36 bytes (multi-byte values are little-endian, and
+// squares are indexed from 0 for a1 to 63 for h8, ie 8 * rank + file) :
+//     0..8    occupancy : bit i is set if square i is occupied
+//     8..24   pieces : one nibble per occupied square, in increasing square order (low
+//             nibble first), containing its PieceCode (1 to 12)
+//     24..26  score : i16, in centipawns from White's point of view
+//     26      result : 0 for a Black win, 1 for a draw, 2 for a White win
+//     27      flags : bit 0 is set if Black is to move, bit 1 for Chess960 positions
+//     28      en passant target square, or 255
+//     29      plies since the last capture or pawn move
+//     30..32  full move number : u16
+//     32..36  castling rook files (0 to 7, or 255) : White kingside, White queenside,
+//             Black kingside, Black queenside
+
+/// Size of a record in the binary format
+pub const BINARY_RECORD_SIZE: usize = 36;
+
+const NO_SQUARE: u8 = 255;
+
+/// A position generated by self-play
+#[derive(Clone, Debug)]
+pub struct DataRecord {
+    pub position: Position,
+    /// Search score, in centipawns from White's point of view
+    pub score: i16,
+    /// Result of the game the position was taken from
+    pub result: GameResult,
+}
+
+/// Format of the generated data
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DataFormat {
+    Text,
+    Binary,
+}
+
+/// Errors that can occur while generating or reading data
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DatagenError {
+    /// A file can't be read or written
+    Io(String),
+    /// The command line arguments are invalid
+    InvalidArgument(String),
+    /// A record (or book line) can't be parsed
+    InvalidRecord(String),
+    /// Every random opening tried for a game ended the game before it could start
+    NoOpening,
+}
+
+impl fmt::Display for DatagenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatagenError::Io(error) => write!(f, "{}", error),
+            DatagenError::InvalidArgument(argument) => write!(f, "invalid argument: {}", argument),
+            DatagenError::InvalidRecord(record) => write!(f, "invalid record: {}", record),
+            DatagenError::NoOpening => write!(f, "no playable opening found"),
+        }
+    }
+}
+
+impl std::error::Error for DatagenError {}
+
+/*****************
+* TEXT CONVERSION
+******************/
+
+impl DataRecord {
+    /// Write the record in the text format (without line break)
+    pub fn to_text(&self) -> String {
+        let result = match self.result {
+            GameResult::WhiteWins => "1.0",
+            GameResult::BlackWins => "0.0",
+            _ => "0.5",
+        };
+        format!("{} | {} | {}", self.position.to_fen(), self.score, result)
+    }
+
+    /// Parse a line of the text format
+    pub fn from_text(line: &str) -> Result<DataRecord, DatagenError> {
+        let invalid = || DatagenError::InvalidRecord(line.to_string());
+
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let [fen, score, result] = fields[..] else {
+            return Err(invalid());
+        };
+        Ok(DataRecord {
+            position: Position::from_fen(fen).map_err(|_| invalid())?,
+            score: score.parse().map_err(|_| invalid())?,
+            result: match result {
+                "1.0" | "1" => GameResult::WhiteWins,
+                "0.5" => GameResult::Draw,
+                "0.0" | "0" => GameResult::BlackWins,
+                _ => return Err(invalid()),
+            },
+        })
+    }
+}
+
+/*******************
+* BINARY CONVERSION
+********************/
+
+impl DataRecord {
+    /// Write the record in the binary format. Positions have at most 32 pieces.
+    pub fn to_binary(&self) -> [u8; BINARY_RECORD_SIZE] {
+        let position = &self.position;
+        let mut bytes = [0; BINARY_RECORD_SIZE];
+
+        let mut occupancy: u64 = 0;
+        let mut piece_count = 0;
+        for square in 0..64 {
+            let piece = position.square_centric_board.main_board[square];
+            if piece == PieceCode::ES {
+                continue;
+            }
+            assert!(piece_count < 32, "Too many pieces to encode the position");
+            occupancy |= 1 << square;
+            bytes[8 + piece_count / 2] |= (piece as u8) << (4 * (piece_count % 2));
+            piece_count += 1;
+        }
+        bytes[0..8].copy_from_slice(&occupancy.to_le_bytes());
+
+        bytes[24..26].copy_from_slice(&self.score.to_le_bytes());
+        bytes[26] = match self.result {
+            GameResult::WhiteWins => 2,
+            GameResult::BlackWins => 0,
+            _ => 1,
+        };
+        bytes[27] = (position.current_turn == Player::Black) as u8 | (position.chess960 as u8) << 1;
+        bytes[28] = position
+            .get_en_passant_square()
+            .map_or(NO_SQUARE, |coord| (coord.r << 3) + coord.f);
+        bytes[29] = position.plys_without_capture;
+        bytes[30..32].copy_from_slice(&position.full_move_number.to_le_bytes());
+
+        let castling_rooks = [
+            position.kingside_castling_rook[Player::White as usize],
+            position.queenside_castling_rook[Player::White as usize],
+            position.kingside_castling_rook[Player::Black as usize],
+            position.queenside_castling_rook[Player::Black as usize],
+        ];
+        for (i, rook) in castling_rooks.iter().enumerate() {
+            bytes[32 + i] = rook.unwrap_or(NO_SQUARE);
+        }
+
+        bytes
+    }
+
+    /// Read a record of the binary format
+    pub fn from_binary(bytes: &[u8]) -> Result<DataRecord, DatagenError> {
+        let invalid = || DatagenError::InvalidRecord(format!("{:02x?}", bytes));
+        if bytes.len() != BINARY_RECORD_SIZE {
+            return Err(invalid());
+        }
+
+        let mut position = Position::empty();
+        let occupancy = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        if occupancy.count_ones() > 32 {
+            return Err(invalid());
+        }
+        let mut piece_count = 0;
+        for square in 0..64 {
+            if occupancy & (1 << square) == 0 {
+                continue;
+            }
+            let code = (bytes[8 + piece_count / 2] >> (4 * (piece_count % 2))) & 0xf;
+            if !(1..=12).contains(&code) {
+                return Err(invalid());
+            }
+            position.square_centric_board.main_board[square] = PieceCode::from_u8(code);
+            piece_count += 1;
+        }
+        position.apply_own_mailbox();
+
+        let result = match bytes[26] {
+            0 => GameResult::BlackWins,
+            1 => GameResult::Draw,
+            2 => GameResult::WhiteWins,
+            _ => return Err(invalid()),
+        };
+        if bytes[27] & 1 != 0 {
+            position.current_turn = Player::Black;
+        }
+        position.chess960 = bytes[27] & 2 != 0;
+        position.set_en_passant_square(match bytes[28] {
+            NO_SQUARE => None,
+            square if square < 64 => Some(Coord::new(square & 7, square >> 3)),
+            _ => return Err(invalid()),
+        });
+        position.plys_without_capture = bytes[29];
+        position.full_move_number = u16::from_le_bytes([bytes[30], bytes[31]]);
+
+        let rook = |byte: u8| match byte {
+            NO_SQUARE => Ok(None),
+            file if file < 8 => Ok(Some(file)),
+            _ => Err(invalid()),
+        };
+        position.kingside_castling_rook = [rook(bytes[32])?, rook(bytes[34])?];
+        position.queenside_castling_rook = [rook(bytes[33])?, rook(bytes[35])?];
+        position.update_zobrist_hash();
+
+        Ok(DataRecord {
+            position,
+            score: i16::from_le_bytes([bytes[24], bytes[25]]),
+            result,
+        })
+    }
+}
+
+/// Read all the records of a file in the binary format
+pub fn read_binary_records(bytes: &[u8]) -> Result<Vec<DataRecord>, DatagenError> {
+    if !bytes.len().is_multiple_of(BINARY_RECORD_SIZE) {
+        return Err(DatagenError::InvalidRecord(
+            "truncated binary record".to_string(),
+        ));
+    }
+    bytes
+        .chunks(BINARY_RECORD_SIZE)
+        .map(DataRecord::from_binary)
+        .collect()
+}
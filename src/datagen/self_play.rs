@@ -0,0 +1,402 @@
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use super::random::*;
+use super::records::*;
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::pgn::*;
+use crate::search::*;
+
+/***********
+* SELF-PLAY
+************/
+
+// Games start from the initial position (or a random position of the opening book),
+// followed by a few random moves so that games don't repeat. The engine then plays against
+// itself with a fixed node or depth limit, which keeps games reproducible, and the searched
+// positions are recorded with their score. Positions that are hard to evaluate statically
+// are filtered out : positions in check, positions whose best move is a capture or a
+// promotion, and mate scores.
+
+/// Node limit used when neither a node nor a depth limit is given
+pub const DEFAULT_DATAGEN_NODES: u64 = 5000;
+
+/// Size in MiB of the transposition table of each game
+const DATAGEN_HASH_SIZE: usize = 4;
+
+/// Number of random openings tried for a game before giving up
+pub const MAX_OPENING_ATTEMPTS: usize = 1000;
+
+/// Settings of the datagen command
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatagenOptions {
+    pub output: String,
+    pub format: DataFormat,
+    pub games: u64,
+    pub threads: usize,
+    pub seed: u64,
+    /// Search limits of each move (only node and depth limits keep games reproducible)
+    pub limits: SearchLimits,
+    /// Number of random moves played at the start of each game
+    pub random_plies: usize,
+    /// File of FEN or EPD positions games start from (one per line)
+    pub book: Option<String>,
+    /// Games reaching this number of plies are adjudicated as draws
+    pub max_plies: usize,
+    /// Network used for the evaluation (handcrafted evaluation if None)
+    pub eval_file: Option<String>,
+}
+
+impl Default for DatagenOptions {
+    fn default() -> DatagenOptions {
+        DatagenOptions {
+            output: String::new(),
+            format: DataFormat::Text,
+            games: 100,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+            limits: SearchLimits {
+                nodes: Some(DEFAULT_DATAGEN_NODES),
+                ..SearchLimits::default()
+            },
+            random_plies: 8,
+            book: None,
+            max_plies: 400,
+            eval_file: None,
+        }
+    }
+}
+
+/// Play random moves from the starting position. Returns None if the game ended during the
+/// random moves.
+fn play_random_opening(start: &Position, plies: usize, rng: &mut Rng) -> Option<Position> {
+    let mut position = start.clone();
+    for _ in 0..plies {
+        let moves = gen_legal_moves(&mut position);
+        if moves.is_empty() {
+            return None;
+        }
+        position.make_move(moves[rng.below(moves.len())]);
+    }
+    if gen_legal_moves(&mut position).is_empty() {
+        return None;
+    }
+    position.history.clear();
+    Some(position)
+}
+
+/// Play a game of the engine against itself, and return the recorded positions. The game
+/// only depends on the starting positions, the options and the state of the generator.
+/// Fails if none of the random openings tried leaves a game to play.
+pub fn play_self_play_game(
+    book: &[Position],
+    options: &DatagenOptions,
+    evaluator: &mut Evaluator,
+    rng: &mut Rng,
+) -> Result<Vec<DataRecord>, DatagenError> {
+    let default_start = Position::new();
+    let mut position = (0..MAX_OPENING_ATTEMPTS)
+        .find_map(|_| {
+            let start = if book.is_empty() {
+                &default_start
+            } else {
+                &book[rng.below(book.len())]
+            };
+            play_random_opening(start, options.random_plies, rng)
+        })
+        .ok_or(DatagenError::NoOpening)?;
+
+    let mut samples: Vec<(Position, i16)> = Vec::new();
+    let mut repetitions: HashMap<u64, u8> = HashMap::new();
+    let mut game_hashes = Vec::new();
+    let tt = TranspositionTable::new(DATAGEN_HASH_SIZE);
+    let stop = AtomicBool::new(false);
+    let mut plies = 0;
+    let result = loop {
+        // Draws by rule or adjudication
+        let repetition_count = repetitions.entry(position.get_zobrist_hash()).or_insert(0);
+        *repetition_count += 1;
+        if *repetition_count >= 3
+            || position.plys_without_capture >= 100
+            || plies >= options.max_plies
+            || evaluate_endgame(&position) == Some(EndgameEvaluation::Score(0))
+        {
+            break GameResult::Draw;
+        }
+
+        let context = SearchContext {
+            tt: &tt,
+            stop: &stop,
+            threads: 1,
+            game_hashes: &game_hashes,
+            tablebase: None,
+        };
+        let search_result =
+            search_with_callback(&mut position, evaluator, &options.limits, &context, |_| {});
+        let in_check = is_in_check(&position, position.current_turn);
+        let Some(best_move) = search_result.best_move else {
+            break match (in_check, position.current_turn) {
+                (false, _) => GameResult::Draw,
+                (true, Player::White) => GameResult::BlackWins,
+                (true, Player::Black) => GameResult::WhiteWins,
+            };
+        };
+
+        let is_noisy = in_check
+            || get_move_capture(best_move)
+            || get_move_promotion(best_move)
+            || is_mate_score(search_result.score);
+        if !is_noisy {
+            let score = match position.current_turn {
+                Player::White => search_result.score,
+                Player::Black => -search_result.score,
+            };
+            let mut sample = position.clone();
+            sample.history.clear();
+            samples.push((sample, score.clamp(i16::MIN as i32, i16::MAX as i32) as i16));
+        }
+
+        game_hashes.push(position.get_zobrist_hash());
+        position.make_move(best_move);
+        plies += 1;
+    };
+
+    Ok(samples
+        .into_iter()
+        .map(|(position, score)| DataRecord {
+            position,
+            score,
+            result,
+        })
+        .collect())
+}
+
+/// Play the games on several threads. The callback receives the records of each game, in
+/// the order of the games, so the output only depends on the seed (and not on the number
+/// of threads). Generation stops at the first game that can't be played.
+pub fn generate_data<F: FnMut(u64, Vec<DataRecord>)>(
+    options: &DatagenOptions,
+    book: &[Position],
+    network: Option<Arc<Network>>,
+    mut callback: F,
+) -> Result<(), DatagenError> {
+    let threads = options.threads.max(1) as u64;
+    let (sender, receiver) = mpsc::channel();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        for thread_index in 0..threads {
+            let sender = sender.clone();
+            let network = network.clone();
+            let stop = &stop;
+            scope.spawn(move || {
+                let mut evaluator = match network {
+                    Some(network) => Evaluator::with_network(network),
+                    None => Evaluator::new(),
+                };
+                for game_index in (thread_index..options.games).step_by(threads as usize) {
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let mut rng = Rng::for_game(options.seed, game_index);
+                    let records = play_self_play_game(book, options, &mut evaluator, &mut rng);
+                    if sender.send((game_index, records)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Games finishing out of order wait until the previous ones are done
+        let mut pending = BTreeMap::new();
+        let mut next_game = 0;
+        for (game_index, records) in receiver {
+            pending.insert(game_index, records);
+            while let Some(records) = pending.remove(&next_game) {
+                match records {
+                    Ok(records) => callback(next_game, records),
+                    Err(error) => {
+                        stop.store(true, Ordering::Relaxed);
+                        return Err(error);
+                    }
+                }
+                next_game += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+/****************
+* DATAGEN COMMAND
+*****************/
+
+impl DatagenOptions {
+    /// Parse the arguments of the datagen command :
+    /// krabnik datagen <output> [--format text|binary] [--games <n>] [--threads <n>]
+    /// [--seed <n>] [--nodes <n>] [--depth <n>] [--random-plies <n>] [--book <file>]
+    /// [--max-plies <n>] [--eval-file <file>]
+    pub fn from_args(args: &[String]) -> Result<DatagenOptions, DatagenError> {
+        let mut options = DatagenOptions::default();
+        let mut nodes = None;
+        let mut depth = None;
+        let mut args = args.iter();
+        let invalid = |arg: &str| DatagenError::InvalidArgument(arg.to_string());
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| invalid(arg));
+            match arg.as_str() {
+                "--format" => {
+                    options.format = match value()?.as_str() {
+                        "text" => DataFormat::Text,
+                        "binary" => DataFormat::Binary,
+                        format => return Err(invalid(format)),
+                    }
+                }
+                "--games" => {
+                    let value = value()?;
+                    options.games = value.parse().map_err(|_| invalid(value))?;
+                }
+                "--threads" => {
+                    let value = value()?;
+                    options.threads = value
+                        .parse()
+                        .ok()
+                        .filter(|threads| *threads > 0)
+                        .ok_or_else(|| invalid(value))?;
+                }
+                "--seed" => {
+                    let value = value()?;
+                    options.seed = value.parse().map_err(|_| invalid(value))?;
+                }
+                "--nodes" => {
+                    let value = value()?;
+                    nodes = Some(value.parse().map_err(|_| invalid(value))?);
+                }
+                "--depth" => {
+                    let value = value()?;
+                    depth = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|depth| *depth > 0)
+                            .ok_or_else(|| invalid(value))?,
+                    );
+                }
+                "--random-plies" => {
+                    let value = value()?;
+                    options.random_plies = value.parse().map_err(|_| invalid(value))?;
+                }
+                "--book" => options.book = Some(value()?.clone()),
+                "--max-plies" => {
+                    let value = value()?;
+                    options.max_plies = value.parse().map_err(|_| invalid(value))?;
+                }
+                "--eval-file" => options.eval_file = Some(value()?.clone()),
+                _ if arg.starts_with("--") || !options.output.is_empty() => {
+                    return Err(invalid(arg))
+                }
+                _ => options.output = arg.clone(),
+            }
+        }
+
+        if nodes.is_some() || depth.is_some() {
+            options.limits = SearchLimits {
+                nodes,
+                depth,
+                ..SearchLimits::default()
+            };
+        }
+        if options.output.is_empty() {
+            return Err(DatagenError::InvalidArgument(
+                "missing output path".to_string(),
+            ));
+        }
+        Ok(options)
+    }
+}
+
+/// Read an opening book : one position per line, either as a FEN or an EPD record. Empty
+/// lines are ignored, and positions without legal moves are rejected.
+pub fn read_book(book: &str) -> Result<Vec<Position>, DatagenError> {
+    book.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            Position::from_fen(line)
+                .or_else(|_| EpdRecord::from_epd(line).map(|record| record.position))
+                .ok()
+                .filter(|position| !gen_legal_moves(&mut position.clone()).is_empty())
+                .ok_or_else(|| DatagenError::InvalidRecord(line.to_string()))
+        })
+        .collect()
+}
+
+/// Run the datagen command : play the self-play games and write the records to the output
+/// file
+pub fn run_datagen_command(args: &[String]) -> Result<(), DatagenError> {
+    let options = DatagenOptions::from_args(args)?;
+    let io_error =
+        |path: &str, error: std::io::Error| DatagenError::Io(format!("{}: {}", path, error));
+
+    let book = match &options.book {
+        Some(path) => read_book(&fs::read_to_string(path).map_err(|error| io_error(path, error))?)?,
+        None => Vec::new(),
+    };
+    let network = match &options.eval_file {
+        Some(path) => {
+            Some(Arc::new(Network::load(path).map_err(|error| {
+                DatagenError::Io(format!("{}: {}", path, error))
+            })?))
+        }
+        None => None,
+    };
+    get_kpk_bitbase();
+
+    let file =
+        fs::File::create(&options.output).map_err(|error| io_error(&options.output, error))?;
+    let mut writer = BufWriter::new(file);
+    let mut write_result = Ok(());
+    let mut position_count = 0;
+
+    generate_data(&options, &book, network, |game_index, records| {
+        if write_result.is_err() {
+            return;
+        }
+        for record in &records {
+            write_result = match options.format {
+                DataFormat::Text => writeln!(writer, "{}", record.to_text()),
+                DataFormat::Binary => writer.write_all(&record.to_binary()),
+            };
+            if write_result.is_err() {
+                return;
+            }
+        }
+        position_count += records.len();
+        if (game_index + 1).is_multiple_of(10) || game_index + 1 == options.games {
+            println!(
+                "{} / {} games, {} positions",
+                game_index + 1,
+                options.games,
+                position_count
+            );
+        }
+    })?;
+
+    write_result
+        .and_then(|_| writer.flush())
+        .map_err(|error| io_error(&options.output, error))?;
+    println!("{} positions written to {}", position_count, options.output);
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+/*
+ * The engine_match module runs matches between two UCI engines (for instance two builds of
+ * Krabnik), to check whether a change makes the engine stronger.
+ *
+ * It is run with "krabnik match <engine1> <engine2>", which starts the engines as child
+ * processes and plays pairs of games from an opening file, each engine playing both colors.
+ * Games are adjudicated by the rules of chess and optional draw, resign and tablebase
+ * rules, and written to a PGN file. After each pair, the Elo difference (with its 95%
+ * confidence interval) and the log-likelihood ratio of the SPRT are reported, and the
+ * match stops as soon as the SPRT accepts one of its hypotheses.
+ */
+
+pub mod engine_process;
+pub mod runner;
+pub mod statistics;
+
+#[allow(unused_imports)]
+pub use engine_process::*;
+pub use runner::*;
+#[allow(unused_imports)]
+pub use statistics::*;
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_engine_match() {
+    use crate::board_representation::*;
+    use crate::search::*;
+    use std::time::Duration;
+
+    // Elo and SPRT
+    assert!(elo_to_score(0.0) == 0.5, "Failed at assert 0");
+    assert!(
+        (score_to_elo(elo_to_score(35.0)) - 35.0).abs() < 1e-9,
+        "Failed at assert 1"
+    );
+    let mut statistics = MatchStatistics::new();
+    assert!(
+        statistics.get_llr(&SprtParameters::default()) == 0.0,
+        "Failed at assert 2"
+    );
+    for _ in 0..100 {
+        statistics.add_pair(1.0, 0.5);
+        statistics.add_pair(0.5, 0.5);
+        statistics.add_pair(0.0, 0.5);
+        statistics.add_pair(1.0, 1.0);
+    }
+    assert!(
+        statistics.games() == 800 && statistics.wins == 300 && statistics.losses == 100,
+        "Failed at assert 3"
+    );
+    assert!(
+        statistics.pairs == [0, 100, 100, 100, 100],
+        "Failed at assert 4"
+    );
+    let (elo, error) = statistics.get_elo();
+    assert!(
+        (statistics.get_score() - 0.625).abs() < 1e-9 && (elo - score_to_elo(0.625)).abs() < 1e-9,
+        "Failed at assert 5"
+    );
+    assert!(error > 10.0 && error < 40.0, "Failed at assert 6");
+    let sprt = SprtParameters::default();
+    let llr = statistics.get_llr(&sprt);
+    assert!(
+        sprt.get_result(llr) == SprtResult::AcceptH1,
+        "Failed at assert 7"
+    );
+    let (lower, upper) = sprt.get_bounds();
+    assert!(
+        (lower + 2.944).abs() < 1e-3 && (upper - 2.944).abs() < 1e-3,
+        "Failed at assert 8"
+    );
+    let mut statistics = MatchStatistics::new();
+    for _ in 0..100 {
+        statistics.add_pair(0.5, 0.0);
+        statistics.add_pair(0.5, 0.5);
+    }
+    assert!(
+        sprt.get_result(statistics.get_llr(&sprt)) == SprtResult::AcceptH0,
+        "Failed at assert 9"
+    );
+
+    // Engine output parsing
+    assert!(
+        parse_info_score("info depth 5 score cp -31 nodes 1000 pv e2e4") == Some(-31),
+        "Failed at assert 10"
+    );
+    assert!(
+        parse_info_score("info depth 5 score mate 2 pv a1a8") == Some(MATE_SCORE - 3),
+        "Failed at assert 11"
+    );
+    assert!(
+        parse_info_score("info depth 5 score mate -1") == Some(-MATE_SCORE + 2),
+        "Failed at assert 12"
+    );
+    assert!(
+        parse_info_score("info nodes 100").is_none(),
+        "Failed at assert 13"
+    );
+
+    // Openings, from PGN or FEN lines
+    let openings = read_openings("[Event \"?\"]\n\n1. e4 e5 2. Nf3 *\n\n1. d4 d5 *\n").unwrap();
+    assert!(
+        openings.len() == 2 && openings[0].moves.len() == 3,
+        "Failed at assert 14"
+    );
+    let openings = read_openings(&format!(
+        "{}\n\n4k3/8/8/8/8/8/8/4K2R w K - 0 1\n",
+        STARTING_FEN
+    ))
+    .unwrap();
+    assert!(
+        openings.len() == 2 && openings[1].moves.is_empty(),
+        "Failed at assert 15"
+    );
+    assert!(read_openings("1. e4 e4 *").is_err(), "Failed at assert 16");
+
+    // Command line
+    let args: Vec<String> = [
+        "./new",
+        "./old --flag",
+        "--tc",
+        "5+0.05",
+        "--draw",
+        "40",
+        "8",
+        "10",
+        "--option1",
+        "Use NNUE=false",
+        "--elo1",
+        "3",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let options = MatchOptions::from_args(&args).unwrap();
+    assert!(
+        options.engines == ["./new", "./old --flag"],
+        "Failed at assert 17"
+    );
+    assert!(
+        options.time_control
+            == TimeControl::Fischer {
+                base: Duration::from_secs(5),
+                increment: Duration::from_millis(50)
+            },
+        "Failed at assert 18"
+    );
+    assert!(
+        options.draw_adjudication
+            == Some(DrawAdjudication {
+                move_number: 40,
+                move_count: 8,
+                score: 10
+            }),
+        "Failed at assert 19"
+    );
+    assert!(
+        options.engine_options[0] == [("Use NNUE".to_string(), "false".to_string())],
+        "Failed at assert 20"
+    );
+    assert!(options.sprt.elo1 == 3.0, "Failed at assert 21");
+    assert!(
+        MatchOptions::from_args(&args[..1]).is_err(),
+        "Failed at assert 22"
+    );
+    assert!(
+        MatchOptions::from_args(&[&args[..2], &["--elo1".to_string(), "-1".to_string()]].concat())
+            .is_err(),
+        "Failed at assert 23"
+    );
+}
@@ -0,0 +1,213 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::search::*;
+
+/****************
+* ENGINE PROCESS
+*****************/
+
+/// Time given to engines to answer "uci" and "isready"
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors that can occur while talking to an engine
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EngineError {
+    /// The process can't be started
+    Spawn(String),
+    /// The process exited or closed its output
+    Disconnected,
+    /// The engine didn't answer in time
+    Timeout,
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Spawn(error) => write!(f, "can't start engine: {}", error),
+            EngineError::Disconnected => write!(f, "engine disconnected"),
+            EngineError::Timeout => write!(f, "engine timed out"),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Answer of an engine to a go command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EngineMove {
+    /// Best move, in UCI notation
+    pub uci: String,
+    /// Last score sent by the engine, from its point of view. Mate scores are converted to
+    /// the MATE_SCORE convention of the search.
+    pub score: Option<i32>,
+    pub elapsed: Duration,
+}
+
+/// A UCI engine running in a child process. Its output is read by a dedicated thread, so
+/// that waiting for an answer can time out.
+pub struct EngineProcess {
+    /// Name sent by the engine ("id name"), or its command if it didn't send one
+    pub name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+/// Parse the score of an info line ("score cp <x>" or "score mate <n>")
+pub fn parse_info_score(line: &str) -> Option<i32> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let index = tokens.iter().position(|token| *token == "score")?;
+    let value: i32 = tokens.get(index + 2)?.parse().ok()?;
+    match *tokens.get(index + 1)? {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE_SCORE - (2 * value - 1)),
+        "mate" => Some(-MATE_SCORE - 2 * value),
+        _ => None,
+    }
+}
+
+impl EngineProcess {
+    /// Start an engine from its command line (program followed by its arguments), and go
+    /// through the UCI handshake
+    pub fn start(command: &str) -> Result<EngineProcess, EngineError> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| EngineError::Spawn("empty command".to_string()))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| EngineError::Spawn(format!("{}: {}", command, error)))?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = EngineProcess {
+            name: command.to_string(),
+            child,
+            stdin,
+            lines,
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.receive(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+        Ok(engine)
+    }
+
+    /// Send a command to the engine
+    pub fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| EngineError::Disconnected)
+    }
+
+    /// Wait for the next line of the engine
+    fn receive(&mut self, deadline: Instant) -> Result<String, EngineError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines
+            .recv_timeout(timeout)
+            .map_err(|error| match error {
+                RecvTimeoutError::Timeout => EngineError::Timeout,
+                RecvTimeoutError::Disconnected => EngineError::Disconnected,
+            })
+    }
+
+    /// Send "isready" and wait for "readyok"
+    pub fn wait_until_ready(&mut self) -> Result<(), EngineError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.receive(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    /// Prepare the engine for a new game
+    pub fn new_game(&mut self) -> Result<(), EngineError> {
+        self.send("ucinewgame")?;
+        self.wait_until_ready()
+    }
+
+    /// Send the position (starting FEN and moves in UCI notation) and the go command, then
+    /// wait for the best move. The timeout applies to the whole search.
+    pub fn search(
+        &mut self,
+        fen: &str,
+        moves: &[String],
+        go: &str,
+        timeout: Duration,
+    ) -> Result<EngineMove, EngineError> {
+        let mut position = format!("position fen {}", fen);
+        if !moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&moves.join(" "));
+        }
+        self.send(&position)?;
+
+        let start = Instant::now();
+        self.send(go)?;
+        let deadline = start + timeout;
+        let mut score = None;
+        loop {
+            let line = self.receive(deadline)?;
+            if line.starts_with("info") {
+                score = parse_info_score(&line).or(score);
+            } else if let Some(best_move) = line.strip_prefix("bestmove") {
+                let uci = best_move.split_whitespace().next().unwrap_or("");
+                return Ok(EngineMove {
+                    uci: uci.to_string(),
+                    score,
+                    elapsed: start.elapsed(),
+                });
+            }
+        }
+    }
+}
+
+impl Drop for EngineProcess {
+    /// Ask the engine to quit, and kill it if it doesn't
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
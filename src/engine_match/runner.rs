@@ -0,0 +1,791 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use super::engine_process::*;
+use super::statistics::*;
+use crate::board_representation::*;
+use crate::datagen::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::pgn::*;
+use crate::tablebase::*;
+
+/**************
+* MATCH RUNNER
+***************/
+
+// Two UCI engines play pairs of games from the same opening, each engine playing both
+// colors. Games end by the rules of chess (checkmate, stalemate, threefold repetition,
+// fifty-move rule, insufficient material), by the fault of an engine (time forfeit, illegal
+// move, crash), or by adjudication :
+// - draw : both engines report a score within [-score, score] for movecount consecutive
+// moves each, after the given move number
+// - resign : an engine reports a score below -score for movecount consecutive moves, while
+// its opponent reports a score above score
+// - tablebase : the position is in the Syzygy tablebase given with --syzygy-path (cursed
+// wins and blessed losses are draws), or in the KPK bitbase.
+
+/// Timeout of searches that aren't limited by the clock (node or depth limits)
+pub const UNLIMITED_SEARCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Errors that can stop a match
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum MatchError {
+    /// A file can't be read or written
+    Io(String),
+    /// The command line arguments are invalid
+    InvalidArgument(String),
+    /// An opening can't be parsed, or contains an illegal move
+    InvalidOpening(String),
+    /// An engine can't be started
+    Engine(EngineError),
+}
+
+impl fmt::Display for MatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatchError::Io(error) => write!(f, "{}", error),
+            MatchError::InvalidArgument(argument) => write!(f, "invalid argument: {}", argument),
+            MatchError::InvalidOpening(opening) => write!(f, "invalid opening: {}", opening),
+            MatchError::Engine(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for MatchError {}
+
+/// Limits of the engines' searches
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TimeControl {
+    /// Time for the whole game, plus an increment after each move
+    Fischer {
+        base: Duration,
+        increment: Duration,
+    },
+    MoveTime(Duration),
+    Nodes(u64),
+    Depth(u8),
+}
+
+impl TimeControl {
+    /// Parse a Fischer time control given in seconds, as "<base>+<increment>" or "<base>"
+    pub fn from_tc(tc: &str) -> Option<TimeControl> {
+        let (base, increment) = tc.split_once('+').unwrap_or((tc, "0"));
+        let seconds = |value: &str| {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                .map(Duration::from_secs_f64)
+        };
+        Some(TimeControl::Fischer {
+            base: seconds(base).filter(|base| !base.is_zero())?,
+            increment: seconds(increment)?,
+        })
+    }
+}
+
+/// Draw adjudication settings
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DrawAdjudication {
+    pub move_number: u16,
+    pub move_count: usize,
+    pub score: i32,
+}
+
+/// Resign adjudication settings
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResignAdjudication {
+    pub move_count: usize,
+    pub score: i32,
+}
+
+/// Settings of the match command
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchOptions {
+    /// Command lines of the engines (program followed by its arguments)
+    pub engines: [String; 2],
+    /// UCI options sent to each engine
+    pub engine_options: [Vec<(String, String)>; 2],
+    /// File of openings : PGN games, or one FEN or EPD position per line
+    pub openings: Option<String>,
+    /// Number of games, rounded up to a whole number of pairs
+    pub games: u64,
+    /// Number of games played at the same time
+    pub concurrency: usize,
+    pub time_control: TimeControl,
+    /// Time an engine can exceed its clock by before losing on time
+    pub time_margin: Duration,
+    pub pgn_output: String,
+    pub draw_adjudication: Option<DrawAdjudication>,
+    pub resign_adjudication: Option<ResignAdjudication>,
+    pub tablebase_adjudication: bool,
+    /// Directories of the Syzygy tables used by tablebase adjudication, in the format of
+    /// the SyzygyPath UCI option
+    pub syzygy_path: Option<String>,
+    pub sprt: SprtParameters,
+    pub chess960: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> MatchOptions {
+        MatchOptions {
+            engines: [String::new(), String::new()],
+            engine_options: [Vec::new(), Vec::new()],
+            openings: None,
+            games: 100,
+            concurrency: 1,
+            time_control: TimeControl::Fischer {
+                base: Duration::from_secs(10),
+                increment: Duration::from_millis(100),
+            },
+            time_margin: Duration::from_millis(100),
+            pgn_output: "match.pgn".to_string(),
+            draw_adjudication: None,
+            resign_adjudication: None,
+            tablebase_adjudication: false,
+            syzygy_path: None,
+            sprt: SprtParameters::default(),
+            chess960: false,
+        }
+    }
+}
+
+/// Starting point of a pair of games : a position, and moves played from it
+#[derive(Clone, Debug)]
+pub struct Opening {
+    pub position: Position,
+    pub moves: Vec<Move>,
+}
+
+/// Reason a game ended
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Repetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    DrawAdjudication,
+    ResignAdjudication,
+    TablebaseAdjudication,
+    TimeForfeit,
+    /// The engine played an illegal move (given in UCI notation)
+    IllegalMove(String),
+    Disconnection,
+}
+
+impl Termination {
+    /// Value of the PGN Termination tag
+    pub fn get_tag_value(&self) -> &'static str {
+        match self {
+            Termination::DrawAdjudication
+            | Termination::ResignAdjudication
+            | Termination::TablebaseAdjudication => "adjudication",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove(_) => "rules infraction",
+            Termination::Disconnection => "abandoned",
+            _ => "normal",
+        }
+    }
+
+    /// Human readable description, written as a comment at the end of the game
+    pub fn get_description(&self) -> String {
+        match self {
+            Termination::Checkmate => "Checkmate".to_string(),
+            Termination::Stalemate => "Stalemate".to_string(),
+            Termination::Repetition => "Draw by threefold repetition".to_string(),
+            Termination::FiftyMoveRule => "Draw by fifty-move rule".to_string(),
+            Termination::InsufficientMaterial => "Draw by insufficient material".to_string(),
+            Termination::DrawAdjudication => "Draw by adjudication".to_string(),
+            Termination::ResignAdjudication => "Resignation by adjudication".to_string(),
+            Termination::TablebaseAdjudication => "Tablebase adjudication".to_string(),
+            Termination::TimeForfeit => "Loss on time".to_string(),
+            Termination::IllegalMove(uci) => format!("Illegal move {}", uci),
+            Termination::Disconnection => "Engine disconnected".to_string(),
+        }
+    }
+}
+
+/// A finished game
+#[derive(Debug)]
+pub struct GameOutcome {
+    pub game: Game,
+    pub termination: Termination,
+}
+
+/*********
+* OPENINGS
+**********/
+
+/// Read an opening file : either PGN games (whose main line is played from their starting
+/// position), or one FEN or EPD position per line
+pub fn read_openings(text: &str) -> Result<Vec<Opening>, MatchError> {
+    let is_pgn = text
+        .trim_start()
+        .starts_with(|c: char| c == '[' || c.is_ascii_digit());
+    if !is_pgn {
+        return read_book(text)
+            .map(|positions| {
+                positions
+                    .into_iter()
+                    .map(|position| Opening {
+                        position,
+                        moves: Vec::new(),
+                    })
+                    .collect()
+            })
+            .map_err(|error| MatchError::InvalidOpening(error.to_string()));
+    }
+
+    // The PGN reader already checks that the moves are legal
+    let games = read_pgn(text).map_err(|error| MatchError::InvalidOpening(error.to_string()))?;
+    Ok(games
+        .into_iter()
+        .map(|game| Opening {
+            moves: game.get_main_line(),
+            position: game.starting_position,
+        })
+        .collect())
+}
+
+/*************
+* ADJUDICATION
+**************/
+
+/// Return the result of a known endgame : positions of the Syzygy tablebase, or of the KPK
+/// bitbase
+fn get_tablebase_result(
+    position: &mut Position,
+    tablebase: Option<&SyzygyTablebase>,
+) -> Option<GameResult> {
+    if let Some(wdl) = tablebase.and_then(|tablebase| tablebase.probe_wdl(position)) {
+        return Some(match wdl {
+            Wdl::Win => GameResult::from_winner(position.current_turn),
+            Wdl::Loss => GameResult::from_winner(invert_player(&position.current_turn)),
+            _ => GameResult::Draw,
+        });
+    }
+
+    let bitboard = &position.piece_centric_board;
+    let pawn_count = bitboard.piece_type_boards[0].count_ones();
+    if bitboard.piece_count() != 3 || pawn_count != 1 {
+        return None;
+    }
+
+    match evaluate_endgame(position)? {
+        EndgameEvaluation::Score(0) => Some(GameResult::Draw),
+        EndgameEvaluation::Score(score) => Some(GameResult::from_winner(if score > 0 {
+            position.current_turn
+        } else {
+            invert_player(&position.current_turn)
+        })),
+        EndgameEvaluation::ScaleFactor(_) => None,
+    }
+}
+
+/// Check whether the last scores of an engine all satisfy a condition (None scores never
+/// do)
+fn last_scores_satisfy<F: Fn(i32) -> bool>(scores: &[Option<i32>], count: usize, f: F) -> bool {
+    scores.len() >= count
+        && count > 0
+        && scores[scores.len() - count..]
+            .iter()
+            .all(|score| score.is_some_and(&f))
+}
+
+/*********
+* GAMEPLAY
+**********/
+
+/// Make a move, and record it in the game (in SAN) and in the moves sent to the engines (in
+/// UCI notation)
+fn play_move(position: &mut Position, game: &mut Game, uci_moves: &mut Vec<String>, mov: Move) {
+    game.moves.push(MoveNode::new(position, mov));
+    uci_moves.push(get_move_uci(position, mov));
+    position.make_move(mov);
+}
+
+/// Play a game between two engines from an opening. Engine failures (crash, illegal move,
+/// time forfeit) lose the game.
+pub fn play_game(
+    white: &mut EngineProcess,
+    black: &mut EngineProcess,
+    opening: &Opening,
+    options: &MatchOptions,
+    tablebase: Option<&SyzygyTablebase>,
+) -> GameOutcome {
+    let mut position = opening.position.clone();
+    position.chess960 |= options.chess960;
+    let start_fen = position.to_fen();
+
+    let mut game = Game::new();
+    game.starting_position = position.clone();
+    game.set_tag("White", &white.name);
+    game.set_tag("Black", &black.name);
+    if start_fen != STARTING_FEN {
+        game.set_tag("SetUp", "1");
+        game.set_tag("FEN", &start_fen);
+    }
+    if options.chess960 {
+        game.set_tag("Variant", "Chess960");
+    }
+
+    let mut uci_moves = Vec::new();
+    let mut repetitions: HashMap<u64, u8> = HashMap::new();
+    for mov in &opening.moves {
+        play_move(&mut position, &mut game, &mut uci_moves, *mov);
+    }
+    if let Some(node) = game.moves.last_mut() {
+        node.comments.push("book".to_string());
+    }
+
+    let engines = [white, black];
+    let mut clocks = match options.time_control {
+        TimeControl::Fischer { base, .. } => [base; 2],
+        _ => [Duration::ZERO; 2],
+    };
+    let mut scores: [Vec<Option<i32>>; 2] = [Vec::new(), Vec::new()];
+
+    let (result, termination) = loop {
+        let side = position.current_turn;
+        let loss = GameResult::from_winner(invert_player(&side));
+
+        // Rules of chess
+        let repetition_count = repetitions.entry(position.get_zobrist_hash()).or_insert(0);
+        *repetition_count += 1;
+        if gen_legal_moves(&mut position).is_empty() {
+            if is_in_check(&position, side) {
+                break (loss, Termination::Checkmate);
+            }
+            break (GameResult::Draw, Termination::Stalemate);
+        }
+        if *repetition_count >= 3 {
+            break (GameResult::Draw, Termination::Repetition);
+        }
+        if position.plys_without_capture >= 100 {
+            break (GameResult::Draw, Termination::FiftyMoveRule);
+        }
+        if position.is_insufficient_material() {
+            break (GameResult::Draw, Termination::InsufficientMaterial);
+        }
+        if options.tablebase_adjudication {
+            if let Some(result) = get_tablebase_result(&mut position, tablebase) {
+                break (result, Termination::TablebaseAdjudication);
+            }
+        }
+
+        // Engine move
+        let (go, timeout) = match options.time_control {
+            TimeControl::Fischer { increment, .. } => (
+                format!(
+                    "go wtime {} btime {} winc {} binc {}",
+                    clocks[0].as_millis(),
+                    clocks[1].as_millis(),
+                    increment.as_millis(),
+                    increment.as_millis()
+                ),
+                clocks[side as usize] + options.time_margin,
+            ),
+            TimeControl::MoveTime(time) => (
+                format!("go movetime {}", time.as_millis()),
+                time + options.time_margin,
+            ),
+            TimeControl::Nodes(nodes) => (format!("go nodes {}", nodes), UNLIMITED_SEARCH_TIMEOUT),
+            TimeControl::Depth(depth) => (format!("go depth {}", depth), UNLIMITED_SEARCH_TIMEOUT),
+        };
+        let engine_move = match engines[side as usize].search(&start_fen, &uci_moves, &go, timeout)
+        {
+            Ok(engine_move) => engine_move,
+            Err(EngineError::Timeout) => break (loss, Termination::TimeForfeit),
+            Err(_) => break (loss, Termination::Disconnection),
+        };
+        if let TimeControl::Fischer { increment, .. } = options.time_control {
+            let clock = &mut clocks[side as usize];
+            if engine_move.elapsed > *clock + options.time_margin {
+                break (loss, Termination::TimeForfeit);
+            }
+            *clock = clock.saturating_sub(engine_move.elapsed) + increment;
+        }
+        let Some(mov) = parse_uci_move(&mut position, &engine_move.uci) else {
+            break (loss, Termination::IllegalMove(engine_move.uci));
+        };
+        play_move(&mut position, &mut game, &mut uci_moves, mov);
+        scores[side as usize].push(engine_move.score);
+
+        // Adjudication
+        let (own_scores, opponent_scores) = match side {
+            Player::White => (&scores[0], &scores[1]),
+            Player::Black => (&scores[1], &scores[0]),
+        };
+        if let Some(resign) = options.resign_adjudication {
+            let is_lost = |scores: &[Option<i32>]| {
+                last_scores_satisfy(scores, resign.move_count, |score| score <= -resign.score)
+            };
+            let is_won = |scores: &[Option<i32>]| {
+                last_scores_satisfy(scores, resign.move_count, |score| score >= resign.score)
+            };
+            if is_lost(own_scores) && is_won(opponent_scores) {
+                break (loss, Termination::ResignAdjudication);
+            }
+            if is_won(own_scores) && is_lost(opponent_scores) {
+                break (
+                    GameResult::from_winner(side),
+                    Termination::ResignAdjudication,
+                );
+            }
+        }
+        if let Some(draw) = options.draw_adjudication {
+            let is_drawn = |scores: &[Option<i32>]| {
+                last_scores_satisfy(scores, draw.move_count, |score| score.abs() <= draw.score)
+            };
+            if position.full_move_number >= draw.move_number
+                && is_drawn(own_scores)
+                && is_drawn(opponent_scores)
+            {
+                break (GameResult::Draw, Termination::DrawAdjudication);
+            }
+        }
+    };
+
+    game.result = result;
+    game.set_tag("Result", result.to_token());
+    game.set_tag("Termination", termination.get_tag_value());
+    let description = termination.get_description();
+    match game.moves.last_mut() {
+        Some(node) => node.comments.push(description),
+        None => game.comments.push(description),
+    }
+    GameOutcome { game, termination }
+}
+
+/// Start an engine and send it its options
+fn start_engine(options: &MatchOptions, index: usize) -> Result<EngineProcess, EngineError> {
+    let mut engine = EngineProcess::start(&options.engines[index])?;
+    if options.chess960 {
+        engine.set_option("UCI_Chess960", "true")?;
+    }
+    for (name, value) in &options.engine_options[index] {
+        engine.set_option(name, value)?;
+    }
+    engine.wait_until_ready()?;
+    Ok(engine)
+}
+
+/// Score of the first engine in a game
+fn get_first_engine_score(outcome: &GameOutcome, first_engine_is_white: bool) -> f64 {
+    let white_score = match outcome.game.result {
+        GameResult::WhiteWins => 1.0,
+        GameResult::BlackWins => 0.0,
+        _ => 0.5,
+    };
+    if first_engine_is_white {
+        white_score
+    } else {
+        1.0 - white_score
+    }
+}
+
+/// Play the match on several threads. The callback receives each pair of games (the first
+/// engine plays White in the first game) as it finishes, with the updated statistics, and
+/// returns false to stop the match.
+pub fn run_match<F: FnMut(u64, &[GameOutcome; 2], &MatchStatistics) -> bool>(
+    options: &MatchOptions,
+    openings: &[Opening],
+    mut callback: F,
+) -> Result<MatchStatistics, MatchError> {
+    let pairs = options.games.div_ceil(2);
+    let next_pair = AtomicU64::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let default_opening = [Opening {
+        position: Position::new(),
+        moves: Vec::new(),
+    }];
+    let openings = if openings.is_empty() {
+        &default_opening[..]
+    } else {
+        openings
+    };
+    let tablebase = match &options.syzygy_path {
+        Some(path) if options.tablebase_adjudication => Some(SyzygyTablebase::new(path)),
+        _ => None,
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..options.concurrency.max(1) {
+            let sender = sender.clone();
+            let (next_pair, stop, tablebase) = (&next_pair, &stop, tablebase.as_ref());
+            scope.spawn(move || {
+                let mut engines: [Option<EngineProcess>; 2] = [None, None];
+                loop {
+                    let pair = next_pair.fetch_add(1, Ordering::Relaxed);
+                    if pair >= pairs || stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let opening = &openings[(pair % openings.len() as u64) as usize];
+
+                    let mut outcomes = Vec::new();
+                    for first_engine_color in 0..2 {
+                        // Engines are (re)started if needed, and must be ready for the game
+                        for (index, engine) in engines.iter_mut().enumerate() {
+                            if engine
+                                .as_mut()
+                                .is_some_and(|engine| engine.new_game().is_err())
+                            {
+                                *engine = None;
+                            }
+                            if engine.is_none() {
+                                match start_engine(options, index)
+                                    .and_then(|mut started| started.new_game().map(|_| started))
+                                {
+                                    Ok(started) => *engine = Some(started),
+                                    Err(error) => {
+                                        let _ = sender.send(Err(MatchError::Engine(error)));
+                                        return;
+                                    }
+                                }
+                            }
+                        }
+
+                        let [first, second] = &mut engines;
+                        let (first, second) = (first.as_mut().unwrap(), second.as_mut().unwrap());
+
+                        // Two builds of the same engine are told apart by their command
+                        if first.name == second.name && options.engines[0] != options.engines[1] {
+                            first.name = options.engines[0].clone();
+                            second.name = options.engines[1].clone();
+                        }
+                        let (white, black) = match first_engine_color {
+                            0 => (first, second),
+                            _ => (second, first),
+                        };
+                        let mut outcome = play_game(white, black, opening, options, tablebase);
+                        outcome
+                            .game
+                            .set_tag("Round", &(2 * pair + first_engine_color + 1).to_string());
+                        outcomes.push(outcome);
+                    }
+
+                    let outcomes: [GameOutcome; 2] = outcomes.try_into().unwrap();
+                    if sender.send(Ok((pair, outcomes))).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut statistics = MatchStatistics::new();
+        let mut error = None;
+        for message in receiver {
+            match message {
+                Ok((pair, outcomes)) => {
+                    if stop.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    statistics.add_pair(
+                        get_first_engine_score(&outcomes[0], true),
+                        get_first_engine_score(&outcomes[1], false),
+                    );
+                    if !callback(pair, &outcomes, &statistics) {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+                Err(engine_error) => {
+                    stop.store(true, Ordering::Relaxed);
+                    error.get_or_insert(engine_error);
+                }
+            }
+        }
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(statistics),
+        }
+    })
+}
+
+/**************
+* MATCH COMMAND
+***************/
+
+impl MatchOptions {
+    /// Parse the arguments of the match command :
+    /// krabnik match <engine1> <engine2> [--openings <file>] [--games <n>]
+    /// [--concurrency <n>] [--tc <base>+<increment>] [--movetime <ms>] [--nodes <n>]
+    /// [--depth <n>] [--time-margin <ms>] [--pgn <file>] [--option1 <name>=<value>]
+    /// [--option2 <name>=<value>] [--draw <movenumber> <movecount> <score>]
+    /// [--resign <movecount> <score>] [--tb-adjudication] [--syzygy-path <dirs>]
+    /// [--elo0 <x>] [--elo1 <x>] [--alpha <x>] [--beta <x>] [--chess960]
+    pub fn from_args(args: &[String]) -> Result<MatchOptions, MatchError> {
+        let mut options = MatchOptions::default();
+        let mut engine_count = 0;
+        let mut args = args.iter();
+        let invalid = |arg: &str| MatchError::InvalidArgument(arg.to_string());
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| invalid(arg));
+            macro_rules! parse_value {
+                () => {{
+                    let value = value()?;
+                    value.parse().map_err(|_| invalid(value))?
+                }};
+            }
+            match arg.as_str() {
+                "--openings" => options.openings = Some(value()?.clone()),
+                "--games" => options.games = parse_value!(),
+                "--concurrency" => {
+                    options.concurrency = parse_value!();
+                    if options.concurrency == 0 {
+                        return Err(invalid(arg));
+                    }
+                }
+                "--tc" => {
+                    let value = value()?;
+                    options.time_control =
+                        TimeControl::from_tc(value).ok_or_else(|| invalid(value))?;
+                }
+                "--movetime" => {
+                    options.time_control =
+                        TimeControl::MoveTime(Duration::from_millis(parse_value!()))
+                }
+                "--nodes" => options.time_control = TimeControl::Nodes(parse_value!()),
+                "--depth" => options.time_control = TimeControl::Depth(parse_value!()),
+                "--time-margin" => options.time_margin = Duration::from_millis(parse_value!()),
+                "--pgn" => options.pgn_output = value()?.clone(),
+                "--option1" | "--option2" => {
+                    let value = value()?;
+                    let (name, option_value) =
+                        value.split_once('=').ok_or_else(|| invalid(value))?;
+                    let index = if arg == "--option1" { 0 } else { 1 };
+                    options.engine_options[index]
+                        .push((name.to_string(), option_value.to_string()));
+                }
+                "--draw" => {
+                    options.draw_adjudication = Some(DrawAdjudication {
+                        move_number: parse_value!(),
+                        move_count: parse_value!(),
+                        score: parse_value!(),
+                    })
+                }
+                "--resign" => {
+                    options.resign_adjudication = Some(ResignAdjudication {
+                        move_count: parse_value!(),
+                        score: parse_value!(),
+                    })
+                }
+                "--tb-adjudication" => options.tablebase_adjudication = true,
+                "--syzygy-path" => options.syzygy_path = Some(value()?.clone()),
+                "--elo0" => options.sprt.elo0 = parse_value!(),
+                "--elo1" => options.sprt.elo1 = parse_value!(),
+                "--alpha" => options.sprt.alpha = parse_value!(),
+                "--beta" => options.sprt.beta = parse_value!(),
+                "--chess960" => options.chess960 = true,
+                _ if arg.starts_with("--") || engine_count == 2 => return Err(invalid(arg)),
+                _ => {
+                    options.engines[engine_count] = arg.clone();
+                    engine_count += 1;
+                }
+            }
+        }
+
+        if engine_count < 2 {
+            return Err(MatchError::InvalidArgument(
+                "two engine commands are required".to_string(),
+            ));
+        }
+        let sprt = &options.sprt;
+        let is_probability = |x: f64| x > 0.0 && x < 1.0;
+        if sprt.elo0 >= sprt.elo1 || !is_probability(sprt.alpha) || !is_probability(sprt.beta) {
+            return Err(MatchError::InvalidArgument(
+                "invalid SPRT parameters".to_string(),
+            ));
+        }
+        Ok(options)
+    }
+}
+
+/// Format the statistics of a match
+pub fn get_match_report(statistics: &MatchStatistics, sprt: &SprtParameters) -> String {
+    let (elo, error) = statistics.get_elo();
+    let (lower, upper) = sprt.get_bounds();
+    format!(
+        "Games: {} (W: {} D: {} L: {}), Score: {:.1}%, Elo: {:.1} +/- {:.1}, LLR: {:.2} ({:.2}, {:.2})",
+        statistics.games(),
+        statistics.wins,
+        statistics.draws,
+        statistics.losses,
+        100.0 * statistics.get_score(),
+        elo,
+        error,
+        statistics.get_llr(sprt),
+        lower,
+        upper
+    )
+}
+
+/// Run the match command : play the games, write them as PGN, and report the Elo and the
+/// SPRT after each pair. The match stops early once the SPRT accepts a hypothesis.
+pub fn run_match_command(args: &[String]) -> Result<(), MatchError> {
+    let options = MatchOptions::from_args(args)?;
+    let io_error =
+        |path: &str, error: std::io::Error| MatchError::Io(format!("{}: {}", path, error));
+
+    let openings = match &options.openings {
+        Some(path) => {
+            read_openings(&fs::read_to_string(path).map_err(|error| io_error(path, error))?)?
+        }
+        None => Vec::new(),
+    };
+    get_kpk_bitbase();
+
+    let mut pgn_file = fs::File::create(&options.pgn_output)
+        .map_err(|error| io_error(&options.pgn_output, error))?;
+    let mut write_result = Ok(());
+    let mut sprt_result = SprtResult::Continue;
+
+    let statistics = run_match(&options, &openings, |pair, outcomes, statistics| {
+        for outcome in outcomes {
+            if write_result.is_ok() {
+                write_result = pgn_file.write_all(outcome.game.to_pgn().as_bytes());
+            }
+            println!(
+                "Game {} ({} vs {}): {} {{{}}}",
+                outcome.game.get_tag("Round").unwrap_or("?"),
+                outcome.game.get_tag("White").unwrap_or("?"),
+                outcome.game.get_tag("Black").unwrap_or("?"),
+                outcome.game.result.to_token(),
+                outcome.termination.get_description()
+            );
+        }
+        println!(
+            "Pair {}: {}",
+            pair + 1,
+            get_match_report(statistics, &options.sprt)
+        );
+
+        sprt_result = options.sprt.get_result(statistics.get_llr(&options.sprt));
+        write_result.is_ok() && sprt_result == SprtResult::Continue
+    })?;
+    write_result.map_err(|error| io_error(&options.pgn_output, error))?;
+
+    println!("Final: {}", get_match_report(&statistics, &options.sprt));
+    match sprt_result {
+        SprtResult::AcceptH0 => println!("SPRT: H0 accepted (elo < {})", options.sprt.elo0),
+        SprtResult::AcceptH1 => println!("SPRT: H1 accepted (elo > {})", options.sprt.elo1),
+        SprtResult::Continue => println!("SPRT: inconclusive"),
+    }
+    println!("Games written to {}", options.pgn_output);
+
+    Ok(())
+}
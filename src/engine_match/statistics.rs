@@ -0,0 +1,164 @@
+#![allow(dead_code)]
+
+/************
+* STATISTICS
+*************/
+
+// Games are played in pairs (same opening, colors swapped), so the results are counted per
+// pair : the pentanomial distribution of the pair scores (0, 0.5, 1, 1.5 or 2 points for the
+// first engine). This removes most of the noise coming from unbalanced openings.
+// Elo differences use the logistic model, and the SPRT uses the normal approximation of the
+// generalized log-likelihood ratio.
+// See : <https://www.chessprogramming.org/Sequential_Probability_Ratio_Test>
+
+/// Quantile of the normal distribution used for the 95% confidence intervals
+const CONFIDENCE_QUANTILE: f64 = 1.959964;
+
+/// Expected score of a player given its Elo advantage
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Elo advantage of a player given its expected score (infinite for scores of 0 or 1)
+pub fn score_to_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Bounds and error probabilities of the Sequential Probability Ratio Test. H0 is "the
+/// first engine is elo0 stronger", and H1 is "the first engine is elo1 stronger".
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SprtParameters {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Probability of accepting H1 while H0 is true
+    pub alpha: f64,
+    /// Probability of accepting H0 while H1 is true
+    pub beta: f64,
+}
+
+impl Default for SprtParameters {
+    fn default() -> SprtParameters {
+        SprtParameters {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+/// Outcome of the SPRT so far
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SprtResult {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+impl SprtParameters {
+    /// Return the lower and upper bounds of the log-likelihood ratio
+    pub fn get_bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Compare a log-likelihood ratio to the bounds
+    pub fn get_result(&self, llr: f64) -> SprtResult {
+        let (lower, upper) = self.get_bounds();
+        if llr <= lower {
+            SprtResult::AcceptH0
+        } else if llr >= upper {
+            SprtResult::AcceptH1
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+/// Results of a match, from the point of view of the first engine
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MatchStatistics {
+    pub wins: u64,
+    pub draws: u64,
+    pub losses: u64,
+    /// Number of pairs by score of the first engine : 0, 0.5, 1, 1.5 and 2 points
+    pub pairs: [u64; 5],
+}
+
+impl MatchStatistics {
+    /// Shorthand for default
+    pub fn new() -> MatchStatistics {
+        MatchStatistics::default()
+    }
+
+    /// Add the result of a pair of games, as the scores of the first engine (0, 0.5 or 1)
+    pub fn add_pair(&mut self, first_score: f64, second_score: f64) {
+        for score in [first_score, second_score] {
+            match (score * 2.0).round() as u8 {
+                0 => self.losses += 1,
+                1 => self.draws += 1,
+                _ => self.wins += 1,
+            }
+        }
+        let pair_score = ((first_score + second_score) * 2.0).round() as usize;
+        self.pairs[pair_score.min(4)] += 1;
+    }
+
+    pub fn games(&self) -> u64 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn pair_count(&self) -> u64 {
+        self.pairs.iter().sum()
+    }
+
+    /// Mean and variance of the pair scores (as fractions between 0 and 1)
+    fn get_pair_score_distribution(&self) -> Option<(f64, f64)> {
+        let pair_count = self.pair_count();
+        if pair_count == 0 {
+            return None;
+        }
+
+        let frequencies = self.pairs.map(|count| count as f64 / pair_count as f64);
+        let mean: f64 = (0..5).map(|i| frequencies[i] * i as f64 / 4.0).sum();
+        let variance: f64 = (0..5)
+            .map(|i| frequencies[i] * (i as f64 / 4.0 - mean).powi(2))
+            .sum();
+        Some((mean, variance))
+    }
+
+    /// Score of the first engine, between 0 and 1
+    pub fn get_score(&self) -> f64 {
+        self.get_pair_score_distribution()
+            .map_or(0.5, |(mean, _)| mean)
+    }
+
+    /// Elo difference between the engines, and half the width of its 95% confidence
+    /// interval
+    pub fn get_elo(&self) -> (f64, f64) {
+        let Some((mean, variance)) = self.get_pair_score_distribution() else {
+            return (0.0, f64::INFINITY);
+        };
+        let margin = CONFIDENCE_QUANTILE * (variance / self.pair_count() as f64).sqrt();
+        let lower = score_to_elo((mean - margin).max(0.0));
+        let upper = score_to_elo((mean + margin).min(1.0));
+        (score_to_elo(mean), (upper - lower) / 2.0)
+    }
+
+    /// Log-likelihood ratio of H1 against H0
+    pub fn get_llr(&self, sprt: &SprtParameters) -> f64 {
+        let Some((mean, variance)) = self.get_pair_score_distribution() else {
+            return 0.0;
+        };
+        if variance <= 0.0 {
+            return 0.0;
+        }
+
+        let score0 = elo_to_score(sprt.elo0);
+        let score1 = elo_to_score(sprt.elo1);
+        self.pair_count() as f64 * (score1 - score0) * (2.0 * mean - score0 - score1)
+            / (2.0 * variance)
+    }
+}
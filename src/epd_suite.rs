@@ -0,0 +1,298 @@
+/*
+ * The epd_suite module implements "krabnik epd <file> [--movetime <ms>]", which runs a test
+ * suite written in EPD (such as Win At Chess) : each position is searched for the given
+ * time, and is solved if the move found is one of its best moves ("bm" operation) and none
+ * of the moves to avoid ("am" operation).
+ *
+ * For each position, the command reports whether it was solved, and the time to solution,
+ * ie the time at which the search found a solving move and never changed its mind until the
+ * end of the search.
+ */
+
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fs;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::search::*;
+
+/**********
+* EPD SUITE
+***********/
+
+/// Default search time of each position, in milliseconds
+pub const DEFAULT_EPD_MOVETIME: u64 = 1000;
+
+/// Size in MiB of the transposition table used to search the positions
+const EPD_HASH_SIZE: usize = 16;
+
+/// Errors that can occur while running the epd command
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EpdSuiteError {
+    /// The EPD file can't be read
+    Io(String),
+    /// The command line arguments are invalid
+    InvalidArgument(String),
+    /// A record of the EPD file is invalid (with its line number, starting at 1)
+    InvalidRecord(usize, EpdError),
+}
+
+impl fmt::Display for EpdSuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpdSuiteError::Io(error) => write!(f, "{}", error),
+            EpdSuiteError::InvalidArgument(arg) => write!(f, "invalid argument: {}", arg),
+            EpdSuiteError::InvalidRecord(line, error) => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for EpdSuiteError {}
+
+/// Settings of the epd command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EpdSuiteOptions {
+    pub file: String,
+    /// Search time of each position
+    pub movetime: Duration,
+}
+
+impl EpdSuiteOptions {
+    /// Parse the arguments of the epd command : <file> [--movetime <ms>]
+    pub fn from_args(args: &[String]) -> Result<EpdSuiteOptions, EpdSuiteError> {
+        let mut file = None;
+        let mut movetime = Duration::from_millis(DEFAULT_EPD_MOVETIME);
+        let mut args = args.iter();
+        let invalid = |arg: &str| EpdSuiteError::InvalidArgument(arg.to_string());
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--movetime" => {
+                    let value = args.next().ok_or_else(|| invalid(arg))?;
+                    movetime = value
+                        .parse()
+                        .ok()
+                        .filter(|&ms| ms > 0)
+                        .map(Duration::from_millis)
+                        .ok_or_else(|| invalid(value))?;
+                }
+                _ if arg.starts_with("--") || file.is_some() => return Err(invalid(arg)),
+                _ => file = Some(arg.clone()),
+            }
+        }
+
+        Ok(EpdSuiteOptions {
+            file: file.ok_or_else(|| invalid("an EPD file is required"))?,
+            movetime,
+        })
+    }
+}
+
+/// Outcome of the search of a single position of the suite
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EpdSolution {
+    /// Move found by the search, in SAN (None if the position has no legal moves)
+    pub best_move: Option<String>,
+    pub solved: bool,
+    /// Time after which the search kept finding a solving move, if solved
+    pub time_to_solution: Option<Duration>,
+}
+
+/// Totals of a test suite run
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EpdSuiteReport {
+    pub solved: usize,
+    pub total: usize,
+    pub elapsed: Duration,
+}
+
+/// Search a position of the suite with the given limits, and check whether the move found
+/// solves it after each iteration of the search
+pub fn solve_epd_record(
+    record: &EpdRecord,
+    evaluator: &mut Evaluator,
+    limits: &SearchLimits,
+    tt: &TranspositionTable,
+) -> EpdSolution {
+    let stop = AtomicBool::new(false);
+    let context = SearchContext {
+        tt,
+        stop: &stop,
+        threads: 1,
+        game_hashes: &[],
+        tablebase: None,
+    };
+
+    // The callback can't borrow the searched position, so moves are written from a copy
+    let mut root = record.position.clone();
+    let mut position = record.position.clone();
+    let mut time_to_solution = None;
+    let start = Instant::now();
+    let result = search_with_callback(&mut position, evaluator, limits, &context, |result| {
+        let solved = result
+            .best_move
+            .is_some_and(|mov| record.is_solved_by(&get_move_san(&mut root, mov)));
+        time_to_solution = match (solved, time_to_solution) {
+            (true, None) => Some(start.elapsed()),
+            (true, time) => time,
+            (false, _) => None,
+        };
+    });
+
+    let best_move = result
+        .best_move
+        .map(|mov| get_move_san(&mut record.position.clone(), mov));
+    let solved = best_move
+        .as_ref()
+        .is_some_and(|san| record.is_solved_by(san));
+    EpdSolution {
+        best_move,
+        solved,
+        time_to_solution: if solved { time_to_solution } else { None },
+    }
+}
+
+/// Search each record of the suite. The callback receives the index of each record and its
+/// solution.
+pub fn run_epd_suite<F: FnMut(usize, &EpdSolution)>(
+    records: &[EpdRecord],
+    limits: &SearchLimits,
+    mut callback: F,
+) -> EpdSuiteReport {
+    let tt = TranspositionTable::new(EPD_HASH_SIZE);
+    let mut evaluator = Evaluator::new();
+
+    let mut solved = 0;
+    let start = Instant::now();
+    for (index, record) in records.iter().enumerate() {
+        tt.clear();
+        let solution = solve_epd_record(record, &mut evaluator, limits, &tt);
+        if solution.solved {
+            solved += 1;
+        }
+        callback(index, &solution);
+    }
+
+    EpdSuiteReport {
+        solved,
+        total: records.len(),
+        elapsed: start.elapsed(),
+    }
+}
+
+pub fn run_epd_command(args: &[String]) -> Result<(), EpdSuiteError> {
+    let options = EpdSuiteOptions::from_args(args)?;
+    let text = fs::read_to_string(&options.file)
+        .map_err(|error| EpdSuiteError::Io(format!("{}: {}", options.file, error)))?;
+    let records =
+        read_epd(&text).map_err(|(line, error)| EpdSuiteError::InvalidRecord(line, error))?;
+    get_kpk_bitbase();
+
+    let limits = SearchLimits {
+        time: Some(options.movetime),
+        ..SearchLimits::default()
+    };
+    let report = run_epd_suite(&records, &limits, |index, solution| {
+        let record = &records[index];
+        let name = match record.id() {
+            Some(id) => format!("Position {}/{} ({})", index + 1, records.len(), id),
+            None => format!("Position {}/{}", index + 1, records.len()),
+        };
+        let best_move = solution.best_move.as_deref().unwrap_or("(none)");
+        match solution.time_to_solution {
+            Some(time) if solution.solved => {
+                println!("{}: pass, {} in {} ms", name, best_move, time.as_millis())
+            }
+            _ => println!(
+                "{}: fail, {} (bm {}, am {})",
+                name,
+                best_move,
+                record.best_moves().join(" "),
+                record.avoid_moves().join(" ")
+            ),
+        }
+    });
+
+    println!("===========================");
+    println!("Solved          : {}/{}", report.solved, report.total);
+    println!("Total time (ms) : {}", report.elapsed.as_millis());
+    Ok(())
+}
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_epd_suite() {
+    // Arguments
+    let args = |args: &[&str]| {
+        EpdSuiteOptions::from_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    };
+    assert!(
+        args(&["wac.epd"])
+            == Ok(EpdSuiteOptions {
+                file: "wac.epd".to_string(),
+                movetime: Duration::from_millis(DEFAULT_EPD_MOVETIME),
+            }),
+        "Failed at assert 0"
+    );
+    assert!(
+        args(&["--movetime", "250", "wac.epd"]).map(|options| options.movetime)
+            == Ok(Duration::from_millis(250)),
+        "Failed at assert 1"
+    );
+    assert!(args(&[]).is_err(), "Failed at assert 2");
+    assert!(
+        args(&["wac.epd", "--movetime"]).is_err(),
+        "Failed at assert 3"
+    );
+    assert!(
+        args(&["wac.epd", "--movetime", "0"]).is_err(),
+        "Failed at assert 4"
+    );
+    assert!(
+        args(&["wac.epd", "other.epd"]).is_err(),
+        "Failed at assert 5"
+    );
+
+    // Mates in one, with best moves, moves to avoid, and no legal moves
+    let epd = r#"6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id "mate";
+6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra8; id "avoid";
+7k/5Q2/6K1/8/8/8/8/8 b - - bm Kh7; id "stalemate";
+"#;
+    let records = read_epd(epd).unwrap();
+    let limits = SearchLimits {
+        depth: Some(3),
+        ..SearchLimits::default()
+    };
+    let mut solutions = Vec::new();
+    let report = run_epd_suite(&records, &limits, |index, solution| {
+        solutions.push((index, solution.clone()))
+    });
+    assert!(report.total == 3, "Failed at assert 6");
+    assert!(report.solved == 1, "Failed at assert 7");
+
+    let (index, solution) = &solutions[0];
+    assert!(*index == 0, "Failed at assert 8");
+    assert!(solution.solved, "Failed at assert 9");
+    assert!(
+        solution.best_move.as_deref() == Some("Ra8#"),
+        "Failed at assert 10"
+    );
+    assert!(solution.time_to_solution.is_some(), "Failed at assert 11");
+
+    let (_, solution) = &solutions[1];
+    assert!(!solution.solved, "Failed at assert 12");
+    assert!(solution.time_to_solution.is_none(), "Failed at assert 13");
+
+    let (_, solution) = &solutions[2];
+    assert!(solution.best_move.is_none(), "Failed at assert 14");
+    assert!(!solution.solved, "Failed at assert 15");
+}
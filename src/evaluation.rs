@@ -0,0 +1,301 @@
+/*
+ * The evaluation module contains functions to statically evaluate a Position.
+ *
+ * The Evaluator is the entry point of the evaluation. It either uses an NNUE network
+ * loaded from a file (with accumulators updated incrementally when moves are made and
+ * unmade), or falls back to a handcrafted evaluation (material and piece-square tables).
+ *
+ * Both are completed by specialized evaluation functions for endgames, which are selected
+ * from the material on the board. They either replace the normal evaluation with a known
+ * result (for instance KBN vs K, or KP vs K using a bitbase generated at startup by
+ * retrograde analysis), or give a factor to scale it down in drawish endgames (bishops of
+ * opposite colors, wrong rook pawn).
+ */
+
+pub mod endgame;
+pub mod evaluator;
+pub mod handcrafted;
+pub mod kpk_bitbase;
+pub mod nnue;
+
+pub use endgame::*;
+pub use evaluator::*;
+pub use handcrafted::*;
+pub use kpk_bitbase::*;
+pub use nnue::*;
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_endgames() {
+    use crate::board_representation::*;
+
+    let bitbase = get_kpk_bitbase();
+    let c = |square: &str| Coord::from_algebraic(square).unwrap();
+
+    // King on a key square wins regardless of the side to move
+    for side_to_move in [Player::White, Player::Black] {
+        let is_win = bitbase.probe(Player::White, c("e6"), c("e5"), c("e8"), side_to_move);
+        assert!(is_win, "Failed at assert 0");
+    }
+
+    // Opposition : the side to move decides the result
+    let is_win = bitbase.probe(Player::White, c("e5"), c("e4"), c("e7"), Player::White);
+    assert!(!is_win, "Failed at assert 1");
+    let is_win = bitbase.probe(Player::White, c("e5"), c("e4"), c("e7"), Player::Black);
+    assert!(is_win, "Failed at assert 2");
+
+    // Same positions with colors swapped and the board mirrored
+    let is_win = bitbase.probe(Player::Black, c("d4"), c("d5"), c("d2"), Player::Black);
+    assert!(!is_win, "Failed at assert 3");
+    let is_win = bitbase.probe(Player::Black, c("d4"), c("d5"), c("d2"), Player::White);
+    assert!(is_win, "Failed at assert 4");
+
+    // Unstoppable pawn, pawn captured, and rook pawn with the king in the corner
+    let is_win = bitbase.probe(Player::White, c("h1"), c("e6"), c("a1"), Player::White);
+    assert!(is_win, "Failed at assert 5");
+    let is_win = bitbase.probe(Player::White, c("a1"), c("e2"), c("d3"), Player::Black);
+    assert!(!is_win, "Failed at assert 6");
+    let is_win = bitbase.probe(Player::White, c("b1"), c("a4"), c("a8"), Player::White);
+    assert!(!is_win, "Failed at assert 7");
+
+    // Endgame evaluations, from the side to move's point of view
+    let evaluate = |fen: &str| evaluate_endgame(&Position::from_fen(fen).unwrap());
+
+    // KP vs K
+    let evaluation = evaluate("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1");
+    assert!(
+        matches!(evaluation, Some(EndgameEvaluation::Score(score)) if score < -KNOWN_WIN),
+        "Failed at assert 8"
+    );
+    let evaluation = evaluate("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1");
+    assert!(
+        evaluation == Some(EndgameEvaluation::Score(0)),
+        "Failed at assert 9"
+    );
+
+    // Insufficient material
+    let evaluation = evaluate("8/8/4k3/8/8/3BK3/8/8 w - - 0 1");
+    assert!(
+        evaluation == Some(EndgameEvaluation::Score(0)),
+        "Failed at assert 10"
+    );
+
+    // KR vs K : the weak king should be pushed to the edges and corners
+    let score = |fen: &str| match evaluate(fen) {
+        Some(EndgameEvaluation::Score(score)) => score,
+        _ => panic!("Expected an endgame score for {}", fen),
+    };
+    let center_score = score("8/8/8/3k4/8/8/8/R3K3 w - - 0 1");
+    let edge_score = score("k7/8/8/8/8/8/8/1R2K3 w - - 0 1");
+    assert!(center_score > KNOWN_WIN, "Failed at assert 11");
+    assert!(edge_score > center_score, "Failed at assert 12");
+
+    // KBN vs K : with a dark squared bishop, the a1/h8 corners are the good ones
+    let good_corner_score = score("7k/8/8/8/8/8/8/2B1KN2 w - - 0 1");
+    let bad_corner_score = score("k7/8/8/8/8/8/8/2B1KN2 w - - 0 1");
+    assert!(good_corner_score > bad_corner_score, "Failed at assert 13");
+
+    // Wrong rook pawn
+    let evaluation = evaluate("7k/8/8/7P/8/8/8/3BK3 w - - 0 1");
+    assert!(
+        evaluation == Some(EndgameEvaluation::ScaleFactor(SCALE_FACTOR_DRAW)),
+        "Failed at assert 14"
+    );
+    assert!(
+        evaluate("7k/8/8/7P/8/8/8/2B1K3 w - - 0 1").is_none(),
+        "Failed at assert 15"
+    );
+
+    // Bishops of opposite colors
+    let evaluation = evaluate("4kb2/8/8/3p4/3P4/8/8/3BK3 w - - 0 1");
+    assert!(
+        evaluation
+            == Some(EndgameEvaluation::ScaleFactor(
+                SCALE_FACTOR_OPPOSITE_BISHOPS
+            )),
+        "Failed at assert 16"
+    );
+    assert!(
+        evaluate("4kb2/8/8/3p4/3P4/8/8/2B1K3 w - - 0 1").is_none(),
+        "Failed at assert 17"
+    );
+    assert!(evaluate(STARTING_FEN).is_none(), "Failed at assert 18");
+}
+
+#[test]
+fn test_handcrafted() {
+    use crate::board_representation::*;
+
+    let evaluate = |fen: &str| evaluate_handcrafted(&Position::from_fen(fen).unwrap());
+
+    // Symmetrical positions are equal
+    assert!(evaluate(STARTING_FEN) == 0, "Failed at assert 0");
+
+    // Evaluations are given from the point of view of the side to move
+    let white_score = evaluate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1");
+    let black_score = evaluate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 b Qkq - 0 1");
+    assert!(white_score < -400, "Failed at assert 1");
+    assert!(white_score == -black_score, "Failed at assert 2");
+
+    // Mirrored positions have the same evaluation
+    let score = evaluate("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let mirrored_score =
+        evaluate("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 2 3");
+    assert!(score == mirrored_score, "Failed at assert 3");
+
+    // Central pieces are better than pieces on the rim
+    let center_score = evaluate("4k3/4p3/8/8/3N4/8/4P3/4K3 w - - 0 1");
+    let rim_score = evaluate("4k3/4p3/8/8/N7/8/4P3/4K3 w - - 0 1");
+    assert!(center_score > rim_score, "Failed at assert 4");
+}
+
+#[test]
+fn test_nnue() {
+    use crate::board_representation::*;
+    use crate::move_generation::*;
+    use std::sync::Arc;
+
+    // Deterministic pseudo-random values to fill the networks
+    let mut seed: u64 = 0x9e3779b97f4a7c15;
+    let mut random = move |range: i32| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % (2 * range as u64 + 1)) as i32 - range
+    };
+
+    // SIMD and portable implementations agree
+    let values: Vec<i16> = (0..100).map(|_| random(30000) as i16).collect();
+    let weights: Vec<i16> = (0..100).map(|_| random(30000) as i16).collect();
+    let inputs: Vec<u8> = (0..100).map(|_| random(127).unsigned_abs() as u8).collect();
+    let i8_weights: Vec<i8> = (0..100).map(|_| random(127) as i8).collect();
+    let (mut simd_values, mut fallback_values) = (values.clone(), values.clone());
+    add_weights(&mut simd_values, &weights);
+    add_weights_fallback(&mut fallback_values, &weights);
+    assert!(simd_values == fallback_values, "Failed at assert 0");
+    sub_weights(&mut simd_values, &weights);
+    sub_weights_fallback(&mut fallback_values, &weights);
+    assert!(simd_values == fallback_values, "Failed at assert 1");
+    assert!(simd_values == values, "Failed at assert 2");
+    assert!(
+        dot_product(&inputs, &i8_weights) == dot_product_fallback(&inputs, &i8_weights),
+        "Failed at assert 3"
+    );
+
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+
+    for feature_set in [FeatureSet::HalfKP, FeatureSet::HalfKA] {
+        let mut network = Network::zeroed(feature_set, 32, 16, 8);
+        network
+            .feature_biases
+            .iter_mut()
+            .for_each(|x| *x = random(64) as i16);
+        network
+            .feature_weights
+            .iter_mut()
+            .for_each(|x| *x = random(32) as i16);
+        network.l1_biases.iter_mut().for_each(|x| *x = random(2000));
+        network
+            .l1_weights
+            .iter_mut()
+            .for_each(|x| *x = random(64) as i8);
+        network.l2_biases.iter_mut().for_each(|x| *x = random(2000));
+        network
+            .l2_weights
+            .iter_mut()
+            .for_each(|x| *x = random(64) as i8);
+        network.output_bias = random(2000);
+        network
+            .output_weights
+            .iter_mut()
+            .for_each(|x| *x = random(64) as i8);
+
+        // Serialization round trip
+        let bytes = network.to_bytes();
+        assert!(
+            Network::from_bytes(&bytes).as_ref() == Ok(&network),
+            "Failed at assert 4"
+        );
+        assert!(
+            Network::from_bytes(&bytes[..bytes.len() - 1]) == Err(NnueError::UnexpectedEndOfFile),
+            "Failed at assert 5"
+        );
+        assert!(
+            Network::from_bytes(b"KRNX") == Err(NnueError::InvalidMagic),
+            "Failed at assert 6"
+        );
+
+        // Incremental updates give the same accumulators as full refreshes, for every
+        // move (including castling, en passant and promotions) up to depth 2
+        let network = Arc::new(network);
+        for fen in fens {
+            let mut position = Position::from_fen(fen).unwrap();
+            let mut evaluator = Evaluator::with_network(network.clone());
+            evaluator.refresh(&position);
+
+            for mov in gen_legal_moves(&mut position) {
+                position.make_move(mov);
+                evaluator.make_move(&position, mov);
+                assert!(
+                    evaluator.evaluate(&position) == network.evaluate_position(&position),
+                    "Failed at assert 7"
+                );
+
+                for reply in gen_legal_moves(&mut position) {
+                    position.make_move(reply);
+                    evaluator.make_move(&position, reply);
+                    assert!(
+                        evaluator.get_accumulator() == Some(&Accumulator::new(&network, &position)),
+                        "Failed at assert 8"
+                    );
+                    position.unmake_move(reply);
+                    evaluator.unmake_move();
+                }
+
+                position.unmake_move(mov);
+                evaluator.unmake_move();
+            }
+            assert!(
+                evaluator.evaluate(&position) == network.evaluate_position(&position),
+                "Failed at assert 9"
+            );
+        }
+    }
+
+    // Without network, or with NNUE disabled, the handcrafted evaluation is used
+    let position = Position::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+    let mut evaluator = Evaluator::new();
+    assert!(!evaluator.is_nnue_active(), "Failed at assert 10");
+    assert!(
+        evaluator.evaluate(&position) == evaluate_handcrafted(&position),
+        "Failed at assert 11"
+    );
+    assert!(
+        evaluator.set_option(USE_NNUE_OPTION, "false") == Ok(true),
+        "Failed at assert 12"
+    );
+    assert!(!evaluator.use_nnue, "Failed at assert 13");
+    assert!(
+        evaluator.set_option(USE_NNUE_OPTION, "maybe").is_err(),
+        "Failed at assert 14"
+    );
+    assert!(
+        evaluator.set_option("Hash", "16") == Ok(false),
+        "Failed at assert 15"
+    );
+    assert!(
+        matches!(
+            evaluator.set_option(EVAL_FILE_OPTION, "/nonexistent/krabnik.nnue"),
+            Err(NnueError::Io(_))
+        ),
+        "Failed at assert 16"
+    );
+}
@@ -0,0 +1,287 @@
+#![allow(dead_code)]
+
+use super::kpk_bitbase::*;
+use crate::board_representation::*;
+
+/*****************
+* ENDGAME DATATYPES
+******************/
+
+/// Material values (in centipawns) used by the endgame evaluation functions.
+/// Should be indexed using PieceCode - 1, modulo 6.
+pub const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+/// Bonus given to positions that are known to be won, so they are always preferred to
+/// positions that are merely good
+pub const KNOWN_WIN: i32 = 10000;
+
+/// Scale factor that leaves the normal evaluation untouched
+pub const SCALE_FACTOR_NORMAL: u8 = 64;
+/// Scale factor of positions that are drawn despite the material imbalance
+pub const SCALE_FACTOR_DRAW: u8 = 0;
+/// Scale factor of endgames with bishops of opposite colors (and pawns)
+pub const SCALE_FACTOR_OPPOSITE_BISHOPS: u8 = 16;
+
+/// Result of a specialized endgame evaluation
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EndgameEvaluation {
+    /// Evaluation that replaces the normal one, in centipawns from the point of view of
+    /// the side to move
+    Score(i32),
+    /// The normal evaluation must be multiplied by this factor, then divided by
+    /// SCALE_FACTOR_NORMAL
+    ScaleFactor(u8),
+}
+
+/// Pieces of one side, as found on the board
+struct SidePieces {
+    player: Player,
+    king: Coord,
+    pawns: Vec<Coord>,
+    knights: Vec<Coord>,
+    bishops: Vec<Coord>,
+    rooks: Vec<Coord>,
+    queens: Vec<Coord>,
+}
+
+/*****************
+* HELPER FUNCTIONS
+******************/
+
+/// Return the Coords of all the squares set on a board
+fn get_board_coords(board: u64) -> Vec<Coord> {
+    Bitboard(board).iter().map(Square::to_coord).collect()
+}
+
+fn get_side_pieces(bitboard: &BitBoard, player: Player) -> SidePieces {
+    let offset = 6 * player as usize;
+    let boards = &bitboard.main_boards[offset..offset + 6];
+
+    SidePieces {
+        player,
+        king: get_board_coords(boards[5])
+            .first()
+            .copied()
+            .unwrap_or_default(),
+        pawns: get_board_coords(boards[0]),
+        knights: get_board_coords(boards[1]),
+        bishops: get_board_coords(boards[2]),
+        rooks: get_board_coords(boards[3]),
+        queens: get_board_coords(boards[4]),
+    }
+}
+
+impl SidePieces {
+    fn non_pawn_count(&self) -> usize {
+        self.knights.len() + self.bishops.len() + self.rooks.len() + self.queens.len()
+    }
+
+    fn is_bare_king(&self) -> bool {
+        self.non_pawn_count() == 0 && self.pawns.is_empty()
+    }
+
+    fn material(&self) -> i32 {
+        [
+            &self.pawns,
+            &self.knights,
+            &self.bishops,
+            &self.rooks,
+            &self.queens,
+        ]
+        .iter()
+        .zip(PIECE_VALUES)
+        .map(|(pieces, value)| pieces.len() as i32 * value)
+        .sum()
+    }
+
+    /// Check whether the side has enough material to force mate against a bare king
+    fn can_force_mate(&self) -> bool {
+        let has_both_bishop_colors = self.bishops.iter().any(|bishop| is_dark_square(*bishop))
+            && self.bishops.iter().any(|bishop| !is_dark_square(*bishop));
+
+        !self.queens.is_empty()
+            || !self.rooks.is_empty()
+            || has_both_bishop_colors
+            || (!self.bishops.is_empty() && !self.knights.is_empty())
+    }
+}
+
+fn is_dark_square(coord: Coord) -> bool {
+    (coord.f + coord.r).is_multiple_of(2)
+}
+
+fn coord_distance(coord_a: Coord, coord_b: Coord) -> i32 {
+    coord_a
+        .f
+        .abs_diff(coord_b.f)
+        .max(coord_a.r.abs_diff(coord_b.r)) as i32
+}
+
+/// Bonus for driving a king towards the edges (and even more the corners) of the board
+fn push_to_edge(coord: Coord) -> i32 {
+    let file_distance = coord.f.min(7 - coord.f) as i32;
+    let rank_distance = coord.r.min(7 - coord.r) as i32;
+    20 * (6 - file_distance - rank_distance)
+}
+
+/// Bonus for driving a king towards the corners of the given color
+fn push_to_corner(coord: Coord, dark_corners: bool) -> i32 {
+    // Dark corners are a1 and h8, light corners are h1 and a8
+    let file = if dark_corners { coord.f } else { 7 - coord.f } as i32;
+    let rank = coord.r as i32;
+    let corner_distance = (file + rank).min(14 - file - rank);
+    20 * (14 - corner_distance)
+}
+
+/// Bonus for bringing two kings closer together
+fn push_close(coord_a: Coord, coord_b: Coord) -> i32 {
+    20 * (7 - coord_distance(coord_a, coord_b))
+}
+
+/// Convert a score from the point of view of the strong side to the point of view of the
+/// side to move
+fn to_side_to_move(score: i32, strong_side: Player, position: &Position) -> i32 {
+    if position.current_turn == strong_side {
+        score
+    } else {
+        -score
+    }
+}
+
+/*********************
+* ENDGAME EVALUATIONS
+*********************/
+
+/// King and mating material vs lone king : drive the weak king to the edge of the board,
+/// and bring the strong king closer to it
+fn evaluate_kxk(position: &Position, strong: &SidePieces, weak: &SidePieces) -> i32 {
+    let mut score =
+        strong.material() + push_to_edge(weak.king) + push_close(strong.king, weak.king);
+    if strong.can_force_mate() {
+        score += KNOWN_WIN;
+    }
+    to_side_to_move(score, strong.player, position)
+}
+
+/// King, bishop and knight vs lone king : mate can only be forced in the corners of the
+/// bishop's color, so the weak king is driven towards them
+fn evaluate_kbnk(position: &Position, strong: &SidePieces, weak: &SidePieces) -> i32 {
+    let dark_corners = is_dark_square(strong.bishops[0]);
+    let score = KNOWN_WIN
+        + strong.material()
+        + push_to_corner(weak.king, dark_corners)
+        + push_close(strong.king, weak.king);
+    to_side_to_move(score, strong.player, position)
+}
+
+/// King and pawn vs lone king : the result is read from the KPK bitbase
+fn evaluate_kpk(position: &Position, strong: &SidePieces, weak: &SidePieces) -> i32 {
+    let pawn = strong.pawns[0];
+    let is_win = get_kpk_bitbase().probe(
+        strong.player,
+        strong.king,
+        pawn,
+        weak.king,
+        position.current_turn,
+    );
+    if !is_win {
+        return 0;
+    }
+
+    // Prefer the positions where the pawn is the closest to promotion
+    let relative_rank = match strong.player {
+        Player::White => pawn.r,
+        Player::Black => 7 - pawn.r,
+    } as i32;
+    let score = KNOWN_WIN + PIECE_VALUES[0] + 10 * relative_rank;
+    to_side_to_move(score, strong.player, position)
+}
+
+/// King, bishop and rook pawns vs lone king : if the bishop doesn't control the promotion
+/// square and the weak king reaches it, the position is a draw
+fn scale_wrong_rook_pawn(strong: &SidePieces, weak: &SidePieces) -> Option<u8> {
+    let pawn_file = strong.pawns[0].f;
+    if (pawn_file != 0 && pawn_file != 7) || strong.pawns.iter().any(|pawn| pawn.f != pawn_file) {
+        return None;
+    }
+
+    let promotion_rank = match strong.player {
+        Player::White => 7,
+        Player::Black => 0,
+    };
+    let promotion_square = Coord::new(pawn_file, promotion_rank);
+
+    if is_dark_square(promotion_square) != is_dark_square(strong.bishops[0])
+        && coord_distance(weak.king, promotion_square) <= 1
+    {
+        Some(SCALE_FACTOR_DRAW)
+    } else {
+        None
+    }
+}
+
+/// Return the specialized evaluation of the position, if its material configuration is
+/// a known endgame
+pub fn evaluate_endgame(position: &Position) -> Option<EndgameEvaluation> {
+    let bitboard = &position.piece_centric_board;
+    let white = get_side_pieces(bitboard, Player::White);
+    let black = get_side_pieces(bitboard, Player::Black);
+
+    // Insufficient material : a single minor piece can't mate
+    let is_insufficient = |side: &SidePieces| {
+        side.pawns.is_empty() && side.rooks.is_empty() && side.queens.is_empty() && {
+            side.knights.len() + side.bishops.len() <= 1
+        }
+    };
+    if is_insufficient(&white) && is_insufficient(&black) {
+        return Some(EndgameEvaluation::Score(0));
+    }
+
+    // Specialized evaluations against a lone king
+    for (strong, weak) in [(&white, &black), (&black, &white)] {
+        if !weak.is_bare_king() {
+            continue;
+        }
+
+        let is_kpk = strong.non_pawn_count() == 0 && strong.pawns.len() == 1;
+        let is_kbnk = strong.pawns.is_empty()
+            && strong.non_pawn_count() == 2
+            && strong.bishops.len() == 1
+            && strong.knights.len() == 1;
+        let is_kbpk =
+            strong.non_pawn_count() == 1 && strong.bishops.len() == 1 && !strong.pawns.is_empty();
+
+        if is_kpk {
+            return Some(EndgameEvaluation::Score(evaluate_kpk(
+                position, strong, weak,
+            )));
+        }
+        if is_kbnk {
+            return Some(EndgameEvaluation::Score(evaluate_kbnk(
+                position, strong, weak,
+            )));
+        }
+        if is_kbpk {
+            return scale_wrong_rook_pawn(strong, weak).map(EndgameEvaluation::ScaleFactor);
+        }
+        if strong.can_force_mate() {
+            return Some(EndgameEvaluation::Score(evaluate_kxk(
+                position, strong, weak,
+            )));
+        }
+    }
+
+    // Bishops of opposite colors, with pawns only
+    let is_single_bishop =
+        |side: &SidePieces| side.non_pawn_count() == 1 && side.bishops.len() == 1;
+    if is_single_bishop(&white)
+        && is_single_bishop(&black)
+        && is_dark_square(white.bishops[0]) != is_dark_square(black.bishops[0])
+    {
+        return Some(EndgameEvaluation::ScaleFactor(
+            SCALE_FACTOR_OPPOSITE_BISHOPS,
+        ));
+    }
+
+    None
+}
@@ -0,0 +1,140 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use super::handcrafted::*;
+use super::nnue::*;
+use crate::board_representation::*;
+use crate::move_generation::Move;
+
+/**********
+* EVALUATOR
+***********/
+
+/// Name of the UCI option giving the path of the network file
+pub const EVAL_FILE_OPTION: &str = "EvalFile";
+/// Name of the UCI option switching between the NNUE and handcrafted evaluations
+pub const USE_NNUE_OPTION: &str = "Use NNUE";
+
+/// Entry point of the static evaluation. It uses the NNUE evaluation when a network is
+/// loaded and enabled, and falls back to the handcrafted evaluation otherwise. In both
+/// cases, known endgames are handled by the specialized endgame evaluations.
+///
+/// When using NNUE, the evaluator must follow the moves made on the position (with
+/// make_move and unmake_move) so it can update its accumulators incrementally.
+#[derive(Clone, Debug)]
+pub struct Evaluator {
+    pub use_nnue: bool,
+    /// Path of the loaded network file (empty if none is loaded)
+    pub eval_file: String,
+    /// Shared between the evaluators of all search threads
+    network: Option<Arc<Network>>,
+    accumulators: Option<AccumulatorStack>,
+}
+
+impl Default for Evaluator {
+    fn default() -> Evaluator {
+        Evaluator {
+            use_nnue: true,
+            eval_file: String::new(),
+            network: None,
+            accumulators: None,
+        }
+    }
+}
+
+impl Evaluator {
+    /// Shorthand for default
+    pub fn new() -> Evaluator {
+        Evaluator::default()
+    }
+
+    /// Initialize an evaluator using an already loaded network
+    pub fn with_network(network: Arc<Network>) -> Evaluator {
+        Evaluator {
+            network: Some(network),
+            ..Evaluator::default()
+        }
+    }
+
+    /// Return the network, if one is loaded
+    pub fn get_network(&self) -> Option<&Arc<Network>> {
+        self.network.as_ref()
+    }
+
+    /// Return the accumulator of the followed position, if any
+    pub fn get_accumulator(&self) -> Option<&Accumulator> {
+        self.accumulators.as_ref().map(AccumulatorStack::current)
+    }
+
+    /// Check whether evaluations are done by the network
+    pub fn is_nnue_active(&self) -> bool {
+        self.use_nnue && self.network.is_some()
+    }
+
+    /// Set one of the evaluation UCI options ("EvalFile" or "Use NNUE"). Returns Ok(false)
+    /// if the option is not an evaluation option. An empty EvalFile unloads the network.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<bool, NnueError> {
+        match name {
+            EVAL_FILE_OPTION => {
+                self.network = if value.is_empty() {
+                    None
+                } else {
+                    Some(Arc::new(Network::load(value)?))
+                };
+                self.eval_file = value.to_string();
+                self.accumulators = None;
+                Ok(true)
+            }
+            USE_NNUE_OPTION => {
+                self.use_nnue = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(NnueError::InvalidOptionValue(value.to_string())),
+                };
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Start following a new position (for instance at the root of a search)
+    pub fn refresh(&mut self, position: &Position) {
+        if let Some(network) = &self.network {
+            match &mut self.accumulators {
+                Some(accumulators) => accumulators.refresh(network, position),
+                None => self.accumulators = Some(AccumulatorStack::new(network, position)),
+            }
+        }
+    }
+
+    /// Update the evaluator after a move was made on the position it follows
+    pub fn make_move(&mut self, position: &Position, mov: Move) {
+        if let (Some(network), Some(accumulators)) = (&self.network, &mut self.accumulators) {
+            accumulators.make_move(network, position, mov);
+        }
+    }
+
+    /// Update the evaluator after the last move was unmade
+    pub fn unmake_move(&mut self) {
+        if let Some(accumulators) = &mut self.accumulators {
+            accumulators.unmake_move();
+        }
+    }
+
+    /// Evaluate the position, in centipawns from the point of view of the side to move.
+    /// If the evaluator doesn't follow the position, the accumulator is computed from
+    /// scratch.
+    pub fn evaluate(&self, position: &Position) -> i32 {
+        let network = match &self.network {
+            Some(network) if self.use_nnue => network,
+            _ => return evaluate_handcrafted(position),
+        };
+
+        let score = match &self.accumulators {
+            Some(accumulators) => network.evaluate(accumulators.current(), position.current_turn),
+            None => network.evaluate_position(position),
+        };
+        apply_endgame_evaluation(position, score)
+    }
+}
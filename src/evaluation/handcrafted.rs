@@ -0,0 +1,377 @@
+#![allow(dead_code)]
+
+use super::endgame::*;
+use crate::board_representation::*;
+
+/*********************
+* HANDCRAFTED WEIGHTS
+**********************/
+
+// Tapered evaluation : every term has a middlegame and an endgame value, which are
+// interpolated using the game phase (computed from the remaining non-pawn material).
+// See : <https://www.chessprogramming.org/Tapered_Eval>
+
+/// Middlegame material values, indexed using PieceCode - 1, modulo 6
+pub const MG_PIECE_VALUES: [i32; 6] = [82, 337, 365, 477, 1025, 0];
+/// Endgame material values, indexed using PieceCode - 1, modulo 6
+pub const EG_PIECE_VALUES: [i32; 6] = [94, 281, 297, 512, 936, 0];
+
+/// Contribution of each piece type to the game phase
+pub const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+/// Game phase of the starting position
+pub const MAX_PHASE: i32 = 24;
+
+// Piece-square tables are written from White's point of view, from the 8th rank (first
+// line) to the 1st (last line). Black's tables are obtained by mirroring the ranks.
+
+#[rustfmt::skip]
+const MG_PAWN_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const EG_PAWN_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const MG_KING_TABLE: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const EG_KING_TABLE: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// All the weights of the handcrafted evaluation. They can be converted to and from a flat
+/// parameter vector, which is used by the tuner (see the tuning module).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvaluationParameters {
+    /// Material values, indexed using PieceCode - 1, modulo 6
+    pub mg_piece_values: [i32; 6],
+    pub eg_piece_values: [i32; 6],
+
+    /// Piece-square tables, indexed using PieceCode - 1, modulo 6, then using the square
+    /// index (from a8 to h1, as written above)
+    pub mg_piece_square_tables: [[i32; 64]; 6],
+    pub eg_piece_square_tables: [[i32; 64]; 6],
+}
+
+/// Number of weights in the parameter vector
+pub const PARAMETER_COUNT: usize = 2 * 6 + 2 * 6 * 64;
+
+/// Offsets of the different weights in the parameter vector
+const MG_PIECE_VALUES_OFFSET: usize = 0;
+const EG_PIECE_VALUES_OFFSET: usize = 6;
+const MG_PIECE_SQUARE_TABLES_OFFSET: usize = 12;
+const EG_PIECE_SQUARE_TABLES_OFFSET: usize = 12 + 6 * 64;
+
+/// Weights used by the engine
+pub static DEFAULT_PARAMETERS: EvaluationParameters = EvaluationParameters {
+    mg_piece_values: MG_PIECE_VALUES,
+    eg_piece_values: EG_PIECE_VALUES,
+    mg_piece_square_tables: [
+        MG_PAWN_TABLE,
+        KNIGHT_TABLE,
+        BISHOP_TABLE,
+        ROOK_TABLE,
+        QUEEN_TABLE,
+        MG_KING_TABLE,
+    ],
+    eg_piece_square_tables: [
+        EG_PAWN_TABLE,
+        KNIGHT_TABLE,
+        BISHOP_TABLE,
+        ROOK_TABLE,
+        QUEEN_TABLE,
+        EG_KING_TABLE,
+    ],
+};
+
+impl Default for EvaluationParameters {
+    fn default() -> EvaluationParameters {
+        DEFAULT_PARAMETERS.clone()
+    }
+}
+
+impl EvaluationParameters {
+    /// Return all the weights as a flat vector
+    pub fn to_vector(&self) -> Vec<i32> {
+        let mut vector = Vec::with_capacity(PARAMETER_COUNT);
+        vector.extend_from_slice(&self.mg_piece_values);
+        vector.extend_from_slice(&self.eg_piece_values);
+        for table in self.mg_piece_square_tables.iter() {
+            vector.extend_from_slice(table);
+        }
+        for table in self.eg_piece_square_tables.iter() {
+            vector.extend_from_slice(table);
+        }
+        vector
+    }
+
+    /// Initialize the weights from a flat vector, as returned by to_vector
+    pub fn from_vector(vector: &[i32]) -> EvaluationParameters {
+        assert!(
+            vector.len() == PARAMETER_COUNT,
+            "Parameter vector has {} weights instead of {}",
+            vector.len(),
+            PARAMETER_COUNT
+        );
+
+        let mut parameters = EvaluationParameters {
+            mg_piece_values: [0; 6],
+            eg_piece_values: [0; 6],
+            mg_piece_square_tables: [[0; 64]; 6],
+            eg_piece_square_tables: [[0; 64]; 6],
+        };
+        parameters
+            .mg_piece_values
+            .copy_from_slice(&vector[MG_PIECE_VALUES_OFFSET..EG_PIECE_VALUES_OFFSET]);
+        parameters
+            .eg_piece_values
+            .copy_from_slice(&vector[EG_PIECE_VALUES_OFFSET..MG_PIECE_SQUARE_TABLES_OFFSET]);
+        for i in 0..6 {
+            let mg_offset = MG_PIECE_SQUARE_TABLES_OFFSET + 64 * i;
+            let eg_offset = EG_PIECE_SQUARE_TABLES_OFFSET + 64 * i;
+            parameters.mg_piece_square_tables[i]
+                .copy_from_slice(&vector[mg_offset..mg_offset + 64]);
+            parameters.eg_piece_square_tables[i]
+                .copy_from_slice(&vector[eg_offset..eg_offset + 64]);
+        }
+        parameters
+    }
+
+    /// Return the Rust definition of DEFAULT_PARAMETERS with these weights, so tuned
+    /// values can be pasted back into this file
+    pub fn to_rust_source(&self) -> String {
+        let write_table = |source: &mut String, table: &[i32; 64]| {
+            source.push_str("        [\n");
+            for rank in table.chunks(8) {
+                let values: Vec<String> = rank.iter().map(|value| format!("{:4}", value)).collect();
+                source.push_str(&format!("           {},\n", values.join(",")));
+            }
+            source.push_str("        ],\n");
+        };
+
+        let mut source = String::from(
+            "pub static DEFAULT_PARAMETERS: EvaluationParameters = EvaluationParameters {\n",
+        );
+        source.push_str(&format!(
+            "    mg_piece_values: {:?},\n",
+            self.mg_piece_values
+        ));
+        source.push_str(&format!(
+            "    eg_piece_values: {:?},\n",
+            self.eg_piece_values
+        ));
+        source.push_str("    mg_piece_square_tables: [\n");
+        for table in self.mg_piece_square_tables.iter() {
+            write_table(&mut source, table);
+        }
+        source.push_str("    ],\n    eg_piece_square_tables: [\n");
+        for table in self.eg_piece_square_tables.iter() {
+            write_table(&mut source, table);
+        }
+        source.push_str("    ],\n};\n");
+        source
+    }
+}
+
+/**********************
+* HANDCRAFTED EVALUATION
+***********************/
+
+/// Return the index of a square in the piece-square tables of a player
+#[inline(always)]
+fn get_table_index(coord: Coord, player: Player) -> usize {
+    match player {
+        Player::White => ((7 - coord.r) * 8 + coord.f) as usize,
+        Player::Black => (coord.r * 8 + coord.f) as usize,
+    }
+}
+
+/// Return the game phase, from MAX_PHASE (all pieces on the board) to 0 (pawns and kings
+/// only)
+pub fn get_game_phase(bitboard: &BitBoard) -> i32 {
+    let phase: i32 = bitboard
+        .main_boards
+        .iter()
+        .enumerate()
+        .map(|(i, board)| board.count_ones() as i32 * PHASE_WEIGHTS[i % 6])
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+/// Call a function for each piece on the board, with its player, its type (PieceCode - 1,
+/// modulo 6) and its index in the piece-square tables
+fn for_each_piece<F: FnMut(Player, usize, usize)>(bitboard: &BitBoard, mut f: F) {
+    for (i, board) in bitboard.main_boards.iter().enumerate() {
+        let player = if i < 6 { Player::White } else { Player::Black };
+
+        for square in Bitboard(*board).iter() {
+            f(player, i % 6, get_table_index(square.to_coord(), player));
+        }
+    }
+}
+
+/// Evaluate the position with the default weights, in centipawns from the point of view
+/// of the side to move. Known endgames are handled by evaluate_endgame.
+pub fn evaluate_handcrafted(position: &Position) -> i32 {
+    evaluate_with_parameters(position, &DEFAULT_PARAMETERS)
+}
+
+/// Evaluate the position with material and piece-square tables, in centipawns from the
+/// point of view of the side to move
+pub fn evaluate_with_parameters(position: &Position, parameters: &EvaluationParameters) -> i32 {
+    let bitboard = &position.piece_centric_board;
+    let mut mg_score = 0;
+    let mut eg_score = 0;
+
+    for_each_piece(bitboard, |player, piece_type, table_index| {
+        let sign = match player {
+            Player::White => 1,
+            Player::Black => -1,
+        };
+        mg_score += sign
+            * (parameters.mg_piece_values[piece_type]
+                + parameters.mg_piece_square_tables[piece_type][table_index]);
+        eg_score += sign
+            * (parameters.eg_piece_values[piece_type]
+                + parameters.eg_piece_square_tables[piece_type][table_index]);
+    });
+
+    let phase = get_game_phase(bitboard);
+    let score = (mg_score * phase + eg_score * (MAX_PHASE - phase)) / MAX_PHASE;
+    let score = match position.current_turn {
+        Player::White => score,
+        Player::Black => -score,
+    };
+
+    apply_endgame_evaluation(position, score)
+}
+
+/// Return the coefficient of each weight of the parameter vector in the evaluation of the
+/// position (from White's point of view), as sparse (index, coefficient) pairs. The
+/// handcrafted evaluation is linear in its weights, so it is the sum of the weights
+/// multiplied by their coefficients (up to rounding). Returns None if the evaluation
+/// doesn't depend on the weights (known endgames).
+pub fn get_parameter_coefficients(position: &Position) -> Option<Vec<(usize, f64)>> {
+    let scale = match evaluate_endgame(position) {
+        Some(EndgameEvaluation::Score(_)) => return None,
+        Some(EndgameEvaluation::ScaleFactor(factor)) => factor as f64 / SCALE_FACTOR_NORMAL as f64,
+        None => 1.0,
+    };
+
+    let bitboard = &position.piece_centric_board;
+    let phase = get_game_phase(bitboard) as f64 / MAX_PHASE as f64;
+    let mut coefficients = vec![0.0; PARAMETER_COUNT];
+
+    for_each_piece(bitboard, |player, piece_type, table_index| {
+        let sign = match player {
+            Player::White => scale,
+            Player::Black => -scale,
+        };
+        coefficients[MG_PIECE_VALUES_OFFSET + piece_type] += sign * phase;
+        coefficients[EG_PIECE_VALUES_OFFSET + piece_type] += sign * (1.0 - phase);
+        coefficients[MG_PIECE_SQUARE_TABLES_OFFSET + 64 * piece_type + table_index] += sign * phase;
+        coefficients[EG_PIECE_SQUARE_TABLES_OFFSET + 64 * piece_type + table_index] +=
+            sign * (1.0 - phase);
+    });
+
+    Some(
+        coefficients
+            .into_iter()
+            .enumerate()
+            .filter(|(_, coefficient)| *coefficient != 0.0)
+            .collect(),
+    )
+}
+
+/// Replace or scale an evaluation (from the point of view of the side to move) using the
+/// specialized endgame evaluations
+pub fn apply_endgame_evaluation(position: &Position, score: i32) -> i32 {
+    match evaluate_endgame(position) {
+        Some(EndgameEvaluation::Score(score)) => score,
+        Some(EndgameEvaluation::ScaleFactor(factor)) => {
+            score * factor as i32 / SCALE_FACTOR_NORMAL as i32
+        }
+        None => score,
+    }
+}
@@ -0,0 +1,255 @@
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+
+use crate::board_representation::*;
+
+/*************
+* KPK BITBASE
+**************/
+
+// The KPK bitbase stores, for every King + Pawn vs King position, whether the side with the
+// pawn wins. It is generated by retrograde analysis : positions that are trivially won
+// (unstoppable promotion) or drawn (stalemate, pawn capture) are classified first, and the
+// result is then propagated to their predecessors until no more positions change.
+// Positions are normalized so that the pawn is white, and on the a-d files.
+// See : <https://www.chessprogramming.org/KPK>
+
+// In this file, squares are indexed from 0 (a1) to 63 (h8) in row-major ordering
+
+/// Number of pawn squares (files a-d, ranks 2-7)
+const PAWN_SQUARES: usize = 24;
+const MAX_INDEX: usize = 2 * PAWN_SQUARES * 64 * 64;
+
+// Results used during the generation. They are used as bit flags, so that the results of
+// all the successors of a position can be OR-ed together.
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+/// Number of king moves needed to go from one square to another
+pub fn square_distance(square_a: usize, square_b: usize) -> usize {
+    let file_distance = file_of(square_a).abs_diff(file_of(square_b));
+    let rank_distance = rank_of(square_a).abs_diff(rank_of(square_b));
+    file_distance.max(rank_distance)
+}
+
+/// Return the squares a king located on the square can move to
+fn king_moves(square: usize) -> impl Iterator<Item = usize> {
+    let (file, rank) = (file_of(square) as isize, rank_of(square) as isize);
+    (-1..=1)
+        .flat_map(move |file_offset| (-1..=1).map(move |rank_offset| (file_offset, rank_offset)))
+        .filter(|offsets| *offsets != (0, 0))
+        .map(move |(file_offset, rank_offset)| (file + file_offset, rank + rank_offset))
+        .filter(|(file, rank)| (0..8).contains(file) && (0..8).contains(rank))
+        .map(|(file, rank)| (rank * 8 + file) as usize)
+}
+
+/// Check whether a white pawn located on pawn_square attacks the square
+fn is_attacked_by_pawn(pawn_square: usize, square: usize) -> bool {
+    rank_of(square) == rank_of(pawn_square) + 1
+        && file_of(square).abs_diff(file_of(pawn_square)) == 1
+}
+
+/// Index of a normalized position in the bitbase
+fn get_index(side_to_move: Player, white_king: usize, black_king: usize, pawn: usize) -> usize {
+    let pawn_index = (rank_of(pawn) - 1) * 4 + file_of(pawn);
+    side_to_move as usize + 2 * (black_king + 64 * (white_king + 64 * pawn_index))
+}
+
+/// Decode an index into the side to move, white king, black king and pawn squares
+fn decode_index(index: usize) -> (Player, usize, usize, usize) {
+    let side_to_move = if index & 1 == 0 {
+        Player::White
+    } else {
+        Player::Black
+    };
+    let black_king = (index >> 1) & 63;
+    let white_king = (index >> 7) & 63;
+    let pawn_index = index >> 13;
+    let pawn = 8 * (pawn_index / 4 + 1) + pawn_index % 4;
+
+    (side_to_move, white_king, black_king, pawn)
+}
+
+/// Classify the positions that can be evaluated without looking at their successors
+fn classify_leaf(index: usize) -> u8 {
+    let (side_to_move, white_king, black_king, pawn) = decode_index(index);
+
+    // Overlapping pieces, adjacent kings, or black king in check with White to move
+    if square_distance(white_king, black_king) <= 1
+        || white_king == pawn
+        || black_king == pawn
+        || (side_to_move == Player::White && is_attacked_by_pawn(pawn, black_king))
+    {
+        return INVALID;
+    }
+
+    // The pawn promotes, and the new queen can't be captured
+    let promotion_square = pawn + 8;
+    if side_to_move == Player::White
+        && rank_of(pawn) == 6
+        && white_king != promotion_square
+        && (square_distance(black_king, promotion_square) > 1
+            || square_distance(white_king, promotion_square) == 1)
+    {
+        return WIN;
+    }
+
+    if side_to_move == Player::Black {
+        let is_safe = |square: usize| {
+            square_distance(square, white_king) > 1 && !is_attacked_by_pawn(pawn, square)
+        };
+
+        // Stalemate
+        if !king_moves(black_king).any(is_safe) {
+            return DRAW;
+        }
+
+        // The black king can capture the undefended pawn
+        if square_distance(black_king, pawn) == 1 && square_distance(white_king, pawn) > 1 {
+            return DRAW;
+        }
+    }
+
+    UNKNOWN
+}
+
+/// Classify a position from the results of its successors
+fn classify(results: &[u8], index: usize) -> u8 {
+    let (side_to_move, white_king, black_king, pawn) = decode_index(index);
+    let mut successors = INVALID;
+
+    // Moves leading to invalid positions (illegal moves) are OR-ed as INVALID, and are
+    // thus ignored
+    if side_to_move == Player::White {
+        for square in king_moves(white_king) {
+            successors |= results[get_index(Player::Black, square, black_king, pawn)];
+        }
+
+        // Single and double pawn pushes (promotions are handled in classify_leaf)
+        if rank_of(pawn) < 6 {
+            successors |= results[get_index(Player::Black, white_king, black_king, pawn + 8)];
+        }
+        if rank_of(pawn) == 1 && pawn + 8 != white_king && pawn + 8 != black_king {
+            successors |= results[get_index(Player::Black, white_king, black_king, pawn + 16)];
+        }
+
+        // White needs a single winning move, Black must have no drawing move
+        if successors & WIN != 0 {
+            WIN
+        } else if successors & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            DRAW
+        }
+    } else {
+        for square in king_moves(black_king) {
+            successors |= results[get_index(Player::White, white_king, square, pawn)];
+        }
+
+        if successors & DRAW != 0 {
+            DRAW
+        } else if successors & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            WIN
+        }
+    }
+}
+
+/// Bitset of all the normalized KPK positions, where a set bit means the side with the
+/// pawn wins
+#[derive(Debug)]
+pub struct KPKBitbase {
+    bits: Vec<u64>,
+}
+
+impl Default for KPKBitbase {
+    /// Generate the bitbase by retrograde analysis
+    fn default() -> KPKBitbase {
+        let mut results: Vec<u8> = (0..MAX_INDEX).map(classify_leaf).collect();
+
+        // Propagate results until we reach a fixed point. The remaining unknown
+        // positions can't be won, and are thus draws.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..MAX_INDEX {
+                if results[index] == UNKNOWN {
+                    results[index] = classify(&results, index);
+                    changed |= results[index] != UNKNOWN;
+                }
+            }
+        }
+
+        let mut bits = vec![0; MAX_INDEX / 64];
+        for (index, result) in results.iter().enumerate() {
+            if *result == WIN {
+                bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+
+        KPKBitbase { bits }
+    }
+}
+
+impl KPKBitbase {
+    /// Shorthand for default
+    pub fn new() -> KPKBitbase {
+        KPKBitbase::default()
+    }
+
+    /// Check whether the side with the pawn wins. Squares are given as Coords, and the
+    /// position doesn't need to be normalized.
+    pub fn probe(
+        &self,
+        strong_side: Player,
+        strong_king: Coord,
+        pawn: Coord,
+        weak_king: Coord,
+        side_to_move: Player,
+    ) -> bool {
+        let to_square = |coord: Coord| (coord.r * 8 + coord.f) as usize;
+        let (mut strong_king, mut pawn, mut weak_king) = (
+            to_square(strong_king),
+            to_square(pawn),
+            to_square(weak_king),
+        );
+        let mut side_to_move = side_to_move;
+
+        // Flip the board vertically so the strong side is white
+        if strong_side == Player::Black {
+            strong_king ^= 56;
+            pawn ^= 56;
+            weak_king ^= 56;
+            side_to_move = invert_player(&side_to_move);
+        }
+
+        // Mirror the board horizontally so the pawn is on the a-d files
+        if file_of(pawn) >= 4 {
+            strong_king ^= 7;
+            pawn ^= 7;
+            weak_king ^= 7;
+        }
+
+        let index = get_index(side_to_move, strong_king, weak_king, pawn);
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+}
+
+static KPK_BITBASE: OnceLock<KPKBitbase> = OnceLock::new();
+
+/// Return the KPK bitbase, generating it on the first call
+pub fn get_kpk_bitbase() -> &'static KPKBitbase {
+    KPK_BITBASE.get_or_init(KPKBitbase::new)
+}
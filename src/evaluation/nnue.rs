@@ -0,0 +1,685 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::board_representation::*;
+use crate::move_generation::*;
+
+/***************
+* NNUE DATATYPES
+****************/
+
+// Efficiently Updatable Neural Networks evaluate a position with a first layer whose inputs
+// are (king square, piece, square) features. Since a move only changes a few features, the
+// output of this layer (the accumulator) can be updated incrementally instead of being
+// recomputed, which makes the network fast enough to be used in the search.
+// See : <https://www.chessprogramming.org/NNUE>
+//
+// Layout of the network :
+// - feature transformer : features -> L1 (int16 weights), once per perspective
+// - hidden layer 1 : 2 * L1 (side to move first) -> L2 (int8 weights, int32 biases)
+// - hidden layer 2 : L2 -> L3 (int8 weights, int32 biases)
+// - output layer : L3 -> 1 (int8 weights, int32 bias)
+// All activations are clipped ReLUs in [0, 127].
+//
+// Network files use the following format (all values in little-endian) :
+// - magic "KRNN", version (u32), feature set (u8, 0 for HalfKP and 1 for HalfKA)
+// - L1, L2 and L3 sizes (u32)
+// - feature biases (i16 * L1), feature weights (i16 * features * L1, by feature)
+// - layer 1 biases (i32 * L2), layer 1 weights (i8 * L2 * 2L1, by output neuron)
+// - layer 2 biases (i32 * L3), layer 2 weights (i8 * L3 * L2, by output neuron)
+// - output bias (i32), output weights (i8 * L3)
+
+/// Magic bytes at the start of network files
+pub const NNUE_MAGIC: &[u8; 4] = b"KRNN";
+/// Version of the network file format
+pub const NNUE_VERSION: u32 = 1;
+
+/// Hidden layers' weights are scaled by 2^WEIGHT_SCALE_BITS
+pub const WEIGHT_SCALE_BITS: u32 = 6;
+/// The network's output is divided by this value to get centipawns
+pub const OUTPUT_SCALE: i32 = 16;
+/// Upper bound of the clipped ReLU activations
+const CLIPPED_RELU_MAX: i16 = 127;
+
+/// Input features of the network
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FeatureSet {
+    /// King square x (non-king piece, square) : 64 * 10 * 64 features
+    HalfKP,
+    /// King square x (piece including kings, square) : 64 * 12 * 64 features
+    HalfKA,
+}
+
+/// Errors that can occur while loading a network, or setting one of the NNUE options
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum NnueError {
+    /// The network file can't be read
+    Io(String),
+    /// The file doesn't start with the network magic bytes
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    UnknownFeatureSet(u8),
+    /// The file ends before all the weights were read
+    UnexpectedEndOfFile,
+    /// The file contains more bytes than the network needs
+    TrailingBytes,
+    /// An option was given an invalid value
+    InvalidOptionValue(String),
+}
+
+impl fmt::Display for NnueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NnueError::Io(error) => write!(f, "can't read network file: {}", error),
+            NnueError::InvalidMagic => write!(f, "not a network file"),
+            NnueError::UnsupportedVersion(version) => {
+                write!(f, "unsupported network version: {}", version)
+            }
+            NnueError::UnknownFeatureSet(id) => write!(f, "unknown feature set: {}", id),
+            NnueError::UnexpectedEndOfFile => write!(f, "unexpected end of network file"),
+            NnueError::TrailingBytes => write!(f, "trailing bytes in network file"),
+            NnueError::InvalidOptionValue(value) => write!(f, "invalid option value: {}", value),
+        }
+    }
+}
+
+impl std::error::Error for NnueError {}
+
+/// Quantized network weights
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Network {
+    pub feature_set: FeatureSet,
+    pub l1_size: usize,
+    pub l2_size: usize,
+    pub l3_size: usize,
+
+    pub feature_biases: Vec<i16>,
+    /// Weights of each feature, stored contiguously (feature_count * l1_size)
+    pub feature_weights: Vec<i16>,
+
+    pub l1_biases: Vec<i32>,
+    /// Weights of each output neuron, stored contiguously (l2_size * 2 * l1_size)
+    pub l1_weights: Vec<i8>,
+
+    pub l2_biases: Vec<i32>,
+    /// Weights of each output neuron, stored contiguously (l3_size * l2_size)
+    pub l2_weights: Vec<i8>,
+
+    pub output_bias: i32,
+    pub output_weights: Vec<i8>,
+}
+
+/// Output of the feature transformer, for both perspectives.
+/// Should be indexed using the Player enum.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Accumulator {
+    pub values: [Vec<i16>; 2],
+}
+
+/// Accumulators of the positions of the current line, so unmaking a move only requires
+/// going back to the previous accumulator
+#[derive(Clone, Debug)]
+pub struct AccumulatorStack {
+    accumulators: Vec<Accumulator>,
+    /// Index of the accumulator of the current position
+    current: usize,
+}
+
+/****************
+* FEATURE INDEXES
+*****************/
+
+impl FeatureSet {
+    fn from_id(id: u8) -> Result<FeatureSet, NnueError> {
+        match id {
+            0 => Ok(FeatureSet::HalfKP),
+            1 => Ok(FeatureSet::HalfKA),
+            _ => Err(NnueError::UnknownFeatureSet(id)),
+        }
+    }
+
+    fn id(self) -> u8 {
+        match self {
+            FeatureSet::HalfKP => 0,
+            FeatureSet::HalfKA => 1,
+        }
+    }
+
+    /// Number of piece kinds (type and color) used as features
+    fn piece_kinds(self) -> usize {
+        match self {
+            FeatureSet::HalfKP => 10,
+            FeatureSet::HalfKA => 12,
+        }
+    }
+
+    /// Total number of input features
+    pub fn feature_count(self) -> usize {
+        64 * self.piece_kinds() * 64
+    }
+
+    /// Return the index of the feature of a piece standing on a square, from the point of
+    /// view of a player whose king is on king_coord. Black's point of view is obtained by
+    /// mirroring the ranks, and piece colors are relative to the perspective. Returns None
+    /// for pieces that are not features (kings with HalfKP).
+    pub fn get_feature_index(
+        self,
+        perspective: Player,
+        king_coord: Coord,
+        piece_code: PieceCode,
+        coord: Coord,
+    ) -> Option<usize> {
+        let piece_type = (piece_code as usize - 1) % 6;
+        if self == FeatureSet::HalfKP && piece_type == 5 {
+            return None;
+        }
+
+        let is_own_piece = (piece_code as usize <= 6) == (perspective == Player::White);
+        let piece_kind = 2 * piece_type + !is_own_piece as usize;
+
+        let orient = |coord: Coord| match perspective {
+            Player::White => (coord.r * 8 + coord.f) as usize,
+            Player::Black => ((7 - coord.r) * 8 + coord.f) as usize,
+        };
+
+        Some((orient(king_coord) * self.piece_kinds() + piece_kind) * 64 + orient(coord))
+    }
+}
+
+/*************
+* SIMD HELPERS
+**************/
+
+// Hot loops have an AVX2 implementation on x86_64, selected at runtime, and a portable
+// fallback that the compiler can still auto-vectorize. Both must give the same results
+// (i16 additions wrap around in both cases).
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    /// # Safety
+    /// The CPU must support AVX2, and weights must be at least as long as values.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn add_weights(values: &mut [i16], weights: &[i16]) {
+        let chunks = values.len() / 16;
+        for i in 0..chunks {
+            let value_ptr = values.as_mut_ptr().add(16 * i) as *mut __m256i;
+            let weight_ptr = weights.as_ptr().add(16 * i) as *const __m256i;
+            let sum = _mm256_add_epi16(
+                _mm256_loadu_si256(value_ptr),
+                _mm256_loadu_si256(weight_ptr),
+            );
+            _mm256_storeu_si256(value_ptr, sum);
+        }
+        super::add_weights_fallback(&mut values[16 * chunks..], &weights[16 * chunks..]);
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2, and weights must be at least as long as values.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn sub_weights(values: &mut [i16], weights: &[i16]) {
+        let chunks = values.len() / 16;
+        for i in 0..chunks {
+            let value_ptr = values.as_mut_ptr().add(16 * i) as *mut __m256i;
+            let weight_ptr = weights.as_ptr().add(16 * i) as *const __m256i;
+            let difference = _mm256_sub_epi16(
+                _mm256_loadu_si256(value_ptr),
+                _mm256_loadu_si256(weight_ptr),
+            );
+            _mm256_storeu_si256(value_ptr, difference);
+        }
+        super::sub_weights_fallback(&mut values[16 * chunks..], &weights[16 * chunks..]);
+    }
+
+    /// Inputs must be in [0, 127], so the intermediate i16 sums can't saturate
+    ///
+    /// # Safety
+    /// The CPU must support AVX2, and weights must be at least as long as inputs.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn dot_product(inputs: &[u8], weights: &[i8]) -> i32 {
+        let chunks = inputs.len() / 32;
+        let ones = _mm256_set1_epi16(1);
+        let mut sums = _mm256_setzero_si256();
+        for i in 0..chunks {
+            let input = _mm256_loadu_si256(inputs.as_ptr().add(32 * i) as *const __m256i);
+            let weight = _mm256_loadu_si256(weights.as_ptr().add(32 * i) as *const __m256i);
+            let products = _mm256_madd_epi16(_mm256_maddubs_epi16(input, weight), ones);
+            sums = _mm256_add_epi32(sums, products);
+        }
+
+        let mut lanes = [0i32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sums);
+        lanes.iter().sum::<i32>()
+            + super::dot_product_fallback(&inputs[32 * chunks..], &weights[32 * chunks..])
+    }
+}
+
+pub(crate) fn add_weights_fallback(values: &mut [i16], weights: &[i16]) {
+    for (value, weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_add(*weight);
+    }
+}
+
+pub(crate) fn sub_weights_fallback(values: &mut [i16], weights: &[i16]) {
+    for (value, weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_sub(*weight);
+    }
+}
+
+pub(crate) fn dot_product_fallback(inputs: &[u8], weights: &[i8]) -> i32 {
+    inputs
+        .iter()
+        .zip(weights)
+        .map(|(input, weight)| *input as i32 * *weight as i32)
+        .sum()
+}
+
+/// Add the weights of a feature to an accumulator
+pub(crate) fn add_weights(values: &mut [i16], weights: &[i16]) {
+    assert_eq!(values.len(), weights.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // Safety : AVX2 support was just checked, and the lengths match
+        return unsafe { avx2::add_weights(values, weights) };
+    }
+    add_weights_fallback(values, weights)
+}
+
+/// Remove the weights of a feature from an accumulator
+pub(crate) fn sub_weights(values: &mut [i16], weights: &[i16]) {
+    assert_eq!(values.len(), weights.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // Safety : AVX2 support was just checked, and the lengths match
+        return unsafe { avx2::sub_weights(values, weights) };
+    }
+    sub_weights_fallback(values, weights)
+}
+
+/// Dot product of clipped activations (in [0, 127]) and int8 weights
+pub(crate) fn dot_product(inputs: &[u8], weights: &[i8]) -> i32 {
+    assert_eq!(inputs.len(), weights.len());
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // Safety : AVX2 support was just checked, and the lengths match
+        return unsafe { avx2::dot_product(inputs, weights) };
+    }
+    dot_product_fallback(inputs, weights)
+}
+
+/****************
+* NETWORK LOADING
+*****************/
+
+/// Little-endian reader over the bytes of a network file
+struct NetworkReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl NetworkReader<'_> {
+    fn read_bytes(&mut self, n: usize) -> Result<&[u8], NnueError> {
+        if self.bytes.len() < n {
+            return Err(NnueError::UnexpectedEndOfFile);
+        }
+        let (bytes, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, NnueError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn read_i16s(&mut self, n: usize) -> Result<Vec<i16>, NnueError> {
+        let bytes = self.read_bytes(2 * n)?;
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+            .collect())
+    }
+
+    fn read_i32s(&mut self, n: usize) -> Result<Vec<i32>, NnueError> {
+        let bytes = self.read_bytes(4 * n)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| i32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    fn read_i8s(&mut self, n: usize) -> Result<Vec<i8>, NnueError> {
+        Ok(self.read_bytes(n)?.iter().map(|byte| *byte as i8).collect())
+    }
+}
+
+impl Network {
+    /// Initialize a network with all its weights and biases set to 0. This is meant for
+    /// training tools, which then fill in the weights.
+    pub fn zeroed(
+        feature_set: FeatureSet,
+        l1_size: usize,
+        l2_size: usize,
+        l3_size: usize,
+    ) -> Network {
+        Network {
+            feature_set,
+            l1_size,
+            l2_size,
+            l3_size,
+            feature_biases: vec![0; l1_size],
+            feature_weights: vec![0; feature_set.feature_count() * l1_size],
+            l1_biases: vec![0; l2_size],
+            l1_weights: vec![0; l2_size * 2 * l1_size],
+            l2_biases: vec![0; l3_size],
+            l2_weights: vec![0; l3_size * l2_size],
+            output_bias: 0,
+            output_weights: vec![0; l3_size],
+        }
+    }
+
+    /// Read a network from the content of a network file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, NnueError> {
+        let mut reader = NetworkReader { bytes };
+
+        if reader.read_bytes(4).map_err(|_| NnueError::InvalidMagic)? != NNUE_MAGIC {
+            return Err(NnueError::InvalidMagic);
+        }
+        let version = reader.read_u32()?;
+        if version != NNUE_VERSION {
+            return Err(NnueError::UnsupportedVersion(version));
+        }
+        let feature_set = FeatureSet::from_id(reader.read_bytes(1)?[0])?;
+        let l1_size = reader.read_u32()? as usize;
+        let l2_size = reader.read_u32()? as usize;
+        let l3_size = reader.read_u32()? as usize;
+
+        let network = Network {
+            feature_set,
+            l1_size,
+            l2_size,
+            l3_size,
+            feature_biases: reader.read_i16s(l1_size)?,
+            feature_weights: reader.read_i16s(feature_set.feature_count() * l1_size)?,
+            l1_biases: reader.read_i32s(l2_size)?,
+            l1_weights: reader.read_i8s(l2_size * 2 * l1_size)?,
+            l2_biases: reader.read_i32s(l3_size)?,
+            l2_weights: reader.read_i8s(l3_size * l2_size)?,
+            output_bias: reader.read_i32s(1)?[0],
+            output_weights: reader.read_i8s(l3_size)?,
+        };
+
+        if !reader.bytes.is_empty() {
+            return Err(NnueError::TrailingBytes);
+        }
+        Ok(network)
+    }
+
+    /// Load a network file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Network, NnueError> {
+        let bytes = fs::read(path).map_err(|error| NnueError::Io(error.to_string()))?;
+        Network::from_bytes(&bytes)
+    }
+
+    /// Return the content of the network file describing this network
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(NNUE_MAGIC);
+        bytes.extend_from_slice(&NNUE_VERSION.to_le_bytes());
+        bytes.push(self.feature_set.id());
+        for size in [self.l1_size, self.l2_size, self.l3_size] {
+            bytes.extend_from_slice(&(size as u32).to_le_bytes());
+        }
+
+        for value in self.feature_biases.iter().chain(&self.feature_weights) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let layers = [
+            (&self.l1_biases, &self.l1_weights),
+            (&self.l2_biases, &self.l2_weights),
+        ];
+        for (biases, weights) in layers {
+            for bias in biases {
+                bytes.extend_from_slice(&bias.to_le_bytes());
+            }
+            bytes.extend(weights.iter().map(|weight| *weight as u8));
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes.extend(self.output_weights.iter().map(|weight| *weight as u8));
+
+        bytes
+    }
+
+    /// Return the weights of a feature in the feature transformer
+    #[inline(always)]
+    fn get_feature_weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.l1_size..(feature + 1) * self.l1_size]
+    }
+}
+
+/*************
+* ACCUMULATORS
+**************/
+
+impl Accumulator {
+    /// Compute the accumulator of a position from scratch
+    pub fn new(network: &Network, position: &Position) -> Accumulator {
+        let mut accumulator = Accumulator {
+            values: [Vec::new(), Vec::new()],
+        };
+        for perspective in [Player::White, Player::Black] {
+            accumulator.refresh(network, position, perspective);
+        }
+        accumulator
+    }
+
+    /// Recompute the accumulator of one perspective from scratch. This is needed when the
+    /// king of the perspective moves, as all its features change.
+    pub fn refresh(&mut self, network: &Network, position: &Position, perspective: Player) {
+        let values = &mut self.values[perspective as usize];
+        values.clear();
+        values.extend_from_slice(&network.feature_biases);
+
+        let king_coord = match position.get_king_coord(perspective) {
+            Some(king_coord) => king_coord,
+            None => return,
+        };
+
+        for (i, board) in position.piece_centric_board.main_boards.iter().enumerate() {
+            let piece_code = PieceCode::from_usize(i + 1);
+            for square in Bitboard(*board).iter() {
+                let coord = square.to_coord();
+                if let Some(feature) = network.feature_set.get_feature_index(
+                    perspective,
+                    king_coord,
+                    piece_code,
+                    coord,
+                ) {
+                    add_weights(values, network.get_feature_weights(feature));
+                }
+            }
+        }
+    }
+}
+
+/// Pieces removed from the board, and pieces added to it, as (piece, square) pairs
+type MoveDeltas = (Vec<(PieceCode, Coord)>, Vec<(PieceCode, Coord)>);
+
+/// Return the pieces removed from and added to the board by a move, as (piece, square)
+/// pairs. The position must be the one reached after making the move.
+fn get_move_deltas(position: &Position, mov: Move) -> MoveDeltas {
+    let player = invert_player(&position.current_turn);
+    let start = get_move_start_coords(mov);
+    let arrival = get_move_arrival_coords(mov);
+    let piece_code = get_move_piece_code(mov);
+
+    let mut removed = vec![(piece_code, start)];
+    let mut added = Vec::with_capacity(2);
+
+    if get_move_kingside_castling(mov) || get_move_queenside_castling(mov) {
+        // Castling rights were cleared by the move, so the rook is found in the history
+        let state = position
+            .history
+            .last()
+            .expect("Castling move without any move history");
+        let kingside = get_move_kingside_castling(mov);
+        let rook_file = if kingside {
+            state.kingside_castling_rook[player as usize]
+        } else {
+            state.queenside_castling_rook[player as usize]
+        }
+        .expect("Castling move without the corresponding castling right");
+        let (_, rook_arrival_file) = get_castling_arrival_files(kingside);
+        let rook_code = position.get_square(Coord::new(rook_arrival_file, start.r));
+
+        removed.push((rook_code, Coord::new(rook_file, start.r)));
+        added.push((piece_code, arrival));
+        added.push((rook_code, Coord::new(rook_arrival_file, start.r)));
+    } else {
+        if get_move_en_passant_capture(mov) {
+            removed.push((get_move_arrival_square(mov), Coord::new(arrival.f, start.r)));
+        } else if get_move_capture(mov) {
+            removed.push((get_move_arrival_square(mov), arrival));
+        }
+
+        if get_move_promotion(mov) {
+            added.push((get_move_promotion_piece_code(mov), arrival));
+        } else {
+            added.push((piece_code, arrival));
+        }
+    }
+
+    (removed, added)
+}
+
+impl AccumulatorStack {
+    /// Initialize the stack with the accumulator of a position
+    pub fn new(network: &Network, position: &Position) -> AccumulatorStack {
+        AccumulatorStack {
+            accumulators: vec![Accumulator::new(network, position)],
+            current: 0,
+        }
+    }
+
+    /// Discard all accumulators and recompute the one of the position from scratch
+    pub fn refresh(&mut self, network: &Network, position: &Position) {
+        self.accumulators.truncate(1);
+        self.accumulators[0] = Accumulator::new(network, position);
+        self.current = 0;
+    }
+
+    /// Return the accumulator of the current position
+    pub fn current(&self) -> &Accumulator {
+        &self.accumulators[self.current]
+    }
+
+    /// Update the accumulators after a move was made on the position. Features are added
+    /// and removed incrementally, except for the perspective whose king moved, which is
+    /// refreshed.
+    pub fn make_move(&mut self, network: &Network, position: &Position, mov: Move) {
+        // Reuse the allocations of previously popped accumulators
+        if self.current + 1 == self.accumulators.len() {
+            let accumulator = self.accumulators[self.current].clone();
+            self.accumulators.push(accumulator);
+        } else {
+            let (previous, next) = self.accumulators.split_at_mut(self.current + 1);
+            next[0].clone_from(&previous[self.current]);
+        }
+        self.current += 1;
+
+        let player = invert_player(&position.current_turn);
+        let piece_code = get_move_piece_code(mov);
+        let is_king_move = piece_code == PieceCode::WK || piece_code == PieceCode::BK;
+        let (removed, added) = get_move_deltas(position, mov);
+        let accumulator = &mut self.accumulators[self.current];
+
+        for perspective in [Player::White, Player::Black] {
+            if is_king_move && perspective == player {
+                accumulator.refresh(network, position, perspective);
+                continue;
+            }
+
+            let king_coord = match position.get_king_coord(perspective) {
+                Some(king_coord) => king_coord,
+                None => continue,
+            };
+            let values = &mut accumulator.values[perspective as usize];
+            let feature_set = network.feature_set;
+            for (piece_code, coord) in &removed {
+                if let Some(feature) =
+                    feature_set.get_feature_index(perspective, king_coord, *piece_code, *coord)
+                {
+                    sub_weights(values, network.get_feature_weights(feature));
+                }
+            }
+            for (piece_code, coord) in &added {
+                if let Some(feature) =
+                    feature_set.get_feature_index(perspective, king_coord, *piece_code, *coord)
+                {
+                    add_weights(values, network.get_feature_weights(feature));
+                }
+            }
+        }
+    }
+
+    /// Go back to the accumulator of the position before the last move
+    pub fn unmake_move(&mut self) {
+        assert!(
+            self.current > 0,
+            "Tried to unmake a move without any accumulator history"
+        );
+        self.current -= 1;
+    }
+}
+
+/**********
+* INFERENCE
+***********/
+
+/// Apply an int8 layer (weights stored by output neuron) followed by a clipped ReLU
+fn propagate_layer(inputs: &[u8], weights: &[i8], biases: &[i32], outputs: &mut Vec<u8>) {
+    outputs.clear();
+    let input_size = inputs.len();
+    for (i, bias) in biases.iter().enumerate() {
+        let sum = bias + dot_product(inputs, &weights[i * input_size..(i + 1) * input_size]);
+        outputs.push((sum >> WEIGHT_SCALE_BITS).clamp(0, CLIPPED_RELU_MAX as i32) as u8);
+    }
+}
+
+impl Network {
+    /// Evaluate a position from its accumulator, in centipawns from the point of view of
+    /// the side to move
+    pub fn evaluate(&self, accumulator: &Accumulator, side_to_move: Player) -> i32 {
+        let perspectives = [side_to_move, invert_player(&side_to_move)];
+        let mut inputs = Vec::with_capacity(2 * self.l1_size);
+        for perspective in perspectives {
+            inputs.extend(
+                accumulator.values[perspective as usize]
+                    .iter()
+                    .map(|value| (*value).clamp(0, CLIPPED_RELU_MAX) as u8),
+            );
+        }
+
+        let mut l2_inputs = Vec::with_capacity(self.l2_size);
+        propagate_layer(&inputs, &self.l1_weights, &self.l1_biases, &mut l2_inputs);
+        let mut l3_inputs = Vec::with_capacity(self.l3_size);
+        propagate_layer(
+            &l2_inputs,
+            &self.l2_weights,
+            &self.l2_biases,
+            &mut l3_inputs,
+        );
+
+        let output = self.output_bias + dot_product(&l3_inputs, &self.output_weights);
+        output / OUTPUT_SCALE
+    }
+
+    /// Evaluate a position from scratch (without any incremental update)
+    pub fn evaluate_position(&self, position: &Position) -> i32 {
+        self.evaluate(&Accumulator::new(self, position), position.current_turn)
+    }
+}
@@ -0,0 +1,118 @@
+//! Krabnik is a UCI compliant chess engine. The library contains the engine itself (board
+//! representation, move generation, evaluation and search), the protocols and the tools
+//! around it, and is used by the krabnik binary and the benchmarks.
+//!
+//! The types and functions needed to use the engine from another crate are re-exported at
+//! the root of the crate :
+//! - [`Position`], loaded from and written to FEN strings, with its [`Player`],
+//!   [`PieceCode`] and [`Coord`] datatypes
+//! - [`Move`], generated by [`gen_legal_moves`] and played with the [`MovableBoard`] trait
+//! - conversions of moves from and to SAN and UCI notations
+//! - the [`search()`] function, with its [`SearchLimits`] and [`SearchResult`], and the
+//!   [`Evaluator`] it uses
+//! - PGN game records
+//!
+//! With the `serde` feature, the core types also implement Serialize and Deserialize :
+//! positions as FEN strings, game records as PGN, moves as a `UciMove` and search results
+//! as a `SearchReport`.
+//!
+//! Moves are opaque : they are only built by the move generator and the notation parsers,
+//! and only make sense in the position they were generated for. Their squares, pieces and
+//! kind are read with the methods of [`Move`]. The fields of a [`Position`] are private,
+//! and read through its getters.
+//!
+//! Only these re-exports make up the API of the crate. The modules themselves are private,
+//! and the few internals needed by the krabnik binary, the benchmarks and the fuzz targets
+//! are gathered in the hidden `internal` module, which may change without notice.
+//!
+//! # Example
+//!
+//! Loading a position, listing its legal moves, playing one and searching the resulting
+//! position :
+//!
+//! ```
+//! use krabnik::*;
+//!
+//! let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+//! let mut position = Position::from_fen(fen)?;
+//!
+//! // Legal moves, written in SAN
+//! let moves = gen_legal_moves(&mut position);
+//! let sans: Vec<String> = moves
+//!     .iter()
+//!     .map(|mov| get_move_san(&mut position, *mov))
+//!     .collect();
+//! assert_eq!(moves.len(), 27);
+//! assert!(sans.contains(&"Bb5".to_string()));
+//!
+//! // Moves can be made and unmade
+//! let mov = parse_san_move(&mut position, "Bb5").unwrap();
+//! position.make_move(mov);
+//! assert_eq!(
+//!     position.to_fen(),
+//!     "r1bqkbnr/pppp1ppp/2n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"
+//! );
+//!
+//! // Search the position to a fixed depth
+//! let mut evaluator = Evaluator::new();
+//! let limits = SearchLimits {
+//!     depth: Some(4),
+//!     ..SearchLimits::default()
+//! };
+//! let result = search(&mut position, &mut evaluator, &limits);
+//! let best_move = result.best_move.unwrap();
+//! println!("best move : {}", get_move_uci(&position, best_move));
+//!
+//! position.unmake_move(mov);
+//! assert_eq!(position.to_fen(), fen);
+//! # Ok::<(), FenError>(())
+//! ```
+
+pub(crate) mod bench;
+pub(crate) mod board_representation;
+pub(crate) mod cecp;
+pub(crate) mod datagen;
+pub(crate) mod engine_match;
+pub(crate) mod epd_suite;
+pub(crate) mod evaluation;
+pub(crate) mod move_generation;
+pub(crate) mod pgn;
+pub(crate) mod play;
+pub(crate) mod search;
+#[cfg(feature = "serde")]
+pub(crate) mod serialization;
+pub(crate) mod tablebase;
+pub(crate) mod tuning;
+pub(crate) mod uci;
+
+/// Entry points of the krabnik binary, and the internals used by the benchmarks and the
+/// fuzz targets. They are not part of the API of the crate, and may change without notice.
+#[doc(hidden)]
+pub mod internal {
+    pub use crate::bench::run_bench_command;
+    pub use crate::cecp::run_cecp;
+    pub use crate::datagen::run_datagen_command;
+    pub use crate::engine_match::run_match_command;
+    pub use crate::epd_suite::run_epd_command;
+    pub use crate::evaluation::{get_kpk_bitbase, EVAL_FILE_OPTION};
+    pub use crate::move_generation::{gen_captures, gen_pseudolegal_moves};
+    pub use crate::play::run_play_command;
+    pub use crate::tuning::run_tune_command;
+    pub use crate::uci::{
+        parse_go_arguments, run_uci, UciSession, HASH_OPTION, SYZYGY_PATH_OPTION,
+    };
+}
+
+pub use board_representation::{
+    BitBoard, Coord, FenError, Mailbox, PieceCode, Player, Position, PositionError, StaticBoard,
+    STARTING_FEN,
+};
+pub use evaluation::{evaluate_handcrafted, Evaluator, NnueError};
+pub use move_generation::{
+    gen_legal_moves, get_move_san, get_move_uci, parse_san_move, parse_uci_move, perft,
+    MovableBoard, Move, MoveList,
+};
+pub use pgn::{read_pgn, write_pgn, Game, GameResult, MoveNode, PgnError};
+pub use search::{search, SearchLimits, SearchResult};
+#[cfg(feature = "serde")]
+pub use serialization::{SearchReport, UciMove};
@@ -1,6 +1,78 @@
-pub mod board_representation;
-pub mod move_generation;
+use krabnik::internal::*;
+
+use std::env;
+use std::io::{self, BufRead};
+use std::process;
 
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().collect();
+
+    // Subcommands
+    match args.get(1).map(String::as_str) {
+        Some("bench") => {
+            if let Err(error) = run_bench_command(&args[2..]) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            return;
+        }
+        Some("datagen") => {
+            if let Err(error) = run_datagen_command(&args[2..]) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            return;
+        }
+        Some("epd") => {
+            if let Err(error) = run_epd_command(&args[2..]) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            return;
+        }
+        Some("match") => {
+            if let Err(error) = run_match_command(&args[2..]) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            return;
+        }
+        Some("play") => {
+            if let Err(error) = run_play_command(&args[2..]) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            return;
+        }
+        Some("tune") => {
+            if let Err(error) = run_tune_command(&args[2..]) {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            return;
+        }
+        Some(command) => {
+            eprintln!("Error: unknown command: {}", command);
+            process::exit(1);
+        }
+        None => {}
+    }
+
+    // Generate the endgame bitbases at startup, rather than during the first search
+    get_kpk_bitbase();
+
+    // The protocol is detected from the first command : "xboard" for CECP, UCI otherwise
+    let mut first_line = String::new();
+    while first_line.trim().is_empty() {
+        first_line.clear();
+        match io::stdin().lock().read_line(&mut first_line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+    }
+    if first_line.trim() == "xboard" {
+        run_cecp(Some(&first_line));
+    } else {
+        run_uci(Some(&first_line));
+    }
 }
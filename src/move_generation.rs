@@ -2,7 +2,8 @@
  * The move_generation module contains functions to generate an make/unmake moves on a
  * Position.
  *
- * Moves are stored in u32 integers using the following patern (in big-endian) :
+ * Moves are stored in u32 integers (the Move type) using the following patern (in
+ * big-endian) :
  * - 6 bits for starting square(3 bits for file + 3 bits for rank)
  * - 6 bits for arrival square (3 bits for file + 3 bits for rank)
  * - 4 bits for the PieceCode of the moved piece
@@ -22,30 +23,814 @@
  *
  * The move generator itself is split into two main parts :
  * - the pseudo-legal generator, which can precompute all potential moves for a given piece
- * on all the 64 squares
+ * on all the 64 squares (the lookup tables themselves are generated at compile time)
  * - the legal move generator, which retrieves the precomputed pseudolegal moves, and
  * filters out the one that are illegal in a given position. All the legal moves in a
  * position can later be evaluated in a negamax algorithm.
  *
+ * Generated moves are stored in a fixed-capacity MoveList, which doesn't allocate, and
+ * the legal generator has separate entry points for captures, quiet moves, check evasions
+ * and quiet checks, so the search can generate only the moves it needs.
+ *
  * Finally, the MovableBoard trait contains utility methods to make/unmake moves on a
- * Position.
+ * Position, and moves can be converted from and to the Standard Algebraic Notation.
+ * Attack queries (attackers of a square, pins, checkers and static exchanges) are built
+ * on the bitboards and the precomputed lookup tables.
+ *
+ * The tests compare the legal generator with a simple reference generator (only compiled
+ * for the tests), on positions reached by playing random games.
  */
 
+pub mod attacks;
 pub mod legal_generator;
+pub mod lookup_tables;
 pub mod misc;
 pub mod movable_board;
+pub mod move_list;
 pub mod pseudolegal_generator;
+#[cfg(test)]
+pub mod reference_generator;
+pub mod san;
 
+#[allow(unused_imports)]
+pub use attacks::*;
+#[allow(unused_imports)]
 pub use legal_generator::*;
+#[allow(unused_imports)]
+pub use lookup_tables::*;
 pub use misc::*;
 pub use movable_board::*;
+pub use move_list::*;
+#[allow(unused_imports)]
 pub use pseudolegal_generator::*;
+pub use san::*;
 
 /*******************
  * PSEUDOLEGAL TESTS
  *******************/
 
 #[test]
-fn test_static_board() {
+fn test_static_board() {}
+
+#[test]
+fn test_lookup_tables() {
+    use crate::board_representation::*;
+
+    // Leapers
+    assert!(PLMoveLUT::default() == PL_MOVE_LUT, "Failed at assert 0");
+
+    // Sliders, on empty and full boards, and random occupancies
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    let mut random = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for index in 0..64 {
+        let coord = Coord::new(index % 8, index / 8);
+        let mut occupancies = vec![0, u64::MAX];
+        occupancies.extend((0..200).map(|_| random() & random()));
+        for occupancy in occupancies {
+            assert!(
+                get_rook_attacks(coord, occupancy) == gen_rook_attacks(coord, occupancy),
+                "Failed at assert 1"
+            );
+            assert!(
+                get_bishop_attacks(coord, occupancy) == gen_bishop_attacks(coord, occupancy),
+                "Failed at assert 2"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_between_and_line() {
+    use crate::board_representation::*;
+
+    // Reference implementations, walking from a to b
+    let step = |a: Coord, b: Coord| {
+        let (df, dr) = (b.f as i8 - a.f as i8, b.r as i8 - a.r as i8);
+        let is_aligned = df == 0 || dr == 0 || df.abs() == dr.abs();
+        (a != b && is_aligned).then_some((df.signum(), dr.signum()))
+    };
+    let walk = |coord: Coord, (df, dr): (i8, i8)| {
+        let mut squares = Vec::new();
+        let (mut f, mut r) = (coord.f as i8 + df, coord.r as i8 + dr);
+        while (0..8).contains(&f) && (0..8).contains(&r) {
+            squares.push(Coord::new(f as u8, r as u8));
+            f += df;
+            r += dr;
+        }
+        squares
+    };
+    let to_bitboard = |squares: &[Coord]| {
+        squares
+            .iter()
+            .fold(0, |bitboard, coord| bitboard | get_square_bitboard(*coord))
+    };
+
+    let (mut aligned_pairs, mut other_pairs) = (0, 0);
+    for a in (0..64).map(|index| Coord::new(index % 8, index / 8)) {
+        for b in (0..64).map(|index| Coord::new(index % 8, index / 8)) {
+            match step(a, b) {
+                Some((df, dr)) => {
+                    aligned_pairs += 1;
+                    let ray = walk(a, (df, dr));
+                    let distance = ray.iter().position(|coord| *coord == b).unwrap();
+                    assert!(
+                        between(a, b) == to_bitboard(&ray[..distance]),
+                        "Failed at assert 0"
+                    );
+                    let full_line = to_bitboard(&ray)
+                        | to_bitboard(&walk(a, (-df, -dr)))
+                        | get_square_bitboard(a);
+                    assert!(line(a, b) == full_line, "Failed at assert 1");
+                    assert!(
+                        between(a, b) == between(b, a) && line(a, b) == line(b, a),
+                        "Failed at assert 2"
+                    );
+                }
+                None => {
+                    other_pairs += 1;
+                    assert!(between(a, b) == 0 && line(a, b) == 0, "Failed at assert 3");
+                }
+            }
+        }
+    }
+
+    // Each square sees 14 squares on its rank and file, and 7 to 13 on its diagonals
+    assert!(aligned_pairs == 64 * 14 + 560, "Failed at assert 4");
+    assert!(other_pairs == 64 * 64 - aligned_pairs, "Failed at assert 5");
+    let (a1, h8, b1, e5) = (
+        Coord::new(0, 0),
+        Coord::new(7, 7),
+        Coord::new(1, 0),
+        Coord::new(4, 4),
+    );
+    assert!(between(a1, h8).count_ones() == 6, "Failed at assert 6");
+    assert!(
+        between(a1, b1) == 0 && line(a1, b1).count_ones() == 8,
+        "Failed at assert 7"
+    );
+    assert!(line(b1, e5) == 0, "Failed at assert 8");
+}
+
+/**************
+ * ATTACK TESTS
+ **************/
+
+#[test]
+fn test_attacks() {
+    use crate::board_representation::*;
+
+    let square = |name: &str| Coord::from_algebraic(name).unwrap();
+    let squares = |names: &[&str]| {
+        names.iter().fold(0, |bitboard, name| {
+            bitboard | get_square_bitboard(square(name))
+        })
+    };
+
+    // Leaper lookup tables
+    let lut = get_pl_move_lut();
+    assert!(lut.n_lut[0].count_ones() == 2, "Failed at assert 0");
+    assert!(lut.n_lut[27].count_ones() == 8, "Failed at assert 1");
+    assert!(lut.k_lut[4].count_ones() == 5, "Failed at assert 2");
+    assert!(
+        lut.white_p_attack_lut[12] == squares(&["d3", "f3"]),
+        "Failed at assert 3"
+    );
+    assert!(
+        lut.black_p_attack_lut[8] == squares(&["b1"]),
+        "Failed at assert 4"
+    );
+
+    // Attacked squares agree with the 0x88 board (which is only kept with the zerox88
+    // feature)
+    #[cfg(feature = "zerox88")]
+    {
+        let fens = [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ];
+        for fen in fens {
+            let position = Position::from_fen(fen).unwrap();
+            for index in 0..64 {
+                let coord = Coord::new(index % 8, index / 8);
+                for player in [Player::White, Player::Black] {
+                    assert!(
+                        position.is_square_attacked(coord, player)
+                            == is_square_attacked(&position, coord, player),
+                        "Failed at assert 5"
+                    );
+                }
+            }
+        }
+    }
+
+    // Attackers with a custom occupancy reveal x-rays
+    let position = Position::from_fen("4k3/8/8/8/3q4/8/3R4/3RK3 w - - 0 1").unwrap();
+    let occupancy = get_all_pieces_bitboard(&position.piece_centric_board);
+    assert!(
+        position.attackers_to(square("d4"), occupancy) == squares(&["d2"]),
+        "Failed at assert 6"
+    );
+    assert!(
+        position.attackers_to(square("d4"), occupancy & !squares(&["d2"])) == squares(&["d1"]),
+        "Failed at assert 7"
+    );
+
+    // Checkers and pins
+    let position = Position::from_fen("4k3/8/8/b7/8/8/3P4/4K2r w - - 0 1").unwrap();
+    assert!(
+        position.checkers() == squares(&["h1"]),
+        "Failed at assert 8"
+    );
+    assert!(
+        position.pins(Player::White) == squares(&["d2"]),
+        "Failed at assert 9"
+    );
+    let position = Position::from_fen("4k3/4r3/8/8/4N3/4P3/8/4K3 w - - 0 1").unwrap();
+    assert!(position.checkers() == 0, "Failed at assert 10");
+    assert!(position.pins(Player::White) == 0, "Failed at assert 11");
+    let position = Position::from_fen("4k3/4r3/8/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+    assert!(
+        position.pins(Player::White) == squares(&["e4"]),
+        "Failed at assert 12"
+    );
+    assert!(position.pins(Player::Black) == 0, "Failed at assert 13");
+
+    // Static exchanges and hanging pieces
+    let position = Position::from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+    assert!(
+        position.get_static_exchange_score(square("e5"), Player::White) == 100,
+        "Failed at assert 14"
+    );
+    let position =
+        Position::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+    assert!(
+        position.get_static_exchange_score(square("e5"), Player::White) < 0,
+        "Failed at assert 15"
+    );
+    let position = Position::from_fen("4k3/8/1n6/3p4/4P3/5P2/8/4K3 w - - 0 1").unwrap();
+    assert!(
+        position.hanging_pieces(Player::White) == 0,
+        "Failed at assert 16"
+    );
+    assert!(
+        position.hanging_pieces(Player::Black) == 0,
+        "Failed at assert 17"
+    );
+    let position = Position::from_fen("4k3/8/1n6/3q4/4P3/5P2/8/4K3 w - - 0 1").unwrap();
+    assert!(
+        position.hanging_pieces(Player::Black) == squares(&["d5"]),
+        "Failed at assert 18"
+    );
+    assert!(
+        position.hanging_pieces(Player::White) == 0,
+        "Failed at assert 19"
+    );
+}
+
+/*************
+ * PERFT TESTS
+ *************/
+
+/// Check the perft results of a position, up to the depth of the last expected result
+#[cfg(test)]
+fn assert_perft(fen: &str, expected_nodes: &[u64]) {
+    let mut position = crate::board_representation::Position::from_fen(fen).unwrap();
+    let fen = position.to_fen();
+
+    for (i, expected) in expected_nodes.iter().enumerate() {
+        let depth = i as u8 + 1;
+        assert_eq!(
+            perft(&mut position, depth),
+            *expected,
+            "Failed at perft({}) of {}",
+            depth,
+            fen
+        );
+        // Making and unmaking moves must leave the position unchanged
+        assert_eq!(position.to_fen(), fen, "Failed to restore {}", fen);
+    }
+}
+
+#[test]
+fn test_perft() {
+    // Starting position
+    assert_perft(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[20, 400, 8902, 197281],
+    );
+
+    // "Kiwipete"
+    assert_perft(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2039, 97862],
+    );
+
+    // En passant discovered checks and pins
+    assert_perft(
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        &[14, 191, 2812, 43238],
+    );
+
+    // Promotions and castling
+    assert_perft(
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9467],
+    );
+    assert_perft(
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        &[44, 1486, 62379],
+    );
+}
+
+#[test]
+fn test_chess960_perft() {
+    // Positions of the Chess960 perft suite
+    // See : <https://www.chessprogramming.org/Chess960_Perft_Results>
+    assert_perft(
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        &[21, 528, 12189],
+    );
+    assert_perft(
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        &[21, 807, 18002],
+    );
+    assert_perft(
+        "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9",
+        &[20, 479, 10471],
+    );
+    assert_perft(
+        "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+        &[22, 593, 13440],
+    );
+    assert_perft(
+        "1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9",
+        &[28, 1120, 31058],
+    );
+    assert_perft(
+        "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+        &[29, 899, 26578],
+    );
+    assert_perft(
+        "q1bnrkr1/ppppp2p/2n2p2/4b1p1/2NP4/8/PPP1PPPP/QNB1RRKB w ge - 1 9",
+        &[30, 860, 24566],
+    );
+    assert_perft(
+        "qbn1brkr/ppp1p1p1/2n4p/3p1p2/P7/6PP/QPPPPP2/1BNNBRKR w HFhf - 0 9",
+        &[25, 635, 17054],
+    );
+    assert_perft(
+        "qnnbbrkr/1p2ppp1/2pp3p/p7/1P5P/2NP4/P1P1PPP1/Q1NBBRKR w HFhf - 0 9",
+        &[24, 572, 15243],
+    );
+    assert_perft(
+        "qn1rbbkr/ppp2p1p/1n1pp1p1/8/3P4/P6P/1PP1PPPK/QNNRBB1R w hd - 2 9",
+        &[28, 811, 23175],
+    );
+
+    // Castling moves are written as the king capturing its rook in Chess960
+    let mut position =
+        crate::board_representation::Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")
+            .unwrap();
+    let castling = parse_uci_move(&mut position, "e1g1").unwrap();
+    assert!(get_move_kingside_castling(castling), "Failed at assert 1");
+    position.chess960 = true;
+    assert!(
+        get_move_uci(&position, castling) == "e1h1",
+        "Failed at assert 2"
+    );
+    assert!(
+        parse_uci_move(&mut position, "e1c1").is_none(),
+        "Failed at assert 3"
+    );
+    assert!(
+        parse_uci_move(&mut position, "e1a1").is_some_and(get_move_queenside_castling),
+        "Failed at assert 4"
+    );
+}
+
+#[test]
+fn test_move() {
+    use crate::board_representation::*;
+
+    let parse = |fen: &str, uci: &str| {
+        let mut position = Position::from_fen(fen).unwrap();
+        parse_uci_move(&mut position, uci).unwrap()
+    };
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    // Squares and pieces
+    let mov = parse(kiwipete, "e5f7");
+    assert!(
+        mov.start() == Coord::new(4, 4) && mov.arrival() == Coord::new(5, 6),
+        "Failed at assert 0"
+    );
+    assert!(
+        mov.piece() == PieceCode::WN && mov.captured() == Some(PieceCode::BP),
+        "Failed at assert 1"
+    );
+    assert!(
+        mov.is_capture() && !mov.is_en_passant() && mov.promotion().is_none(),
+        "Failed at assert 2"
+    );
+
+    // Special moves
+    let mov = parse(STARTING_FEN, "e2e4");
+    assert!(
+        mov.is_double_pawn_push() && mov.captured().is_none(),
+        "Failed at assert 3"
+    );
+    let mov = parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "e5d6");
+    assert!(
+        mov.is_en_passant() && mov.captured() == Some(PieceCode::BP),
+        "Failed at assert 4"
+    );
+    let mov = parse(kiwipete, "e1c1");
+    assert!(
+        mov.is_castling() && mov.piece() == PieceCode::WK,
+        "Failed at assert 5"
+    );
+    let mov = parse("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n");
+    assert!(
+        mov.promotion() == Some(PieceCode::WN) && !mov.is_capture(),
+        "Failed at assert 6"
+    );
+}
+
+/*****************
+ * MOVE LIST TESTS
+ *****************/
+
+#[test]
+fn test_move_list() {
+    let mut moves: MoveList = (1..=5).map(Move).collect();
+    assert!(
+        moves.len() == 5 && moves[4] == Move(5),
+        "Failed at assert 0"
+    );
+
+    // Scores follow their moves when sorting and filtering
+    moves.score_moves(|mov| [3, 1, 4, 1, 5][mov.0 as usize - 1]);
+    moves.sort_by_score();
+    assert!(
+        moves.as_slice() == [5, 3, 1, 2, 4].map(Move),
+        "Failed at assert 1"
+    );
+    assert!(moves.scores() == [5, 4, 3, 1, 1], "Failed at assert 2");
+    moves.retain(|mov| *mov != Move(3));
+    assert!(moves.scores() == [5, 3, 1, 1], "Failed at assert 3");
+
+    // Picking the best moves one by one
+    let mut moves = MoveList::new();
+    for (mov, score) in [(1, 10), (2, 30), (3, 20)] {
+        moves.push_scored(Move(mov), score);
+    }
+    let picked: Vec<Move> = (0..4).map_while(|index| moves.pick_best(index)).collect();
+    assert!(picked == [2, 3, 1].map(Move), "Failed at assert 4");
+
+    // Consuming iterator
+    assert!(
+        moves.clone().into_iter().collect::<Vec<Move>>() == [2, 3, 1].map(Move),
+        "Failed at assert 5"
+    );
+    moves.clear();
+    assert!(moves.is_empty(), "Failed at assert 6");
+}
+
+#[test]
+fn test_staged_generation() {
+    use crate::board_representation::*;
+
+    let sorted = |moves: &[Move]| {
+        let mut moves = moves.to_vec();
+        moves.sort_by_key(|mov| mov.0);
+        moves
+    };
+
+    let fens = [
+        STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    ];
+    let mut checks = 0;
+    for fen in fens {
+        let mut position = Position::from_fen(fen).unwrap();
+        // Test the position and all its children
+        let mut positions = vec![position.clone()];
+        for mov in gen_legal_moves(&mut position) {
+            position.make_move(mov);
+            positions.push(position.clone());
+            position.unmake_move(mov);
+        }
+
+        for mut position in positions {
+            let legal_moves = gen_legal_moves(&mut position);
+            let captures = gen_captures(&mut position);
+            let quiets = gen_quiets(&mut position);
+
+            assert!(
+                captures
+                    .iter()
+                    .all(|mov| get_move_capture(*mov) || get_move_promotion(*mov)),
+                "Failed at assert 0"
+            );
+            assert!(
+                quiets
+                    .iter()
+                    .all(|mov| !get_move_capture(*mov) && !get_move_promotion(*mov)),
+                "Failed at assert 1"
+            );
+            let mut union = captures.to_vec();
+            union.extend_from_slice(&quiets);
+            assert!(sorted(&union) == sorted(&legal_moves), "Failed at assert 2");
+
+            let quiet_checks: Vec<Move> = quiets
+                .iter()
+                .copied()
+                .filter(|mov| get_move_check(*mov))
+                .collect();
+            assert!(
+                gen_quiet_checks(&mut position).as_slice() == quiet_checks,
+                "Failed at assert 3"
+            );
+
+            if is_in_check(&position, position.current_turn) {
+                checks += 1;
+                assert!(
+                    sorted(&gen_evasions(&mut position)) == sorted(&legal_moves),
+                    "Failed at assert 4"
+                );
+            }
+        }
+    }
+    assert!(checks > 0, "Failed at assert 5");
+
+    // Double check : only the king can move
+    let mut position = Position::from_fen("4k3/8/5N2/8/8/8/8/4RK2 b - - 0 1").unwrap();
+    let evasions = gen_evasions(&mut position);
+    assert!(
+        !evasions.is_empty()
+            && evasions
+                .iter()
+                .all(|mov| get_move_piece_code(*mov) == PieceCode::BK),
+        "Failed at assert 6"
+    );
+
+    // An en passant capture removes the checking pawn
+    let mut position = Position::from_fen("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1").unwrap();
+    let evasions = gen_evasions(&mut position);
+    assert!(
+        evasions.iter().any(|mov| get_move_en_passant_capture(*mov)),
+        "Failed at assert 7"
+    );
+    // Quiet checks : discovered checks from any square, and castling checking with the rook
+    let quiet_checks = |fen: &str| {
+        let mut position = Position::from_fen(fen).unwrap();
+        let mut moves: Vec<String> = gen_quiet_checks(&mut position)
+            .iter()
+            .map(|mov| get_move_uci(&position, *mov))
+            .collect();
+        moves.sort();
+        moves
+    };
+    assert!(
+        quiet_checks("4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1")
+            == ["e4c3", "e4c5", "e4d2", "e4d6", "e4f2", "e4f6", "e4g3", "e4g5"],
+        "Failed at assert 8"
+    );
+    assert!(
+        quiet_checks("5k2/8/8/8/8/8/8/4K2R w K - 0 1") == ["e1g1", "h1f1", "h1h8"],
+        "Failed at assert 9"
+    );
+}
+
+/***********
+ * SAN TESTS
+ ***********/
+
+#[test]
+fn test_san() {
+    use crate::board_representation::*;
+
+    let san = |fen: &str, uci: &str| {
+        let mut position = Position::from_fen(fen).unwrap();
+        let mov = parse_uci_move(&mut position, uci).unwrap();
+        get_move_san(&mut position, mov)
+    };
+
+    // Piece moves, captures, castling and promotions
+    assert!(san(STARTING_FEN, "g1f3") == "Nf3", "Failed at assert 0");
+    assert!(san(STARTING_FEN, "e2e4") == "e4", "Failed at assert 1");
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    assert!(san(kiwipete, "e5f7") == "Nxf7", "Failed at assert 2");
+    assert!(san(kiwipete, "d5e6") == "dxe6", "Failed at assert 3");
+    assert!(san(kiwipete, "e1g1") == "O-O", "Failed at assert 4");
+    assert!(san(kiwipete, "e1c1") == "O-O-O", "Failed at assert 5");
+    assert!(
+        san("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q") == "b8=Q+",
+        "Failed at assert 6"
+    );
+
+    // Disambiguation by file, by rank, and by both
+    assert!(
+        san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2") == "Nbd2",
+        "Failed at assert 7"
+    );
+    assert!(
+        san("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3") == "R1a3",
+        "Failed at assert 8"
+    );
+    assert!(
+        san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2") == "Qa1b2",
+        "Failed at assert 9"
+    );
+    assert!(
+        san("1k6/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1", "a4d4") == "Qa4d4",
+        "Failed at assert 10"
+    );
+
+    // Checkmate
+    assert!(
+        san("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1", "a1a8") == "Ra8#",
+        "Failed at assert 11"
+    );
+
+    // Parsing, including lenient notations
+    let mut position = Position::from_fen(kiwipete).unwrap();
+    for mov in gen_legal_moves(&mut position) {
+        let san = get_move_san(&mut position, mov);
+        assert!(
+            parse_san_move(&mut position, &san) == Some(mov),
+            "Failed at assert 12"
+        );
+    }
+    let mut position = Position::from_fen(STARTING_FEN).unwrap();
+    for (san, uci) in [("Nf3!?", "g1f3"), ("e2e4", "e2e4"), ("Ngf3", "g1f3")] {
+        assert!(
+            parse_san_move(&mut position, san) == parse_uci_move(&mut position, uci),
+            "Failed at assert 13"
+        );
+    }
+    let mut position = Position::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert!(
+        parse_san_move(&mut position, "b8N") == parse_uci_move(&mut position, "b7b8n"),
+        "Failed at assert 14"
+    );
+    let mut position = Position::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+    assert!(
+        parse_san_move(&mut position, "Nd2").is_none(),
+        "Failed at assert 15"
+    );
+    assert!(
+        parse_san_move(&mut position, "Ke9").is_none(),
+        "Failed at assert 16"
+    );
+    assert!(
+        parse_san_move(&mut position, "O-O").is_none(),
+        "Failed at assert 17"
+    );
+}
+
+/****************
+ * PROPERTY TESTS
+ ****************/
+
+/// Starting positions of the random games, including Chess960 positions
+#[cfg(test)]
+const RANDOM_GAMES_FENS: [&str; 6] = [
+    crate::board_representation::STARTING_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    "qnbnr1kr/ppp1b1pp/4p3/3p1p2/8/2NPP3/PPP1BPPP/QNB1R1KR w HEhe - 1 9",
+];
+
+/// Play random legal games from each of the RANDOM_GAMES_FENS, and call the function on
+/// every position reached (before playing its move), with its legal moves. Returns the
+/// number of positions visited.
+#[cfg(test)]
+fn play_random_games<F>(games_per_fen: u64, max_plies: usize, mut f: F) -> usize
+where
+    F: FnMut(&mut crate::board_representation::Position, &MoveList),
+{
+    use crate::board_representation::*;
+    use crate::datagen::Rng;
+
+    let mut count = 0;
+    for (fen_index, fen) in RANDOM_GAMES_FENS.iter().enumerate() {
+        for game in 0..games_per_fen {
+            let mut rng = Rng::for_game(fen_index as u64, game);
+            let mut position = Position::from_fen(fen).unwrap();
+            for _ in 0..max_plies {
+                let moves = gen_legal_moves(&mut position);
+                f(&mut position, &moves);
+                count += 1;
+                if moves.is_empty() {
+                    break;
+                }
+                position.make_move(moves[rng.below(moves.len())]);
+            }
+        }
+    }
+    count
+}
+
+#[test]
+fn test_random_games() {
+    use crate::board_representation::*;
+
+    let count = play_random_games(16, 80, |position, moves| {
+        for mov in moves.iter().copied() {
+            let before = position.clone();
+            position.make_move(mov);
+
+            // The boards agree, and the position is legal
+            if let Err(error) = position.validate() {
+                panic!("Failed at assert 0 ({}): {}", error, position.to_fen());
+            }
+            // The incremental hash matches the one computed from scratch, and only depends
+            // on the position, not on the moves that led to it
+            let fen = position.to_fen();
+            assert!(
+                position.get_zobrist_hash() == position.compute_zobrist_hash(),
+                "Failed at assert 1 ({})",
+                fen
+            );
+            assert!(
+                position.get_zobrist_hash() == Position::from_fen(&fen).unwrap().get_zobrist_hash(),
+                "Failed at assert 2 ({})",
+                fen
+            );
+
+            // Unmaking the move restores the exact same position and hash
+            position.unmake_move(mov);
+            assert!(*position == before, "Failed at assert 3 ({})", fen);
+            assert!(
+                position.get_zobrist_hash() == position.compute_zobrist_hash(),
+                "Failed at assert 4 ({})",
+                fen
+            );
+        }
+    });
+    assert!(count > 5000, "Failed at assert 5 ({})", count);
+}
+
+#[test]
+fn test_reference_generator() {
+    use crate::board_representation::*;
+    use reference_generator::*;
+
+    // Sanity check of the reference generator itself, with the known perft results
+    let position = Position::from_fen(STARTING_FEN).unwrap();
+    assert!(
+        gen_reference_moves(&position).len() == 20,
+        "Failed at assert 0"
+    );
+    let mut position = Position::from_fen(RANDOM_GAMES_FENS[1]).unwrap();
+    assert!(
+        gen_reference_moves(&position).len() == 48,
+        "Failed at assert 1"
+    );
+    let mut nodes = 0;
+    for mov in gen_legal_moves(&mut position) {
+        position.make_move(mov);
+        nodes += gen_reference_moves(&position).len();
+        position.unmake_move(mov);
+    }
+    assert!(nodes == 2039, "Failed at assert 2");
 
+    // The engine generates the same moves as the reference on random positions
+    let to_reference_move = |mov: Move| -> ReferenceMove {
+        let promotion = if get_move_promotion(mov) {
+            get_move_promotion_piece_code(mov)
+        } else {
+            PieceCode::ES
+        };
+        (
+            get_move_start_coords(mov),
+            get_move_arrival_coords(mov),
+            promotion,
+        )
+    };
+    let count = play_random_games(10, 80, |position, moves| {
+        let mut engine_moves: Vec<ReferenceMove> =
+            moves.iter().copied().map(to_reference_move).collect();
+        engine_moves
+            .sort_by_key(|(from, to, promotion)| (from.r, from.f, to.r, to.f, *promotion as u8));
+        assert!(
+            engine_moves == gen_reference_moves(position),
+            "Failed at assert 3 ({})",
+            position.to_fen()
+        );
+    });
+    assert!(count > 3000, "Failed at assert 4");
 }
@@ -0,0 +1,206 @@
+#![allow(dead_code)]
+
+use super::lookup_tables::*;
+use super::misc::*;
+use crate::board_representation::*;
+
+/***********
+ * ATTACKS
+ ***********/
+
+// Attack queries on a Position, built on the piece-centric bitboards : leapers (pawns,
+// knights and kings) use the precomputed lookup tables, and sliders (bishops, rooks and
+// queens) scan their rays until the first blocker. Queries take the occupancy as a
+// parameter where it makes sense, so that pieces can be "removed" from the board without
+// modifying it (for x-rays and static exchanges).
+
+/// Piece values used by the static exchange evaluation, indexed using PieceCode - 1,
+/// modulo 6
+const SEE_VALUES: [i32; 6] = [100, 300, 300, 500, 900, 20000];
+
+/// Index of a square in the lookup tables
+#[inline(always)]
+fn get_lut_index(coord: Coord) -> usize {
+    (coord.r * 8 + coord.f) as usize
+}
+
+impl Position {
+    /// Return the pieces of both players attacking a square, considering only the given
+    /// occupied squares (pieces outside of the occupancy neither attack nor block)
+    pub fn attackers_to(&self, coord: Coord, occupancy: u64) -> u64 {
+        let lut = get_pl_move_lut();
+        let boards = &self.piece_centric_board.main_boards;
+        let index = get_lut_index(coord);
+
+        let types = &self.piece_centric_board.piece_type_boards;
+        let rooks_and_queens = types[3] | types[4];
+        let bishops_and_queens = types[2] | types[4];
+
+        // A pawn attacks a square if a pawn of the other color on that square would attack
+        // the pawn's square
+        let attackers = (lut.black_p_attack_lut[index] & boards[0])
+            | (lut.white_p_attack_lut[index] & boards[6])
+            | (lut.n_lut[index] & types[1])
+            | (lut.k_lut[index] & types[5])
+            | (get_rook_attacks(coord, occupancy) & rooks_and_queens)
+            | (get_bishop_attacks(coord, occupancy) & bishops_and_queens);
+        attackers & occupancy
+    }
+
+    /// Check whether a square is attacked by any piece of the given player
+    pub fn is_square_attacked(&self, coord: Coord, by: Player) -> bool {
+        let bitboard = &self.piece_centric_board;
+        self.attackers_to(coord, get_all_pieces_bitboard(bitboard))
+            & get_player_bitboard(bitboard, by)
+            != 0
+    }
+
+    /// Return the pieces giving check to the king of the side to move
+    pub fn checkers(&self) -> u64 {
+        let Some(king) = self.get_king_coord(self.current_turn) else {
+            return 0;
+        };
+        let bitboard = &self.piece_centric_board;
+        let opponent = invert_player(&self.current_turn);
+        self.attackers_to(king, get_all_pieces_bitboard(bitboard))
+            & get_player_bitboard(bitboard, opponent)
+    }
+
+    /// Return the pieces of a player that stand alone between a king and a slider of the
+    /// given player
+    fn get_slider_blockers(&self, king: Coord, slider_player: Player, player: Player) -> u64 {
+        let bitboard = &self.piece_centric_board;
+        let boards = &bitboard.main_boards;
+        let occupancy = get_all_pieces_bitboard(bitboard);
+        let own_pieces = get_player_bitboard(bitboard, player);
+
+        // Sliders aligned with the king, whatever stands between them
+        let sliders = 6 * slider_player as usize;
+        let snipers = (get_rook_attacks(king, 0) & (boards[sliders + 3] | boards[sliders + 4]))
+            | (get_bishop_attacks(king, 0) & (boards[sliders + 2] | boards[sliders + 4]));
+
+        let mut blockers = 0;
+        for sniper in Bitboard(snipers) {
+            let between_pieces = between(king, sniper.to_coord()) & occupancy;
+            if between_pieces.count_ones() == 1 && between_pieces & own_pieces != 0 {
+                blockers |= between_pieces;
+            }
+        }
+        blockers
+    }
+
+    /// Return the pieces of a player that are pinned to their king by an opponent slider
+    pub fn pins(&self, player: Player) -> u64 {
+        let Some(king) = self.get_king_coord(player) else {
+            return 0;
+        };
+        self.get_slider_blockers(king, invert_player(&player), player)
+    }
+
+    /// Return the pieces of a player that block one of its own sliders from the opponent
+    /// king : moving them off the line gives a discovered check
+    pub fn get_discovered_checkers(&self, player: Player) -> u64 {
+        let Some(king) = self.get_king_coord(invert_player(&player)) else {
+            return 0;
+        };
+        self.get_slider_blockers(king, player, player)
+    }
+
+    /// Return the squares from which each piece type of a player (indexed from pawn to
+    /// king) would attack the opponent king
+    pub fn get_check_squares(&self, player: Player) -> [u64; 6] {
+        let Some(king) = self.get_king_coord(invert_player(&player)) else {
+            return [0; 6];
+        };
+        let lut = get_pl_move_lut();
+        let index = get_lut_index(king);
+        let occupancy = get_all_pieces_bitboard(&self.piece_centric_board);
+
+        // A pawn attacks the king from the squares a pawn of the other color on the king's
+        // square would attack
+        let pawns = match player {
+            Player::White => lut.black_p_attack_lut[index],
+            Player::Black => lut.white_p_attack_lut[index],
+        };
+        let bishops = get_bishop_attacks(king, occupancy);
+        let rooks = get_rook_attacks(king, occupancy);
+        [pawns, lut.n_lut[index], bishops, rooks, bishops | rooks, 0]
+    }
+
+    /// Return the least valuable piece of a player among the attackers, with its value
+    fn get_least_valuable_attacker(&self, attackers: u64, player: Player) -> Option<(u64, i32)> {
+        let boards = &self.piece_centric_board.main_boards;
+        (0..6).find_map(|piece_type| {
+            let pieces = Bitboard(attackers & boards[6 * player as usize + piece_type]);
+            pieces
+                .msb()
+                .map(|square| (square.bitboard().0, SEE_VALUES[piece_type]))
+        })
+    }
+
+    /// Static exchange evaluation : material won by the given player by starting a sequence
+    /// of captures on a square, both sides capturing with their least valuable piece and
+    /// being free to stop at any time. Returns 0 if the square is empty or can't be
+    /// captured. Pins are ignored.
+    pub fn get_static_exchange_score(&self, coord: Coord, attacker: Player) -> i32 {
+        let target = self.get_square(coord);
+        if target == PieceCode::ES {
+            return 0;
+        }
+
+        let mut occupancy = get_all_pieces_bitboard(&self.piece_centric_board);
+        let mut side = attacker;
+        let mut gains = [0; 32];
+        let mut depth = 0;
+        gains[0] = SEE_VALUES[(target as usize - 1) % 6];
+
+        let attackers = self.attackers_to(coord, occupancy);
+        let Some((mut from, mut piece_value)) = self.get_least_valuable_attacker(attackers, side)
+        else {
+            return 0;
+        };
+
+        loop {
+            // Score if the piece that just captured is captured in turn
+            depth += 1;
+            gains[depth] = piece_value - gains[depth - 1];
+            if (-gains[depth - 1]).max(gains[depth]) < 0 || depth == gains.len() - 1 {
+                break;
+            }
+
+            // Removing the capturing piece may uncover sliders behind it (x-rays)
+            occupancy &= !from;
+            side = invert_player(&side);
+            let attackers = self.attackers_to(coord, occupancy);
+            match self.get_least_valuable_attacker(attackers, side) {
+                Some((next_from, next_value)) => {
+                    from = next_from;
+                    piece_value = next_value;
+                }
+                None => break,
+            }
+        }
+
+        // Each side only captures if it gains something
+        while depth > 1 {
+            depth -= 1;
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+        }
+        gains[0]
+    }
+
+    /// Return the pieces of a player (king excepted) that the opponent wins material by
+    /// capturing, according to the static exchange evaluation
+    pub fn hanging_pieces(&self, player: Player) -> u64 {
+        let bitboard = &self.piece_centric_board;
+        let king = bitboard.main_boards[6 * player as usize + 5];
+        let pieces = Bitboard(get_player_bitboard(bitboard, player) & !king);
+        let opponent = invert_player(&player);
+
+        pieces
+            .iter()
+            .filter(|square| self.get_static_exchange_score(square.to_coord(), opponent) > 0)
+            .collect::<Bitboard>()
+            .0
+    }
+}
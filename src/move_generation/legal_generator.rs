@@ -1,5 +1,614 @@
 #![allow(dead_code)]
 
+use super::lookup_tables::*;
+use super::misc::*;
+use super::movable_board::*;
+use super::move_list::*;
+use crate::board_representation::*;
+
 /*************************
  * MOVES GENERATOR (LEGAL)
  *************************/
+
+// This generator walks over the board with 0x88 indexes : a square index is (rank << 4) +
+// file, and an index is off the board as soon as (index & 0x88) != 0, which makes sliding
+// and jumping over the board edges easy to detect. The pieces are read from the mailbox of
+// the position, after converting the 0x88 indexes to mailbox indexes.
+// Pseudo-legal moves are generated first, and are then filtered out by making them and
+// checking whether the king of the moving side is attacked.
+
+const KNIGHT_OFFSETS: [i16; 8] = [33, 31, 18, 14, -14, -18, -31, -33];
+const KING_OFFSETS: [i16; 8] = [1, -1, 16, -16, 15, -15, 17, -17];
+const ROOK_OFFSETS: [i16; 4] = [1, -1, 16, -16];
+const BISHOP_OFFSETS: [i16; 4] = [15, -15, 17, -17];
+
+/// Convert a Coord to a 0x88 index
+#[inline(always)]
+fn get_0x88_index(coord: Coord) -> i16 {
+    ((coord.r << 4) + coord.f) as i16
+}
+
+/// Convert a (valid) 0x88 index to a Coord
+#[inline(always)]
+fn get_0x88_coord(index: i16) -> Coord {
+    Coord::new((index & 7) as u8, (index >> 4) as u8)
+}
+
+#[inline(always)]
+fn is_on_board(index: i16) -> bool {
+    index & 0x88 == 0
+}
+
+/// Convert a (valid) 0x88 index to a mailbox index
+#[inline(always)]
+fn get_mailbox_square(index: i16) -> usize {
+    (((index >> 4) << 3) + (index & 7)) as usize
+}
+
+/// Return the player owning a piece, or None for the empty square
+#[inline(always)]
+pub fn get_piece_player(piece_code: PieceCode) -> Option<Player> {
+    match piece_code as u8 {
+        0 => None,
+        1..=6 => Some(Player::White),
+        _ => Some(Player::Black),
+    }
+}
+
+/// Return the piece code of a piece type (given as its white PieceCode) for a player
+#[inline(always)]
+fn get_player_piece(white_piece_code: PieceCode, player: Player) -> PieceCode {
+    PieceCode::from_u8(white_piece_code as u8 + 6 * player as u8)
+}
+
+/// Check whether a square is attacked by any piece of the given player, by walking along
+/// the attack directions on the 0x88 board
+#[cfg(feature = "zerox88")]
+pub fn is_square_attacked(position: &Position, coord: Coord, attacker: Player) -> bool {
+    let board = &position.zerox88_board.main_board;
+    let index = get_0x88_index(coord);
+    let piece_at = |index: i16| {
+        if is_on_board(index) {
+            board[index as usize]
+        } else {
+            PieceCode::ES
+        }
+    };
+
+    // Pawns attack diagonally towards the opponent's side
+    let (pawn, pawn_offsets) = match attacker {
+        Player::White => (PieceCode::WP, [-15, -17]),
+        Player::Black => (PieceCode::BP, [15, 17]),
+    };
+    if pawn_offsets
+        .iter()
+        .any(|offset| piece_at(index + offset) == pawn)
+    {
+        return true;
+    }
+
+    let knight = get_player_piece(PieceCode::WN, attacker);
+    if KNIGHT_OFFSETS
+        .iter()
+        .any(|offset| piece_at(index + offset) == knight)
+    {
+        return true;
+    }
+
+    let king = get_player_piece(PieceCode::WK, attacker);
+    if KING_OFFSETS
+        .iter()
+        .any(|offset| piece_at(index + offset) == king)
+    {
+        return true;
+    }
+
+    // Sliding pieces
+    let queen = get_player_piece(PieceCode::WQ, attacker);
+    let sliders = [
+        (ROOK_OFFSETS, get_player_piece(PieceCode::WR, attacker)),
+        (BISHOP_OFFSETS, get_player_piece(PieceCode::WB, attacker)),
+    ];
+    for (offsets, slider) in sliders {
+        for offset in offsets {
+            let mut target = index + offset;
+            while is_on_board(target) {
+                let piece_code = board[target as usize];
+                if piece_code == slider || piece_code == queen {
+                    return true;
+                }
+                if piece_code != PieceCode::ES {
+                    break;
+                }
+                target += offset;
+            }
+        }
+    }
+
+    false
+}
+
+/// Check whether a square is attacked by any piece of the given player
+#[cfg(not(feature = "zerox88"))]
+#[inline(always)]
+pub fn is_square_attacked(position: &Position, coord: Coord, attacker: Player) -> bool {
+    position.is_square_attacked(coord, attacker)
+}
+
+/// Check whether the king of the given player is in check
+pub fn is_in_check(position: &Position, player: Player) -> bool {
+    match position.get_king_coord(player) {
+        Some(king) => is_square_attacked(position, king, invert_player(&player)),
+        None => false,
+    }
+}
+
+/// Restriction of the generated moves to some categories and arrival squares, used by the
+/// staged generation entry points
+#[derive(Copy, Clone, Debug)]
+struct MoveFilter {
+    /// Generate captures and promotions
+    tactical: bool,
+    /// Generate the other moves
+    quiet: bool,
+    /// Allowed arrival squares (or captured pawn squares for en passant) for all the pieces
+    /// but the king
+    targets: u64,
+}
+
+impl MoveFilter {
+    const ALL: MoveFilter = MoveFilter {
+        tactical: true,
+        quiet: true,
+        targets: u64::MAX,
+    };
+
+    #[inline(always)]
+    fn accepts(&self, is_tactical: bool) -> bool {
+        if is_tactical {
+            self.tactical
+        } else {
+            self.quiet
+        }
+    }
+}
+
+/// Generate pawn moves (pushes, captures, en passant and promotions) from a square
+fn gen_pawn_moves(position: &Position, index: i16, filter: &MoveFilter, moves: &mut MoveList) {
+    let board = &position.square_centric_board.main_board;
+    let player = position.current_turn;
+    let piece_code = board[get_mailbox_square(index)];
+    let start = get_0x88_coord(index);
+
+    let (direction, start_rank, promotion_rank) = match player {
+        Player::White => (16, 1, 7),
+        Player::Black => (-16, 6, 0),
+    };
+
+    // Add a move, or all four promotions if the pawn reaches the last rank
+    let mut push_move = |arrival: Coord, arrival_square: PieceCode, flags: u8| {
+        let is_promotion = arrival.r == promotion_rank;
+        if !filter.accepts(flags & CAPTURE_FLAG != 0 || is_promotion) {
+            return;
+        }
+        // An en passant capture can remove a checking pawn without landing on its square
+        let mut target_mask = get_square_bitboard(arrival);
+        if flags & EN_PASSANT_CAPTURE_FLAG != 0 {
+            target_mask |= get_square_bitboard(Coord::new(arrival.f, start.r));
+        }
+        if filter.targets & target_mask == 0 {
+            return;
+        }
+
+        if is_promotion {
+            for promotion in [PieceCode::WQ, PieceCode::WR, PieceCode::WB, PieceCode::WN] {
+                moves.push(encode_move(
+                    start,
+                    arrival,
+                    piece_code,
+                    arrival_square,
+                    flags | PROMOTION_FLAG,
+                    get_player_piece(promotion, player),
+                ));
+            }
+        } else {
+            moves.push(encode_move(
+                start,
+                arrival,
+                piece_code,
+                arrival_square,
+                flags,
+                PieceCode::ES,
+            ));
+        }
+    };
+
+    // Pushes
+    let target = index + direction;
+    if is_on_board(target) && board[get_mailbox_square(target)] == PieceCode::ES {
+        push_move(get_0x88_coord(target), PieceCode::ES, 0);
+
+        let double_target = target + direction;
+        if start.r == start_rank && board[get_mailbox_square(double_target)] == PieceCode::ES {
+            push_move(
+                get_0x88_coord(double_target),
+                PieceCode::ES,
+                DOUBLE_PAWN_PUSH_FLAG,
+            );
+        }
+    }
+
+    // Captures
+    let en_passant_square = position.get_en_passant_square();
+    for target in [index + direction - 1, index + direction + 1] {
+        if !is_on_board(target) {
+            continue;
+        }
+        let arrival = get_0x88_coord(target);
+        let target_code = board[get_mailbox_square(target)];
+
+        if get_piece_player(target_code) == Some(invert_player(&player)) {
+            push_move(arrival, target_code, CAPTURE_FLAG);
+        } else if en_passant_square == Some(arrival) {
+            // The captured pawn is stored as the arrival square piece, so it can be
+            // restored when unmaking the move
+            let captured_pawn = get_player_piece(PieceCode::WP, invert_player(&player));
+            push_move(
+                arrival,
+                captured_pawn,
+                CAPTURE_FLAG | EN_PASSANT_CAPTURE_FLAG,
+            );
+        }
+    }
+}
+
+/// Generate moves of a jumping (knight, king) or sliding (bishop, rook, queen) piece
+fn gen_piece_moves(
+    position: &Position,
+    index: i16,
+    offsets: &[i16],
+    is_slider: bool,
+    filter: &MoveFilter,
+    moves: &mut MoveList,
+) {
+    let board = &position.square_centric_board.main_board;
+    let player = position.current_turn;
+    let piece_code = board[get_mailbox_square(index)];
+    let start = get_0x88_coord(index);
+
+    for offset in offsets {
+        let mut target = index + offset;
+        while is_on_board(target) {
+            let target_code = board[get_mailbox_square(target)];
+            let arrival = get_0x88_coord(target);
+            let is_target = filter.targets & get_square_bitboard(arrival) != 0;
+
+            match get_piece_player(target_code) {
+                None if !is_target || !filter.quiet => {}
+                None => moves.push(encode_move(
+                    start,
+                    arrival,
+                    piece_code,
+                    PieceCode::ES,
+                    0,
+                    PieceCode::ES,
+                )),
+                Some(target_player) => {
+                    if target_player != player && is_target && filter.tactical {
+                        moves.push(encode_move(
+                            start,
+                            arrival,
+                            piece_code,
+                            target_code,
+                            CAPTURE_FLAG,
+                            PieceCode::ES,
+                        ));
+                    }
+                    break;
+                }
+            }
+
+            if !is_slider {
+                break;
+            }
+            target += offset;
+        }
+    }
+}
+
+/// Generate castling moves. This works for both normal chess and Chess960 : all the squares
+/// between the king and its arrival square, and between the rook and its arrival square,
+/// must be empty (except for the castling king and rook themselves), and the king must not
+/// be in check, nor cross or land on an attacked square.
+fn gen_castling_moves(position: &Position, moves: &mut MoveList) {
+    let player = position.current_turn;
+    let back_rank = match player {
+        Player::White => 0,
+        Player::Black => 7,
+    };
+    let king = match position.get_king_coord(player) {
+        Some(king) if king.r == back_rank => king,
+        _ => return,
+    };
+    let king_code = get_player_piece(PieceCode::WK, player);
+    let rook_code = get_player_piece(PieceCode::WR, player);
+
+    let rights = [
+        (
+            position.kingside_castling_rook[player as usize],
+            true,
+            KINGSIDE_CASTLING_FLAG,
+        ),
+        (
+            position.queenside_castling_rook[player as usize],
+            false,
+            QUEENSIDE_CASTLING_FLAG,
+        ),
+    ];
+
+    for (rook_file, kingside, flag) in rights {
+        let rook_file = match rook_file {
+            Some(rook_file) => rook_file,
+            None => continue,
+        };
+        if position.get_square(Coord::new(rook_file, back_rank)) != rook_code {
+            continue;
+        }
+
+        let (king_arrival_file, rook_arrival_file) = get_castling_arrival_files(kingside);
+
+        let min_file = king
+            .f
+            .min(rook_file)
+            .min(king_arrival_file)
+            .min(rook_arrival_file);
+        let max_file = king
+            .f
+            .max(rook_file)
+            .max(king_arrival_file)
+            .max(rook_arrival_file);
+        let is_path_empty = (min_file..=max_file).all(|file| {
+            file == king.f
+                || file == rook_file
+                || position.get_square(Coord::new(file, back_rank)) == PieceCode::ES
+        });
+        if !is_path_empty {
+            continue;
+        }
+
+        let opponent = invert_player(&player);
+        let is_path_safe = (king.f.min(king_arrival_file)..=king.f.max(king_arrival_file))
+            .all(|file| !is_square_attacked(position, Coord::new(file, back_rank), opponent));
+        if !is_path_safe {
+            continue;
+        }
+
+        moves.push(encode_move(
+            king,
+            Coord::new(king_arrival_file, back_rank),
+            king_code,
+            PieceCode::ES,
+            flag,
+            PieceCode::ES,
+        ));
+    }
+}
+
+/// Generate the moves of the piece standing on a square, given its type (from 0 for pawns
+/// to 5 for kings)
+fn gen_square_moves(
+    position: &Position,
+    index: i16,
+    piece_type: u8,
+    filter: &MoveFilter,
+    moves: &mut MoveList,
+) {
+    match piece_type {
+        0 => gen_pawn_moves(position, index, filter, moves),
+        1 => gen_piece_moves(position, index, &KNIGHT_OFFSETS, false, filter, moves),
+        2 => gen_piece_moves(position, index, &BISHOP_OFFSETS, true, filter, moves),
+        3 => gen_piece_moves(position, index, &ROOK_OFFSETS, true, filter, moves),
+        4 => {
+            gen_piece_moves(position, index, &ROOK_OFFSETS, true, filter, moves);
+            gen_piece_moves(position, index, &BISHOP_OFFSETS, true, filter, moves);
+        }
+        _ => gen_piece_moves(position, index, &KING_OFFSETS, false, filter, moves),
+    }
+}
+
+/// Generate the pseudo-legal moves accepted by the filter
+fn gen_filtered_moves(position: &Position, filter: &MoveFilter, moves: &mut MoveList) {
+    let board = &position.square_centric_board.main_board;
+    let player = position.current_turn;
+    // The king can always go anywhere, even when the other pieces must block a check
+    let king_filter = MoveFilter {
+        targets: u64::MAX,
+        ..*filter
+    };
+
+    for index in 0..128 {
+        if !is_on_board(index) {
+            continue;
+        }
+        let piece_code = board[get_mailbox_square(index)];
+        if get_piece_player(piece_code) != Some(player) {
+            continue;
+        }
+
+        let piece_type = (piece_code as u8 - 1) % 6;
+        let filter = if piece_type == 5 {
+            &king_filter
+        } else {
+            filter
+        };
+        gen_square_moves(position, index, piece_type, filter, moves);
+    }
+
+    // Castling is never possible when some arrival squares are excluded (in check)
+    if filter.quiet && filter.targets == u64::MAX {
+        gen_castling_moves(position, moves);
+    }
+}
+
+/// Remove the moves leaving the king of the current player in check, and set the check bit
+/// of the moves giving check
+fn filter_legal_moves(position: &mut Position, moves: &mut MoveList) {
+    let player = position.current_turn;
+    let opponent = invert_player(&player);
+
+    moves.retain(|mov| {
+        position.make_move(*mov);
+        let is_legal = !is_in_check(position, player);
+        if is_legal && is_in_check(position, opponent) {
+            mov.0 |= (CHECK_FLAG as u32) << 4;
+        }
+        position.unmake_move(*mov);
+        is_legal
+    });
+}
+
+/// Generate all pseudo-legal moves of the current player (moves that may leave the king in
+/// check). Castling moves are only generated if they are fully legal.
+pub fn gen_pseudolegal_moves(position: &Position) -> MoveList {
+    let mut moves = MoveList::new();
+    gen_filtered_moves(position, &MoveFilter::ALL, &mut moves);
+    moves
+}
+
+/// Generate all legal moves of the current player. Moves giving check have their check bit
+/// set, but the checkmate bit is never set.
+pub fn gen_legal_moves(position: &mut Position) -> MoveList {
+    let mut moves = gen_pseudolegal_moves(position);
+    filter_legal_moves(position, &mut moves);
+    moves
+}
+
+/*************************
+ * STAGED MOVE GENERATION
+ *************************/
+
+// The search doesn't always need all the moves of a position : the quiescence search only
+// looks at captures, and a search with move ordering can try the captures before even
+// generating the quiet moves. The captures and the quiets of a position form a partition of
+// its legal moves.
+
+/// Generate the legal captures (including en passant) and promotions of the current player
+pub fn gen_captures(position: &mut Position) -> MoveList {
+    let filter = MoveFilter {
+        quiet: false,
+        ..MoveFilter::ALL
+    };
+    let mut moves = MoveList::new();
+    gen_filtered_moves(position, &filter, &mut moves);
+    filter_legal_moves(position, &mut moves);
+    moves
+}
+
+/// Generate the legal moves of the current player which are neither captures nor
+/// promotions (including castling)
+pub fn gen_quiets(position: &mut Position) -> MoveList {
+    let filter = MoveFilter {
+        tactical: false,
+        ..MoveFilter::ALL
+    };
+    let mut moves = MoveList::new();
+    gen_filtered_moves(position, &filter, &mut moves);
+    filter_legal_moves(position, &mut moves);
+    moves
+}
+
+/// Generate the legal moves of a player in check : king moves, captures of the checking
+/// piece, and moves blocking the check (only king moves when in double check). If the
+/// player isn't in check, all the legal moves are generated.
+pub fn gen_evasions(position: &mut Position) -> MoveList {
+    let checkers = position.checkers();
+    let targets = match (
+        checkers.count_ones(),
+        position.get_king_coord(position.current_turn),
+    ) {
+        (0, _) | (_, None) => u64::MAX,
+        (1, Some(king)) => {
+            let index = checkers.leading_zeros() as u8;
+            checkers | between(king, Coord::new(index & 7, index >> 3))
+        }
+        _ => 0,
+    };
+    let filter = MoveFilter {
+        targets,
+        ..MoveFilter::ALL
+    };
+    let mut moves = MoveList::new();
+    gen_filtered_moves(position, &filter, &mut moves);
+    filter_legal_moves(position, &mut moves);
+    moves
+}
+
+/// Generate the legal quiet moves (as defined by gen_quiets) giving check. Pieces are only
+/// moved to the squares from which they attack the opponent king, unless they block one of
+/// their sliders from it (discovered checks). Castling moves may check with the rook.
+pub fn gen_quiet_checks(position: &mut Position) -> MoveList {
+    let board = &position.square_centric_board.main_board;
+    let player = position.current_turn;
+    let check_squares = position.get_check_squares(player);
+    let discovered_checkers = position.get_discovered_checkers(player);
+
+    let mut moves = MoveList::new();
+    for index in 0..128 {
+        if !is_on_board(index) {
+            continue;
+        }
+        let piece_code = board[get_mailbox_square(index)];
+        if get_piece_player(piece_code) != Some(player) {
+            continue;
+        }
+
+        let piece_type = (piece_code as u8 - 1) % 6;
+        let filter = MoveFilter {
+            tactical: false,
+            quiet: true,
+            targets: if discovered_checkers & get_square_bitboard(get_0x88_coord(index)) != 0 {
+                u64::MAX
+            } else {
+                check_squares[piece_type as usize]
+            },
+        };
+        if filter.targets != 0 {
+            gen_square_moves(position, index, piece_type, &filter, &mut moves);
+        }
+    }
+    gen_castling_moves(position, &mut moves);
+
+    // Discovered checkers moving along the line and castling moves may not give check
+    filter_legal_moves(position, &mut moves);
+    moves.retain(|mov| get_move_check(*mov));
+    moves
+}
+
+/// Find the legal move corresponding to a move in UCI notation. In Chess960 positions,
+/// castling moves must be given as the king capturing its own rook.
+pub fn parse_uci_move(position: &mut Position, uci: &str) -> Option<Move> {
+    gen_legal_moves(position)
+        .into_iter()
+        .find(|mov| get_move_uci(position, *mov) == uci)
+}
+
+/// Count the leaf nodes of the legal moves tree at the given depth
+/// See : <https://www.chessprogramming.org/Perft>
+pub fn perft(position: &mut Position, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = gen_legal_moves(position);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mov in moves {
+        position.make_move(mov);
+        nodes += perft(position, depth - 1);
+        position.unmake_move(mov);
+    }
+    nodes
+}
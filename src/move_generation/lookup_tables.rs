@@ -0,0 +1,450 @@
+#![allow(dead_code)]
+
+use super::pseudolegal_generator::*;
+use crate::board_representation::*;
+
+/****************************
+ * COMPILE-TIME LOOKUP TABLES
+ ****************************/
+
+// All the lookup tables of the move generator are generated by const functions, and stored
+// in statics : there is no startup cost, and they can be shared between threads without
+// any synchronization. The generators of this file are independent from the runtime ones
+// of the pseudolegal generator, which are only kept to test the tables.
+
+/// Bitboard of the square at the given file and rank
+const fn get_square(file: i8, rank: i8) -> u64 {
+    a1_bitboard!() >> (8 * rank as u32) >> file as u32
+}
+
+/// Check whether the given file and rank are on the board
+const fn is_on_board(file: i8, rank: i8) -> bool {
+    file >= 0 && file < 8 && rank >= 0 && rank < 8
+}
+
+/// Generate the table of a piece jumping by the given (file, rank) offsets, from all the
+/// squares of the given ranks (the table is empty on the other ranks)
+const fn gen_leaper_table(offsets: &[(i8, i8)], first_rank: i8, last_rank: i8) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut index = first_rank as usize * 8;
+    while index < (last_rank as usize + 1) * 8 {
+        let (file, rank) = ((index % 8) as i8, (index / 8) as i8);
+        let mut i = 0;
+        while i < offsets.len() {
+            let (target_file, target_rank) = (file + offsets[i].0, rank + offsets[i].1);
+            if is_on_board(target_file, target_rank) {
+                table[index] |= get_square(target_file, target_rank);
+            }
+            i += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+/// Generate the table of pawn moves (pushes and captures). Double pushes are only possible
+/// from the start rank of the player.
+const fn gen_pawn_moves_table(direction: i8) -> [u64; 64] {
+    let mut table = gen_leaper_table(&[(0, direction), (-1, direction), (1, direction)], 1, 6);
+    let start_rank = if direction == 1 { 1 } else { 6 };
+    let mut file = 0;
+    while file < 8 {
+        table[(start_rank * 8 + file) as usize] |= get_square(file, start_rank + 2 * direction);
+        file += 1;
+    }
+    table
+}
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// Pseudolegal moves lookup table of the leapers (pawns, knights and kings)
+pub static PL_MOVE_LUT: PLMoveLUT = PLMoveLUT {
+    white_p_lut: gen_pawn_moves_table(1),
+    black_p_lut: gen_pawn_moves_table(-1),
+    white_p_attack_lut: gen_leaper_table(&[(-1, 1), (1, 1)], 0, 7),
+    black_p_attack_lut: gen_leaper_table(&[(-1, -1), (1, -1)], 0, 7),
+    n_lut: gen_leaper_table(&KNIGHT_OFFSETS, 0, 7),
+    k_lut: gen_leaper_table(&KING_OFFSETS, 0, 7),
+};
+
+/// Return the pseudolegal moves lookup table
+#[inline(always)]
+pub fn get_pl_move_lut() -> &'static PLMoveLUT {
+    &PL_MOVE_LUT
+}
+
+/**********************
+ * BETWEEN & LINE TABLES
+ **********************/
+
+/// Generate the between (squares strictly between two squares) and line (full line going
+/// through two squares) tables, from the rook and bishop masks. Both are empty for squares
+/// that are not on the same rank, file or diagonal.
+const fn gen_between_and_line_tables() -> ([[u64; 64]; 64], [[u64; 64]; 64]) {
+    let mut between = [[0; 64]; 64];
+    let mut line = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let coord_a = Coord {
+            f: a as u8 % 8,
+            r: a as u8 / 8,
+        };
+        let square_a = a1_bitboard!() >> a;
+        let mut b = 0;
+        while b < 64 {
+            let coord_b = Coord {
+                f: b as u8 % 8,
+                r: b as u8 / 8,
+            };
+            let square_b = a1_bitboard!() >> b;
+
+            // Intersecting the masks of both squares (with the squares themselves) only
+            // leaves the line they share
+            let mut shared_line = 0;
+            if gen_pl_r_moves(coord_a) & square_b != 0 {
+                shared_line =
+                    (gen_pl_r_moves(coord_a) | square_a) & (gen_pl_r_moves(coord_b) | square_b);
+            } else if gen_pl_b_moves(coord_a) & square_b != 0 {
+                shared_line =
+                    (gen_pl_b_moves(coord_a) | square_a) & (gen_pl_b_moves(coord_b) | square_b);
+            }
+            line[a][b] = shared_line;
+
+            // Squares of a line are ordered like their bits, so the squares between a and b
+            // are the ones between their bits
+            if shared_line != 0 {
+                let (first, last) = if a < b {
+                    (square_a, square_b)
+                } else {
+                    (square_b, square_a)
+                };
+                between[a][b] = shared_line & (first - 1) & !(last | (last - 1));
+            }
+            b += 1;
+        }
+        a += 1;
+    }
+    (between, line)
+}
+
+const BETWEEN_AND_LINE_TABLES: ([[u64; 64]; 64], [[u64; 64]; 64]) = gen_between_and_line_tables();
+static BETWEEN_TABLE: [[u64; 64]; 64] = BETWEEN_AND_LINE_TABLES.0;
+static LINE_TABLE: [[u64; 64]; 64] = BETWEEN_AND_LINE_TABLES.1;
+
+/// Return the squares strictly between two squares on the same rank, file or diagonal (or
+/// an empty bitboard if they are not aligned)
+#[inline(always)]
+pub fn between(a: Coord, b: Coord) -> u64 {
+    BETWEEN_TABLE[(a.r * 8 + a.f) as usize][(b.r * 8 + b.f) as usize]
+}
+
+/// Return the full line (rank, file or diagonal, from edge to edge) going through two
+/// squares, or an empty bitboard if they are not aligned
+#[inline(always)]
+pub fn line(a: Coord, b: Coord) -> u64 {
+    LINE_TABLE[(a.r * 8 + a.f) as usize][(b.r * 8 + b.f) as usize]
+}
+
+/*****************
+ * MAGIC BITBOARDS
+ *****************/
+
+// Slider attacks are looked up with "magic bitboards" : the occupied squares of the rays
+// of a slider (its "mask", edges excluded) are multiplied by a magic number, so that the
+// highest bits of the product form a perfect hash of the attacks. The magic numbers were
+// found by trial and error, for this bitboard layout (a1 being the MSB).
+// See : <https://www.chessprogramming.org/Magic_Bitboards>
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Number of entries of all the rook attack tables
+const ROOK_TABLE_SIZE: usize = 102400;
+/// Number of entries of all the bishop attack tables
+const BISHOP_TABLE_SIZE: usize = 5248;
+
+const ROOK_MAGIC_NUMBERS: [u64; 64] = [
+    0x8121000042003081,
+    0x08510002000400C1,
+    0x0011000804000201,
+    0xA001000410020801,
+    0x2002002010404816,
+    0x04241021800A0242,
+    0x0008142045008202,
+    0x1000215980030041,
+    0x00D1090420804200,
+    0x2800800100020080,
+    0x0A02040080020080,
+    0x0048001400088180,
+    0x400200401388A200,
+    0x0010201040820200,
+    0x00E0400020008080,
+    0x0080004000200840,
+    0x6001002094410002,
+    0x00C0040200010100,
+    0x008A000844820050,
+    0x0003000800050010,
+    0x820020100101000B,
+    0x1000200010008080,
+    0x1480400081010020,
+    0x1244208040148000,
+    0x4430008102000044,
+    0x0402000102000884,
+    0x0000040080800200,
+    0x8008000401800980,
+    0x1008001000800881,
+    0x0042018246002010,
+    0x8400804000802000,
+    0x0000400020800081,
+    0x00040842000108A4,
+    0x5800020400881041,
+    0x0011008900020400,
+    0x008A080080040080,
+    0x0208000880100481,
+    0x2038200080801000,
+    0x0211008200220040,
+    0x0840400880008020,
+    0x2820420001008044,
+    0x2400840001220810,
+    0x9000880110042040,
+    0x0081310004680100,
+    0x3000210010010208,
+    0x8060008010002080,
+    0x0020008020401081,
+    0x8102828000C000F2,
+    0x04018000C0802300,
+    0x0084004108100244,
+    0x8000808002000400,
+    0x0082804400080080,
+    0x0002000840142201,
+    0x3425001120010440,
+    0x800A802000400F80,
+    0x0400800020804010,
+    0x8100110000204582,
+    0x2880020000804100,
+    0x2080040080020001,
+    0x4200100420080200,
+    0x0100042010000900,
+    0x16801000806000C8,
+    0x0440200010004001,
+    0x0080008010204001,
+];
+const BISHOP_MAGIC_NUMBERS: [u64; 64] = [
+    0x0050041128002100,
+    0x0001888308020C00,
+    0x0140000820280088,
+    0x2000808043104100,
+    0x0000000420460804,
+    0x00A0201B04290409,
+    0x2003384202012084,
+    0x1800A12202304000,
+    0x0050114800828403,
+    0x0405084218420010,
+    0x0010046024810002,
+    0x0004811042088200,
+    0x200C500442088001,
+    0x48041024A4100004,
+    0x0045026110086040,
+    0xC282080202100051,
+    0x0008010040900602,
+    0x0820044400400080,
+    0x2108100082000020,
+    0x204202020C003601,
+    0x0800054200820800,
+    0x0041008050100901,
+    0x0152641024000808,
+    0x0A00842060200808,
+    0x8002220140020460,
+    0x9024040040440110,
+    0x00200E0081484801,
+    0x0208030040040142,
+    0x4000280800620A00,
+    0x1041080440520400,
+    0x0482480200043040,
+    0x044420051C081000,
+    0x00041880230D4500,
+    0x0000820000A21022,
+    0x0210208083008080,
+    0x0200840042802008,
+    0x0040040045410060,
+    0x1001100911024200,
+    0x2848208022045500,
+    0x2184220840880100,
+    0x0030800100909000,
+    0x0000400A12100450,
+    0x8400802300A01120,
+    0x0001040820080200,
+    0x0008008226004100,
+    0x841404A848020110,
+    0x0050010404081040,
+    0x0842830410020208,
+    0xC001120084410800,
+    0x0444410082104208,
+    0x0090020222208008,
+    0x0009020211400048,
+    0x000008A092000800,
+    0x0142040124050800,
+    0x0440200282085100,
+    0x4400116021040082,
+    0x140242080082102A,
+    0x0680848820700080,
+    0x8200882008C30530,
+    0x0104042000006500,
+    0x0848209221040011,
+    0x0851240410402041,
+    0x88110400808A0400,
+    0x011122008C008200,
+];
+
+/// Magic hashing parameters of a square
+#[derive(Copy, Clone, Debug)]
+pub struct Magic {
+    /// Squares of the rays whose occupancy matters (edges excluded)
+    pub mask: u64,
+    pub number: u64,
+    /// 64 minus the number of squares of the mask
+    pub shift: u32,
+    /// Index of the first attack of the square in the attack table
+    pub offset: usize,
+}
+
+impl Magic {
+    #[inline(always)]
+    const fn get_index(&self, occupancy: u64) -> usize {
+        self.offset + ((occupancy & self.mask).wrapping_mul(self.number) >> self.shift) as usize
+    }
+}
+
+/// Generate the attacks of a slider in the given directions, stopping on the first occupied
+/// square of each ray. If edges is false, the last square of each ray is excluded (to
+/// generate masks).
+const fn gen_ray_attacks(
+    index: usize,
+    occupancy: u64,
+    directions: &[(i8, i8); 4],
+    edges: bool,
+) -> u64 {
+    let (file, rank) = ((index % 8) as i8, (index / 8) as i8);
+    let mut attacks = 0;
+    let mut i = 0;
+    while i < 4 {
+        let (df, dr) = directions[i];
+        let (mut f, mut r) = (file + df, rank + dr);
+        while is_on_board(f, r) && (edges || is_on_board(f + df, r + dr)) {
+            attacks |= get_square(f, r);
+            if occupancy & get_square(f, r) != 0 {
+                break;
+            }
+            f += df;
+            r += dr;
+        }
+        i += 1;
+    }
+    attacks
+}
+
+/// Generate the magic parameters of all squares
+const fn gen_magics(
+    numbers: &[u64; 64],
+    directions: &[(i8, i8); 4],
+    table_size: usize,
+) -> [Magic; 64] {
+    let empty = Magic {
+        mask: 0,
+        number: 0,
+        shift: 0,
+        offset: 0,
+    };
+    let mut magics = [empty; 64];
+    let mut offset = 0;
+    let mut index = 0;
+    while index < 64 {
+        let mask = gen_ray_attacks(index, 0, directions, false);
+        magics[index] = Magic {
+            mask,
+            number: numbers[index],
+            shift: 64 - mask.count_ones(),
+            offset,
+        };
+        offset += 1 << mask.count_ones();
+        index += 1;
+    }
+    assert!(offset == table_size, "Invalid magic table size");
+    magics
+}
+
+/// Generate the attack table of all squares, by enumerating all the subsets of each mask
+const fn gen_magic_attacks<const N: usize>(
+    magics: &[Magic; 64],
+    directions: &[(i8, i8); 4],
+) -> [u64; N] {
+    let mut table = [0; N];
+    let mut index = 0;
+    while index < 64 {
+        let magic = &magics[index];
+        let mut occupancy: u64 = 0;
+        loop {
+            let attacks = gen_ray_attacks(index, occupancy, directions, true);
+            let entry = magic.get_index(occupancy);
+            assert!(
+                table[entry] == 0 || table[entry] == attacks,
+                "Invalid magic number"
+            );
+            table[entry] = attacks;
+
+            // Next subset of the mask ("Carry-Rippler" trick)
+            occupancy = occupancy.wrapping_sub(magic.mask) & magic.mask;
+            if occupancy == 0 {
+                break;
+            }
+        }
+        index += 1;
+    }
+    table
+}
+
+const ROOK_MAGICS: [Magic; 64] = gen_magics(&ROOK_MAGIC_NUMBERS, &ROOK_DIRECTIONS, ROOK_TABLE_SIZE);
+const BISHOP_MAGICS: [Magic; 64] =
+    gen_magics(&BISHOP_MAGIC_NUMBERS, &BISHOP_DIRECTIONS, BISHOP_TABLE_SIZE);
+
+// Generating the rook table takes a few seconds of constant evaluation
+#[allow(long_running_const_eval)]
+static ROOK_ATTACKS: [u64; ROOK_TABLE_SIZE] = gen_magic_attacks(&ROOK_MAGICS, &ROOK_DIRECTIONS);
+static BISHOP_ATTACKS: [u64; BISHOP_TABLE_SIZE] =
+    gen_magic_attacks(&BISHOP_MAGICS, &BISHOP_DIRECTIONS);
+
+/// Return the squares attacked by a rook on the coord square, given the occupied squares
+/// (the first blocker of each ray is included)
+#[inline(always)]
+pub fn get_rook_attacks(coord: Coord, occupancy: u64) -> u64 {
+    let magic = &ROOK_MAGICS[(coord.r * 8 + coord.f) as usize];
+    ROOK_ATTACKS[magic.get_index(occupancy)]
+}
+
+/// Return the squares attacked by a bishop on the coord square, given the occupied squares
+/// (the first blocker of each ray is included)
+#[inline(always)]
+pub fn get_bishop_attacks(coord: Coord, occupancy: u64) -> u64 {
+    let magic = &BISHOP_MAGICS[(coord.r * 8 + coord.f) as usize];
+    BISHOP_ATTACKS[magic.get_index(occupancy)]
+}
@@ -6,142 +6,258 @@ use crate::board_representation::*;
  * MOVE ENCODING
  ***************/
 
+/// A move, encoded in a u32 (see the module documentation for the layout). Moves are made
+/// by the move generator, and only make sense in the position they were generated for.
+/// The encoding is internal to the crate : moves are read with the get_move_* functions,
+/// and written in UCI or SAN notation.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Move(pub(crate) u32);
+
+/// Capture bit of the special moves encoding byte
+pub const CAPTURE_FLAG: u8 = 0b10000000;
+/// En-passant capture bit of the special moves encoding byte
+pub const EN_PASSANT_CAPTURE_FLAG: u8 = 0b01000000;
+/// Double pawn push bit of the special moves encoding byte
+pub const DOUBLE_PAWN_PUSH_FLAG: u8 = 0b00100000;
+/// Promotion bit of the special moves encoding byte
+pub const PROMOTION_FLAG: u8 = 0b00010000;
+/// Kingside castling bit of the special moves encoding byte
+pub const KINGSIDE_CASTLING_FLAG: u8 = 0b00001000;
+/// Queenside castling bit of the special moves encoding byte
+pub const QUEENSIDE_CASTLING_FLAG: u8 = 0b00000100;
+/// Check bit of the special moves encoding byte
+pub const CHECK_FLAG: u8 = 0b00000010;
+/// Checkmate bit of the special moves encoding byte
+pub const CHECKMATE_FLAG: u8 = 0b00000001;
+
+/// Encode a move from its components (see the module documentation for the layout).
+/// Castling moves are encoded with the king's start and arrival squares.
+#[inline(always)]
+pub fn encode_move(
+    start: Coord,
+    arrival: Coord,
+    piece_code: PieceCode,
+    arrival_square: PieceCode,
+    flags: u8,
+    promotion: PieceCode,
+) -> Move {
+    Move(
+        (start.f as u32) << 29
+            | (start.r as u32) << 26
+            | (arrival.f as u32) << 23
+            | (arrival.r as u32) << 20
+            | (piece_code as u32) << 16
+            | (arrival_square as u32) << 12
+            | (flags as u32) << 4
+            | promotion as u32,
+    )
+}
+
 /// Get the start file of a move
 #[inline(always)]
-pub fn get_move_start_file(mov: u32) -> u8 {
-    (mov >> 29) as u8
+pub fn get_move_start_file(mov: Move) -> u8 {
+    (mov.0 >> 29) as u8
 }
 
 /// Get the start rank of a move
 #[inline(always)]
-pub fn get_move_start_rank(mov: u32) -> u8 {
-    ((0b111 << 26) & mov) as u8
+pub fn get_move_start_rank(mov: Move) -> u8 {
+    ((mov.0 >> 26) & 0b111) as u8
 }
 
 /// Get the start Coord of a move
 #[inline(always)]
-pub fn get_move_start_coords(mov: u32) -> Coord {
+pub fn get_move_start_coords(mov: Move) -> Coord {
     Coord::new(get_move_start_file(mov), get_move_start_rank(mov))
 }
 
 /// Get the arrival file of a move
 #[inline(always)]
-pub fn get_move_arrival_file(mov: u32) -> u8 {
-    ((0b111 << 23) & mov) as u8
+pub fn get_move_arrival_file(mov: Move) -> u8 {
+    ((mov.0 >> 23) & 0b111) as u8
 }
 
 /// Get the arrival rank of a move
 #[inline(always)]
-pub fn get_move_arrival_rank(mov: u32) -> u8 {
-    ((0b111 << 20) & mov) as u8
+pub fn get_move_arrival_rank(mov: Move) -> u8 {
+    ((mov.0 >> 20) & 0b111) as u8
 }
 
 /// Get the arrival Coord of a move
 #[inline(always)]
-pub fn get_move_arrival_coords(mov: u32) -> Coord {
-    Coord::new(get_move_start_file(mov), get_move_start_rank(mov))
+pub fn get_move_arrival_coords(mov: Move) -> Coord {
+    Coord::new(get_move_arrival_file(mov), get_move_arrival_rank(mov))
 }
 
 /// Get moved PieceCode from a move
 #[inline(always)]
-pub fn get_move_piece_code(mov: u32) -> PieceCode {
-    PieceCode::from_u32((0b1111 << 16) & mov)
+pub fn get_move_piece_code(mov: Move) -> PieceCode {
+    PieceCode::from_u32((mov.0 >> 16) & 0b1111)
 }
 
 /// Get arrival square PieceCode of a move
 #[inline(always)]
-pub fn get_move_arrival_square(mov: u32) -> PieceCode {
-    PieceCode::from_u32((0b1111 << 12) & mov)
+pub fn get_move_arrival_square(mov: Move) -> PieceCode {
+    PieceCode::from_u32((mov.0 >> 12) & 0b1111)
+}
+
+/// Get the special moves encoding byte of a move
+#[inline(always)]
+pub fn get_move_flags(mov: Move) -> u8 {
+    (mov.0 >> 4) as u8
 }
 
 /// Get capture bit of a move
 #[inline(always)]
-pub fn get_move_capture(mov: u32) -> bool {
-    ((0b1 << 15) & mov) != 0
+pub fn get_move_capture(mov: Move) -> bool {
+    get_move_flags(mov) & CAPTURE_FLAG != 0
 }
 
 /// Get en-passant capture bit of a move
 #[inline(always)]
-pub fn get_move_en_passant_capture(mov: u32) -> bool {
-    ((0b1 << 14) & mov) != 0
+pub fn get_move_en_passant_capture(mov: Move) -> bool {
+    get_move_flags(mov) & EN_PASSANT_CAPTURE_FLAG != 0
 }
 
 /// Get double pawn push bit of a move
 #[inline(always)]
-pub fn get_move_double_pawn_push(mov: u32) -> bool {
-    ((0b1 << 13) & mov) != 0
+pub fn get_move_double_pawn_push(mov: Move) -> bool {
+    get_move_flags(mov) & DOUBLE_PAWN_PUSH_FLAG != 0
 }
 
 /// Get promotion bit of a move
 #[inline(always)]
-pub fn get_move_promotion(mov: u32) -> bool {
-    ((0b1 << 12) & mov) != 0
+pub fn get_move_promotion(mov: Move) -> bool {
+    get_move_flags(mov) & PROMOTION_FLAG != 0
 }
 
 /// Get kingside castling bit of a move
 #[inline(always)]
-pub fn get_move_kingside_castling(mov: u32) -> bool {
-    ((0b1 << 11) & mov) != 0
+pub fn get_move_kingside_castling(mov: Move) -> bool {
+    get_move_flags(mov) & KINGSIDE_CASTLING_FLAG != 0
 }
 
 /// Get queenside castling bit of a move
 #[inline(always)]
-pub fn get_move_queenside_castling(mov: u32) -> bool {
-    ((0b1 << 10) & mov) != 0
+pub fn get_move_queenside_castling(mov: Move) -> bool {
+    get_move_flags(mov) & QUEENSIDE_CASTLING_FLAG != 0
 }
 
 /// Get check castling bit of a move
 #[inline(always)]
-pub fn get_move_check(mov: u32) -> bool {
-    ((0b1 << 9) & mov) != 0
+pub fn get_move_check(mov: Move) -> bool {
+    get_move_flags(mov) & CHECK_FLAG != 0
 }
 
 /// Get checkmate castling bit of a move
 #[inline(always)]
-pub fn get_move_checkmate(mov: u32) -> bool {
-    ((0b1 << 8) & mov) != 0
+pub fn get_move_checkmate(mov: Move) -> bool {
+    get_move_flags(mov) & CHECKMATE_FLAG != 0
+}
+
+/// Get promotion PieceCode of a move (ES if the move is not a promotion)
+#[inline(always)]
+pub fn get_move_promotion_piece_code(mov: Move) -> PieceCode {
+    PieceCode::from_u32(mov.0 & 0b1111)
+}
+
+impl Move {
+    /// Start square of the move (the king's square for castling moves)
+    #[inline(always)]
+    pub fn start(self) -> Coord {
+        get_move_start_coords(self)
+    }
+
+    /// Arrival square of the move (the king's arrival square for castling moves)
+    #[inline(always)]
+    pub fn arrival(self) -> Coord {
+        get_move_arrival_coords(self)
+    }
+
+    /// Moved piece
+    #[inline(always)]
+    pub fn piece(self) -> PieceCode {
+        get_move_piece_code(self)
+    }
+
+    /// Captured piece (the pawn taken for en passant captures), or None if the move is not
+    /// a capture
+    #[inline(always)]
+    pub fn captured(self) -> Option<PieceCode> {
+        self.is_capture().then(|| get_move_arrival_square(self))
+    }
+
+    /// Piece the pawn is promoted to, or None if the move is not a promotion
+    #[inline(always)]
+    pub fn promotion(self) -> Option<PieceCode> {
+        get_move_promotion(self).then(|| get_move_promotion_piece_code(self))
+    }
+
+    /// Whether the move is a capture (en passant captures included)
+    #[inline(always)]
+    pub fn is_capture(self) -> bool {
+        get_move_capture(self)
+    }
+
+    /// Whether the move is an en passant capture
+    #[inline(always)]
+    pub fn is_en_passant(self) -> bool {
+        get_move_en_passant_capture(self)
+    }
+
+    /// Whether the move is a pawn moving two squares forward
+    #[inline(always)]
+    pub fn is_double_pawn_push(self) -> bool {
+        get_move_double_pawn_push(self)
+    }
+
+    /// Whether the move is a castling move, on either side
+    #[inline(always)]
+    pub fn is_castling(self) -> bool {
+        get_move_kingside_castling(self) || get_move_queenside_castling(self)
+    }
+}
+
+/// Get the UCI notation of a move (for instance "e2e4" or "e7e8q"). In Chess960 positions,
+/// castling moves are written as the king capturing its own rook.
+pub fn get_move_uci(position: &Position, mov: Move) -> String {
+    let start = get_move_start_coords(mov);
+    let mut arrival = get_move_arrival_coords(mov);
+
+    if position.chess960 {
+        let player = position.current_turn as usize;
+        let rook_file = if get_move_kingside_castling(mov) {
+            position.kingside_castling_rook[player]
+        } else if get_move_queenside_castling(mov) {
+            position.queenside_castling_rook[player]
+        } else {
+            None
+        };
+        if let Some(rook_file) = rook_file {
+            arrival = Coord::new(rook_file, arrival.r);
+        }
+    }
+
+    let mut uci = get_algebraic_square(start) + &get_algebraic_square(arrival);
+    if get_move_promotion(mov) {
+        let promotion_piece = get_fen_piece(get_move_promotion_piece_code(mov));
+        uci.push(promotion_piece.to_ascii_lowercase());
+    }
+    uci
 }
 
 /************************
  * MISC UTILITY FUNCTIONS
  ************************/
 
-/// Pop (set to 0) the MSB of a bitboard (u64) and return its index
-fn pop_msb(bitboard: &mut u64) {}
-
-fn get_player_bitboard(bitboard: &BitBoard, player: Player) -> u64 {
-    if player == Player::White {
-        bitboard.main_boards[0]
-            & bitboard.main_boards[1]
-            & bitboard.main_boards[2]
-            & bitboard.main_boards[3]
-            & bitboard.main_boards[4]
-            & bitboard.main_boards[5]
-    } else {
-        bitboard.main_boards[6]
-            & bitboard.main_boards[7]
-            & bitboard.main_boards[8]
-            & bitboard.main_boards[9]
-            & bitboard.main_boards[10]
-            & bitboard.main_boards[11]
-    }
+/// Return a bitboard of all the pieces of a player
+#[inline(always)]
+pub fn get_player_bitboard(bitboard: &BitBoard, player: Player) -> u64 {
+    bitboard.color_boards[player as usize]
 }
 
-fn get_all_pieces_bitboard(bitboard: &BitBoard) -> u64 {
-    bitboard.main_boards[0]
-        & bitboard.main_boards[1]
-        & bitboard.main_boards[2]
-        & bitboard.main_boards[3]
-        & bitboard.main_boards[4]
-        & bitboard.main_boards[5]
-        & bitboard.main_boards[6]
-        & bitboard.main_boards[7]
-        & bitboard.main_boards[8]
-        & bitboard.main_boards[9]
-        & bitboard.main_boards[10]
-        & bitboard.main_boards[11]
-}
-
-// Generate a bitboard of all our own pinned pieces (by checking which squares are attacked
-// by the opponent's long range pieces)
-// TODO
+/// Return a bitboard of all the pieces on the board (the occupied squares)
+#[inline(always)]
+pub fn get_all_pieces_bitboard(bitboard: &BitBoard) -> u64 {
+    bitboard.occupancy_board
+}
@@ -1,7 +1,188 @@
 #![allow(dead_code)]
 
+use super::misc::*;
+use crate::board_representation::*;
+
 /*********************
  * MOVABLE BOARD TRAIT
  *********************/
 
-pub trait MovableBoard {}
+/// This trait contains methods to make and unmake moves on a board. Moves are expected to be
+/// legal (or at least pseudo-legal) in the current position.
+pub trait MovableBoard {
+    /// Play a move, and switch the current turn
+    fn make_move(&mut self, mov: Move);
+    /// Take back a move. It must be the last move that was made on the board.
+    fn unmake_move(&mut self, mov: Move);
+}
+
+/// Return the arrival files of the king and the rook when castling
+#[inline(always)]
+pub fn get_castling_arrival_files(kingside: bool) -> (u8, u8) {
+    if kingside {
+        (6, 5)
+    } else {
+        (2, 3)
+    }
+}
+
+impl Position {
+    /// Return the file of the rook used by a castling move
+    fn get_castling_rook_file(&self, player: Player, mov: Move) -> u8 {
+        let rook_file = if get_move_kingside_castling(mov) {
+            self.kingside_castling_rook[player as usize]
+        } else {
+            self.queenside_castling_rook[player as usize]
+        };
+        rook_file.expect("Castling move without the corresponding castling right")
+    }
+
+    /// Remove the castling right associated to a rook standing on the given square, if any
+    fn remove_castling_rook(&mut self, coord: Coord) {
+        for player in [Player::White, Player::Black] {
+            let back_rank = match player {
+                Player::White => 0,
+                Player::Black => 7,
+            };
+            if coord.r != back_rank {
+                continue;
+            }
+
+            let player = player as usize;
+            if self.kingside_castling_rook[player] == Some(coord.f) {
+                self.kingside_castling_rook[player] = None;
+            }
+            if self.queenside_castling_rook[player] == Some(coord.f) {
+                self.queenside_castling_rook[player] = None;
+            }
+        }
+    }
+}
+
+impl MovableBoard for Position {
+    fn make_move(&mut self, mov: Move) {
+        let player = self.current_turn;
+
+        self.history.push(IrreversibleState {
+            kingside_castling_rook: self.kingside_castling_rook,
+            queenside_castling_rook: self.queenside_castling_rook,
+            en_passant_board: self.piece_centric_board.en_passant_board,
+            plys_without_capture: self.plys_without_capture,
+            hash: self.hash,
+        });
+        // The keys of the pieces are updated by set_square, and the ones of the castling
+        // rights and en passant file are replaced once they are updated
+        self.hash ^= self.get_state_zobrist_key();
+
+        let start = get_move_start_coords(mov);
+        let arrival = get_move_arrival_coords(mov);
+        let piece_code = get_move_piece_code(mov);
+
+        if get_move_kingside_castling(mov) || get_move_queenside_castling(mov) {
+            // In Chess960, the king or the rook can already stand on the arrival square of
+            // the other piece, so both have to be removed before being put back
+            let rook_file = self.get_castling_rook_file(player, mov);
+            let rook_code = self.get_square(Coord::new(rook_file, start.r));
+            let (_, rook_arrival_file) =
+                get_castling_arrival_files(get_move_kingside_castling(mov));
+
+            self.set_square(PieceCode::ES, start);
+            self.set_square(PieceCode::ES, Coord::new(rook_file, start.r));
+            self.set_square(piece_code, arrival);
+            self.set_square(rook_code, Coord::new(rook_arrival_file, start.r));
+        } else {
+            if get_move_en_passant_capture(mov) {
+                self.set_square(PieceCode::ES, Coord::new(arrival.f, start.r));
+            }
+
+            let arrival_code = if get_move_promotion(mov) {
+                get_move_promotion_piece_code(mov)
+            } else {
+                piece_code
+            };
+            self.set_square(PieceCode::ES, start);
+            self.set_square(arrival_code, arrival);
+        }
+
+        // Update castling rights
+        if piece_code == PieceCode::WK || piece_code == PieceCode::BK {
+            self.kingside_castling_rook[player as usize] = None;
+            self.queenside_castling_rook[player as usize] = None;
+        }
+        self.remove_castling_rook(start);
+        self.remove_castling_rook(arrival);
+
+        // Update en passant square
+        if get_move_double_pawn_push(mov) {
+            self.set_en_passant_square(Some(Coord::new(start.f, (start.r + arrival.r) / 2)));
+        } else {
+            self.set_en_passant_square(None);
+        }
+
+        // Update move counters
+        if piece_code == PieceCode::WP || piece_code == PieceCode::BP || get_move_capture(mov) {
+            self.plys_without_capture = 0;
+        } else {
+            self.plys_without_capture = self.plys_without_capture.saturating_add(1);
+        }
+        if player == Player::Black {
+            self.full_move_number += 1;
+        }
+
+        self.current_turn = invert_player(&player);
+        self.hash ^= self.get_state_zobrist_key() ^ ZOBRIST_KEYS.black_to_move;
+        self.debug_validate();
+    }
+
+    fn unmake_move(&mut self, mov: Move) {
+        let state = self
+            .history
+            .pop()
+            .expect("Tried to unmake a move without any move history");
+
+        let player = invert_player(&self.current_turn);
+        self.current_turn = player;
+        if player == Player::Black {
+            self.full_move_number -= 1;
+        }
+
+        // Castling rights have to be restored first, as they are needed to find the rook
+        // of castling moves
+        self.kingside_castling_rook = state.kingside_castling_rook;
+        self.queenside_castling_rook = state.queenside_castling_rook;
+        self.plys_without_capture = state.plys_without_capture;
+
+        let start = get_move_start_coords(mov);
+        let arrival = get_move_arrival_coords(mov);
+        let piece_code = get_move_piece_code(mov);
+
+        if get_move_kingside_castling(mov) || get_move_queenside_castling(mov) {
+            let rook_file = self.get_castling_rook_file(player, mov);
+            let (_, rook_arrival_file) =
+                get_castling_arrival_files(get_move_kingside_castling(mov));
+            let rook_code = self.get_square(Coord::new(rook_arrival_file, start.r));
+
+            self.set_square(PieceCode::ES, arrival);
+            self.set_square(PieceCode::ES, Coord::new(rook_arrival_file, start.r));
+            self.set_square(piece_code, start);
+            self.set_square(rook_code, Coord::new(rook_file, start.r));
+        } else {
+            self.set_square(piece_code, start);
+            if get_move_en_passant_capture(mov) {
+                self.set_square(PieceCode::ES, arrival);
+                self.set_square(get_move_arrival_square(mov), Coord::new(arrival.f, start.r));
+            } else {
+                self.set_square(get_move_arrival_square(mov), arrival);
+            }
+        }
+
+        if state.en_passant_board == 0 {
+            self.set_en_passant_square(None);
+        } else {
+            let index = state.en_passant_board.leading_zeros() as u8;
+            self.set_en_passant_square(Some(Coord::new(index & 7, index >> 3)));
+        }
+        self.hash = state.hash;
+        self.debug_validate();
+    }
+}
@@ -0,0 +1,229 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::ops::Deref;
+
+use super::misc::Move;
+
+/***********
+ * MOVE LIST
+ ***********/
+
+// The move generator writes its moves in a MoveList instead of a Vec, so that generating the
+// moves of a node doesn't allocate. No legal chess position has more than 218 moves, so a
+// fixed capacity of 256 moves is always enough. Each move has a score stored next to it,
+// which the search fills in to order the moves.
+
+/// Maximum number of moves a MoveList can hold
+pub const MAX_MOVES: usize = 256;
+
+/// Fixed-capacity, stack-allocated list of moves, with a score for each move
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    scores: [i32; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub const fn new() -> MoveList {
+        MoveList {
+            moves: [Move(0); MAX_MOVES],
+            scores: [0; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Add a move with a score of 0. Panics if the list is full.
+    #[inline(always)]
+    pub fn push(&mut self, mov: Move) {
+        self.push_scored(mov, 0);
+    }
+
+    /// Add a move with the given score. Panics if the list is full.
+    #[inline(always)]
+    pub fn push_scored(&mut self, mov: Move, score: i32) {
+        assert!(
+            self.len < MAX_MOVES,
+            "Tried to push a move in a full MoveList"
+        );
+        self.moves[self.len] = mov;
+        self.scores[self.len] = score;
+        self.len += 1;
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+
+    #[inline(always)]
+    pub fn scores(&self) -> &[i32] {
+        &self.scores[..self.len]
+    }
+
+    /// Score of the move at the given index
+    #[inline(always)]
+    pub fn get_score(&self, index: usize) -> i32 {
+        self.scores()[index]
+    }
+
+    #[inline(always)]
+    pub fn set_score(&mut self, index: usize, score: i32) {
+        self.scores[..self.len][index] = score;
+    }
+
+    /// Give each move the score returned by the function
+    pub fn score_moves<F: FnMut(Move) -> i32>(&mut self, mut score: F) {
+        for index in 0..self.len {
+            self.scores[index] = score(self.moves[index]);
+        }
+    }
+
+    /// Only keep the moves for which the predicate returns true. The predicate may modify
+    /// the moves it keeps (to set their check bit, for instance).
+    pub fn retain<F: FnMut(&mut Move) -> bool>(&mut self, mut predicate: F) {
+        let mut kept = 0;
+        for index in 0..self.len {
+            let mut mov = self.moves[index];
+            if predicate(&mut mov) {
+                self.moves[kept] = mov;
+                self.scores[kept] = self.scores[index];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+
+    /// Sort the moves by decreasing score. The sort is stable, so moves with the same
+    /// score keep their generation order.
+    pub fn sort_by_score(&mut self) {
+        // Insertion sort, which is fast on the short lists of a chess position
+        for index in 1..self.len {
+            let (mov, score) = (self.moves[index], self.scores[index]);
+            let mut target = index;
+            while target > 0 && self.scores[target - 1] < score {
+                self.moves[target] = self.moves[target - 1];
+                self.scores[target] = self.scores[target - 1];
+                target -= 1;
+            }
+            self.moves[target] = mov;
+            self.scores[target] = score;
+        }
+    }
+
+    /// Move the best scored move among the ones from the given index to the end of the list
+    /// to this index, and return it. Picking the moves one by one avoids sorting the whole
+    /// list when the search gets a cutoff on one of the first moves.
+    pub fn pick_best(&mut self, index: usize) -> Option<Move> {
+        if index >= self.len {
+            return None;
+        }
+        let mut best = index;
+        for candidate in index + 1..self.len {
+            if self.scores[candidate] > self.scores[best] {
+                best = candidate;
+            }
+        }
+        self.moves.swap(index, best);
+        self.scores.swap(index, best);
+        Some(self.moves[index])
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> MoveList {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    #[inline(always)]
+    fn deref(&self) -> &[Move] {
+        self.as_slice()
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.as_slice().iter().zip(self.scores()))
+            .finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &MoveList) -> bool {
+        self.as_slice() == other.as_slice() && self.scores() == other.scores()
+    }
+}
+
+impl Eq for MoveList {}
+
+impl Extend<Move> for MoveList {
+    fn extend<I: IntoIterator<Item = Move>>(&mut self, iter: I) {
+        for mov in iter {
+            self.push(mov);
+        }
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(iter: I) -> MoveList {
+        let mut moves = MoveList::new();
+        moves.extend(iter);
+        moves
+    }
+}
+
+/// Iterator over the moves of a MoveList, consuming the list
+pub struct MoveListIntoIter {
+    list: MoveList,
+    index: usize,
+}
+
+impl Iterator for MoveListIntoIter {
+    type Item = Move;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Move> {
+        let mov = self.list.as_slice().get(self.index).copied()?;
+        self.index += 1;
+        Some(mov)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for MoveListIntoIter {}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = MoveListIntoIter;
+
+    fn into_iter(self) -> MoveListIntoIter {
+        MoveListIntoIter {
+            list: self,
+            index: 0,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> std::slice::Iter<'a, Move> {
+        self.as_slice().iter()
+    }
+}
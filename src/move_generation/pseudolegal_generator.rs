@@ -27,7 +27,7 @@ fn gen_pl_p_moves(player: Player, coord: Coord) -> u64 {
         /* Generate forward pawn pushes */
         // Generate simple and double pawn push
         if coord.r == 1 {
-            moves_bitboard |= 0b10000001 << 55 >> (16 + coord.f);
+            moves_bitboard |= (pawn_bitboard >> 8) | (pawn_bitboard >> 16);
         }
         // Generate only a single pawn push
         else {
@@ -49,7 +49,7 @@ fn gen_pl_p_moves(player: Player, coord: Coord) -> u64 {
         /* Generate forward pawn pushes */
         // Generate simple and double pawn push
         if coord.r == 6 {
-            moves_bitboard |= 0b10000001 << (31 + coord.f);
+            moves_bitboard |= (pawn_bitboard << 8) | (pawn_bitboard << 16);
         }
         // Generate only a single pawn push
         else {
@@ -70,6 +70,36 @@ fn gen_pl_p_moves(player: Player, coord: Coord) -> u64 {
     moves_bitboard
 }
 
+/// Generate a bitboard of the squares attacked by a pawn located on the coord square (and
+/// controlled by the specified player). Unlike moves, attacks are also generated on the
+/// first and last ranks, so that they can be looked up from the attacked square.
+fn gen_p_attacks(player: Player, coord: Coord) -> u64 {
+    let mut attacks_bitboard: u64 = 0;
+    let pawn_bitboard: u64 = a1_bitboard!() >> (8 * coord.r) >> coord.f;
+
+    match player {
+        Player::White if coord.r != 7 => {
+            if coord.f != 0 {
+                attacks_bitboard |= pawn_bitboard >> 7;
+            }
+            if coord.f != 7 {
+                attacks_bitboard |= pawn_bitboard >> 9;
+            }
+        }
+        Player::Black if coord.r != 0 => {
+            if coord.f != 0 {
+                attacks_bitboard |= pawn_bitboard << 9;
+            }
+            if coord.f != 7 {
+                attacks_bitboard |= pawn_bitboard << 7;
+            }
+        }
+        _ => {}
+    }
+
+    attacks_bitboard
+}
+
 /// Generate a bitboard of all pseudo-legal moves for a knight located on the coord square.
 /// Used during pseudolegal moves precomputation.
 fn gen_pl_n_moves(coord: Coord) -> u64 {
@@ -94,29 +124,29 @@ fn gen_pl_n_moves(coord: Coord) -> u64 {
     // Generate moves one rank down
     if coord.r >= 1 {
         // Two files left
-        if coord.f >= 1 {
+        if coord.f >= 2 {
             moves_bitboard |= knight_bitboard << 10;
         }
         // Two files right
-        if coord.f <= 6 {
+        if coord.f <= 5 {
             moves_bitboard |= knight_bitboard << 6;
         }
     }
 
     // Generate moves one rank up
-    if coord.r <= 5 {
+    if coord.r <= 6 {
         // Two files left
-        if coord.f >= 1 {
+        if coord.f >= 2 {
             moves_bitboard |= knight_bitboard >> 6;
         }
         // Two files right
-        if coord.f <= 6 {
+        if coord.f <= 5 {
             moves_bitboard |= knight_bitboard >> 10;
         }
     }
 
     // Generate moves two ranks up
-    if coord.r <= 6 {
+    if coord.r <= 5 {
         // One file left
         if coord.f >= 1 {
             moves_bitboard |= knight_bitboard >> 15;
@@ -172,11 +202,11 @@ fn gen_pl_k_moves(coord: Coord) -> u64 {
     }
 
     // Move up
-    if coord.f != 7 {
+    if coord.r != 7 {
         moves_bitboard |= king_bitboard >> 8;
     }
     // Move down
-    if coord.f != 0 {
+    if coord.r != 0 {
         moves_bitboard |= king_bitboard << 8;
     }
 
@@ -187,7 +217,8 @@ fn gen_pl_k_moves(coord: Coord) -> u64 {
 /// bitboards of size 64
 fn precompute_pl_p_moves(player: Player) -> [u64; 64] {
     let mut pseudolegal_moves: [u64; 64] = [0; 64];
-    for i in 0..8 {
+    // Pawns can't stand on the first and last ranks
+    for i in 1..7 {
         for j in 0..8 {
             pseudolegal_moves[i * 8 + j] = gen_pl_p_moves(player, Coord::new(j as u8, i as u8));
         }
@@ -195,6 +226,18 @@ fn precompute_pl_p_moves(player: Player) -> [u64; 64] {
     pseudolegal_moves
 }
 
+/// Precompute pawn attacks for all squares, and return them in an array of bitboards of
+/// size 64
+fn precompute_p_attacks(player: Player) -> [u64; 64] {
+    let mut attacks: [u64; 64] = [0; 64];
+    for i in 0..8 {
+        for j in 0..8 {
+            attacks[i * 8 + j] = gen_p_attacks(player, Coord::new(j as u8, i as u8));
+        }
+    }
+    attacks
+}
+
 /// Precompute pseudolegal knight moves for all squares, and return them in an array of
 /// bitboards of size 64
 fn precompute_pl_n_moves() -> [u64; 64] {
@@ -225,32 +268,35 @@ fn precompute_pl_k_moves() -> [u64; 64] {
 
 // NOTE Because sliding/long-range pieces can get their line of sight blocked by other
 // pieces, efficiently generating moves for them is a bit trickier. To that effect, we use
-// "magic bitboards". See this blog post for a good explanation of the following code :
-// <https://rhysre.net/fast-chess-move-generation-with-magic-bitboards.html>
+// "magic bitboards" (see the lookup_tables module). See this blog post for a good
+// explanation : <https://rhysre.net/fast-chess-move-generation-with-magic-bitboards.html>
 
-fn gen_pl_r_moves(coord : Coord) -> u64 {
-    let mut pseudolegal_moves : u64 = 0;
+/// Generate the squares on the rank and file of a rook, regardless of any blocker. This is a
+/// const fn, as it is also used to build the line and between tables at compile time.
+pub const fn gen_pl_r_moves(coord: Coord) -> u64 {
+    let mut pseudolegal_moves: u64 = 0;
 
-    let file_mask : u64 = 0x80_80_80_80_80_80_80_80;
-    let rank_mask : u64 = 0xff_00_00_00_00_00_00_00;
+    let file_mask: u64 = 0x80_80_80_80_80_80_80_80;
+    let rank_mask: u64 = 0xff_00_00_00_00_00_00_00;
 
     // Shift masks and apply them on rook mask
     pseudolegal_moves |= file_mask >> coord.f;
-    pseudolegal_moves |= rank_mask >> coord.r;
+    pseudolegal_moves |= rank_mask >> (8 * coord.r);
 
     // Substract the square on which the rook itself is located
-    pseudolegal_moves &= !(a1_bitboard!() >> (8*coord.r) >> coord.f);
+    pseudolegal_moves &= !(a1_bitboard!() >> (8 * coord.r) >> coord.f);
 
     pseudolegal_moves
 }
 
-fn gen_pl_b_moves(coord : Coord) -> u64 {
-    let mut pseudolegal_moves : u64 = 0;
+/// Generate the squares on the diagonals of a bishop, regardless of any blocker
+pub const fn gen_pl_b_moves(coord: Coord) -> u64 {
+    let mut pseudolegal_moves: u64 = 0;
 
     let bishop_bitboard: u64 = a1_bitboard!() >> (8 * coord.r) >> coord.f;
 
     // Precompute all SW-NE diagonals, and see on which one (if any) the piece is located
-    let sw_ne_diags : [u64; 15] = [
+    let sw_ne_diags: [u64; 15] = [
         0x00_00_00_00_00_00_00_80, // a8
         0x00_00_00_00_00_00_80_40, // a7-b8
         0x00_00_00_00_00_80_40_20, // a6-c8
@@ -268,16 +314,18 @@ fn gen_pl_b_moves(coord : Coord) -> u64 {
         0x01_00_00_00_00_00_00_00, // h1
     ];
 
-    for i in 0..15 {
+    let mut i = 0;
+    while i < sw_ne_diags.len() {
         if bishop_bitboard & sw_ne_diags[i] != 0 {
             // We found our SW-NE diagonal, add it to the pseudolegal_moves
             pseudolegal_moves |= sw_ne_diags[i];
             break;
         }
+        i += 1;
     }
 
     // Same for NW-SE diagonals
-    let nw_se_diags : [u64; 15] = [
+    let nw_se_diags: [u64; 15] = [
         0x00_00_00_00_00_00_00_01, // h8
         0x00_00_00_00_00_00_01_02, // h7-g8
         0x00_00_00_00_00_01_02_04, // h6-f8
@@ -295,30 +343,69 @@ fn gen_pl_b_moves(coord : Coord) -> u64 {
         0x80_00_00_00_00_00_00_00, // a1
     ];
 
-    for i in 0..15 {
+    let mut i = 0;
+    while i < nw_se_diags.len() {
         if bishop_bitboard & nw_se_diags[i] != 0 {
             // We found our NW-SE diagonal, add it to the pseudolegal_moves
             pseudolegal_moves |= nw_se_diags[i];
             break;
         }
+        i += 1;
     }
 
-
     // Substract the square on which the bishop itself is located
     pseudolegal_moves &= !bishop_bitboard;
 
     pseudolegal_moves
 }
 
+/// Generate the attacks of a slider in the given directions (as file and rank steps),
+/// stopping on the first occupied square of each ray
+fn gen_slider_attacks(coord: Coord, occupancy: u64, directions: &[(i8, i8); 4]) -> u64 {
+    let mut attacks: u64 = 0;
+    for (df, dr) in directions {
+        let (mut f, mut r) = (coord.f as i8 + df, coord.r as i8 + dr);
+        while (0..8).contains(&f) && (0..8).contains(&r) {
+            let square_bitboard = a1_bitboard!() >> (8 * r) >> f;
+            attacks |= square_bitboard;
+            if occupancy & square_bitboard != 0 {
+                break;
+            }
+            f += df;
+            r += dr;
+        }
+    }
+    attacks
+}
+
+/// Generate the squares attacked by a rook on the coord square, given the occupied squares
+/// (the first blocker of each ray is included). The move generator uses the magic lookup
+/// (get_rook_attacks) instead.
+pub fn gen_rook_attacks(coord: Coord, occupancy: u64) -> u64 {
+    gen_slider_attacks(coord, occupancy, &[(1, 0), (-1, 0), (0, 1), (0, -1)])
+}
+
+/// Generate the squares attacked by a bishop on the coord square, given the occupied squares
+/// (the first blocker of each ray is included). The move generator uses the magic lookup
+/// (get_bishop_attacks) instead.
+pub fn gen_bishop_attacks(coord: Coord, occupancy: u64) -> u64 {
+    gen_slider_attacks(coord, occupancy, &[(1, 1), (1, -1), (-1, 1), (-1, -1)])
+}
 
 /**************************
  * PSEUDOLEGAL LOOKUP TABLE
  **************************/
 
-#[derive(Debug)]
+/// Pseudolegal moves of the leapers on each square. The table used by the move generator is
+/// generated at compile time (see PL_MOVE_LUT), and the Default implementation generates it
+/// at runtime.
+#[derive(Debug, Eq, PartialEq)]
 pub struct PLMoveLUT {
     pub white_p_lut: [u64; 64],
     pub black_p_lut: [u64; 64],
+    /// Squares attacked by pawns (captures only)
+    pub white_p_attack_lut: [u64; 64],
+    pub black_p_attack_lut: [u64; 64],
     pub n_lut: [u64; 64],
     pub k_lut: [u64; 64],
 }
@@ -328,6 +415,8 @@ impl Default for PLMoveLUT {
         PLMoveLUT {
             white_p_lut: precompute_pl_p_moves(Player::White),
             black_p_lut: precompute_pl_p_moves(Player::Black),
+            white_p_attack_lut: precompute_p_attacks(Player::White),
+            black_p_attack_lut: precompute_p_attacks(Player::Black),
             n_lut: precompute_pl_n_moves(),
             k_lut: precompute_pl_k_moves(),
         }
@@ -0,0 +1,243 @@
+#![allow(dead_code)]
+
+use crate::board_representation::*;
+
+/*****************************
+ * REFERENCE MOVES GENERATOR
+ *****************************/
+
+// A deliberately simple legal move generator, used by the tests as a reference for the
+// engine's generator. It only shares the Position type (which it reads square by square)
+// with the engine : it copies the pieces on its own 0x88 board, generates the moves of each
+// piece following the rules of the game, and plays each move on a copy of the board to check
+// that the king isn't attacked, looking at the attacks of every enemy piece. It is slow, but
+// easy to check by reading it.
+
+/// A move, as (start square, arrival square of the king for castling, promotion piece)
+pub type ReferenceMove = (Coord, Coord, PieceCode);
+
+type Board = [PieceCode; 128];
+
+const KNIGHT_OFFSETS: [i16; 8] = [33, 31, 18, 14, -14, -18, -31, -33];
+const KING_OFFSETS: [i16; 8] = [1, -1, 16, -16, 15, -15, 17, -17];
+const ROOK_OFFSETS: [i16; 4] = [1, -1, 16, -16];
+const BISHOP_OFFSETS: [i16; 4] = [15, -15, 17, -17];
+
+fn index(coord: Coord) -> i16 {
+    (coord.r as i16) * 16 + coord.f as i16
+}
+
+fn coord(index: i16) -> Coord {
+    Coord::new((index % 16) as u8, (index / 16) as u8)
+}
+
+fn on_board(index: i16) -> bool {
+    (0..128).contains(&index) && index % 16 < 8
+}
+
+fn owner(piece_code: PieceCode) -> Option<Player> {
+    match piece_code as u8 {
+        0 => None,
+        1..=6 => Some(Player::White),
+        _ => Some(Player::Black),
+    }
+}
+
+/// Piece type, from 0 (pawn) to 5 (king)
+fn kind(piece_code: PieceCode) -> u8 {
+    (piece_code as u8 - 1) % 6
+}
+
+fn piece(kind: u8, player: Player) -> PieceCode {
+    PieceCode::from_u8(kind + 1 + 6 * player as u8)
+}
+
+fn other(player: Player) -> Player {
+    match player {
+        Player::White => Player::Black,
+        Player::Black => Player::White,
+    }
+}
+
+/// Squares attacked by the piece standing on a square
+fn attacked_squares(board: &Board, from: i16) -> Vec<i16> {
+    let piece_code = board[from as usize];
+    let player = owner(piece_code).unwrap();
+    let mut squares = Vec::new();
+
+    let mut slide = |offsets: &[i16], repeat: bool| {
+        for offset in offsets {
+            let mut target = from + offset;
+            while on_board(target) {
+                squares.push(target);
+                if !repeat || board[target as usize] != PieceCode::ES {
+                    break;
+                }
+                target += offset;
+            }
+        }
+    };
+
+    match kind(piece_code) {
+        0 => match player {
+            Player::White => slide(&[15, 17], false),
+            Player::Black => slide(&[-15, -17], false),
+        },
+        1 => slide(&KNIGHT_OFFSETS, false),
+        2 => slide(&BISHOP_OFFSETS, true),
+        3 => slide(&ROOK_OFFSETS, true),
+        4 => {
+            slide(&ROOK_OFFSETS, true);
+            slide(&BISHOP_OFFSETS, true);
+        }
+        _ => slide(&KING_OFFSETS, false),
+    }
+    squares
+}
+
+/// Check whether any piece of the player attacks the square
+fn is_attacked(board: &Board, square: i16, by: Player) -> bool {
+    (0..128).filter(|from| on_board(*from)).any(|from| {
+        owner(board[from as usize]) == Some(by) && attacked_squares(board, from).contains(&square)
+    })
+}
+
+fn is_king_attacked(board: &Board, player: Player) -> bool {
+    let king = piece(5, player);
+    let square = (0..128)
+        .find(|square| on_board(*square) && board[*square as usize] == king)
+        .expect("No king on the board");
+    is_attacked(board, square, other(player))
+}
+
+/// Generate the legal moves of the current player
+pub fn gen_reference_moves(position: &Position) -> Vec<ReferenceMove> {
+    let mut board: Board = [PieceCode::ES; 128];
+    for r in 0..8 {
+        for f in 0..8 {
+            board[index(Coord::new(f, r)) as usize] = position.get_square(Coord::new(f, r));
+        }
+    }
+    let player = position.current_turn;
+    let en_passant = position.get_en_passant_square().map(index);
+
+    // Each candidate is a move, and the board after the move
+    let mut candidates: Vec<(ReferenceMove, Board)> = Vec::new();
+
+    for from in (0..128).filter(|from| on_board(*from)) {
+        let piece_code = board[from as usize];
+        if owner(piece_code) != Some(player) {
+            continue;
+        }
+
+        // Play a move from the square (and all the promotions of pawns reaching the last
+        // rank), capturing the piece on the captured square
+        let mut add = |to: i16, captured: i16| {
+            let mut after = board;
+            after[captured as usize] = PieceCode::ES;
+            after[from as usize] = PieceCode::ES;
+            let last_rank = to / 16 == 0 || to / 16 == 7;
+            if kind(piece_code) == 0 && last_rank {
+                for promotion in [4, 3, 2, 1] {
+                    let promoted = piece(promotion, player);
+                    after[to as usize] = promoted;
+                    candidates.push(((coord(from), coord(to), promoted), after));
+                }
+            } else {
+                after[to as usize] = piece_code;
+                candidates.push(((coord(from), coord(to), PieceCode::ES), after));
+            }
+        };
+
+        if kind(piece_code) == 0 {
+            let (forward, start_rank) = match player {
+                Player::White => (16, 1),
+                Player::Black => (-16, 6),
+            };
+            let one = from + forward;
+            if on_board(one) && board[one as usize] == PieceCode::ES {
+                add(one, one);
+                let two = one + forward;
+                if from / 16 == start_rank && board[two as usize] == PieceCode::ES {
+                    add(two, two);
+                }
+            }
+            for to in [one - 1, one + 1] {
+                if !on_board(to) {
+                    continue;
+                }
+                if owner(board[to as usize]) == Some(other(player)) {
+                    add(to, to);
+                } else if Some(to) == en_passant {
+                    add(to, to - forward);
+                }
+            }
+        } else {
+            for to in attacked_squares(&board, from) {
+                if owner(board[to as usize]) != Some(player) {
+                    add(to, to);
+                }
+            }
+        }
+    }
+
+    let mut moves: Vec<ReferenceMove> = candidates
+        .into_iter()
+        .filter(|(_, after)| !is_king_attacked(after, player))
+        .map(|(mov, _)| mov)
+        .collect();
+
+    moves.extend(gen_reference_castling_moves(position, &board));
+    moves.sort_by_key(|(from, to, promotion)| (from.r, from.f, to.r, to.f, *promotion as u8));
+    moves
+}
+
+/// Generate the legal castling moves of the current player (Chess960 rules, which include the
+/// normal chess ones)
+fn gen_reference_castling_moves(position: &Position, board: &Board) -> Vec<ReferenceMove> {
+    let player = position.current_turn;
+    let rank = match player {
+        Player::White => 0,
+        Player::Black => 7,
+    };
+    let mut moves = Vec::new();
+
+    let sides = [
+        (position.kingside_castling_rook[player as usize], 6, 5),
+        (position.queenside_castling_rook[player as usize], 2, 3),
+    ];
+    for (rook_file, king_to, rook_to) in sides {
+        let Some(rook_file) = rook_file else {
+            continue;
+        };
+        let square = |file: u8| index(Coord::new(file, rank));
+        let Some(king_file) = (0..8).find(|file| board[square(*file) as usize] == piece(5, player))
+        else {
+            continue;
+        };
+        if board[square(rook_file) as usize] != piece(3, player) {
+            continue;
+        }
+
+        // All the squares the king and the rook go through (or to) must be empty, except for
+        // the king and the rook themselves
+        let files = [king_file, rook_file, king_to, rook_to];
+        let (min, max) = (*files.iter().min().unwrap(), *files.iter().max().unwrap());
+        let is_empty = (min..=max).all(|file| {
+            file == king_file || file == rook_file || board[square(file) as usize] == PieceCode::ES
+        });
+
+        // The king can't be in check, nor go through or to an attacked square
+        let is_safe = (king_file.min(king_to)..=king_file.max(king_to))
+            .all(|file| !is_attacked(board, square(file), other(player)));
+
+        if is_empty && is_safe {
+            moves.push((
+                Coord::new(king_file, rank),
+                Coord::new(king_to, rank),
+                PieceCode::ES,
+            ));
+        }
+    }
+    moves
+}
@@ -0,0 +1,193 @@
+#![allow(dead_code)]
+
+use super::legal_generator::*;
+use super::misc::*;
+use super::movable_board::*;
+use crate::board_representation::*;
+
+/*****************************
+ * STANDARD ALGEBRAIC NOTATION
+ *****************************/
+
+// SAN is the notation used by humans and in PGN files : the piece letter (none for pawns),
+// the starting file and/or rank when several pieces of the same kind can reach the arrival
+// square, "x" for captures, the arrival square, "=" and the piece for promotions, and a
+// "+" or "#" suffix for checks and checkmates. Castling is written "O-O" or "O-O-O".
+// See : <https://en.wikipedia.org/wiki/Algebraic_notation_(chess)>
+
+/// Letter of a piece in SAN (uppercase, and None for pawns)
+fn get_san_piece_letter(piece_code: PieceCode) -> Option<char> {
+    match get_fen_piece(piece_code).to_ascii_uppercase() {
+        'P' => None,
+        letter => Some(letter),
+    }
+}
+
+/// Return the SAN of a legal move, without its check suffix
+fn get_move_san_without_suffix(mov: Move, legal_moves: &[Move]) -> String {
+    if get_move_kingside_castling(mov) {
+        return "O-O".to_string();
+    }
+    if get_move_queenside_castling(mov) {
+        return "O-O-O".to_string();
+    }
+
+    let piece_code = get_move_piece_code(mov);
+    let start = get_move_start_coords(mov);
+    let arrival = get_move_arrival_coords(mov);
+    let mut san = String::new();
+
+    match get_san_piece_letter(piece_code) {
+        Some(letter) => {
+            san.push(letter);
+
+            // Disambiguate with the file if possible, then the rank, then both
+            let ambiguous_moves: Vec<Move> = legal_moves
+                .iter()
+                .copied()
+                .filter(|other| {
+                    *other != mov
+                        && get_move_piece_code(*other) == piece_code
+                        && get_move_arrival_coords(*other) == arrival
+                        && !get_move_kingside_castling(*other)
+                        && !get_move_queenside_castling(*other)
+                })
+                .collect();
+            if !ambiguous_moves.is_empty() {
+                let same_file = ambiguous_moves
+                    .iter()
+                    .any(|other| get_move_start_file(*other) == start.f);
+                let same_rank = ambiguous_moves
+                    .iter()
+                    .any(|other| get_move_start_rank(*other) == start.r);
+                let algebraic_start = get_algebraic_square(start);
+                if !same_file {
+                    san.push_str(&algebraic_start[0..1]);
+                } else if !same_rank {
+                    san.push_str(&algebraic_start[1..2]);
+                } else {
+                    san.push_str(&algebraic_start);
+                }
+            }
+        }
+        None => {
+            if get_move_capture(mov) {
+                san.push_str(&get_algebraic_square(start)[0..1]);
+            }
+        }
+    }
+
+    if get_move_capture(mov) {
+        san.push('x');
+    }
+    san.push_str(&get_algebraic_square(arrival));
+
+    if get_move_promotion(mov) {
+        san.push('=');
+        san.push(get_fen_piece(get_move_promotion_piece_code(mov)).to_ascii_uppercase());
+    }
+
+    san
+}
+
+/// Return the SAN of a legal move, including its check or checkmate suffix
+pub fn get_move_san(position: &mut Position, mov: Move) -> String {
+    let legal_moves = gen_legal_moves(position);
+    let mut san = get_move_san_without_suffix(mov, &legal_moves);
+
+    position.make_move(mov);
+    let opponent = position.current_turn;
+    if is_in_check(position, opponent) {
+        if gen_legal_moves(position).is_empty() {
+            san.push('#');
+        } else {
+            san.push('+');
+        }
+    }
+    position.unmake_move(mov);
+
+    san
+}
+
+/// Find the legal move corresponding to a move in SAN. The parsing is lenient : check
+/// suffixes and annotations ("+", "#", "!", "?") are ignored, "0-0" is accepted for
+/// castling, and the "x" of captures and the "=" of promotions are optional.
+pub fn parse_san_move(position: &mut Position, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves = gen_legal_moves(position);
+
+    match san {
+        "O-O" | "0-0" => {
+            return legal_moves
+                .into_iter()
+                .find(|mov| get_move_kingside_castling(*mov))
+        }
+        "O-O-O" | "0-0-0" => {
+            return legal_moves
+                .into_iter()
+                .find(|mov| get_move_queenside_castling(*mov))
+        }
+        _ => {}
+    }
+
+    let mut chars: Vec<char> = san.chars().filter(|c| *c != 'x' && *c != '=').collect();
+
+    // Piece letter (pawn moves don't have one)
+    let piece_letter = match chars.first() {
+        Some(letter @ ('K' | 'Q' | 'R' | 'B' | 'N')) => {
+            let letter = *letter;
+            chars.remove(0);
+            Some(letter)
+        }
+        _ => None,
+    };
+
+    // Promotion piece
+    let promotion_letter = match chars.last() {
+        Some(letter @ ('Q' | 'R' | 'B' | 'N')) if piece_letter.is_none() => {
+            let letter = *letter;
+            chars.pop();
+            Some(letter)
+        }
+        _ => None,
+    };
+
+    // Arrival square, preceded by the optional starting file and rank
+    if chars.len() < 2 {
+        return None;
+    }
+    let arrival: String = chars[chars.len() - 2..].iter().collect();
+    let arrival = Coord::from_algebraic(&arrival)?;
+    let mut start_file = None;
+    let mut start_rank = None;
+    for c in &chars[..chars.len() - 2] {
+        match c {
+            'a'..='h' if start_file.is_none() => start_file = Some(*c as u8 - b'a'),
+            '1'..='8' if start_rank.is_none() => start_rank = Some(*c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+
+    let mut candidates = legal_moves.into_iter().filter(|mov| {
+        let letter = get_san_piece_letter(get_move_piece_code(*mov));
+        let promotion = if get_move_promotion(*mov) {
+            get_san_piece_letter(get_move_promotion_piece_code(*mov))
+        } else {
+            None
+        };
+        letter == piece_letter
+            && promotion == promotion_letter
+            && get_move_arrival_coords(*mov) == arrival
+            && start_file.is_none_or(|f| get_move_start_file(*mov) == f)
+            && start_rank.is_none_or(|r| get_move_start_rank(*mov) == r)
+            && !get_move_kingside_castling(*mov)
+            && !get_move_queenside_castling(*mov)
+    });
+
+    // Ambiguous moves are rejected
+    let mov = candidates.next()?;
+    match candidates.next() {
+        Some(_) => None,
+        None => Some(mov),
+    }
+}
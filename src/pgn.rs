@@ -0,0 +1,246 @@
+/*
+ * The pgn module contains a reader and a writer for the Portable Game Notation, the standard
+ * format for chess game records. It allows games played or analyzed by the engine to be
+ * exchanged with other chess tools.
+ *
+ * A game is represented by the Game type, which contains the tag pairs, the starting
+ * position (either the normal one, or the one given in the FEN tag), and a tree of moves.
+ * Each MoveNode of the tree stores its move and SAN, annotations (NAGs and comments), as
+ * well as the recursive variations that were given as alternatives to it.
+ *
+ * Every move, including the ones of variations, is resolved against the position it is
+ * played from when reading a game, so that games containing illegal moves are rejected.
+ * The positions of a line can then be replayed from its starting position.
+ * See : <https://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm>
+ */
+
+pub mod datatypes;
+pub mod reader;
+pub mod writer;
+
+pub use datatypes::*;
+pub use reader::*;
+pub use writer::*;
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_pgn() {
+    use crate::board_representation::*;
+    use crate::move_generation::*;
+
+    let pgn = r#"[Event "Casual \"blitz\" game"]
+[Site "?"]
+[Date "2022.01.01"]
+[Round "-"]
+[White "Krabnik"]
+[Black "Human"]
+[Result "1-0"]
+[ECO "C50"]
+
+% This line is escaped
+{Opening comment} 1. e4 e5 2. Nf3 Nc6 3.Bc4 Bc5!? (3... Nf6 $1 {Two knights} 4. Ng5
+(4. d4) 4... d5) 4. c3 ; Giuoco Piano
+Nf6 5. d4?! exd4 1-0
+
+[Event "Second game"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12"]
+
+12... Kd7 13. e4 *
+"#;
+
+    let games = read_pgn(pgn).unwrap();
+    assert!(games.len() == 2, "Failed at assert 0");
+
+    // First game
+    let game = &games[0];
+    assert!(
+        game.get_tag("Event") == Some("Casual \"blitz\" game"),
+        "Failed at assert 1"
+    );
+    assert!(game.get_tag("ECO") == Some("C50"), "Failed at assert 2");
+    assert!(game.result == GameResult::WhiteWins, "Failed at assert 3");
+    assert!(game.moves.len() == 10, "Failed at assert 4");
+    assert!(
+        game.moves[0].pre_comments == vec!["Opening comment"],
+        "Failed at assert 5"
+    );
+    assert!(game.moves[5].san == "Bc5", "Failed at assert 6");
+    assert!(game.moves[5].nags == vec![5], "Failed at assert 7");
+    assert!(
+        game.moves[6].comments == vec!["Giuoco Piano"],
+        "Failed at assert 8"
+    );
+    assert!(game.moves[8].nags == vec![6], "Failed at assert 9");
+
+    let variation = &game.moves[5].variations[0];
+    assert!(variation.len() == 3, "Failed at assert 10");
+    assert!(variation[0].san == "Nf6", "Failed at assert 11");
+    assert!(variation[0].nags == vec![1], "Failed at assert 12");
+    assert!(
+        variation[0].comments == vec!["Two knights"],
+        "Failed at assert 13"
+    );
+    assert!(
+        variation[1].variations[0][0].san == "d4",
+        "Failed at assert 14"
+    );
+
+    // Second game, starting from a FEN position
+    let game = &games[1];
+    assert!(game.result == GameResult::Unknown, "Failed at assert 15");
+    assert!(
+        game.starting_position.get_square(Coord::new(4, 1)) == PieceCode::WP,
+        "Failed at assert 16"
+    );
+    assert!(
+        game.starting_position.current_turn == Player::Black,
+        "Failed at assert 17"
+    );
+    assert!(game.moves.len() == 2, "Failed at assert 18");
+
+    // Export, and check that reading the exported games gives back the same games
+    let exported = write_pgn(&games);
+    println!("Exported PGN :\n{}", exported);
+    assert!(
+        exported.lines().all(|line| line.len() <= PGN_LINE_LENGTH),
+        "Failed at assert 19"
+    );
+    assert!(exported.contains("[Result \"*\"]"), "Failed at assert 20");
+    assert!(
+        exported.contains("12... Kd7 13. e4 *"),
+        "Failed at assert 21"
+    );
+    let movetext = exported.replace('\n', " ");
+    assert!(
+        movetext.contains("Bc5 $5 (3... Nf6 $1 {Two knights} 4. Ng5 (4. d4) 4... d5) 4. c3"),
+        "Failed at assert 22"
+    );
+
+    let reread_games = read_pgn(&exported).unwrap();
+    assert!(reread_games.len() == 2, "Failed at assert 23");
+    for (game, reread_game) in games.iter().zip(reread_games.iter()) {
+        assert!(game.moves == reread_game.moves, "Failed at assert 24");
+        assert!(game.result == reread_game.result, "Failed at assert 25");
+        assert!(
+            game.starting_position.to_fen() == reread_game.starting_position.to_fen(),
+            "Failed at assert 26"
+        );
+    }
+    assert!(write_pgn(&reread_games) == exported, "Failed at assert 27");
+
+    // Line wrapping of long games and comments
+    let mut game = Game::new();
+    let mut position = Position::new();
+    for i in 0..60 {
+        let mov = parse_san_move(&mut position, ["Nf3", "Nf6", "Ng1", "Ng8"][i % 4]).unwrap();
+        let mut node = MoveNode::new(&mut position, mov);
+        node.comments
+            .push("a rather long comment that will need to be wrapped".to_string());
+        game.moves.push(node);
+        position.make_move(mov);
+    }
+    let exported = game.to_pgn();
+    assert!(
+        exported.lines().all(|line| line.len() <= PGN_LINE_LENGTH),
+        "Failed at assert 28"
+    );
+    assert!(
+        Game::from_pgn(&exported).unwrap().moves == game.moves,
+        "Failed at assert 29"
+    );
+
+    // Malformed inputs
+    assert!(read_pgn("1. e4 (e5").is_err(), "Failed at assert 30");
+    assert!(read_pgn("1. e4 e5)").is_err(), "Failed at assert 31");
+    assert!(
+        read_pgn("1. e4 {unterminated").is_err(),
+        "Failed at assert 32"
+    );
+    assert!(
+        read_pgn("[Event \"?\" 1. e4").is_err(),
+        "Failed at assert 33"
+    );
+    assert!(
+        read_pgn("[FEN \"invalid\"]\n1. e4 *").is_err(),
+        "Failed at assert 34"
+    );
+    assert!(read_pgn("(1. e4) *").is_err(), "Failed at assert 35");
+
+    // Illegal moves, in the main line and in variations
+    assert!(
+        matches!(read_pgn("1. e4 Zz9 Qxh7 *"), Err(PgnError::IllegalMove(1, san)) if san == "Zz9"),
+        "Failed at assert 36"
+    );
+    assert!(
+        matches!(read_pgn("1. e4 e5\n2. Qxh7 *"), Err(PgnError::IllegalMove(2, san)) if san == "Qxh7"),
+        "Failed at assert 37"
+    );
+    assert!(
+        matches!(read_pgn("1. e4 e5 (1... e4) *"), Err(PgnError::IllegalMove(1, san)) if san == "e4"),
+        "Failed at assert 38"
+    );
+    assert!(
+        read_pgn("1. e4 e5 2. Nf3 (2. Nf6) *").is_err(),
+        "Failed at assert 39"
+    );
+    assert!(
+        read_pgn("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n13. e4 *").is_err(),
+        "Failed at assert 40"
+    );
+
+    // Replay of the main line
+    let game = &games[0];
+    let positions: Vec<Position> = game.replay().collect();
+    assert!(positions.len() == 11, "Failed at assert 41");
+    assert!(positions[0].to_fen() == STARTING_FEN, "Failed at assert 42");
+    assert!(
+        positions[10].to_fen()
+            == "r1bqk2r/pppp1ppp/2n2n2/2b5/2BpP3/2P2N2/PP3PPP/RNBQK2R w KQkq - 0 6",
+        "Failed at assert 43"
+    );
+    let mut position = Position::new();
+    for mov in game.get_main_line() {
+        position.make_move(mov);
+    }
+    assert!(
+        position.to_fen() == positions[10].to_fen(),
+        "Failed at assert 44"
+    );
+    let variation_positions: Vec<Position> =
+        Replay::new(&positions[5], &game.moves[5].variations[0]).collect();
+    assert!(
+        variation_positions[3].to_fen()
+            == "r1bqkb1r/ppp2ppp/2n2n2/3pp1N1/2B1P3/8/PPPP1PPP/RNBQK2R w KQkq d6 0 5",
+        "Failed at assert 45"
+    );
+
+    // Strings broken across lines, and variations nested too deeply
+    assert!(
+        read_pgn("[Event \"a\\\nb\"]").is_err(),
+        "Failed at assert 46"
+    );
+    let depth = MAX_VARIATION_DEPTH;
+    let nested = format!("1. e4 {}{} *", "(1. d4 ".repeat(depth), ")".repeat(depth));
+    assert!(read_pgn(&nested).is_ok(), "Failed at assert 47");
+    let nested = format!(
+        "1. e4 {}{} *",
+        "(1. d4 ".repeat(depth + 1),
+        ")".repeat(depth + 1)
+    );
+    assert!(
+        matches!(read_pgn(&nested), Err(PgnError::VariationTooDeep(1))),
+        "Failed at assert 48"
+    );
+
+    // Comments that can't be written as is between braces
+    let pgn = "1. e4 ; a } in a comment\n1... e5 {words ( and ) in a comment} *";
+    let game = Game::from_pgn(pgn).unwrap();
+    let exported = game.to_pgn();
+    let reread_game = Game::from_pgn(&exported).unwrap();
+    assert!(reread_game.moves == game.moves, "Failed at assert 49");
+    assert!(reread_game.to_pgn() == exported, "Failed at assert 50");
+}
@@ -0,0 +1,257 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use crate::board_representation::*;
+use crate::move_generation::*;
+
+/**********
+* DATATYPES
+***********/
+
+/// Tags of the Seven Tag Roster, in the order they must be exported
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Outcome of a game, as written in the result token and the Result tag
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// Game still in progress, abandoned, or result unknown
+    Unknown,
+}
+
+/// A single move of the game tree. Moves are stored both as resolved moves and in Standard
+/// Algebraic Notation, along with their annotations and the alternative lines that were
+/// given in place of them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MoveNode {
+    /// Move, legal in the position it is played from
+    pub mov: Move,
+    /// Move in SAN (without move number or suffix annotations)
+    pub san: String,
+
+    /// Numeric Annotation Glyphs. Suffix annotations ("!", "?!", ...) are stored as their
+    /// NAG equivalent.
+    pub nags: Vec<u8>,
+
+    /// Comments placed before the move. This only happens at the start of a line.
+    pub pre_comments: Vec<String>,
+    /// Comments placed after the move
+    pub comments: Vec<String>,
+
+    /// Recursive annotation variations, ie alternative lines starting in place of this move
+    pub variations: Vec<Vec<MoveNode>>,
+}
+
+/// A game record, made of its tag pairs, its starting position, and the tree of moves
+/// played from it
+#[derive(Debug)]
+pub struct Game {
+    /// Tag pairs, in the order they were read or inserted
+    pub tags: Vec<(String, String)>,
+
+    /// Either the normal starting position, or the one given in the FEN tag
+    pub starting_position: Position,
+
+    /// Main line of the game
+    pub moves: Vec<MoveNode>,
+
+    /// Comments of a game without any move
+    pub comments: Vec<String>,
+
+    pub result: GameResult,
+}
+
+/// Errors that can occur while reading a PGN file. Line numbers start at 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PgnError {
+    /// A "{" comment is never closed
+    UnterminatedComment(usize),
+    /// A tag value string is never closed
+    UnterminatedString(usize),
+    /// A tag pair is not of the form [Name "Value"]
+    InvalidTag(usize),
+    /// A token can't appear at this point of the movetext
+    UnexpectedToken(usize, String),
+    /// A variation is closed without being opened, or opened without being closed
+    UnbalancedVariation(usize),
+    /// A variation doesn't follow any move
+    MisplacedVariation(usize),
+    /// A move is not legal (or ambiguous) in the position it is played from
+    IllegalMove(usize, String),
+    /// Variations are nested deeper than MAX_VARIATION_DEPTH
+    VariationTooDeep(usize),
+    /// The FEN tag contains an invalid position
+    InvalidFen(usize, FenError),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::UnterminatedComment(line) => {
+                write!(f, "line {}: unterminated comment", line)
+            }
+            PgnError::UnterminatedString(line) => write!(f, "line {}: unterminated string", line),
+            PgnError::InvalidTag(line) => write!(f, "line {}: invalid tag pair", line),
+            PgnError::UnexpectedToken(line, token) => {
+                write!(f, "line {}: unexpected token \"{}\"", line, token)
+            }
+            PgnError::UnbalancedVariation(line) => {
+                write!(f, "line {}: unbalanced variation", line)
+            }
+            PgnError::MisplacedVariation(line) => {
+                write!(f, "line {}: variation without a preceding move", line)
+            }
+            PgnError::IllegalMove(line, san) => {
+                write!(f, "line {}: illegal move \"{}\"", line, san)
+            }
+            PgnError::VariationTooDeep(line) => {
+                write!(f, "line {}: variations nested too deeply", line)
+            }
+            PgnError::InvalidFen(line, error) => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/*************
+* INIT METHODS
+**************/
+
+/// Default trait for Game is an empty game from the normal starting position, with the
+/// Seven Tag Roster filled with unknown values
+impl Default for Game {
+    fn default() -> Game {
+        Game {
+            tags: vec![
+                ("Event".to_string(), "?".to_string()),
+                ("Site".to_string(), "?".to_string()),
+                ("Date".to_string(), "????.??.??".to_string()),
+                ("Round".to_string(), "?".to_string()),
+                ("White".to_string(), "?".to_string()),
+                ("Black".to_string(), "?".to_string()),
+                ("Result".to_string(), "*".to_string()),
+            ],
+            starting_position: Position::new(),
+            moves: Vec::new(),
+            comments: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+}
+
+impl Game {
+    /// Shorthand for default
+    pub fn new() -> Game {
+        Game::default()
+    }
+
+    /// Return the value of a tag, if present
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of a tag, overwriting it if already present
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some((_, old_value)) => *old_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Replay the main line of the game
+    pub fn replay(&self) -> Replay<'_> {
+        Replay::new(&self.starting_position, &self.moves)
+    }
+
+    /// Return the moves of the main line
+    pub fn get_main_line(&self) -> Vec<Move> {
+        self.moves.iter().map(|node| node.mov).collect()
+    }
+}
+
+impl MoveNode {
+    /// Initialize a node from a move legal in the given position, without annotations
+    pub fn new(position: &mut Position, mov: Move) -> MoveNode {
+        MoveNode {
+            mov,
+            san: get_move_san(position, mov),
+            ..MoveNode::default()
+        }
+    }
+}
+
+impl GameResult {
+    /// Parse a result token ("1-0", "0-1", "1/2-1/2" or "*")
+    pub fn from_token(token: &str) -> Option<GameResult> {
+        match token {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Return the result of a game won by the given player
+    pub fn from_winner(winner: Player) -> GameResult {
+        match winner {
+            Player::White => GameResult::WhiteWins,
+            Player::Black => GameResult::BlackWins,
+        }
+    }
+
+    /// Return the result token
+    pub fn to_token(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+/*******
+* REPLAY
+********/
+
+/// Iterator over the positions of a line : its starting position, then the position
+/// reached after each of its moves. A variation can be replayed from the position
+/// preceding the move it replaces.
+pub struct Replay<'a> {
+    position: Option<Position>,
+    moves: std::slice::Iter<'a, MoveNode>,
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(position: &Position, line: &'a [MoveNode]) -> Replay<'a> {
+        Replay {
+            position: Some(position.clone()),
+            moves: line.iter(),
+        }
+    }
+}
+
+impl Iterator for Replay<'_> {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Position> {
+        let position = self.position.take()?;
+        if let Some(node) = self.moves.next() {
+            let mut next_position = position.clone();
+            next_position.make_move(node.mov);
+            self.position = Some(next_position);
+        }
+        Some(position)
+    }
+}
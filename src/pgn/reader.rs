@@ -0,0 +1,400 @@
+#![allow(dead_code)]
+
+use super::datatypes::*;
+use crate::board_representation::*;
+use crate::move_generation::*;
+
+/**********
+* TOKENIZER
+***********/
+
+// See the PGN standard for the complete description of the tokens :
+// <https://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm#c7>
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    TagOpen,
+    TagClose,
+    String(String),
+    /// Move, move number, result, or tag name
+    Symbol(String),
+    Period,
+    Nag(u8),
+    Comment(String),
+    VariationOpen,
+    VariationClose,
+}
+
+/// Characters that can continue a symbol token
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+#=:-/".contains(c)
+}
+
+/// Convert a suffix annotation ("!", "?!", ...) to its NAG equivalent
+fn suffix_annotation_to_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Split a PGN string into tokens, each paired with the line it starts on
+fn tokenize(pgn: &str) -> Result<Vec<(Token, usize)>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = pgn.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let token_line = line;
+
+        // A "%" in the first column escapes the rest of the line
+        if c == '%' && line_start {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    line += 1;
+                    break;
+                }
+            }
+            continue;
+        }
+        line_start = c == '\n';
+
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+
+            '[' => tokens.push((Token::TagOpen, token_line)),
+            ']' => tokens.push((Token::TagClose, token_line)),
+            '(' => tokens.push((Token::VariationOpen, token_line)),
+            ')' => tokens.push((Token::VariationClose, token_line)),
+            '.' => tokens.push((Token::Period, token_line)),
+            '*' => tokens.push((Token::Symbol("*".to_string()), token_line)),
+
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        // Strings can't span several lines, even with an escaped line break
+                        Some('\\') => match chars.next() {
+                            Some('\n') | None => {
+                                return Err(PgnError::UnterminatedString(token_line))
+                            }
+                            Some(escaped) => value.push(escaped),
+                        },
+                        Some('"') => break,
+                        Some('\n') | None => return Err(PgnError::UnterminatedString(token_line)),
+                        Some(c) => value.push(c),
+                    }
+                }
+                tokens.push((Token::String(value), token_line));
+            }
+
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            comment.push(c);
+                        }
+                        None => return Err(PgnError::UnterminatedComment(token_line)),
+                    }
+                }
+                // Line breaks are not meaningful in comments, as they are added by the
+                // line wrapping of the movetext
+                let words: Vec<&str> = comment.split_whitespace().collect();
+                tokens.push((Token::Comment(words.join(" ")), token_line));
+            }
+
+            ';' => {
+                let mut comment = String::new();
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        line_start = true;
+                        break;
+                    }
+                    comment.push(c);
+                }
+                tokens.push((Token::Comment(comment.trim().to_string()), token_line));
+            }
+
+            '$' => {
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    digits.push(c);
+                    chars.next();
+                }
+                let nag = digits
+                    .parse()
+                    .map_err(|_| PgnError::UnexpectedToken(token_line, format!("${}", digits)))?;
+                tokens.push((Token::Nag(nag), token_line));
+            }
+
+            '!' | '?' => {
+                let mut suffix = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c != '!' && c != '?' {
+                        break;
+                    }
+                    suffix.push(c);
+                    chars.next();
+                }
+                let nag = suffix_annotation_to_nag(&suffix)
+                    .ok_or(PgnError::UnexpectedToken(token_line, suffix))?;
+                tokens.push((Token::Nag(nag), token_line));
+            }
+
+            c if c.is_ascii_alphanumeric() => {
+                let mut symbol = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !is_symbol_char(c) {
+                        break;
+                    }
+                    symbol.push(c);
+                    chars.next();
+                }
+                tokens.push((Token::Symbol(symbol), token_line));
+            }
+
+            c => return Err(PgnError::UnexpectedToken(token_line, c.to_string())),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/*******
+* PARSER
+********/
+
+/// Maximum nesting level of variations. Lines are parsed (and written) recursively, so
+/// deeper variations are rejected to keep the recursion from overflowing the stack.
+pub const MAX_VARIATION_DEPTH: usize = 128;
+
+/// Moves of a line, comments that couldn't be attached to any move, and result token
+type ParsedLine = (Vec<MoveNode>, Vec<String>, Option<GameResult>);
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    /// Line of the next token, or of the last one if we reached the end of the input
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|(token, _)| token.clone());
+        self.index += 1;
+        token
+    }
+
+    /// Parse a tag pair, starting after its opening bracket
+    fn parse_tag(&mut self) -> Result<(String, String), PgnError> {
+        let line = self.line();
+        match (self.next(), self.next(), self.next()) {
+            (Some(Token::Symbol(name)), Some(Token::String(value)), Some(Token::TagClose)) => {
+                Ok((name, value))
+            }
+            _ => Err(PgnError::InvalidTag(line)),
+        }
+    }
+
+    /// Parse a line of moves until its end (closing parenthesis for variations, result
+    /// token or start of a new game for the main line). The depth is 0 for the main line,
+    /// and the nesting level for variations. Each move is resolved in the position it is
+    /// played from, starting with the given one, which is left at the end of the line.
+    /// Returns the parsed moves, the comments that couldn't be attached to any move, and
+    /// the result token if any.
+    fn parse_line(
+        &mut self,
+        position: &mut Position,
+        depth: usize,
+    ) -> Result<ParsedLine, PgnError> {
+        let is_variation = depth > 0;
+        let mut moves: Vec<MoveNode> = Vec::new();
+        let mut pending_comments: Vec<String> = Vec::new();
+        let mut result = None;
+
+        loop {
+            let line = self.line();
+            match self.peek() {
+                None | Some(Token::TagOpen) => {
+                    // End of input, or start of a new game without any result token
+                    if is_variation {
+                        return Err(PgnError::UnbalancedVariation(line));
+                    }
+                    break;
+                }
+
+                Some(Token::VariationClose) => {
+                    if !is_variation {
+                        return Err(PgnError::UnbalancedVariation(line));
+                    }
+                    self.next();
+                    break;
+                }
+
+                Some(Token::VariationOpen) => {
+                    if depth == MAX_VARIATION_DEPTH {
+                        return Err(PgnError::VariationTooDeep(line));
+                    }
+                    self.next();
+                    let last_move = moves.last_mut().ok_or(PgnError::MisplacedVariation(line))?;
+
+                    // The variation replaces the last move, so it starts from the position
+                    // that move was played from
+                    position.unmake_move(last_move.mov);
+                    let mut variation_position = position.clone();
+                    position.make_move(last_move.mov);
+
+                    let (variation, comments, _) =
+                        self.parse_line(&mut variation_position, depth + 1)?;
+                    if !variation.is_empty() {
+                        last_move.variations.push(variation);
+                    } else {
+                        last_move.comments.extend(comments);
+                    }
+                }
+
+                Some(Token::Comment(_)) => {
+                    if let Some(Token::Comment(comment)) = self.next() {
+                        match moves.last_mut() {
+                            Some(last_move) => last_move.comments.push(comment),
+                            None => pending_comments.push(comment),
+                        }
+                    }
+                }
+
+                Some(Token::Nag(nag)) => {
+                    let nag = *nag;
+                    let last_move = moves
+                        .last_mut()
+                        .ok_or(PgnError::UnexpectedToken(line, format!("${}", nag)))?;
+                    last_move.nags.push(nag);
+                    self.next();
+                }
+
+                // Move number indications are ignored, as they can be deduced from the
+                // starting position
+                Some(Token::Period) => {
+                    self.next();
+                }
+
+                Some(Token::Symbol(symbol)) => {
+                    if let Some(game_result) = GameResult::from_token(symbol) {
+                        if is_variation {
+                            return Err(PgnError::UnexpectedToken(line, symbol.clone()));
+                        }
+                        result = Some(game_result);
+                        self.next();
+                        break;
+                    }
+
+                    if !symbol.chars().all(|c| c.is_ascii_digit()) {
+                        let mov = parse_san_move(position, symbol)
+                            .ok_or_else(|| PgnError::IllegalMove(line, symbol.clone()))?;
+                        let mut node = MoveNode::new(position, mov);
+                        node.pre_comments.append(&mut pending_comments);
+                        moves.push(node);
+                        position.make_move(mov);
+                    }
+                    self.next();
+                }
+
+                Some(token @ (Token::String(_) | Token::TagClose)) => {
+                    let token = format!("{:?}", token);
+                    return Err(PgnError::UnexpectedToken(line, token));
+                }
+            }
+        }
+
+        Ok((moves, pending_comments, result))
+    }
+
+    /// Parse a complete game (tag pairs and movetext)
+    fn parse_game(&mut self) -> Result<Game, PgnError> {
+        let mut game = Game {
+            tags: Vec::new(),
+            ..Game::default()
+        };
+
+        // Tag pairs section
+        let mut fen_line = 0;
+        while self.peek() == Some(&Token::TagOpen) {
+            let line = self.line();
+            self.next();
+            let (name, value) = self.parse_tag()?;
+            if name == "FEN" {
+                fen_line = line;
+            }
+            game.set_tag(&name, &value);
+        }
+
+        if let Some(fen) = game.get_tag("FEN") {
+            game.starting_position =
+                Position::from_fen(fen).map_err(|error| PgnError::InvalidFen(fen_line, error))?;
+        }
+
+        // Movetext section
+        let mut position = game.starting_position.clone();
+        let (moves, comments, result) = self.parse_line(&mut position, 0)?;
+        game.moves = moves;
+        game.comments = comments;
+
+        // The result token has priority over the Result tag
+        game.result = result
+            .or_else(|| game.get_tag("Result").and_then(GameResult::from_token))
+            .unwrap_or(GameResult::Unknown);
+
+        Ok(game)
+    }
+}
+
+/***********
+* PGN READER
+************/
+
+/// Read all the games contained in a PGN string
+pub fn read_pgn(pgn: &str) -> Result<Vec<Game>, PgnError> {
+    let mut parser = Parser {
+        tokens: tokenize(pgn)?,
+        index: 0,
+    };
+
+    let mut games = Vec::new();
+    while parser.peek().is_some() {
+        games.push(parser.parse_game()?);
+    }
+    Ok(games)
+}
+
+impl Game {
+    /// Read the first game of a PGN string. Returns an empty game if the string doesn't
+    /// contain any.
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        Ok(read_pgn(pgn)?.into_iter().next().unwrap_or_default())
+    }
+}
@@ -0,0 +1,170 @@
+#![allow(dead_code)]
+
+use super::datatypes::*;
+use crate::board_representation::*;
+
+/***********
+* PGN WRITER
+************/
+
+/// Maximum length of a movetext line in exported PGN
+pub const PGN_LINE_LENGTH: usize = 79;
+
+/// Escape a tag value so it can be written between quotes
+fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Push the words of a comment as separate tokens, so the line wrapping can break long
+/// comments. Comments containing a "}" can't be written between braces, so they are
+/// written as a single rest-of-line comment instead.
+fn push_comment_tokens(tokens: &mut Vec<String>, comment: &str) {
+    if comment.contains('}') {
+        tokens.push(format!("; {}", comment));
+        return;
+    }
+
+    let words: Vec<&str> = comment.split_whitespace().collect();
+    match words.len() {
+        0 => tokens.push("{}".to_string()),
+        1 => tokens.push(format!("{{{}}}", words[0])),
+        n => {
+            tokens.push(format!("{{{}", words[0]));
+            tokens.extend(words[1..n - 1].iter().map(|word| word.to_string()));
+            tokens.push(format!("{}}}", words[n - 1]));
+        }
+    }
+}
+
+/// Recursively convert a line of moves to movetext tokens. The ply is counted from the
+/// first move of the game (0 for 1. White).
+fn push_line_tokens(tokens: &mut Vec<String>, line: &[MoveNode], first_ply: usize) {
+    // Black move numbers are only written at the start of a line, and after comments and
+    // variations
+    let mut needs_move_number = true;
+
+    for (i, node) in line.iter().enumerate() {
+        let ply = first_ply + i;
+
+        for comment in &node.pre_comments {
+            push_comment_tokens(tokens, comment);
+            needs_move_number = true;
+        }
+
+        if ply.is_multiple_of(2) {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if needs_move_number {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(node.san.clone());
+        needs_move_number = false;
+
+        for nag in &node.nags {
+            tokens.push(format!("${}", nag));
+        }
+        for comment in &node.comments {
+            push_comment_tokens(tokens, comment);
+            needs_move_number = true;
+        }
+        for variation in &node.variations {
+            tokens.push("(".to_string());
+            push_line_tokens(tokens, variation, ply);
+            tokens.push(")".to_string());
+            needs_move_number = true;
+        }
+    }
+}
+
+/// Join tokens with spaces, wrapping lines so they don't exceed the given length. No
+/// space is inserted after an opening parenthesis or before a closing one (unless they
+/// are words of a comment), and the line always ends after a rest-of-line comment.
+fn wrap_tokens(tokens: &[String], line_length: usize) -> String {
+    let mut text = String::new();
+    let mut current_line_length = 0;
+    let mut previous_token: Option<&str> = None;
+    let mut in_comment = false;
+
+    for token in tokens {
+        let needs_space = match previous_token {
+            None => false,
+            Some(_) if in_comment => true,
+            Some(previous) if previous.starts_with(';') => {
+                text.push('\n');
+                current_line_length = 0;
+                false
+            }
+            Some("(") => false,
+            Some(_) => token != ")",
+        };
+
+        if needs_space && current_line_length + 1 + token.len() > line_length {
+            text.push('\n');
+            current_line_length = 0;
+        } else if needs_space {
+            text.push(' ');
+            current_line_length += 1;
+        }
+
+        text.push_str(token);
+        current_line_length += token.len();
+        previous_token = Some(token);
+
+        // Brace comments can't contain a "}", so it can only be their end
+        if token.starts_with('{') {
+            in_comment = true;
+        }
+        if token.ends_with('}') {
+            in_comment = false;
+        }
+    }
+
+    text
+}
+
+impl Game {
+    /// Return the PGN representation of the game, in export format : Seven Tag Roster
+    /// first, and movetext wrapped at 79 characters
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+
+        // Tag pairs section
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.to_token(),
+                "Date" => self.get_tag(name).unwrap_or("????.??.??"),
+                _ => self.get_tag(name).unwrap_or("?"),
+            };
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag_value(value)));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag_value(value)));
+            }
+        }
+        pgn.push('\n');
+
+        // Movetext section
+        let position = &self.starting_position;
+        let mut first_ply = 2 * (position.full_move_number as usize - 1);
+        if position.current_turn == Player::Black {
+            first_ply += 1;
+        }
+
+        let mut tokens = Vec::new();
+        for comment in &self.comments {
+            push_comment_tokens(&mut tokens, comment);
+        }
+        push_line_tokens(&mut tokens, &self.moves, first_ply);
+        tokens.push(self.result.to_token().to_string());
+
+        pgn.push_str(&wrap_tokens(&tokens, PGN_LINE_LENGTH));
+        pgn.push_str("\n\n");
+
+        pgn
+    }
+}
+
+/// Write several games in a single PGN string
+pub fn write_pgn(games: &[Game]) -> String {
+    games.iter().map(Game::to_pgn).collect()
+}
@@ -0,0 +1,638 @@
+/*
+ * The play module implements "krabnik play", an interactive game against the engine in
+ * the terminal, without any GUI.
+ *
+ * The board is printed after each move (flipped when playing Black, with the squares of the
+ * last move highlighted), and moves are entered in SAN ("Nf3") or in coordinate notation
+ * ("g1f3"). Other commands allow to undo and redo moves, take back a move, print the FEN,
+ * flip the board, ask the engine for a hint, resign, offer a draw and save the game as PGN.
+ */
+
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::pgn::*;
+use crate::search::*;
+use crate::uci::ENGINE_NAME;
+
+/*********
+* OPTIONS
+**********/
+
+/// Time given to the engine for each move when no limit is set
+pub const DEFAULT_PLAY_MOVETIME: Duration = Duration::from_millis(1000);
+
+/// Name of the human player in saved games
+pub const HUMAN_NAME: &str = "Human";
+
+/// The engine accepts draw offers when its score is not higher than this
+pub const DRAW_ACCEPTANCE_SCORE: i32 = 0;
+
+/// Errors that can occur while starting the play command
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PlayError {
+    InvalidArgument(String),
+    InvalidPosition(FenError),
+}
+
+impl fmt::Display for PlayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlayError::InvalidArgument(arg) => write!(f, "invalid argument: {}", arg),
+            PlayError::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PlayError {}
+
+/// Settings of the play command
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlayOptions {
+    /// Side played by the human
+    pub human: Player,
+    /// Starting position (the normal starting position if None)
+    pub fen: Option<String>,
+    pub limits: SearchLimits,
+    pub threads: usize,
+    /// Size of the transposition table, in MiB
+    pub hash: usize,
+    /// File the game is saved to when it is over
+    pub pgn_output: Option<String>,
+    /// Highlight the last move with ANSI escape codes
+    pub color: bool,
+}
+
+impl Default for PlayOptions {
+    fn default() -> PlayOptions {
+        PlayOptions {
+            human: Player::White,
+            fen: None,
+            limits: SearchLimits {
+                time: Some(DEFAULT_PLAY_MOVETIME),
+                ..SearchLimits::default()
+            },
+            threads: 1,
+            hash: DEFAULT_HASH_SIZE,
+            pgn_output: None,
+            color: true,
+        }
+    }
+}
+
+impl PlayOptions {
+    /// Parse the arguments of the play command
+    pub fn from_args(args: &[String]) -> Result<PlayOptions, PlayError> {
+        let mut options = PlayOptions::default();
+        let mut args = args.iter();
+        let invalid = |arg: &str| PlayError::InvalidArgument(arg.to_string());
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| invalid(arg));
+            match arg.as_str() {
+                "--black" => options.human = Player::Black,
+                "--white" => options.human = Player::White,
+                "--fen" => options.fen = Some(value()?.clone()),
+                "--depth" => {
+                    let value = value()?;
+                    options.limits = SearchLimits {
+                        depth: Some(
+                            value
+                                .parse()
+                                .ok()
+                                .filter(|depth| (1..=MAX_DEPTH).contains(depth))
+                                .ok_or_else(|| invalid(value))?,
+                        ),
+                        ..SearchLimits::default()
+                    };
+                }
+                "--movetime" => {
+                    let value = value()?;
+                    let millis: u64 = value.parse().map_err(|_| invalid(value))?;
+                    options.limits = SearchLimits {
+                        time: Some(Duration::from_millis(millis.max(1))),
+                        ..SearchLimits::default()
+                    };
+                }
+                "--threads" => {
+                    let value = value()?;
+                    options.threads = value
+                        .parse()
+                        .ok()
+                        .filter(|threads| (1..=MAX_THREADS).contains(threads))
+                        .ok_or_else(|| invalid(value))?;
+                }
+                "--hash" => {
+                    let value = value()?;
+                    options.hash = value
+                        .parse()
+                        .ok()
+                        .filter(|hash| (1..=MAX_HASH_SIZE).contains(hash))
+                        .ok_or_else(|| invalid(value))?;
+                }
+                "--pgn" => options.pgn_output = Some(value()?.clone()),
+                "--no-color" => options.color = false,
+                _ => return Err(invalid(arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/*********
+* SESSION
+**********/
+
+const HELP: &str = "Commands:
+  <move>          play a move, in SAN (Nf3) or coordinate notation (g1f3)
+  undo / redo     undo or redo a single move
+  takeback        undo moves until it is your turn again
+  go              let the engine play the side to move (you play the other side)
+  hint            ask the engine for a move
+  draw            offer a draw
+  resign          resign the game
+  fen             print the FEN of the position
+  flip            flip the board
+  save [file]     save the game as PGN
+  new             start a new game
+  quit            leave";
+
+/// State of an interactive game. Output is written to any writer, so that sessions can be
+/// tested.
+pub struct PlaySession<W: Write> {
+    output: W,
+    options: PlayOptions,
+    start: Position,
+    position: Position,
+    /// Moves played since the start position
+    moves: Vec<Move>,
+    /// Undone moves, replayed by redo (last undone first)
+    undone_moves: Vec<Move>,
+    /// Hashes of the positions before each move, for repetitions
+    hashes: Vec<u64>,
+    flipped: bool,
+    evaluator: Evaluator,
+    tt: TranspositionTable,
+    /// Result and description of the end of the game, once it is over
+    outcome: Option<(GameResult, String)>,
+}
+
+impl<W: Write> PlaySession<W> {
+    pub fn new(output: W, options: PlayOptions) -> Result<PlaySession<W>, PlayError> {
+        let start = match &options.fen {
+            Some(fen) => Position::from_fen(fen).map_err(PlayError::InvalidPosition)?,
+            None => Position::new(),
+        };
+        Ok(PlaySession {
+            output,
+            flipped: options.human == Player::Black,
+            tt: TranspositionTable::new(options.hash),
+            options,
+            position: start.clone(),
+            start,
+            moves: Vec::new(),
+            undone_moves: Vec::new(),
+            hashes: Vec::new(),
+            evaluator: Evaluator::new(),
+            outcome: None,
+        })
+    }
+
+    pub fn get_output(&self) -> &W {
+        &self.output
+    }
+
+    fn send(&mut self, line: &str) {
+        // A closed output can't be reported anywhere
+        let _ = writeln!(self.output, "{}", line);
+    }
+
+    /// Print the board, and let the engine play if it has the first move
+    pub fn start(&mut self) {
+        self.show_board();
+        self.check_game_over();
+        self.play_engine_move_if_needed();
+    }
+
+    fn show_board(&mut self) {
+        let highlighted = match self.moves.last() {
+            Some(mov) if self.options.color => {
+                vec![get_move_start_coords(*mov), get_move_arrival_coords(*mov)]
+            }
+            _ => Vec::new(),
+        };
+        let board = self.position.ascii_highlighted(self.flipped, &highlighted);
+        let turn = match self.position.current_turn {
+            Player::White => "White",
+            Player::Black => "Black",
+        };
+        self.send(&format!("\n{}{} to move", board, turn));
+    }
+
+    fn engine_side(&self) -> Player {
+        invert_player(&self.options.human)
+    }
+
+    /// Search the current position with the limits of the options
+    fn search(&mut self) -> SearchResult {
+        let stop = AtomicBool::new(false);
+        let context = SearchContext {
+            tt: &self.tt,
+            stop: &stop,
+            threads: self.options.threads,
+            game_hashes: &self.hashes,
+            tablebase: None,
+        };
+        let mut position = self.position.clone();
+        search_with_callback(
+            &mut position,
+            &mut self.evaluator,
+            &self.options.limits,
+            &context,
+            |_| {},
+        )
+    }
+
+    fn make_move(&mut self, mov: Move) {
+        self.hashes.push(self.position.get_zobrist_hash());
+        self.position.make_move(mov);
+        self.moves.push(mov);
+    }
+
+    /// Play a new move (which makes redoing undone moves impossible), then the answer of
+    /// the engine
+    fn play_move(&mut self, mov: Move) {
+        self.make_move(mov);
+        self.undone_moves.clear();
+        self.show_board();
+        if !self.check_game_over() {
+            self.play_engine_move_if_needed();
+        }
+    }
+
+    fn play_engine_move_if_needed(&mut self) {
+        if self.outcome.is_some() || self.position.current_turn != self.engine_side() {
+            return;
+        }
+        let result = self.search();
+        let Some(mov) = result.best_move else {
+            return;
+        };
+        let san = get_move_san(&mut self.position, mov);
+        self.send(&format!(
+            "{} plays {} (score {})",
+            ENGINE_NAME,
+            san,
+            format_score(result.score)
+        ));
+        self.make_move(mov);
+        self.show_board();
+        self.check_game_over();
+    }
+
+    /// Check whether the game is over by the rules of chess, and end it if so
+    fn check_game_over(&mut self) -> bool {
+        let side = self.position.current_turn;
+        let hash = self.position.get_zobrist_hash();
+        let repetitions = self
+            .hashes
+            .iter()
+            .filter(|previous| **previous == hash)
+            .count();
+
+        let outcome = if gen_legal_moves(&mut self.position).is_empty() {
+            if is_in_check(&self.position, side) {
+                let winner = invert_player(&side);
+                (GameResult::from_winner(winner), "Checkmate".to_string())
+            } else {
+                (GameResult::Draw, "Stalemate".to_string())
+            }
+        } else if repetitions >= 2 {
+            (GameResult::Draw, "Draw by threefold repetition".to_string())
+        } else if self.position.plys_without_capture >= 100 {
+            (GameResult::Draw, "Draw by the fifty-move rule".to_string())
+        } else if self.position.is_insufficient_material() {
+            (
+                GameResult::Draw,
+                "Draw by insufficient material".to_string(),
+            )
+        } else {
+            return false;
+        };
+        self.end_game(outcome.0, &outcome.1);
+        true
+    }
+
+    fn end_game(&mut self, result: GameResult, description: &str) {
+        self.send(&format!(
+            "Game over: {} {{{}}}",
+            result.to_token(),
+            description
+        ));
+        self.outcome = Some((result, description.to_string()));
+        if let Some(path) = self.options.pgn_output.clone() {
+            self.save(&path);
+        }
+    }
+
+    fn undo(&mut self) -> bool {
+        let Some(mov) = self.moves.pop() else {
+            return false;
+        };
+        self.position.unmake_move(mov);
+        self.hashes.pop();
+        self.undone_moves.push(mov);
+        self.outcome = None;
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        let Some(mov) = self.undone_moves.pop() else {
+            return false;
+        };
+        self.make_move(mov);
+        self.check_game_over();
+        true
+    }
+
+    /// Write the game in PGN
+    pub fn get_game(&self) -> Game {
+        let mut game = Game::new();
+        game.set_tag("Event", &format!("{} play", ENGINE_NAME));
+        let (white, black) = match self.options.human {
+            Player::White => (HUMAN_NAME, ENGINE_NAME),
+            Player::Black => (ENGINE_NAME, HUMAN_NAME),
+        };
+        game.set_tag("White", white);
+        game.set_tag("Black", black);
+        let start_fen = self.start.to_fen();
+        if start_fen != STARTING_FEN {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &start_fen);
+        }
+        game.starting_position = self.start.clone();
+
+        let mut position = self.start.clone();
+        for mov in &self.moves {
+            game.moves.push(MoveNode::new(&mut position, *mov));
+            position.make_move(*mov);
+        }
+
+        if let Some((result, description)) = &self.outcome {
+            game.result = *result;
+            game.set_tag("Result", result.to_token());
+            match game.moves.last_mut() {
+                Some(node) => node.comments.push(description.clone()),
+                None => game.comments.push(description.clone()),
+            }
+        }
+        game
+    }
+
+    fn save(&mut self, path: &str) {
+        match fs::write(path, self.get_game().to_pgn()) {
+            Ok(()) => self.send(&format!("Game saved to {}", path)),
+            Err(error) => self.send(&format!("Can't save the game to {}: {}", path, error)),
+        }
+    }
+
+    /// Handle a line of input. Returns false once the player quits.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match *command {
+            "quit" | "exit" => return false,
+            "help" => self.send(HELP),
+            "fen" => {
+                let fen = self.position.to_fen();
+                self.send(&fen);
+            }
+            "flip" => {
+                self.flipped = !self.flipped;
+                self.show_board();
+            }
+            "undo" => {
+                if self.undo() {
+                    self.show_board();
+                } else {
+                    self.send("Nothing to undo");
+                }
+            }
+            "redo" => {
+                if self.redo() {
+                    self.show_board();
+                } else {
+                    self.send("Nothing to redo");
+                }
+            }
+            "takeback" => {
+                if !self.undo() {
+                    self.send("Nothing to take back");
+                    return true;
+                }
+                if self.position.current_turn != self.options.human {
+                    self.undo();
+                }
+                self.show_board();
+            }
+            "new" => {
+                self.position = self.start.clone();
+                self.moves.clear();
+                self.undone_moves.clear();
+                self.hashes.clear();
+                self.outcome = None;
+                self.tt.clear();
+                self.start();
+            }
+            "save" => match args.first().map(|path| path.to_string()) {
+                Some(path) => self.save(&path),
+                None => match self.options.pgn_output.clone() {
+                    Some(path) => self.save(&path),
+                    None => self.send("Usage: save <file>"),
+                },
+            },
+            _ if self.outcome.is_some() => {
+                self.send("The game is over (use undo, new, save or quit)");
+            }
+            "go" => {
+                self.options.human = invert_player(&self.position.current_turn);
+                self.play_engine_move_if_needed();
+            }
+            "hint" => {
+                let result = self.search();
+                if let Some(mov) = result.best_move {
+                    let san = get_move_san(&mut self.position, mov);
+                    self.send(&format!("Hint: {}", san));
+                }
+            }
+            "resign" => {
+                let winner = invert_player(&self.options.human);
+                self.end_game(
+                    GameResult::from_winner(winner),
+                    &format!("{} resigns", HUMAN_NAME),
+                );
+            }
+            "draw" => {
+                // The engine must evaluate the position from its own point of view
+                let mut score = self.search().score;
+                if self.position.current_turn != self.engine_side() {
+                    score = -score;
+                }
+                if score <= DRAW_ACCEPTANCE_SCORE {
+                    self.end_game(GameResult::Draw, "Draw by agreement");
+                } else {
+                    self.send(&format!("{} declines the draw", ENGINE_NAME));
+                }
+            }
+            _ => {
+                let mov = parse_uci_move(&mut self.position, command)
+                    .or_else(|| parse_san_move(&mut self.position, command));
+                match mov {
+                    Some(mov) => self.play_move(mov),
+                    None => self.send(&format!(
+                        "Illegal move or unknown command: {} (type help for the commands)",
+                        command
+                    )),
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Format a score for humans : pawns with a sign, or "#n" for mates
+fn format_score(score: i32) -> String {
+    if is_mate_score(score) {
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        format!("#{}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("{:+.2}", score as f64 / 100.0)
+    }
+}
+
+pub fn run_play_command(args: &[String]) -> Result<(), PlayError> {
+    let options = PlayOptions::from_args(args)?;
+    get_kpk_bitbase();
+
+    let mut session = PlaySession::new(io::stdout(), options)?;
+    session.send("Type help for the list of commands");
+    session.start();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !session.handle_command(&line) {
+            break;
+        }
+        let _ = session.output.flush();
+    }
+    Ok(())
+}
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_play() {
+    let options = PlayOptions::from_args(
+        &["--depth", "2", "--no-color"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    assert!(
+        options.limits.depth == Some(2) && !options.color,
+        "Failed at assert 0"
+    );
+    assert!(
+        PlayOptions::from_args(&["--depth".to_string()]).is_err(),
+        "Failed at assert 1"
+    );
+
+    // Moves in both notations, with the engine answering each of them
+    let mut session = PlaySession::new(Vec::new(), options.clone()).unwrap();
+    session.start();
+    assert!(session.moves.is_empty(), "Failed at assert 2");
+    session.handle_command("e4");
+    assert!(session.moves.len() == 2, "Failed at assert 3");
+    session.handle_command("d2d4");
+    assert!(session.moves.len() == 4, "Failed at assert 4");
+    session.handle_command("Ke3");
+    session.handle_command("e5e6");
+    assert!(session.moves.len() == 4, "Failed at assert 5");
+
+    // Undo, redo and takeback
+    session.handle_command("undo");
+    assert!(
+        session.moves.len() == 3 && session.position.current_turn == Player::Black,
+        "Failed at assert 6"
+    );
+    session.handle_command("redo");
+    assert!(session.moves.len() == 4, "Failed at assert 7");
+    session.handle_command("takeback");
+    assert!(
+        session.moves.len() == 2 && session.position.current_turn == Player::White,
+        "Failed at assert 8"
+    );
+    session.handle_command("redo");
+    session.handle_command("redo");
+    assert!(session.moves.len() == 4, "Failed at assert 9");
+
+    // Resigning ends the game, and the game is saved with its result
+    session.handle_command("resign");
+    assert!(
+        session.outcome.as_ref().map(|outcome| outcome.0) == Some(GameResult::BlackWins),
+        "Failed at assert 10"
+    );
+    session.handle_command("Nf3");
+    assert!(session.moves.len() == 4, "Failed at assert 11");
+    let game = session.get_game();
+    assert!(game.get_tag("Result") == Some("0-1"), "Failed at assert 12");
+    assert!(
+        game.moves.len() == 4 && game.moves[0].san == "e4",
+        "Failed at assert 13"
+    );
+
+    // Playing Black, the engine moves first, and the board is flipped
+    let options = PlayOptions {
+        human: Player::Black,
+        ..options
+    };
+    let mut session = PlaySession::new(Vec::new(), options.clone()).unwrap();
+    session.start();
+    assert!(session.moves.len() == 1, "Failed at assert 14");
+    let output = String::from_utf8(session.get_output().clone()).unwrap();
+    assert!(output.contains("   h g f e d c b a"), "Failed at assert 15");
+
+    // Checkmate
+    let options = PlayOptions {
+        human: Player::White,
+        fen: Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string()),
+        ..options
+    };
+    let mut session = PlaySession::new(Vec::new(), options).unwrap();
+    session.start();
+    session.handle_command("Ra8");
+    assert!(
+        session.outcome == Some((GameResult::WhiteWins, "Checkmate".to_string())),
+        "Failed at assert 16"
+    );
+    assert!(
+        session.get_game().to_pgn().contains("Ra8#"),
+        "Failed at assert 17"
+    );
+}
@@ -0,0 +1,241 @@
+/*
+ * The search module contains the functions exploring the tree of moves from a Position to
+ * find the best one.
+ *
+ * The main search is a negamax alpha-beta inside an iterative deepening loop, stopped by
+ * depth, node or time limits. Its leaves are resolved by the quiescence search, which only
+ * looks at captures and promotions until the position is quiet. Searched nodes are saved in a
+ * transposition table shared by all the search threads.
+ */
+
+pub mod misc;
+pub mod negamax;
+pub mod quiescence;
+pub mod transposition_table;
+
+pub use misc::*;
+pub use negamax::*;
+pub use quiescence::*;
+pub use transposition_table::*;
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_quiescence() {
+    use crate::board_representation::*;
+    use crate::evaluation::*;
+    use crate::move_generation::*;
+
+    let mut evaluator = Evaluator::new();
+    let search = |fen: &str, evaluator: &mut Evaluator| {
+        let mut position = Position::from_fen(fen).unwrap();
+        let mut pv = Vec::new();
+        let mut nodes = 0;
+        evaluator.refresh(&position);
+        let score = quiescence(
+            &mut position,
+            evaluator,
+            -INFINITE_SCORE,
+            INFINITE_SCORE,
+            &mut pv,
+            &mut nodes,
+        );
+        (score, pv, position)
+    };
+
+    // Quiet positions are evaluated statically
+    let position = Position::from_fen(STARTING_FEN).unwrap();
+    let (score, pv, _) = search(STARTING_FEN, &mut evaluator);
+    assert!(
+        score == evaluate_handcrafted(&position),
+        "Failed at assert 0"
+    );
+    assert!(pv.is_empty(), "Failed at assert 1");
+
+    // A hanging queen is taken
+    let fen = "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1";
+    let (score, pv, position) = search(fen, &mut evaluator);
+    assert!(score > 300, "Failed at assert 2");
+    assert!(
+        pv.first()
+            .is_some_and(|mov| get_move_uci(&position, *mov) == "d1d5"),
+        "Failed at assert 3"
+    );
+    let quiet_position = get_quiet_position(&position, &mut evaluator);
+    assert!(
+        quiet_position
+            .to_fen()
+            .starts_with("4k3/8/8/3R4/8/8/8/4K3 b"),
+        "Failed at assert 4"
+    );
+    assert!(
+        position.to_fen() == Position::from_fen(fen).unwrap().to_fen(),
+        "Failed at assert 5"
+    );
+
+    // A defended pawn isn't taken by the queen
+    let (score, pv, _) = search("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", &mut evaluator);
+    let position = Position::from_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+    assert!(pv.is_empty(), "Failed at assert 6");
+    assert!(
+        score == evaluate_handcrafted(&position),
+        "Failed at assert 7"
+    );
+
+    // Captures are ordered by Most Valuable Victim - Least Valuable Aggressor
+    let mut position = Position::from_fen("4k3/8/8/2q1r3/3P4/8/8/2Q4K w - - 0 1").unwrap();
+    let moves = gen_tactical_moves(&mut position);
+    assert!(
+        get_move_arrival_square(moves[0]) == PieceCode::BQ,
+        "Failed at assert 8"
+    );
+    assert!(
+        get_move_piece_code(moves[0]) == PieceCode::WP,
+        "Failed at assert 9"
+    );
+}
+
+#[test]
+fn test_negamax() {
+    use crate::board_representation::*;
+    use crate::evaluation::*;
+    use crate::move_generation::*;
+
+    let mut evaluator = Evaluator::new();
+    let depth_limit = |depth: u8| SearchLimits {
+        depth: Some(depth),
+        ..SearchLimits::default()
+    };
+
+    // Mates are found, and the searched position is left unchanged
+    let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+    let mut position = Position::from_fen(fen).unwrap();
+    let result = search(&mut position, &mut evaluator, &depth_limit(4));
+    assert!(
+        result
+            .best_move
+            .is_some_and(|mov| get_move_uci(&position, mov) == "a1a8"),
+        "Failed at assert 0"
+    );
+    assert!(
+        is_mate_score(result.score) && result.score > 0,
+        "Failed at assert 1"
+    );
+    assert!(position.to_fen() == fen, "Failed at assert 2");
+
+    // Material is won
+    let mut position = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+    let result = search(&mut position, &mut evaluator, &depth_limit(3));
+    assert!(
+        result.pv.first().copied() == result.best_move,
+        "Failed at assert 3"
+    );
+    assert!(
+        result
+            .best_move
+            .is_some_and(|mov| get_move_uci(&position, mov) == "d1d5"),
+        "Failed at assert 4"
+    );
+
+    // No legal moves
+    let mut position = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    let result = search(&mut position, &mut evaluator, &depth_limit(3));
+    assert!(result.best_move.is_none(), "Failed at assert 5");
+
+    // Depth and node limits
+    let mut position = Position::from_fen(STARTING_FEN).unwrap();
+    let result = search(&mut position, &mut evaluator, &depth_limit(3));
+    assert!(result.depth == 3, "Failed at assert 6");
+    let limits = SearchLimits {
+        nodes: Some(2000),
+        ..SearchLimits::default()
+    };
+    let result = search(&mut position, &mut evaluator, &limits);
+    assert!(result.best_move.is_some(), "Failed at assert 7");
+    assert!(result.depth < MAX_DEPTH, "Failed at assert 8");
+}
+
+#[test]
+fn test_transposition_table() {
+    use crate::board_representation::*;
+    use crate::evaluation::*;
+    use crate::move_generation::*;
+    use std::sync::atomic::AtomicBool;
+
+    let tt = TranspositionTable::new(1);
+    assert!(tt.len() > 1000, "Failed at assert 0");
+    assert!(tt.probe(0x1234).is_none(), "Failed at assert 1");
+    assert!(tt.get_hashfull() == 0, "Failed at assert 2");
+
+    // Entries are read back from the same hash only
+    let entry = TranspositionEntry {
+        best_move: Some(Move(0x0c1c_0600)),
+        score: -150,
+        depth: 6,
+        bound: Bound::Lower,
+    };
+    tt.store(0x1234, entry);
+    assert!(tt.probe(0x1234) == Some(entry), "Failed at assert 3");
+    assert!(tt.probe(0x1235).is_none(), "Failed at assert 4");
+
+    // Deeper entries are only replaced by exact scores
+    let shallow_entry = TranspositionEntry {
+        best_move: None,
+        score: 20,
+        depth: 2,
+        bound: Bound::Upper,
+    };
+    tt.store(0x1234, shallow_entry);
+    assert!(tt.probe(0x1234) == Some(entry), "Failed at assert 5");
+    let exact_entry = TranspositionEntry {
+        bound: Bound::Exact,
+        ..shallow_entry
+    };
+    tt.store(0x1234, exact_entry);
+    assert!(tt.probe(0x1234) == Some(exact_entry), "Failed at assert 6");
+    tt.clear();
+    assert!(tt.probe(0x1234).is_none(), "Failed at assert 7");
+
+    // Mate scores are stored relative to the node
+    let score = MATE_SCORE - 7;
+    assert!(
+        get_tt_score(score, 4) == MATE_SCORE - 3,
+        "Failed at assert 8"
+    );
+    assert!(
+        get_search_score(get_tt_score(-score, 4), 4) == -score,
+        "Failed at assert 9"
+    );
+    assert!(get_tt_score(120, 4) == 120, "Failed at assert 10");
+
+    // Several threads share the table
+    let mut position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let stop = AtomicBool::new(false);
+    let context = SearchContext {
+        tt: &tt,
+        stop: &stop,
+        threads: 4,
+        game_hashes: &[],
+        tablebase: None,
+    };
+    let limits = SearchLimits {
+        depth: Some(4),
+        ..SearchLimits::default()
+    };
+    let result = search_with_callback(
+        &mut position,
+        &mut Evaluator::new(),
+        &limits,
+        &context,
+        |_| {},
+    );
+    assert!(
+        result
+            .best_move
+            .is_some_and(|mov| get_move_uci(&position, mov) == "a1a8"),
+        "Failed at assert 11"
+    );
+    assert!(tt.get_hashfull() > 0, "Failed at assert 12");
+}
@@ -0,0 +1,62 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use crate::move_generation::*;
+
+/***************
+ * SEARCH SCORES
+ ***************/
+
+/// Bound of all the scores returned by the search
+pub const INFINITE_SCORE: i32 = 32000;
+
+/// Score of being checkmated at the root. Mates found at a given ply are scored
+/// MATE_SCORE - ply, so shorter mates are preferred.
+pub const MATE_SCORE: i32 = 31000;
+
+/// Score of a tablebase win at the root. Wins found at a given ply are scored
+/// TB_WIN_SCORE - ply, below all the mate scores.
+pub const TB_WIN_SCORE: i32 = MATE_SCORE - 1000;
+
+/****************
+ * MOVE ORDERING
+ ****************/
+
+/// Piece values used to order captures, indexed using PieceCode - 1, modulo 6
+const ORDERING_VALUES: [i32; 6] = [1, 3, 3, 5, 9, 100];
+
+/// Most Valuable Victim - Least Valuable Aggressor score of a move, used to search the
+/// most promising captures (and promotions) first
+pub fn get_mvv_lva_score(mov: Move) -> i32 {
+    let mut score = 0;
+    if get_move_capture(mov) {
+        let victim = get_move_arrival_square(mov) as usize;
+        let aggressor = get_move_piece_code(mov) as usize;
+        score += 100 * ORDERING_VALUES[(victim - 1) % 6] - ORDERING_VALUES[(aggressor - 1) % 6];
+    }
+    if get_move_promotion(mov) {
+        let promotion = get_move_promotion_piece_code(mov) as usize;
+        score += 100 * ORDERING_VALUES[(promotion - 1) % 6];
+    }
+    score
+}
+
+/*****************
+ * TIME MANAGEMENT
+ *****************/
+
+/// Number of moves the remaining time is split into, when the time control doesn't give it
+pub const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Time kept on the clock to absorb the communication delays with the GUI
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// Return the time to spend on the next move, given the time left on the clock, the
+/// increment, and the number of moves until the next time control (if any)
+pub fn get_time_limit(time: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+    let available = time.saturating_sub(MOVE_OVERHEAD);
+    let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+    let limit = available / moves_to_go + increment * 3 / 4;
+    limit.min(available).max(Duration::from_millis(1))
+}
@@ -0,0 +1,483 @@
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::misc::*;
+use super::quiescence::*;
+use super::transposition_table::*;
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::tablebase::*;
+
+/*************
+ * DATATYPES
+ *************/
+
+/// Maximum depth of the iterative deepening
+pub const MAX_DEPTH: u8 = 64;
+
+/// Maximum number of search threads
+pub const MAX_THREADS: usize = 256;
+
+/// Conditions stopping the search. The search stops as soon as one of them is met, and
+/// goes on until MAX_DEPTH if none is set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+/// Result of the last completed iteration of a search
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchResult {
+    /// None if the root position has no legal moves
+    pub best_move: Option<Move>,
+    /// Score of the best move, from the point of view of the side to move
+    pub score: i32,
+    pub depth: u8,
+    /// Nodes visited since the start of the search by all threads (including quiescence
+    /// nodes)
+    pub nodes: u64,
+    /// Principal variation, starting with the best move
+    pub pv: Vec<Move>,
+    /// Positions found in the tablebase by all threads
+    pub tb_hits: u64,
+}
+
+/// Shared resources and options of a search
+#[derive(Copy, Clone)]
+pub struct SearchContext<'a> {
+    pub tt: &'a TranspositionTable,
+    /// Set by another thread to stop the search (for instance on the UCI stop command)
+    pub stop: &'a AtomicBool,
+    /// Number of search threads, including the main one
+    pub threads: usize,
+    /// Hashes of the positions played before the root (oldest first), used to detect
+    /// repetitions
+    pub game_hashes: &'a [u64],
+    /// Syzygy tablebase, probed at the root and during the search
+    pub tablebase: Option<&'a SyzygyTablebase>,
+}
+
+/// Data shared by all the search threads
+struct SharedState<'a> {
+    /// Nodes of all the threads, updated when the clock is read
+    nodes: AtomicU64,
+    /// Tablebase hits of all the threads, updated with the nodes
+    tb_hits: AtomicU64,
+    /// Moves searched at the root when it is in the tablebase (all the legal moves if
+    /// empty)
+    root_moves: Vec<Move>,
+    /// Tablebase probed during the search
+    tablebase: Option<&'a SyzygyTablebase>,
+}
+
+struct SearchState<'a> {
+    evaluator: &'a mut Evaluator,
+    limits: &'a SearchLimits,
+    tt: &'a TranspositionTable,
+    stop: &'a AtomicBool,
+    start: Instant,
+    nodes: u64,
+    tb_hits: u64,
+    shared: &'a SharedState<'a>,
+    /// Part of the node and tablebase hits counts already added to the shared ones
+    reported_nodes: u64,
+    reported_tb_hits: u64,
+    /// Node count at which the clock is read next
+    next_time_check: u64,
+    /// Set when a limit is reached in the middle of an iteration
+    aborted: bool,
+    /// Principal variation of the previous iteration, searched first
+    previous_pv: Vec<Move>,
+    /// Hashes of the game positions and of the current line, excluding the current node
+    hashes: Vec<u64>,
+}
+
+/// Check whether a score is a mate score (either for or against the side to move)
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() > MATE_SCORE - MAX_DEPTH as i32 - 1
+}
+
+/// Check whether a score is a tablebase win or loss
+pub fn is_tablebase_score(score: i32) -> bool {
+    (TB_WIN_SCORE - MAX_DEPTH as i32..=TB_WIN_SCORE).contains(&score.abs())
+}
+
+/****************
+ * NEGAMAX SEARCH
+ ****************/
+
+// Alpha-beta search in its negamax form, inside an iterative deepening loop. Leaves are
+// resolved with the quiescence search. Results are saved in the transposition table, to cut
+// transpositions and to search the best move of the previous visit first.
+// With several threads, helpers run the same iterative deepening on their own copy of the
+// position, and only share the transposition table with the main thread ("lazy SMP").
+// See : <https://www.chessprogramming.org/Alpha-Beta>
+// and : <https://www.chessprogramming.org/Lazy_SMP>
+
+impl<'a> SearchState<'a> {
+    fn new(
+        evaluator: &'a mut Evaluator,
+        limits: &'a SearchLimits,
+        context: &SearchContext<'a>,
+        stop: &'a AtomicBool,
+        shared: &'a SharedState<'a>,
+    ) -> SearchState<'a> {
+        SearchState {
+            evaluator,
+            limits,
+            tt: context.tt,
+            stop,
+            start: Instant::now(),
+            nodes: 0,
+            tb_hits: 0,
+            shared,
+            reported_nodes: 0,
+            reported_tb_hits: 0,
+            next_time_check: 0,
+            aborted: false,
+            previous_pv: Vec::new(),
+            hashes: context.game_hashes.to_vec(),
+        }
+    }
+
+    /// Check the node and time limits, and the stop flag
+    fn check_limits(&mut self) {
+        if self.stop.load(Ordering::Relaxed) {
+            self.aborted = true;
+        }
+        if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+            self.aborted = true;
+        }
+        // Reading the clock is comparatively slow, so it is only done every 1024 nodes
+        if self.nodes >= self.next_time_check {
+            self.next_time_check = self.nodes + 1024;
+            self.report_nodes();
+            if self
+                .limits
+                .time
+                .is_some_and(|time| self.start.elapsed() >= time)
+            {
+                self.aborted = true;
+            }
+        }
+    }
+
+    /// Add the nodes searched and tablebase hits since the last call to the counts of all
+    /// threads
+    fn report_nodes(&mut self) {
+        self.shared
+            .nodes
+            .fetch_add(self.nodes - self.reported_nodes, Ordering::Relaxed);
+        self.shared
+            .tb_hits
+            .fetch_add(self.tb_hits - self.reported_tb_hits, Ordering::Relaxed);
+        self.reported_nodes = self.nodes;
+        self.reported_tb_hits = self.tb_hits;
+    }
+
+    /// Check whether the position already occured since the last irreversible move. Only
+    /// positions with the same side to move (every other ply) need to be compared.
+    fn is_repetition(&self, position: &Position, hash: u64) -> bool {
+        self.hashes
+            .iter()
+            .rev()
+            .take(position.plys_without_capture as usize)
+            .skip(1)
+            .step_by(2)
+            .any(|previous| *previous == hash)
+    }
+
+    /// Order moves : transposition table move first, then principal variation move, then
+    /// captures and promotions (MVV-LVA), then quiet moves
+    fn order_moves(&self, moves: &mut MoveList, ply: usize, tt_move: Option<Move>) {
+        let pv_move = self.previous_pv.get(ply).copied();
+        moves.score_moves(|mov| {
+            if Some(mov) == tt_move {
+                i32::MAX
+            } else if Some(mov) == pv_move {
+                i32::MAX - 1
+            } else if get_move_capture(mov) || get_move_promotion(mov) {
+                get_mvv_lva_score(mov)
+            } else {
+                0
+            }
+        });
+        moves.sort_by_score();
+    }
+
+    fn negamax(
+        &mut self,
+        position: &mut Position,
+        depth: u8,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+
+        // Fifty-move rule and repetitions
+        let hash = position.get_zobrist_hash();
+        if ply > 0 && (position.plys_without_capture >= 100 || self.is_repetition(position, hash)) {
+            return 0;
+        }
+
+        if depth == 0 {
+            let mut quiescence_pv = Vec::new();
+            return quiescence(
+                position,
+                self.evaluator,
+                alpha,
+                beta,
+                &mut quiescence_pv,
+                &mut self.nodes,
+            );
+        }
+
+        self.nodes += 1;
+        self.check_limits();
+        if self.aborted {
+            return 0;
+        }
+
+        // Transposition table cutoff (never at the root, which needs a move)
+        let tt_entry = self.tt.probe(hash);
+        if let Some(entry) = tt_entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = get_search_score(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score.clamp(alpha, beta),
+                Bound::Lower if score >= beta => return beta,
+                Bound::Upper if score <= alpha => return alpha,
+                _ => {}
+            }
+        }
+
+        // Tablebase probe, only right after a capture or a pawn move since the tables
+        // assume the fifty-move counter is at 0
+        if let Some(tablebase) = self
+            .shared
+            .tablebase
+            .filter(|_| ply > 0 && position.plys_without_capture == 0)
+        {
+            if let Some(wdl) = tablebase.probe_wdl(position) {
+                self.tb_hits += 1;
+                // Cursed wins and blessed losses are draws, scored slightly above or below
+                let score = match wdl {
+                    Wdl::Win => TB_WIN_SCORE - ply as i32,
+                    Wdl::Loss => -TB_WIN_SCORE + ply as i32,
+                    _ => 2 * wdl as i32,
+                };
+                self.store(hash, None, score, MAX_DEPTH, ply, Bound::Exact);
+                return score.clamp(alpha, beta);
+            }
+        }
+
+        let mut moves = gen_legal_moves(position);
+        if moves.is_empty() {
+            return if is_in_check(position, position.current_turn) {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+        if ply == 0 && !self.shared.root_moves.is_empty() {
+            moves.retain(|mov| self.shared.root_moves.contains(mov));
+        }
+        self.order_moves(&mut moves, ply, tt_entry.and_then(|entry| entry.best_move));
+
+        let mut child_pv = Vec::new();
+        let mut bound = Bound::Upper;
+        self.hashes.push(hash);
+        for mov in moves {
+            position.make_move(mov);
+            self.evaluator.make_move(position, mov);
+            let score = -self.negamax(position, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            position.unmake_move(mov);
+            self.evaluator.unmake_move();
+
+            if self.aborted {
+                self.hashes.pop();
+                return 0;
+            }
+            if score >= beta {
+                self.hashes.pop();
+                self.store(hash, Some(mov), beta, depth, ply, Bound::Lower);
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                bound = Bound::Exact;
+                pv.clear();
+                pv.push(mov);
+                pv.extend_from_slice(&child_pv);
+            }
+        }
+        self.hashes.pop();
+
+        self.store(hash, pv.first().copied(), alpha, depth, ply, bound);
+        alpha
+    }
+
+    fn store(
+        &self,
+        hash: u64,
+        best_move: Option<Move>,
+        score: i32,
+        depth: u8,
+        ply: usize,
+        bound: Bound,
+    ) {
+        self.tt.store(
+            hash,
+            TranspositionEntry {
+                best_move,
+                score: get_tt_score(score, ply),
+                depth,
+                bound,
+            },
+        );
+    }
+
+    /// Search with increasing depths, from first_depth to max_depth. The callback is called
+    /// after each completed iteration.
+    fn iterative_deepening<F: FnMut(&SearchResult)>(
+        &mut self,
+        position: &mut Position,
+        first_depth: u8,
+        max_depth: u8,
+        mut callback: F,
+    ) -> SearchResult {
+        self.evaluator.refresh(position);
+
+        // Always have a move to play, even if the first iteration doesn't complete
+        let best_move = match self.shared.root_moves.first() {
+            Some(mov) => Some(*mov),
+            None => gen_legal_moves(position).first().copied(),
+        };
+        let mut result = SearchResult {
+            best_move,
+            ..SearchResult::default()
+        };
+        if result.best_move.is_none() {
+            return result;
+        }
+
+        let mut pv = Vec::new();
+        for depth in first_depth..=max_depth {
+            let score = self.negamax(position, depth, 0, -INFINITE_SCORE, INFINITE_SCORE, &mut pv);
+            if self.aborted {
+                break;
+            }
+
+            self.report_nodes();
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                score,
+                depth,
+                nodes: self.shared.nodes.load(Ordering::Relaxed),
+                pv: pv.clone(),
+                tb_hits: self.shared.tb_hits.load(Ordering::Relaxed),
+            };
+            self.previous_pv = pv.clone();
+            callback(&result);
+
+            // No need to search deeper once a mate is found
+            if is_mate_score(score) {
+                break;
+            }
+        }
+
+        self.report_nodes();
+        result
+    }
+}
+
+/// Search the position with iterative deepening until one of the limits is reached or the
+/// stop flag is set, and return the result of the last completed iteration. The callback
+/// is called after each completed iteration of the main thread.
+pub fn search_with_callback<F: FnMut(&SearchResult)>(
+    position: &mut Position,
+    evaluator: &mut Evaluator,
+    limits: &SearchLimits,
+    context: &SearchContext,
+    callback: F,
+) -> SearchResult {
+    let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+
+    // When the root is in the tablebase, only the moves keeping its outcome are searched.
+    // Moves ranked with the DTZ tables also make progress towards the win, so the tables
+    // don't need to be probed during the search.
+    let root = context
+        .tablebase
+        .and_then(|tablebase| tablebase.probe_root(position, context.game_hashes));
+    let shared = SharedState {
+        nodes: AtomicU64::new(0),
+        tb_hits: AtomicU64::new(match root {
+            Some(_) => gen_legal_moves(position).len() as u64,
+            None => 0,
+        }),
+        tablebase: match &root {
+            Some(root) if root.dtz || root.wdl <= Wdl::Draw => None,
+            _ => context.tablebase,
+        },
+        root_moves: root.map(|root| root.moves).unwrap_or_default(),
+    };
+
+    // Helpers are stopped as soon as the main thread is done
+    let helpers_stop = AtomicBool::new(false);
+    let mut result = thread::scope(|scope| {
+        for helper in 1..context.threads {
+            let mut position = position.clone();
+            let mut evaluator = evaluator.clone();
+            let (helpers_stop, shared) = (&helpers_stop, &shared);
+            scope.spawn(move || {
+                let mut state =
+                    SearchState::new(&mut evaluator, limits, context, helpers_stop, shared);
+                // Starting at different depths makes the threads search different trees
+                let first_depth = (1 + helper % 2) as u8;
+                state.iterative_deepening(
+                    &mut position,
+                    first_depth.min(max_depth),
+                    max_depth,
+                    |_| {},
+                );
+            });
+        }
+
+        let mut state = SearchState::new(evaluator, limits, context, context.stop, &shared);
+        let result = state.iterative_deepening(position, 1, max_depth, callback);
+        helpers_stop.store(true, Ordering::Relaxed);
+        result
+    });
+
+    // The helpers have all been joined, so their nodes are included
+    result.nodes = shared.nodes.load(Ordering::Relaxed);
+    result.tb_hits = shared.tb_hits.load(Ordering::Relaxed);
+    result
+}
+
+/// Size in MiB of the transposition table used by search
+const SEARCH_HASH_SIZE: usize = 1;
+
+/// Search the position with iterative deepening on a single thread until one of the limits
+/// is reached, with a small transposition table of its own
+pub fn search(
+    position: &mut Position,
+    evaluator: &mut Evaluator,
+    limits: &SearchLimits,
+) -> SearchResult {
+    let context = SearchContext {
+        tt: &TranspositionTable::new(SEARCH_HASH_SIZE),
+        stop: &AtomicBool::new(false),
+        threads: 1,
+        game_hashes: &[],
+        tablebase: None,
+    };
+    search_with_callback(position, evaluator, limits, &context, |_| {})
+}
@@ -0,0 +1,89 @@
+#![allow(dead_code)]
+
+use super::misc::*;
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+
+/*******************
+ * QUIESCENCE SEARCH
+ *******************/
+
+// The quiescence search only looks at captures and promotions, until the position is quiet,
+// so that the static evaluation is never applied in the middle of an exchange. The side to
+// move can always "stand pat", ie keep the static evaluation instead of capturing.
+// See : <https://www.chessprogramming.org/Quiescence_Search>
+
+/// Generate the legal captures and promotions of the position, best ones first
+pub fn gen_tactical_moves(position: &mut Position) -> MoveList {
+    let mut moves = gen_captures(position);
+    moves.score_moves(get_mvv_lva_score);
+    moves.sort_by_score();
+    moves
+}
+
+/// Search captures and promotions with a fail-hard alpha-beta, and return the score of the
+/// position from the point of view of the side to move. The principal variation (the
+/// sequence of captures leading to the quiet position) is written in pv, and the number of
+/// visited nodes is added to nodes.
+pub fn quiescence(
+    position: &mut Position,
+    evaluator: &mut Evaluator,
+    mut alpha: i32,
+    beta: i32,
+    pv: &mut Vec<Move>,
+    nodes: &mut u64,
+) -> i32 {
+    *nodes += 1;
+    pv.clear();
+
+    let stand_pat = evaluator.evaluate(position);
+    if stand_pat >= beta {
+        return beta;
+    }
+    alpha = alpha.max(stand_pat);
+
+    let mut child_pv = Vec::new();
+    for mov in gen_tactical_moves(position) {
+        position.make_move(mov);
+        evaluator.make_move(position, mov);
+        let score = -quiescence(position, evaluator, -beta, -alpha, &mut child_pv, nodes);
+        position.unmake_move(mov);
+        evaluator.unmake_move();
+
+        if score >= beta {
+            return beta;
+        }
+        if score > alpha {
+            alpha = score;
+            pv.clear();
+            pv.push(mov);
+            pv.extend_from_slice(&child_pv);
+        }
+    }
+
+    alpha
+}
+
+/// Return the quiet position reached at the end of the quiescence search's principal
+/// variation
+pub fn get_quiet_position(position: &Position, evaluator: &mut Evaluator) -> Position {
+    let mut position = position.clone();
+    let mut pv = Vec::new();
+    let mut nodes = 0;
+
+    evaluator.refresh(&position);
+    quiescence(
+        &mut position,
+        evaluator,
+        -INFINITE_SCORE,
+        INFINITE_SCORE,
+        &mut pv,
+        &mut nodes,
+    );
+
+    for mov in pv {
+        position.make_move(mov);
+    }
+    position
+}
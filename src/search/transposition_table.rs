@@ -0,0 +1,184 @@
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::misc::*;
+use super::negamax::*;
+use crate::move_generation::Move;
+
+/*********************
+ * TRANSPOSITION TABLE
+ *********************/
+
+// The transposition table stores the results of previously searched nodes, indexed by
+// their Zobrist hash. It is shared by all the search threads without any lock : each slot
+// stores its data and its hash XORed with the data, so an entry torn by concurrent writes
+// fails the key check and is ignored ("lockless hashing").
+// See : <https://www.chessprogramming.org/Transposition_Table>
+// and : <https://www.chessprogramming.org/Shared_Hash_Table#Lockless>
+
+/// Default size of the table, in MiB
+pub const DEFAULT_HASH_SIZE: usize = 16;
+
+/// Largest size of the table, in MiB
+pub const MAX_HASH_SIZE: usize = 65536;
+
+/// Kind of score stored in an entry. With a fail-hard search, a node failing high returns
+/// beta (a lower bound of its real score) and a node failing low returns alpha (an upper
+/// bound).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TranspositionEntry {
+    pub best_move: Option<Move>,
+    /// Score from the point of view of the side to move. Mate scores are relative to the
+    /// node (see get_tt_score and get_search_score).
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+/// Set in the data of every stored entry, so that empty slots (all zeros) are never valid
+const VALID_ENTRY_FLAG: u64 = 1 << 58;
+
+impl TranspositionEntry {
+    /// Pack the entry on 64 bits : move (32 bits), score (16), depth (8) and bound (2)
+    fn to_data(self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        self.best_move.map_or(0, |mov| mov.0 as u64)
+            | (self.score as i16 as u16 as u64) << 32
+            | (self.depth as u64) << 48
+            | bound << 56
+            | VALID_ENTRY_FLAG
+    }
+
+    fn from_data(data: u64) -> TranspositionEntry {
+        TranspositionEntry {
+            best_move: match data as u32 {
+                0 => None,
+                mov => Some(Move(mov)),
+            },
+            score: (data >> 32) as u16 as i16 as i32,
+            depth: (data >> 48) as u8,
+            bound: match (data >> 56) & 0b11 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+        }
+    }
+}
+
+/// Convert a score found at some ply to a score stored in the table. Mate scores are made
+/// relative to the node, so that they stay correct when the node is reached at another ply.
+pub fn get_tt_score(score: i32, ply: usize) -> i32 {
+    if is_mate_score(score) || is_tablebase_score(score) {
+        score + score.signum() * ply as i32
+    } else {
+        score
+    }
+}
+
+/// Inverse of get_tt_score
+pub fn get_search_score(score: i32, ply: usize) -> i32 {
+    if is_mate_score(score) || is_tablebase_score(score) {
+        score - score.signum() * ply as i32
+    } else {
+        score
+    }
+}
+
+struct Slot {
+    /// Hash of the position XORed with the data
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    /// Create a table of the given size in MiB (at least one slot is allocated)
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let slot_count = (size_mb.min(MAX_HASH_SIZE) << 20) / std::mem::size_of::<Slot>();
+        TranspositionTable {
+            slots: (0..slot_count.max(1))
+                .map(|_| Slot {
+                    key: AtomicU64::new(0),
+                    data: AtomicU64::new(0),
+                })
+                .collect(),
+        }
+    }
+
+    /// Number of slots of the table
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Empty the table
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Map a hash to a slot, without requiring a power of two number of slots
+    fn get_slot(&self, hash: u64) -> &Slot {
+        let index = ((hash as u128 * self.slots.len() as u128) >> 64) as usize;
+        &self.slots[index]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TranspositionEntry> {
+        let slot = self.get_slot(hash);
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+        if data & VALID_ENTRY_FLAG == 0 || key ^ data != hash {
+            return None;
+        }
+        Some(TranspositionEntry::from_data(data))
+    }
+
+    /// Store an entry. A deeper entry of the same position is only replaced by an exact
+    /// score, and entries of other positions are always replaced.
+    pub fn store(&self, hash: u64, entry: TranspositionEntry) {
+        let slot = self.get_slot(hash);
+        if let Some(previous) = self.probe(hash) {
+            if previous.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+        }
+
+        let mut entry = entry;
+        entry.score = entry.score.clamp(-INFINITE_SCORE, INFINITE_SCORE);
+        let data = entry.to_data();
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Occupation of the table in permille, estimated on its first 1000 slots (as expected
+    /// by the "hashfull" field of UCI info lines)
+    pub fn get_hashfull(&self) -> u32 {
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .filter(|slot| slot.data.load(Ordering::Relaxed) & VALID_ENTRY_FLAG != 0)
+            .count();
+        (used * 1000 / sample) as u32
+    }
+}
@@ -0,0 +1,396 @@
+/*
+ * The serialization module implements Serialize and Deserialize for the core types, when the
+ * serde feature is enabled, so that positions, search results and game records can be
+ * exchanged as JSON (or any other format supported by serde).
+ *
+ * The types are written in the text notations used by the rest of the engine : positions as
+ * FEN strings, squares in algebraic notation, moves in UCI notation and game records as PGN.
+ * Players, piece codes and game results simply derive the serde traits.
+ *
+ * Moves are opaque, and encode the moved and captured pieces, so they can only be read back
+ * from their UCI notation in the position they were played in. Their serializable form is a
+ * UciMove, built from a move and its position, and search results are serialized as a
+ * SearchReport, which contains the root position along with the moves. Game records don't
+ * need it, as PGN already writes their moves in the positions they are played from.
+ */
+
+#![allow(dead_code)]
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::board_representation::*;
+use crate::move_generation::*;
+use crate::pgn::*;
+use crate::search::*;
+
+/*****************
+ * STRING FORMATS
+ *****************/
+
+/// Visitor reading a string, and parsing it with a function returning a displayable error
+struct ParseVisitor<F> {
+    expected: &'static str,
+    parse: F,
+}
+
+impl<'de, T, E, F> Visitor<'de> for ParseVisitor<F>
+where
+    E: fmt::Display,
+    F: FnOnce(&str) -> Result<T, E>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.expected)
+    }
+
+    fn visit_str<Er: de::Error>(self, value: &str) -> Result<T, Er> {
+        (self.parse)(value).map_err(Er::custom)
+    }
+}
+
+/// Positions are written as FEN strings. The history of the moves that led to the position
+/// is not kept, so a deserialized position can't unmake them.
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Position, D::Error> {
+        deserializer.deserialize_str(ParseVisitor {
+            expected: "a FEN string",
+            parse: Position::from_fen,
+        })
+    }
+}
+
+/// Coordinates are written in algebraic notation (such as "e4")
+impl Serialize for Coord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&get_algebraic_square(*self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Coord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Coord, D::Error> {
+        deserializer.deserialize_str(ParseVisitor {
+            expected: "a square in algebraic notation",
+            parse: |square: &str| {
+                Coord::from_algebraic(square).ok_or(format!("invalid square: {}", square))
+            },
+        })
+    }
+}
+
+/// Game records are written as PGN strings, in export format (so the Seven Tag Roster comes
+/// first in a deserialized game)
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_pgn())
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        deserializer.deserialize_str(ParseVisitor {
+            expected: "a PGN game",
+            parse: Game::from_pgn,
+        })
+    }
+}
+
+/*******
+ * MOVES
+ *******/
+
+/// Serializable form of a Move : its UCI notation (such as "e2e4" or "e7e8q"). Parsing only
+/// checks the notation, the move itself is resolved in the position it is played from.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UciMove(String);
+
+impl UciMove {
+    /// Write a move legal in the given position
+    pub fn new(position: &Position, mov: Move) -> UciMove {
+        UciMove(get_move_uci(position, mov))
+    }
+
+    /// Resolve the move in the position it is played from. Returns None if it isn't legal.
+    pub fn to_move(&self, position: &mut Position) -> Option<Move> {
+        parse_uci_move(position, &self.0)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for UciMove {
+    type Err = String;
+
+    /// Parse a move in UCI notation : start and arrival squares, and an optional promotion
+    /// piece
+    fn from_str(uci: &str) -> Result<UciMove, String> {
+        let squares = uci.get(0..2).zip(uci.get(2..4));
+        let valid = squares.is_some_and(|(start, arrival)| {
+            Coord::from_algebraic(start).is_some() && Coord::from_algebraic(arrival).is_some()
+        }) && matches!(uci.get(4..), Some("" | "q" | "r" | "b" | "n"));
+        if !valid {
+            return Err(format!("invalid UCI move: {}", uci));
+        }
+        Ok(UciMove(uci.to_string()))
+    }
+}
+
+impl fmt::Display for UciMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for UciMove {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for UciMove {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<UciMove, D::Error> {
+        deserializer.deserialize_str(ParseVisitor {
+            expected: "a move in UCI notation",
+            parse: UciMove::from_str,
+        })
+    }
+}
+
+/****************
+ * SEARCH REPORTS
+ ****************/
+
+/// Serializable form of a SearchResult, with its moves in UCI notation and the position
+/// they are played from
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SearchReport {
+    /// Root position of the search
+    pub position: Position,
+    pub best_move: Option<UciMove>,
+    pub score: i32,
+    pub depth: u8,
+    pub nodes: u64,
+    pub pv: Vec<UciMove>,
+    pub tb_hits: u64,
+}
+
+impl SearchReport {
+    /// Build the report of a search of the given root position
+    pub fn new(position: &Position, result: &SearchResult) -> SearchReport {
+        let best_move = result.best_move.map(|mov| UciMove::new(position, mov));
+
+        // Each move of the principal variation is written in the position it is played in
+        let mut pv_position = position.clone();
+        let mut pv = Vec::new();
+        for mov in &result.pv {
+            pv.push(UciMove::new(&pv_position, *mov));
+            pv_position.make_move(*mov);
+        }
+
+        SearchReport {
+            position: position.clone(),
+            best_move,
+            score: result.score,
+            depth: result.depth,
+            nodes: result.nodes,
+            pv,
+            tb_hits: result.tb_hits,
+        }
+    }
+
+    /// Convert the report back to a SearchResult. Returns None if one of the moves is not
+    /// legal in the position it is played in.
+    pub fn to_search_result(&self) -> Option<SearchResult> {
+        let mut position = self.position.clone();
+        let best_move = match &self.best_move {
+            Some(uci) => Some(uci.to_move(&mut position)?),
+            None => None,
+        };
+
+        let mut pv = Vec::new();
+        for uci in &self.pv {
+            let mov = uci.to_move(&mut position)?;
+            position.make_move(mov);
+            pv.push(mov);
+        }
+
+        Some(SearchResult {
+            best_move,
+            score: self.score,
+            depth: self.depth,
+            nodes: self.nodes,
+            pv,
+            tb_hits: self.tb_hits,
+        })
+    }
+}
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_serialization() {
+    use crate::evaluation::*;
+    use crate::pgn::*;
+
+    // Datatypes
+    let json = serde_json::to_string(&(Player::Black, PieceCode::WN, Coord::new(4, 3))).unwrap();
+    assert!(json == r#"["Black","WN","e4"]"#, "Failed at assert 0");
+    let datatypes: (Player, PieceCode, Coord) = serde_json::from_str(&json).unwrap();
+    assert!(
+        datatypes == (Player::Black, PieceCode::WN, Coord::new(4, 3)),
+        "Failed at assert 1"
+    );
+    assert!(
+        serde_json::from_str::<Coord>(r#""i9""#).is_err(),
+        "Failed at assert 2"
+    );
+
+    // Positions, including Chess960 ones
+    let fens = [
+        STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+    ];
+    for (i, fen) in fens.iter().enumerate() {
+        let position = Position::from_fen(fen).unwrap();
+        let json = serde_json::to_string(&position).unwrap();
+        assert!(
+            json == format!("\"{}\"", position.to_fen()),
+            "Failed at assert 3.{}",
+            i
+        );
+        let deserialized: Position = serde_json::from_str(&json).unwrap();
+        assert!(deserialized == position, "Failed at assert 4.{}", i);
+    }
+    assert!(
+        serde_json::from_str::<Position>(r#""8/8/8/8/8/8/8/8 w - - 0 1""#).is_err(),
+        "Failed at assert 5"
+    );
+    assert!(
+        serde_json::from_str::<Position>("42").is_err(),
+        "Failed at assert 6"
+    );
+
+    // Search results
+    let mut position = Position::from_fen(fens[1]).unwrap();
+    let limits = SearchLimits {
+        depth: Some(3),
+        ..SearchLimits::default()
+    };
+    let result = search(&mut position, &mut Evaluator::new(), &limits);
+    let report = SearchReport::new(&position, &result);
+    assert!(report.pv.len() == result.pv.len(), "Failed at assert 7");
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(
+        json.contains(&format!(
+            "\"best_move\":\"{}\"",
+            report.best_move.as_ref().unwrap()
+        )),
+        "Failed at assert 8"
+    );
+    let deserialized: SearchReport = serde_json::from_str(&json).unwrap();
+    assert!(deserialized == report, "Failed at assert 9");
+    assert!(
+        deserialized.to_search_result() == Some(result),
+        "Failed at assert 10"
+    );
+
+    // Moves are checked against the position when converting the report back
+    let mut report = deserialized;
+    report.pv.push("a1a8".parse().unwrap());
+    assert!(report.to_search_result().is_none(), "Failed at assert 11");
+
+    // Game records
+    let pgn = r#"[Event "Serialization"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12"]
+
+{Opening comment} 12... Kd7 $1 (12... Kf7 13. e4) 13. e4 {Pawn push} 1/2-1/2
+"#;
+    let game = Game::from_pgn(pgn).unwrap();
+    let json = serde_json::to_string(&game).unwrap();
+    let deserialized: Game = serde_json::from_str(&json).unwrap();
+    // The Seven Tag Roster is added in front of the other tags
+    assert!(
+        game.tags
+            .iter()
+            .all(|(name, value)| deserialized.get_tag(name) == Some(value)),
+        "Failed at assert 12"
+    );
+    assert!(
+        deserialized.starting_position == game.starting_position,
+        "Failed at assert 13"
+    );
+    assert!(deserialized.moves == game.moves, "Failed at assert 14");
+    assert!(
+        deserialized.result == GameResult::Draw,
+        "Failed at assert 15"
+    );
+    assert!(
+        deserialized.to_pgn() == game.to_pgn(),
+        "Failed at assert 16"
+    );
+    assert!(
+        json == serde_json::to_string(&game.to_pgn()).unwrap(),
+        "Failed at assert 17"
+    );
+
+    // Moves, including promotions and Chess960 castling, round trip in their position
+    let fens = [
+        fens[1],
+        fens[3],
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ];
+    for (i, fen) in fens.iter().enumerate() {
+        let mut position = Position::from_fen(fen).unwrap();
+        for (j, mov) in gen_legal_moves(&mut position).into_iter().enumerate() {
+            let json = serde_json::to_string(&UciMove::new(&position, mov)).unwrap();
+            assert!(
+                json == format!("\"{}\"", get_move_uci(&position, mov)),
+                "Failed at assert 18.{}.{}",
+                i,
+                j
+            );
+            let deserialized: UciMove = serde_json::from_str(&json).unwrap();
+            assert!(
+                deserialized.to_move(&mut position) == Some(mov),
+                "Failed at assert 19.{}.{}",
+                i,
+                j
+            );
+        }
+    }
+    for (i, json) in [r#""e2e9""#, r#""e7e8k""#, r#""e2""#, r#""e2e4q ""#, "42"]
+        .iter()
+        .enumerate()
+    {
+        assert!(
+            serde_json::from_str::<UciMove>(json).is_err(),
+            "Failed at assert 20.{}",
+            i
+        );
+    }
+    // Well-formed moves are only resolved in their position
+    let uci_move: UciMove = serde_json::from_str(r#""a1a8""#).unwrap();
+    assert!(uci_move.as_str() == "a1a8", "Failed at assert 21");
+    let mut position = Position::from_fen(STARTING_FEN).unwrap();
+    assert!(
+        uci_move.to_move(&mut position).is_none(),
+        "Failed at assert 22"
+    );
+}
@@ -0,0 +1,413 @@
+/*
+ * The tablebase module gives access to endgame tablebases stored on disk. For now, only the
+ * Syzygy format is supported.
+ *
+ * Syzygy tablebases are made of one file per material configuration (for instance
+ * "KRvK.rtbw"), in two flavours : WDL tables (win/draw/loss, used during the search) and DTZ
+ * tables (distance to zeroing move, used to pick moves at the root). The right table for a
+ * position is found from the material signature of its bitboard.
+ *
+ * The probing code (decompression and indexing of the tables) follows the Stockfish
+ * prober. The tables are probed at the root to only search the moves keeping the outcome of
+ * the position, during the search after captures and pawn moves, and to adjudicate matches.
+ *
+ * NOTE The tests use the tables of all the three-piece endgames, built by the test_tables
+ * module : it solves these endgames and writes them in the Syzygy format, with its own
+ * indexing code. The prober hasn't been checked against the official table files, which
+ * can't be downloaded by the tests.
+ * See : <https://www.chessprogramming.org/Syzygy_Bases>
+ */
+
+pub mod syzygy;
+pub mod table;
+#[cfg(test)]
+pub mod test_tables;
+
+pub use syzygy::*;
+#[allow(unused_imports)]
+pub use table::*;
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_syzygy() {
+    use crate::board_representation::*;
+    use std::fs;
+
+    // Table names
+    let position = Position::from_fen("7k/8/8/8/8/8/8/KQ6 w - - 0 1").unwrap();
+    let table_name = get_syzygy_table_name(&position.piece_centric_board);
+    assert!(
+        table_name == ("KQvK".to_string(), false),
+        "Failed at assert 0"
+    );
+
+    let position = Position::from_fen("8/8/8/8/8/8/8/K5rk w - - 0 1").unwrap();
+    let table_name = get_syzygy_table_name(&position.piece_centric_board);
+    assert!(
+        table_name == ("KRvK".to_string(), true),
+        "Failed at assert 1"
+    );
+
+    let position = Position::from_fen("8/8/8/8/8/8/8/KBN2qk1 w - - 0 1").unwrap();
+    let table_name = get_syzygy_table_name(&position.piece_centric_board);
+    assert!(
+        table_name == ("KBNvKQ".to_string(), false),
+        "Failed at assert 2"
+    );
+
+    let position = Position::from_fen("8/8/8/8/8/8/8/KBN2rqk b - - 0 1").unwrap();
+    let table_name = get_syzygy_table_name(&position.piece_centric_board);
+    assert!(
+        table_name == ("KQRvKBN".to_string(), true),
+        "Failed at assert 3"
+    );
+
+    // Table files lookup
+    let directory = std::env::temp_dir().join(format!("krabnik-syzygy-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for file in [
+        "KQvK.rtbw",
+        "KQvK.rtbz",
+        "KRvK.rtbw",
+        "KRvKP.rtbw",
+        "README.txt",
+    ] {
+        fs::write(directory.join(file), []).unwrap();
+    }
+
+    let syzygy_path = format!(
+        "/nonexistent{}{}",
+        SYZYGY_PATH_SEPARATOR,
+        directory.display()
+    );
+    let tablebase = SyzygyTablebase::new(&syzygy_path);
+    assert!(tablebase.wdl_tables.len() == 3, "Failed at assert 4");
+    assert!(tablebase.dtz_tables.len() == 1, "Failed at assert 5");
+    assert!(tablebase.max_pieces == 4, "Failed at assert 6");
+
+    let position = Position::from_fen("8/8/8/8/8/8/8/K5rk w - - 0 1").unwrap();
+    assert!(tablebase.can_probe(&position), "Failed at assert 7");
+    let (path, colors_swapped) = tablebase.get_wdl_table(&position).unwrap();
+    assert!(path == directory.join("KRvK.rtbw"), "Failed at assert 8");
+    assert!(colors_swapped, "Failed at assert 9");
+    assert!(
+        tablebase.get_dtz_table(&position).is_none(),
+        "Failed at assert 10"
+    );
+
+    assert!(
+        !tablebase.can_probe(&Position::new()),
+        "Failed at assert 11"
+    );
+    assert!(
+        tablebase.get_wdl_table(&Position::new()).is_none(),
+        "Failed at assert 12"
+    );
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_syzygy_indexing() {
+    use std::collections::HashSet;
+    use test_tables::*;
+
+    // Indices computed by hand from the description of the format
+    let squares = get_canonical_squares([1, 0, 18], false);
+    assert!(squares == [1, 0, 18], "Failed at assert 0");
+    assert!(get_pieces_index(squares) == 16, "Failed at assert 1");
+    let squares = get_canonical_squares([0, 9, 63], false);
+    assert!(get_pieces_index(squares) == 31169, "Failed at assert 2");
+    let squares = get_canonical_squares([9, 16, 0], false);
+    assert!(squares == [9, 2, 0], "Failed at assert 3");
+    assert!(
+        get_pieces_index(squares) == 23436 + 29 * 62,
+        "Failed at assert 4"
+    );
+    let squares = get_canonical_squares([12, 56, 7], true);
+    assert!(squares == [11, 63, 0], "Failed at assert 5");
+    assert!(get_pawn_index(squares, 1) == 62, "Failed at assert 6");
+    assert!(get_pawn_index(squares, 0) == 6 * 62, "Failed at assert 7");
+
+    // Canonical positions are indexed without gaps nor collisions
+    let placements =
+        || (0..64).flat_map(|a| (0..64).flat_map(move |b| (0..64).map(move |c| [a, b, c])));
+    let indices: HashSet<usize> = placements()
+        .filter(|squares| {
+            squares[0] != squares[1] && squares[1] != squares[2] && squares[0] != squares[2]
+        })
+        .filter(|squares| get_canonical_squares(*squares, false) == *squares)
+        .map(get_pieces_index)
+        .collect();
+    assert!(
+        indices.len() == PIECES_TABLE_SIZE && indices.iter().all(|idx| *idx < PIECES_TABLE_SIZE),
+        "Failed at assert 8"
+    );
+    for file in 0..4 {
+        let indices: HashSet<usize> = placements()
+            .filter(|squares| {
+                squares[0] != squares[1] && squares[1] != squares[2] && squares[0] != squares[2]
+            })
+            .filter(|squares| squares[0] % 8 == file && (8..56).contains(&squares[0]))
+            .map(|squares| get_pawn_index(squares, 1))
+            .collect();
+        assert!(
+            indices.len() == PAWN_TABLE_SIZE && indices.iter().all(|idx| *idx < PAWN_TABLE_SIZE),
+            "Failed at assert 9.{}",
+            file
+        );
+    }
+}
+
+#[test]
+fn test_syzygy_probing() {
+    use crate::board_representation::*;
+    use crate::evaluation::*;
+    use crate::move_generation::*;
+    use crate::search::*;
+    use crate::uci::*;
+    use std::fs;
+    use std::sync::atomic::AtomicBool;
+    use test_tables::*;
+
+    let directory =
+        std::env::temp_dir().join(format!("krabnik-syzygy-probing-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let queen = EndgameSolution::new('Q');
+    let rook = EndgameSolution::new('R');
+    let pawn = EndgameSolution::new_pawn(&queen, &rook);
+    let bishop = EndgameSolution::new('B');
+    let knight = EndgameSolution::new('N');
+    let solutions = [queen, rook, pawn, bishop, knight];
+    for solution in &solutions {
+        solution.write_tables(&directory).unwrap();
+    }
+
+    // Longest mates, with white to move
+    let longest_mate = |solution: &EndgameSolution| {
+        solution.outcomes[..POSITIONS / 2]
+            .iter()
+            .filter_map(|outcome| match outcome {
+                Outcome::Win(plies) => Some(*plies),
+                _ => None,
+            })
+            .max()
+    };
+    assert!(
+        longest_mate(&solutions[0]) == Some(19),
+        "Failed at assert 0"
+    );
+    assert!(
+        longest_mate(&solutions[1]) == Some(31),
+        "Failed at assert 1"
+    );
+
+    let tablebase = SyzygyTablebase::new(&directory.display().to_string());
+    assert!(tablebase.max_pieces == 3, "Failed at assert 2");
+
+    // Probes match the solutions, including with the colors swapped
+    let mirror = |fen: &str| {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let board: Vec<String> = fields[0]
+            .split('/')
+            .rev()
+            .map(|rank| {
+                rank.chars()
+                    .map(|c| {
+                        if c.is_uppercase() {
+                            c.to_ascii_lowercase()
+                        } else {
+                            c.to_ascii_uppercase()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        format!("{} {} - - 0 1", board.join("/"), side)
+    };
+    for (i, solution) in solutions.iter().enumerate() {
+        for index in (0..POSITIONS).step_by(173) {
+            let (white_to_move, ..) = get_position(index);
+            let (wdl, dtz) = match solution.outcomes[index] {
+                Outcome::Illegal => continue,
+                Outcome::Draw => (Wdl::Draw, 0),
+                Outcome::Win(plies) if white_to_move => (Wdl::Win, plies as i32),
+                Outcome::Win(plies) => (Wdl::Loss, -(plies.max(1) as i32)),
+            };
+
+            let fen = solution.get_fen(index);
+            let mut position = Position::from_fen(&fen).unwrap();
+            assert!(
+                tablebase.probe_wdl(&mut position) == Some(wdl),
+                "Failed at assert 3.{} ({})",
+                i,
+                fen
+            );
+            assert!(
+                tablebase.probe_dtz(&mut position) == Some(dtz),
+                "Failed at assert 4.{} ({})",
+                i,
+                fen
+            );
+
+            if index % 7 == 0 {
+                let mut position = Position::from_fen(&mirror(&fen)).unwrap();
+                assert!(
+                    tablebase.probe_wdl(&mut position) == Some(wdl),
+                    "Failed at assert 5.{} ({})",
+                    i,
+                    fen
+                );
+                assert!(
+                    tablebase.probe_dtz(&mut position) == Some(dtz),
+                    "Failed at assert 6.{} ({})",
+                    i,
+                    fen
+                );
+            }
+        }
+    }
+
+    // Captures are searched before reading the tables
+    let mut position = Position::from_fen("8/8/8/8/8/8/6kR/K7 b - - 0 1").unwrap();
+    assert!(
+        tablebase.probe_wdl(&mut position) == Some(Wdl::Draw),
+        "Failed at assert 7"
+    );
+    let mut position = Position::from_fen("k7/8/8/8/8/8/5K2/6r1 w - - 0 1").unwrap();
+    assert!(
+        tablebase.probe_wdl(&mut position) == Some(Wdl::Draw),
+        "Failed at assert 8"
+    );
+
+    // Positions without tables
+    let mut position = Position::from_fen("8/8/8/8/8/8/6kN/K6N b - - 0 1").unwrap();
+    assert!(
+        tablebase.probe_wdl(&mut position).is_none(),
+        "Failed at assert 9"
+    );
+    assert!(
+        tablebase.probe_root(&mut Position::new(), &[]).is_none(),
+        "Failed at assert 10"
+    );
+
+    // Playing the root moves of the longest KRvK win mates in exactly 31 plies
+    let index = (0..POSITIONS / 2)
+        .find(|index| solutions[1].outcomes[*index] == Outcome::Win(31))
+        .unwrap();
+    let mut position = Position::from_fen(&solutions[1].get_fen(index)).unwrap();
+    let mut hashes = Vec::new();
+    let mut plies = 0;
+    while !gen_legal_moves(&mut position).is_empty() {
+        let root = tablebase.probe_root(&mut position, &hashes).unwrap();
+        let expected = if position.current_turn == Player::White {
+            Wdl::Win
+        } else {
+            Wdl::Loss
+        };
+        assert!(root.wdl == expected, "Failed at assert 11.{}", plies);
+        assert!(root.dtz, "Failed at assert 12.{}", plies);
+        hashes.push(position.get_zobrist_hash());
+        position.make_move(root.moves[0]);
+        plies += 1;
+    }
+    assert!(
+        is_in_check(&position, position.current_turn),
+        "Failed at assert 13"
+    );
+    assert!(plies == 31, "Failed at assert 14");
+
+    // Searches only play the root moves, and probe the tables after captures
+    let tt = TranspositionTable::new(1);
+    let stop = AtomicBool::new(false);
+    let context = SearchContext {
+        tt: &tt,
+        stop: &stop,
+        threads: 1,
+        game_hashes: &[],
+        tablebase: Some(&tablebase),
+    };
+    let limits = SearchLimits {
+        depth: Some(4),
+        ..SearchLimits::default()
+    };
+    let mut evaluator = Evaluator::new();
+    let mut position = Position::from_fen(&solutions[1].get_fen(index)).unwrap();
+    let root = tablebase.probe_root(&mut position, &[]).unwrap();
+    let result = search_with_callback(&mut position, &mut evaluator, &limits, &context, |_| {});
+    assert!(
+        result
+            .best_move
+            .is_some_and(|mov| root.moves.contains(&mov)),
+        "Failed at assert 15"
+    );
+    assert!(
+        result.tb_hits == gen_legal_moves(&mut position).len() as u64,
+        "Failed at assert 16"
+    );
+
+    tt.clear();
+    let mut position = Position::from_fen("4k3/8/8/8/8/8/4r3/K3R3 w - - 0 1").unwrap();
+    let result = search_with_callback(&mut position, &mut evaluator, &limits, &context, |_| {});
+    assert!(
+        result.best_move == parse_uci_move(&mut position, "e1e2"),
+        "Failed at assert 17"
+    );
+    assert!(result.score == TB_WIN_SCORE - 1, "Failed at assert 18");
+    assert!(result.tb_hits > 0, "Failed at assert 19");
+
+    // SyzygyPath UCI option
+    let mut session = UciSession::new(Vec::new());
+    let output = session.get_output().clone();
+    session.handle_command(&format!(
+        "setoption name SyzygyPath value {}",
+        directory.display()
+    ));
+    session.handle_command("position fen 4k3/8/8/8/8/8/4r3/K3R3 w - - 0 1");
+    session.handle_command("go depth 3");
+    session.wait_for_search();
+    let lines = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+    assert!(
+        lines.starts_with("info string found 10 Syzygy tables"),
+        "Failed at assert 20"
+    );
+    let last_info = lines.lines().rev().nth(1).unwrap();
+    assert!(
+        last_info.contains(" tbhits ") && !last_info.contains(" tbhits 0 "),
+        "Failed at assert 21"
+    );
+    assert!(lines.ends_with("bestmove e1e2\n"), "Failed at assert 22");
+
+    // The solution of the pawn endgame matches the KPK bitbase
+    let bitbase = get_kpk_bitbase();
+    let to_coord = |square: usize| Coord::new((square % 8) as u8, (square / 8) as u8);
+    for (index, outcome) in solutions[2].outcomes.iter().enumerate() {
+        if *outcome == Outcome::Illegal {
+            continue;
+        }
+        let (white_to_move, white_king, square, black_king) = get_position(index);
+        let side_to_move = if white_to_move {
+            Player::White
+        } else {
+            Player::Black
+        };
+        let win = bitbase.probe(
+            Player::White,
+            to_coord(white_king),
+            to_coord(square),
+            to_coord(black_king),
+            side_to_move,
+        );
+        assert!(
+            win == matches!(outcome, Outcome::Win(_)),
+            "Failed at assert 23 ({})",
+            solutions[2].get_fen(index)
+        );
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,503 @@
+#![allow(dead_code)]
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::table::*;
+use crate::board_representation::*;
+use crate::move_generation::*;
+
+/*****************
+* SYZYGY TABLEBASE
+******************/
+
+/// Extension of Syzygy Win/Draw/Loss table files
+pub const SYZYGY_WDL_EXTENSION: &str = "rtbw";
+/// Extension of Syzygy Distance To Zeroing table files
+pub const SYZYGY_DTZ_EXTENSION: &str = "rtbz";
+
+/// Separator of the directories listed in the SyzygyPath option
+#[cfg(windows)]
+pub const SYZYGY_PATH_SEPARATOR: char = ';';
+#[cfg(not(windows))]
+pub const SYZYGY_PATH_SEPARATOR: char = ':';
+
+/// Return the name of the Syzygy table covering the material on the board, as well as
+/// whether the colors of the board must be swapped to match the table. Tables are named
+/// after the material signature, with the strongest side first : the side with the most
+/// pieces, or with the most valuable pieces in case of equality (for instance "KRvKBN",
+/// but "KBNvKQ").
+pub fn get_syzygy_table_name(bitboard: &BitBoard) -> (String, bool) {
+    let signature = bitboard.material_signature();
+    let (white, black) = signature.split_once('v').unwrap_or((&signature, ""));
+
+    // Pieces are already sorted from the most to the least valuable in the signature, so we
+    // only need to map them to their value to compare the two sides
+    let piece_values = |side: &str| -> Vec<u8> {
+        side.chars()
+            .map(|piece| 6 - "KQRBNP".find(piece).unwrap_or(5) as u8)
+            .collect()
+    };
+    let white_values = piece_values(white);
+    let black_values = piece_values(black);
+
+    let colors_swapped = (black_values.len(), &black_values) > (white_values.len(), &white_values);
+    if colors_swapped {
+        (format!("{}v{}", black, white), true)
+    } else {
+        (signature.clone(), false)
+    }
+}
+
+/// A table file found on disk. The table is opened on its first probe.
+#[derive(Debug)]
+pub struct TableEntry {
+    pub path: PathBuf,
+    /// None if the file is not a valid table
+    table: OnceLock<Option<SyzygyTable>>,
+}
+
+impl TableEntry {
+    fn new(path: PathBuf) -> TableEntry {
+        TableEntry {
+            path,
+            table: OnceLock::new(),
+        }
+    }
+
+    fn get_table(&self, name: &str, kind: TableKind) -> Option<&SyzygyTable> {
+        self.table
+            .get_or_init(|| SyzygyTable::open(&self.path, name, kind).ok())
+            .as_ref()
+    }
+}
+
+/// Collection of the Syzygy table files available on disk, indexed by table name
+#[derive(Debug, Default)]
+pub struct SyzygyTablebase {
+    pub wdl_tables: HashMap<String, TableEntry>,
+    pub dtz_tables: HashMap<String, TableEntry>,
+
+    /// Number of pieces (kings included) of the largest table found
+    pub max_pieces: u32,
+}
+
+impl SyzygyTablebase {
+    /// Look for table files in the directories listed in a SyzygyPath option value.
+    /// Directories that can't be read are skipped.
+    pub fn new(syzygy_path: &str) -> SyzygyTablebase {
+        let mut tablebase = SyzygyTablebase::default();
+
+        let directories = syzygy_path
+            .split(SYZYGY_PATH_SEPARATOR)
+            .map(str::trim)
+            .filter(|directory| !directory.is_empty() && *directory != "<empty>");
+
+        for directory in directories {
+            let entries = match fs::read_dir(directory) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            for path in entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+            {
+                tablebase.add_table_file(path);
+            }
+        }
+
+        tablebase
+    }
+
+    /// Register a table file if its name is a valid table name. Files listed first have
+    /// priority over later ones with the same name.
+    fn add_table_file(&mut self, path: PathBuf) {
+        let (name, extension) = match (path.file_stem(), path.extension()) {
+            (Some(name), Some(extension)) => (name.to_string_lossy(), extension.to_string_lossy()),
+            _ => return,
+        };
+
+        let is_valid_name = name.len() <= 9
+            && name.split_once('v').is_some_and(|(strong, weak)| {
+                strong.starts_with('K')
+                    && weak.starts_with('K')
+                    && name.chars().all(|c| "KQRBNPv".contains(c))
+                    && name.matches('K').count() == 2
+            });
+        if !is_valid_name {
+            return;
+        }
+
+        let tables = if extension == SYZYGY_WDL_EXTENSION {
+            &mut self.wdl_tables
+        } else if extension == SYZYGY_DTZ_EXTENSION {
+            &mut self.dtz_tables
+        } else {
+            return;
+        };
+
+        let piece_count = name.len() as u32 - 1;
+        if let Entry::Vacant(entry) = tables.entry(name.to_string()) {
+            entry.insert(TableEntry::new(path));
+            self.max_pieces = self.max_pieces.max(piece_count);
+        }
+    }
+
+    /// Check whether the tablebase can be used for the position : tables don't include
+    /// castling rights, and must cover its number of pieces.
+    pub fn can_probe(&self, position: &Position) -> bool {
+        let can_castle = position.kingside_castling_rook.iter().any(Option::is_some)
+            || position.queenside_castling_rook.iter().any(Option::is_some);
+        !can_castle && position.piece_centric_board.piece_count() <= self.max_pieces
+    }
+
+    /// Return the WDL table file covering the position (if available), and whether colors
+    /// must be swapped to match the table
+    pub fn get_wdl_table(&self, position: &Position) -> Option<(&Path, bool)> {
+        let (name, colors_swapped) = get_syzygy_table_name(&position.piece_centric_board);
+        self.wdl_tables
+            .get(&name)
+            .map(|entry| (entry.path.as_path(), colors_swapped))
+    }
+
+    /// Return the DTZ table file covering the position (if available), and whether colors
+    /// must be swapped to match the table
+    pub fn get_dtz_table(&self, position: &Position) -> Option<(&Path, bool)> {
+        let (name, colors_swapped) = get_syzygy_table_name(&position.piece_centric_board);
+        self.dtz_tables
+            .get(&name)
+            .map(|entry| (entry.path.as_path(), colors_swapped))
+    }
+}
+
+/********
+* PROBING
+*********/
+
+// WDL tables don't store the value of positions where capturing is the best move (which
+// compresses better), so the captures are always searched before reading the table. In the
+// same way, DTZ tables don't store positions where a capture or a pawn move is best, and
+// often only store one side to move : the other one is found with a 1-ply search.
+// See : <https://github.com/official-stockfish/Stockfish/blob/master/src/syzygy/tbprobe.cpp>
+
+/// Outcome of a position for the side to move. Cursed wins and blessed losses are wins and
+/// losses that the fifty-move rule turns into draws.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    /// Convert a value read from a WDL table
+    pub fn from_value(value: i32) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome for the other side
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+/// Distance to zeroing of a position where the best move is a zeroing move
+fn get_dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Loss => -1,
+        Wdl::BlessedLoss => -101,
+        Wdl::Draw => 0,
+        Wdl::CursedWin => 101,
+        Wdl::Win => 1,
+    }
+}
+
+fn is_zeroing_move(mov: Move) -> bool {
+    get_move_capture(mov) || matches!(get_move_piece_code(mov), PieceCode::WP | PieceCode::BP)
+}
+
+/// Rank of the root moves. Wins are ranked above MAX_DTZ / 2, and losses below -MAX_DTZ / 2,
+/// unless the fifty-move rule is in sight.
+const MAX_DTZ: i32 = 1 << 18;
+
+/// Root moves keeping the best outcome according to the tablebase
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RootProbe {
+    pub moves: Vec<Move>,
+    /// Outcome of the root position
+    pub wdl: Wdl,
+    /// Whether the moves were ranked with the DTZ tables, which only keep the moves
+    /// getting closest to the next zeroing move. Moves ranked with the WDL tables only keep
+    /// the outcome, so the search must still probe the tables to make progress.
+    pub dtz: bool,
+}
+
+impl SyzygyTablebase {
+    fn probe_wdl_table(&self, position: &Position) -> Option<Wdl> {
+        // Lone kings don't have a table
+        if position.piece_centric_board.piece_count() == 2 {
+            return Some(Wdl::Draw);
+        }
+        let (name, colors_swapped) = get_syzygy_table_name(&position.piece_centric_board);
+        let table = self
+            .wdl_tables
+            .get(&name)?
+            .get_table(&name, TableKind::Wdl)?;
+        match table.probe(position, colors_swapped, 0)? {
+            TableValue::Value(value) => Wdl::from_value(value),
+            TableValue::ChangeStm => None,
+        }
+    }
+
+    fn probe_dtz_table(&self, position: &Position, wdl: Wdl) -> Option<TableValue> {
+        let (name, colors_swapped) = get_syzygy_table_name(&position.piece_centric_board);
+        let table = self
+            .dtz_tables
+            .get(&name)?
+            .get_table(&name, TableKind::Dtz)?;
+        table.probe(position, colors_swapped, wdl as i32)
+    }
+
+    /// Search the captures of the position (and its pawn moves if asked), which may be
+    /// better than the value stored in the table. Also returns whether the best move is
+    /// one of the searched moves.
+    fn search_zeroing_moves(
+        &self,
+        position: &mut Position,
+        pawn_moves: bool,
+    ) -> Option<(Wdl, bool)> {
+        let moves = gen_legal_moves(position);
+        if moves.is_empty() {
+            let wdl = if is_in_check(position, position.current_turn) {
+                Wdl::Loss
+            } else {
+                Wdl::Draw
+            };
+            return Some((wdl, false));
+        }
+
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mov in moves.iter() {
+            if !(get_move_capture(mov) || pawn_moves && is_zeroing_move(mov)) {
+                continue;
+            }
+            searched += 1;
+            position.make_move(mov);
+            let result = self.search_zeroing_moves(position, false);
+            position.unmake_move(mov);
+
+            let wdl = -result?.0;
+            if wdl > best {
+                best = wdl;
+                if wdl == Wdl::Win {
+                    return Some((wdl, true));
+                }
+            }
+        }
+
+        // The table is not needed when all the moves were searched
+        let all_searched = searched == moves.len();
+        let wdl = if all_searched {
+            best
+        } else {
+            self.probe_wdl_table(position)?
+        };
+        if best >= wdl {
+            Some((best, best > Wdl::Draw || all_searched))
+        } else {
+            Some((wdl, false))
+        }
+    }
+
+    /// Probe the outcome of the position. Returns None if the position is not covered by
+    /// the tablebase.
+    pub fn probe_wdl(&self, position: &mut Position) -> Option<Wdl> {
+        if !self.can_probe(position) {
+            return None;
+        }
+        self.search_zeroing_moves(position, false)
+            .map(|(wdl, _)| wdl)
+    }
+
+    /// Probe the distance to zeroing of the position : the number of plies before the next
+    /// capture or pawn move with the best play, positive for wins and negative for losses
+    /// (0 for draws). Cursed wins and blessed losses are 100 plies further away. Returns
+    /// None if the position is not covered by the tablebase.
+    pub fn probe_dtz(&self, position: &mut Position) -> Option<i32> {
+        if !self.can_probe(position) {
+            return None;
+        }
+
+        let (wdl, zeroing_is_best) = self.search_zeroing_moves(position, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_is_best {
+            return Some(get_dtz_before_zeroing(wdl));
+        }
+        if let TableValue::Value(dtz) = self.probe_dtz_table(position, wdl)? {
+            let dtz = if matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss) {
+                dtz + 100
+            } else {
+                dtz
+            };
+            return Some(if wdl > Wdl::Draw { dtz } else { -dtz });
+        }
+
+        // The table only stores the other side to move. Zeroing moves were already searched,
+        // so the best one is kept if the other moves are not better.
+        let mut min_dtz = i32::MAX;
+        for &mov in gen_legal_moves(position).iter() {
+            let zeroing = is_zeroing_move(mov);
+            position.make_move(mov);
+            let dtz = if zeroing {
+                self.search_zeroing_moves(position, false)
+                    .map(|(wdl, _)| -get_dtz_before_zeroing(wdl))
+            } else {
+                self.probe_dtz(position).map(|dtz| -dtz)
+            };
+            let is_mate = dtz == Some(1)
+                && is_in_check(position, position.current_turn)
+                && gen_legal_moves(position).is_empty();
+            position.unmake_move(mov);
+
+            let mut dtz = dtz?;
+            if is_mate {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == (wdl as i32).signum() {
+                min_dtz = dtz;
+            }
+        }
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    /// Find the root moves keeping the best outcome : with the DTZ tables, the winning
+    /// moves getting closest to the next zeroing move (or the losing moves getting furthest
+    /// from it), while taking the fifty-move rule into account. Without DTZ tables, the WDL
+    /// tables are used. Hashes of the positions played before the root (oldest first) are
+    /// used to detect repetitions. Returns None if the position is not covered by the
+    /// tablebase.
+    pub fn probe_root(&self, position: &mut Position, game_hashes: &[u64]) -> Option<RootProbe> {
+        let wdl = self.probe_wdl(position)?;
+        let moves = gen_legal_moves(position);
+        let (ranks, dtz) = match self.rank_root_moves_dtz(position, &moves, game_hashes) {
+            Some(ranks) => (ranks, true),
+            None => (self.rank_root_moves_wdl(position, &moves)?, false),
+        };
+
+        let best_rank = *ranks.iter().max()?;
+        let moves = moves
+            .iter()
+            .zip(&ranks)
+            .filter(|(_, rank)| **rank == best_rank)
+            .map(|(mov, _)| *mov)
+            .collect();
+        Some(RootProbe { moves, wdl, dtz })
+    }
+
+    fn rank_root_moves_dtz(
+        &self,
+        position: &mut Position,
+        moves: &[Move],
+        game_hashes: &[u64],
+    ) -> Option<Vec<i32>> {
+        // Positions since the last zeroing move, including the root
+        let plys_without_capture = position.plys_without_capture as i32;
+        let mut hashes: Vec<u64> = game_hashes
+            .iter()
+            .rev()
+            .take(position.plys_without_capture as usize)
+            .copied()
+            .collect();
+        hashes.push(position.get_zobrist_hash());
+        let repeated = hashes
+            .iter()
+            .enumerate()
+            .any(|(i, hash)| hashes[i + 1..].contains(hash));
+
+        let mut ranks = Vec::with_capacity(moves.len());
+        for &mov in moves {
+            position.make_move(mov);
+            let dtz = if position.plys_without_capture == 0 {
+                self.probe_wdl(position)
+                    .map(|wdl| get_dtz_before_zeroing(-wdl))
+            } else if position.plys_without_capture >= 100
+                || hashes.contains(&position.get_zobrist_hash())
+            {
+                Some(0)
+            } else {
+                self.probe_dtz(position).map(|dtz| -dtz - dtz.signum())
+            };
+            let is_mate = dtz == Some(2)
+                && is_in_check(position, position.current_turn)
+                && gen_legal_moves(position).is_empty();
+            position.unmake_move(mov);
+
+            let dtz = if is_mate { 1 } else { dtz? };
+            let rank = if dtz > 0 {
+                if dtz + plys_without_capture <= 99 && !repeated {
+                    MAX_DTZ - dtz
+                } else {
+                    MAX_DTZ / 2 - (dtz + plys_without_capture)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + plys_without_capture < 100 {
+                    -MAX_DTZ - dtz
+                } else {
+                    -MAX_DTZ / 2 + (-dtz + plys_without_capture)
+                }
+            } else {
+                0
+            };
+            ranks.push(rank);
+        }
+        Some(ranks)
+    }
+
+    fn rank_root_moves_wdl(&self, position: &mut Position, moves: &[Move]) -> Option<Vec<i32>> {
+        let mut ranks = Vec::with_capacity(moves.len());
+        for &mov in moves {
+            position.make_move(mov);
+            let wdl = self.probe_wdl(position);
+            position.unmake_move(mov);
+
+            ranks.push(match -wdl? {
+                Wdl::Loss => -MAX_DTZ,
+                Wdl::BlessedLoss => -MAX_DTZ + 101,
+                Wdl::Draw => 0,
+                Wdl::CursedWin => MAX_DTZ - 101,
+                Wdl::Win => MAX_DTZ,
+            });
+        }
+        Some(ranks)
+    }
+}
@@ -0,0 +1,1009 @@
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::board_representation::*;
+
+/*************
+* FILE FORMAT
+**************/
+
+// A Syzygy table file starts with a header describing, for each file of the leading pawn (or
+// once for pawnless tables) and each side to move, how the pieces are ordered and grouped to
+// compute the index of a position. Then come the parameters of the compression of each of
+// these sub-tables, their sparse indices and block lengths, and the compressed data itself,
+// split in blocks of fixed size. Values are compressed with Re-Pair (pairs of symbols are
+// recursively replaced by new symbols) and canonical Huffman codes.
+// See : <https://github.com/syzygy1/tb>
+// and : <https://github.com/official-stockfish/Stockfish/blob/master/src/syzygy/tbprobe.cpp>
+
+// In this file, squares are indexed from 0 (a1) to 63 (h8) in row-major ordering, and pieces
+// are written with the codes of the table files : 1 to 6 for white pawns to kings, and 9 to
+// 14 for black ones.
+
+/// Magic number at the start of WDL table files
+pub const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+/// Magic number at the start of DTZ table files
+pub const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/// Maximum number of pieces of a table
+pub const TB_MAX_PIECES: usize = 7;
+
+// Flags of the table header
+pub const SPLIT_FLAG: u8 = 1;
+pub const HAS_PAWNS_FLAG: u8 = 2;
+
+// Flags of each sub-table
+/// DTZ sub-table : side to move stored by the table
+pub const STM_FLAG: u8 = 1;
+/// DTZ sub-table : values are indices into a map of the actual values
+pub const MAPPED_FLAG: u8 = 2;
+/// DTZ sub-table : distances of winning positions are stored in plies (instead of moves)
+pub const WIN_PLIES_FLAG: u8 = 4;
+/// DTZ sub-table : distances of losing positions are stored in plies (instead of moves)
+pub const LOSS_PLIES_FLAG: u8 = 8;
+/// DTZ sub-table : the map stores 16 bits values
+pub const WIDE_FLAG: u8 = 16;
+/// All the positions of the sub-table have the same value
+pub const SINGLE_VALUE_FLAG: u8 = 128;
+
+/// Number of unique placements of three pieces when none of them are pawns (after applying
+/// the symmetries of the board)
+pub const UNIQUE_PIECES_SIZE: u64 = 31332;
+/// Number of legal placements of the two kings (after applying the symmetries of the board)
+pub const KINGS_SIZE: u64 = 462;
+
+/// Kind of a table file
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// Value read from a table
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TableValue {
+    /// WDL value (from -2 to 2), or DTZ value in plies
+    Value(i32),
+    /// The DTZ table only stores the other side to move : the value must be found with a
+    /// 1-ply search
+    ChangeStm,
+}
+
+/// Convert a PieceCode to the code of the piece in table files
+pub fn get_syzygy_piece(piece: PieceCode) -> u8 {
+    match piece as u8 {
+        0 => 0,
+        piece @ 1..=6 => piece,
+        piece => piece + 2,
+    }
+}
+
+/// Pieces of the position with their squares (from 0 for a1 to 63 for h8), as used by the
+/// table files
+fn get_board_pieces(position: &Position) -> impl Iterator<Item = (usize, u8)> + '_ {
+    position
+        .square_centric_board
+        .main_board
+        .iter()
+        .enumerate()
+        .map(|(square, piece)| (square, get_syzygy_piece(*piece)))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/****************
+* INDEXING TABLES
+*****************/
+
+/// Tables used to compute the index of a position, which don't depend on the table file
+pub struct IndexingTables {
+    /// Binomial coefficients, indexed by [k][n]
+    pub binomial: [[u64; 64]; TB_MAX_PIECES],
+    /// Index of the squares of pawns, from 47 (a2) to 0 (only the 48 squares of ranks 2 to 7
+    /// are used), so that the leading pawn is the one with the highest index
+    pub map_pawns: [u64; 64],
+    /// Index of the leading pawns, indexed by [number of leading pawns][square]
+    pub lead_pawn_idx: [[u64; 64]; 6],
+    /// Number of placements of the leading pawns, indexed by [number of leading pawns][file]
+    pub lead_pawns_size: [[u64; 4]; 6],
+    /// Index of the squares under the a1-h8 diagonal
+    pub map_b1h1h7: [u64; 64],
+    /// Index of the squares of the a1-d1-d4 triangle
+    pub map_a1d1d4: [u64; 64],
+    /// Index of the placements of the two kings, indexed by [map_a1d1d4[king 1]][king 2]
+    pub map_kk: [[u64; 64]; 10],
+}
+
+/// Difference between the rank and the file of a square : 0 on the a1-h8 diagonal, negative
+/// under it and positive above it
+pub fn off_a1h8(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+fn are_kings_adjacent(square_a: usize, square_b: usize) -> bool {
+    (square_a / 8).abs_diff(square_b / 8) <= 1 && (square_a % 8).abs_diff(square_b % 8) <= 1
+}
+
+impl IndexingTables {
+    fn new() -> IndexingTables {
+        let mut tables = IndexingTables {
+            binomial: [[0; 64]; TB_MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+
+        for (code, square) in (0..64).filter(|square| off_a1h8(*square) < 0).enumerate() {
+            tables.map_b1h1h7[square] = code as u64;
+        }
+
+        // Squares of the triangle under the diagonal come first, then the ones on it
+        let mut code = 0;
+        let mut diagonal = Vec::new();
+        for square in (0..=27).filter(|square| square % 8 <= 3) {
+            if off_a1h8(square) < 0 {
+                tables.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_a1h8(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            tables.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // The first king is in the triangle. When both kings are on the diagonal, they come
+        // last. When the first one is on the diagonal, the second one can't be above it.
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for idx in 0..10 {
+            // Squares outside of the triangle are also mapped to 0, so b1 must be checked
+            for king_a in (0..=27)
+                .filter(|square| tables.map_a1d1d4[*square] == idx && (idx != 0 || *square == 1))
+            {
+                for king_b in 0..64 {
+                    if are_kings_adjacent(king_a, king_b) {
+                        continue;
+                    }
+                    if off_a1h8(king_a) == 0 && off_a1h8(king_b) > 0 {
+                        continue;
+                    }
+                    if off_a1h8(king_a) == 0 && off_a1h8(king_b) == 0 {
+                        both_on_diagonal.push((idx as usize, king_b));
+                    } else {
+                        tables.map_kk[idx as usize][king_b] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, king_b) in both_on_diagonal {
+            tables.map_kk[idx][king_b] = code;
+            code += 1;
+        }
+
+        tables.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..TB_MAX_PIECES.min(n + 1) {
+                tables.binomial[k][n] = if k > 0 {
+                    tables.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n { tables.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // Pawns squares are indexed from the a and h files towards the center, so that the
+        // leading pawn (the one with the highest index) is the closest to the edge
+        let mut available_squares = 47;
+        for lead_pawns_count in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_pawns_count == 1 {
+                        tables.map_pawns[square] = available_squares;
+                        tables.map_pawns[square ^ 7] = available_squares - 1;
+                        available_squares = available_squares.saturating_sub(2);
+                    }
+                    tables.lead_pawn_idx[lead_pawns_count][square] = idx;
+                    idx += tables.binomial[lead_pawns_count - 1][tables.map_pawns[square] as usize];
+                }
+                tables.lead_pawns_size[lead_pawns_count][file] = idx;
+            }
+        }
+
+        tables
+    }
+}
+
+static INDEXING_TABLES: OnceLock<IndexingTables> = OnceLock::new();
+
+/// Return the indexing tables, computing them on the first call
+pub fn get_indexing_tables() -> &'static IndexingTables {
+    INDEXING_TABLES.get_or_init(IndexingTables::new)
+}
+
+/************
+* TABLE FILES
+*************/
+
+/// Table file, read at arbitrary offsets
+#[derive(Debug)]
+struct RandomAccessFile {
+    file: File,
+    len: u64,
+    /// Reads are done with a seek when positioned reads aren't available
+    #[cfg(not(any(unix, windows)))]
+    lock: std::sync::Mutex<()>,
+}
+
+impl RandomAccessFile {
+    fn open(path: &Path) -> io::Result<RandomAccessFile> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(RandomAccessFile {
+            file,
+            len,
+            #[cfg(not(any(unix, windows)))]
+            lock: std::sync::Mutex::new(()),
+        })
+    }
+
+    #[cfg(unix)]
+    fn read_exact_at(&self, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(&self.file, buffer, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_exact_at(&self, mut buffer: &mut [u8], mut offset: u64) -> io::Result<()> {
+        use std::os::windows::fs::FileExt;
+        while !buffer.is_empty() {
+            match self.file.seek_read(buffer, offset) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => {
+                    buffer = &mut buffer[read..];
+                    offset += read as u64;
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn read_exact_at(&self, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+        use std::io::{Read, Seek, SeekFrom};
+        let _lock = self.lock.lock().unwrap_or_else(|error| error.into_inner());
+        (&self.file).seek(SeekFrom::Start(offset))?;
+        (&self.file).read_exact(buffer)
+    }
+
+    /// Read len bytes at the given offset. Bytes past the end of the file are read as zeros.
+    fn read_padded(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0; len];
+        let available = self.len.saturating_sub(offset).min(len as u64) as usize;
+        self.read_exact_at(&mut buffer[..available], offset)?;
+        Ok(buffer)
+    }
+}
+
+/// Sequential reader of the header of a table file
+struct HeaderReader<'a> {
+    file: &'a RandomAccessFile,
+    offset: u64,
+}
+
+impl HeaderReader<'_> {
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        if self.offset + len as u64 > self.file.len {
+            return Err(invalid_data("truncated table file"));
+        }
+        let mut buffer = vec![0; len];
+        self.file.read_exact_at(&mut buffer, self.offset)?;
+        self.offset += len as u64;
+        Ok(buffer)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn skip(&mut self, len: u64) {
+        self.offset += len;
+    }
+
+    fn align(&mut self, alignment: u64) {
+        self.offset = self.offset.div_ceil(alignment) * alignment;
+    }
+}
+
+/*****************
+* COMPRESSED DATA
+******************/
+
+/// A sub-table, for a given file of the leading pawn and side to move : how its pieces are
+/// indexed, and how its values are compressed
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+    /// Pieces of the table, in the order they are indexed
+    pieces: [u8; TB_MAX_PIECES],
+    /// Number of pieces of each group of identical pieces (0 after the last group)
+    group_len: [usize; TB_MAX_PIECES + 1],
+    /// Index multiplier of each group
+    group_idx: [u64; TB_MAX_PIECES + 1],
+
+    /// Number of positions of the sub-table
+    size: u64,
+    /// Shortest Huffman code length, or value of single value sub-tables
+    min_sym_len: u8,
+    /// Huffman codes base values, for each code length
+    base64: Vec<u64>,
+    /// First symbol of each code length
+    lowest_sym: Vec<u16>,
+    /// Left and right symbols of each pair (right is 0xFFF for a value)
+    btree: Vec<(u16, u16)>,
+    /// Number of values of each symbol, minus 1
+    symlen: Vec<u32>,
+
+    block_size: u64,
+    /// Number of values between two entries of the sparse index
+    span: u64,
+    /// Block and offset in the block of the value at the middle of each span
+    sparse_index: Vec<(u32, u16)>,
+    /// Number of values of each block, minus 1
+    block_length: Vec<u16>,
+    num_blocks: u64,
+    /// Offset of the first block in the file
+    data_offset: u64,
+
+    /// Offsets of the DTZ maps of winning, losing, cursed winning and blessed losing
+    /// positions
+    map_idx: [u16; 4],
+}
+
+impl PairsData {
+    /// Compute the groups of identical pieces, and the index multiplier of each group
+    fn set_groups(&mut self, table: &TableInfo, order: [u8; 2], file: usize) {
+        let tables = get_indexing_tables();
+
+        // The first group is made of the leading pawns, or of the kings (and a unique
+        // piece) for pawnless tables
+        let mut first_len: i32 = if table.has_pawns {
+            0
+        } else if table.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let mut n = 0;
+        self.group_len[0] = 1;
+        for i in 1..table.piece_count {
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1] {
+                self.group_len[n] += 1;
+            } else {
+                n += 1;
+                self.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        self.group_len[n] = 0;
+
+        // The other pawns group is indexed on the 48 pawn squares, the other groups on the
+        // squares left free. The order bytes tell where the first two groups are placed.
+        let other_pawns = table.has_pawns && table.pawn_count[1] > 0;
+        let mut next = if other_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - self.group_len[0];
+        if other_pawns {
+            free_squares -= self.group_len[1];
+        }
+        let mut idx = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                self.group_idx[0] = idx;
+                idx *= if table.has_pawns {
+                    tables.lead_pawns_size[self.group_len[0]][file]
+                } else if table.has_unique_pieces {
+                    UNIQUE_PIECES_SIZE
+                } else {
+                    KINGS_SIZE
+                };
+            } else if k == order[1] {
+                self.group_idx[1] = idx;
+                idx *= tables.binomial[self.group_len[1]][48 - self.group_len[0]];
+            } else {
+                self.group_idx[next] = idx;
+                idx *= tables.binomial[self.group_len[next]][free_squares];
+                free_squares -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        self.group_idx[n] = idx;
+        self.size = idx;
+    }
+
+    /// Read the compression parameters of the sub-table
+    fn read_sizes(&mut self, reader: &mut HeaderReader) -> io::Result<()> {
+        self.flags = reader.u8()?;
+        if self.flags & SINGLE_VALUE_FLAG != 0 {
+            self.min_sym_len = reader.u8()?;
+            return Ok(());
+        }
+
+        let header = reader.bytes(9)?;
+        if header[0] > 32 || header[1] > 32 || header[8] == 0 || header[7] < header[8] {
+            return Err(invalid_data("invalid compression parameters"));
+        }
+        self.block_size = 1 << header[0];
+        self.span = 1 << header[1];
+        let padding = header[2] as u64;
+        self.num_blocks = u32::from_le_bytes([header[3], header[4], header[5], header[6]]) as u64;
+        let (max_sym_len, min_sym_len) = (header[7] as usize, header[8] as usize);
+        if max_sym_len > 64 {
+            return Err(invalid_data("invalid compression parameters"));
+        }
+        self.min_sym_len = min_sym_len as u8;
+
+        // Canonical Huffman codes : base64[i] is the lowest code of length i + min_sym_len,
+        // left-aligned in 64 bits
+        let lengths = max_sym_len - min_sym_len + 1;
+        let lowest_sym = reader.bytes(2 * lengths)?;
+        self.lowest_sym = lowest_sym
+            .chunks(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            self.base64[i] = self.base64[i + 1]
+                .wrapping_add(self.lowest_sym[i] as u64)
+                .wrapping_sub(self.lowest_sym[i + 1] as u64)
+                / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            *base = base.checked_shl((64 - i - min_sym_len) as u32).unwrap_or(0);
+        }
+
+        let num_syms = reader.u16()? as usize;
+        let btree = reader.bytes(3 * num_syms)?;
+        reader.skip(num_syms as u64 & 1);
+        self.btree = btree
+            .chunks(3)
+            .map(|lr| {
+                let left = (lr[1] as u16 & 0xF) << 8 | lr[0] as u16;
+                let right = (lr[2] as u16) << 4 | (lr[1] as u16) >> 4;
+                (left, right)
+            })
+            .collect();
+        self.set_symlen()?;
+
+        // Space for the sparse index and block lengths
+        self.sparse_index = vec![(0, 0); self.size.div_ceil(self.span) as usize];
+        self.block_length = vec![0; (self.num_blocks + padding) as usize];
+        Ok(())
+    }
+
+    /// Compute the number of values of each symbol
+    fn set_symlen(&mut self) -> io::Result<()> {
+        let num_syms = self.btree.len();
+        if self.btree.iter().any(|&(left, right)| {
+            right != 0xFFF && (left as usize >= num_syms || right as usize >= num_syms)
+        }) {
+            return Err(invalid_data("invalid symbol tree"));
+        }
+
+        fn set_symlen(btree: &[(u16, u16)], symlen: &mut [u32], visited: &mut [bool], sym: usize) {
+            visited[sym] = true;
+            let (left, right) = btree[sym];
+            if right == 0xFFF {
+                return;
+            }
+            for child in [left as usize, right as usize] {
+                if !visited[child] {
+                    set_symlen(btree, symlen, visited, child);
+                }
+            }
+            symlen[sym] = symlen[left as usize] + symlen[right as usize] + 1;
+        }
+
+        self.symlen = vec![0; num_syms];
+        let mut visited = vec![false; num_syms];
+        for sym in 0..num_syms {
+            if !visited[sym] {
+                set_symlen(&self.btree, &mut self.symlen, &mut visited, sym);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the value at the given index
+    fn decompress(&self, file: &RandomAccessFile, idx: u64) -> Option<u32> {
+        if self.flags & SINGLE_VALUE_FLAG != 0 {
+            return Some(self.min_sym_len as u32);
+        }
+
+        // The sparse index gives the position of the value at the middle of each span, from
+        // which we walk to the block of the value
+        let (mut block, offset) = *self.sparse_index.get((idx / self.span) as usize)?;
+        let mut offset = offset as i64 + (idx % self.span) as i64 - (self.span / 2) as i64;
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += *self.block_length.get(block as usize)? as i64 + 1;
+        }
+        while offset > *self.block_length.get(block as usize)? as i64 {
+            offset -= self.block_length[block as usize] as i64 + 1;
+            block += 1;
+        }
+
+        // Huffman codes are read in big endian, through a 64 bits buffer refilled 32 bits at
+        // a time, until the symbol containing the value is found
+        let data = file
+            .read_padded(
+                self.data_offset + block as u64 * self.block_size,
+                self.block_size as usize + 8,
+            )
+            .ok()?;
+        let read_u32 = |position: usize| -> Option<u64> {
+            let bytes = data.get(position..position + 4)?;
+            Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64)
+        };
+        let mut buffer = read_u32(0)? << 32 | read_u32(4)?;
+        let mut buffer_size = 64;
+        let mut position = 8;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buffer < *self.base64.get(len)? {
+                len += 1;
+            }
+            let code_len = len + self.min_sym_len as usize;
+            sym = ((buffer - self.base64[len]) >> (64 - code_len)) as usize
+                + self.lowest_sym[len] as usize;
+            let symlen = *self.symlen.get(sym)? as i64;
+            if offset < symlen + 1 {
+                break;
+            }
+            offset -= symlen + 1;
+            buffer <<= code_len;
+            buffer_size -= code_len;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= read_u32(position)? << (64 - buffer_size);
+                position += 4;
+            }
+        }
+
+        // Expand the pairs of the symbol down to the value
+        while self.symlen[sym] != 0 {
+            let (left, right) = self.btree[sym];
+            let left_len = self.symlen[left as usize] as i64;
+            if offset < left_len + 1 {
+                sym = left as usize;
+            } else {
+                offset -= left_len + 1;
+                sym = right as usize;
+            }
+        }
+        Some(self.btree[sym].0 as u32)
+    }
+}
+
+/*******
+* TABLES
+********/
+
+/// Material of a table, deduced from its name
+#[derive(Clone, Debug)]
+struct TableInfo {
+    piece_count: usize,
+    has_pawns: bool,
+    /// Whether a side has a single piece of some type (other than the king)
+    has_unique_pieces: bool,
+    /// Both sides have the same material
+    symmetric: bool,
+    /// Number of pawns of the leading color, then of the other color. The leading color is
+    /// the one with the fewest pawns (other than zero), white in case of equality.
+    pawn_count: [usize; 2],
+}
+
+impl TableInfo {
+    fn from_name(name: &str) -> Option<TableInfo> {
+        let (white, black) = name.split_once('v')?;
+        let count = |side: &str, piece: char| side.matches(piece).count();
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        let leading_white = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        Some(TableInfo {
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: [white, black]
+                .iter()
+                .any(|side| "PNBRQ".chars().any(|piece| count(side, piece) == 1)),
+            symmetric: white == black,
+            pawn_count: if leading_white {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+        })
+    }
+}
+
+/// A Syzygy table file, with its header loaded in memory. Compressed blocks are read from
+/// the file when probing.
+#[derive(Debug)]
+pub struct SyzygyTable {
+    file: RandomAccessFile,
+    kind: TableKind,
+    info: TableInfo,
+    /// Number of sides to move stored in the table (WDL tables of asymmetric material store
+    /// both)
+    sides: usize,
+    /// Sub-tables, indexed by file of the leading pawn * sides + side to move
+    pairs: Vec<PairsData>,
+    /// Maps of the values of DTZ tables
+    dtz_map: Vec<u8>,
+}
+
+impl SyzygyTable {
+    /// Open a table file, and read its header
+    pub fn open(path: &Path, name: &str, kind: TableKind) -> io::Result<SyzygyTable> {
+        let info = TableInfo::from_name(name)
+            .filter(|info| info.piece_count <= TB_MAX_PIECES)
+            .ok_or_else(|| invalid_data("invalid table name"))?;
+        let file = RandomAccessFile::open(path)?;
+        if file.len % 64 != 16 {
+            return Err(invalid_data("invalid table file size"));
+        }
+
+        let mut reader = HeaderReader {
+            file: &file,
+            offset: 0,
+        };
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if reader.bytes(4)? != magic {
+            return Err(invalid_data("invalid table magic number"));
+        }
+        let flags = reader.u8()?;
+        if (flags & HAS_PAWNS_FLAG != 0) != info.has_pawns
+            || (flags & SPLIT_FLAG != 0) == info.symmetric
+        {
+            return Err(invalid_data("table header doesn't match its name"));
+        }
+
+        // Pieces order of each sub-table. The low nibbles are for the first side, the high
+        // ones for the second side.
+        let sides = if kind == TableKind::Wdl && !info.symmetric {
+            2
+        } else {
+            1
+        };
+        let files = if info.has_pawns { 4 } else { 1 };
+        let other_pawns = info.has_pawns && info.pawn_count[1] > 0;
+        let mut pairs = Vec::with_capacity(files * sides);
+        for file in 0..files {
+            let order = reader.bytes(1 + other_pawns as usize)?;
+            let order_of = |shift: u8| {
+                [
+                    order[0] >> shift & 0xF,
+                    if other_pawns {
+                        order[1] >> shift & 0xF
+                    } else {
+                        0xF
+                    },
+                ]
+            };
+            let pieces = reader.bytes(info.piece_count)?;
+            for side in 0..sides {
+                let shift = 4 * side as u8;
+                let mut data = PairsData::default();
+                for (i, piece) in pieces.iter().enumerate() {
+                    data.pieces[i] = piece >> shift & 0xF;
+                }
+                data.set_groups(&info, order_of(shift), file);
+                pairs.push(data);
+            }
+        }
+        reader.align(2);
+
+        for data in pairs.iter_mut() {
+            data.read_sizes(&mut reader)?;
+        }
+
+        // DTZ tables can map their values, for each of the 4 kinds of outcome
+        let mut dtz_map = Vec::new();
+        if kind == TableKind::Dtz {
+            let map_start = reader.offset;
+            for data in pairs
+                .iter_mut()
+                .filter(|data| data.flags & MAPPED_FLAG != 0)
+            {
+                if data.flags & WIDE_FLAG != 0 {
+                    reader.align(2);
+                    for map_idx in data.map_idx.iter_mut() {
+                        *map_idx = ((reader.offset - map_start) / 2 + 1) as u16;
+                        let len = reader.u16()? as u64;
+                        reader.skip(2 * len);
+                    }
+                } else {
+                    for map_idx in data.map_idx.iter_mut() {
+                        *map_idx = (reader.offset - map_start + 1) as u16;
+                        let len = reader.u8()? as u64;
+                        reader.skip(len);
+                    }
+                }
+            }
+            reader.align(2);
+            let map_end = reader.offset;
+            reader.offset = map_start;
+            dtz_map = reader.bytes((map_end - map_start) as usize)?;
+        }
+
+        for data in pairs.iter_mut() {
+            let entries = reader.bytes(6 * data.sparse_index.len())?;
+            for (entry, bytes) in data.sparse_index.iter_mut().zip(entries.chunks(6)) {
+                *entry = (
+                    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                    u16::from_le_bytes([bytes[4], bytes[5]]),
+                );
+            }
+        }
+        for data in pairs.iter_mut() {
+            let lengths = reader.bytes(2 * data.block_length.len())?;
+            for (length, bytes) in data.block_length.iter_mut().zip(lengths.chunks(2)) {
+                *length = u16::from_le_bytes([bytes[0], bytes[1]]);
+            }
+        }
+        for data in pairs.iter_mut() {
+            reader.align(64);
+            data.data_offset = reader.offset;
+            reader.skip(data.num_blocks * data.block_size);
+        }
+        if reader.offset > file.len {
+            return Err(invalid_data("truncated table file"));
+        }
+
+        Ok(SyzygyTable {
+            file,
+            kind,
+            info,
+            sides,
+            pairs,
+            dtz_map,
+        })
+    }
+}
+
+/********
+* PROBING
+*********/
+
+impl SyzygyTable {
+    /// Read the value of a position. Colors are swapped when the position has the material
+    /// of the table with the colors reversed. DTZ tables also need the WDL value of the
+    /// position. Returns None if the table doesn't match the position, or can't be read.
+    pub fn probe(&self, position: &Position, colors_swapped: bool, wdl: i32) -> Option<TableValue> {
+        let tables = get_indexing_tables();
+        let info = &self.info;
+
+        // Tables of symmetric material only store white to move
+        let black_to_move = position.current_turn == Player::Black;
+        let flip = colors_swapped || (black_to_move && info.symmetric);
+        let (flip_color, flip_squares) = if flip { (8, 56) } else { (0, 0) };
+        let side_to_move = (flip != black_to_move) as usize;
+
+        let mut squares = [0usize; TB_MAX_PIECES];
+        let mut pieces = [0u8; TB_MAX_PIECES];
+        let mut size = 0;
+
+        // The leading pawns come first, starting with the closest one to the edge, which
+        // gives the file of the sub-table
+        let mut lead_pawns_count = 0;
+        let mut file = 0;
+        let lead_pawn = if info.has_pawns {
+            Some(self.pairs[0].pieces[0] ^ flip_color)
+        } else {
+            None
+        };
+        if let Some(lead_pawn) = lead_pawn {
+            for (square, piece) in get_board_pieces(position) {
+                if piece == lead_pawn && size < TB_MAX_PIECES {
+                    squares[size] = square ^ flip_squares;
+                    size += 1;
+                }
+            }
+            lead_pawns_count = size;
+            let lead = (0..size).max_by_key(|&i| tables.map_pawns[squares[i]])?;
+            squares.swap(0, lead);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+
+        if self.kind == TableKind::Dtz {
+            let stored_side = (self.pairs[file].flags & STM_FLAG) as usize;
+            if stored_side != side_to_move && (!info.symmetric || info.has_pawns) {
+                return Some(TableValue::ChangeStm);
+            }
+        }
+
+        for (square, piece) in get_board_pieces(position) {
+            if piece == 0 || Some(piece) == lead_pawn {
+                continue;
+            }
+            if size == TB_MAX_PIECES {
+                return None;
+            }
+            squares[size] = square ^ flip_squares;
+            pieces[size] = piece ^ flip_color;
+            size += 1;
+        }
+        if size != info.piece_count {
+            return None;
+        }
+
+        // Pieces are put in the order of the sub-table
+        let data = &self.pairs[file * self.sides + side_to_move % self.sides];
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| data.pieces[i] == pieces[j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+        if pieces[lead_pawns_count..size] != data.pieces[lead_pawns_count..size] {
+            return None;
+        }
+
+        let idx = get_index(tables, info, data, &mut squares[..size], lead_pawns_count)?;
+        let value = data.decompress(&self.file, idx)?;
+
+        match self.kind {
+            TableKind::Wdl => Some(TableValue::Value(value as i32 - 2)),
+            TableKind::Dtz => self.map_dtz(data, value, wdl).map(TableValue::Value),
+        }
+    }
+
+    /// Convert a value read from a DTZ table to plies
+    fn map_dtz(&self, data: &PairsData, value: u32, wdl: i32) -> Option<i32> {
+        let mut value = value as usize;
+        if data.flags & MAPPED_FLAG != 0 {
+            // Maps are stored for wins, losses, cursed wins and blessed losses
+            let map = data.map_idx[[1, 3, 0, 2, 0][(wdl + 2).clamp(0, 4) as usize]] as usize;
+            value = if data.flags & WIDE_FLAG != 0 {
+                let offset = 2 * (map + value);
+                let bytes = self.dtz_map.get(offset..offset + 2)?;
+                u16::from_le_bytes([bytes[0], bytes[1]]) as usize
+            } else {
+                *self.dtz_map.get(map + value)? as usize
+            };
+        }
+
+        // Distances are stored in moves rather than plies when it doesn't lose information
+        let in_moves = match wdl {
+            2 => data.flags & WIN_PLIES_FLAG == 0,
+            -2 => data.flags & LOSS_PLIES_FLAG == 0,
+            _ => true,
+        };
+        let plies = if in_moves { 2 * value } else { value };
+        Some(plies as i32 + 1)
+    }
+}
+
+/// Compute the index of a position in a sub-table, from its squares in the order of the
+/// sub-table (starting with the leading pawns)
+fn get_index(
+    tables: &IndexingTables,
+    info: &TableInfo,
+    data: &PairsData,
+    squares: &mut [usize],
+    lead_pawns_count: usize,
+) -> Option<u64> {
+    // The leading pawn (or the first king) is brought to the a-d files
+    if squares[0] % 8 > 3 {
+        for square in squares.iter_mut() {
+            *square ^= 7;
+        }
+    }
+
+    let mut idx;
+    if info.has_pawns {
+        idx = tables.lead_pawn_idx[lead_pawns_count][squares[0]];
+        squares[1..lead_pawns_count].sort_by_key(|square| tables.map_pawns[*square]);
+        for (i, square) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+            idx += tables.binomial[i][tables.map_pawns[*square] as usize];
+        }
+    } else {
+        // Pawnless positions are also brought to ranks 1-4, and under the a1-h8 diagonal
+        if squares[0] / 8 > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 56;
+            }
+        }
+        for i in 0..data.group_len[0] {
+            if off_a1h8(squares[i]) == 0 {
+                continue;
+            }
+            if off_a1h8(squares[i]) > 0 {
+                for square in squares[i..].iter_mut() {
+                    *square = (*square >> 3 | *square << 3) & 63;
+                }
+            }
+            break;
+        }
+
+        idx = if info.has_unique_pieces {
+            // The kings and a unique piece are indexed together
+            let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+            let adjust1 = (s1 > s0) as usize;
+            let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+            if off_a1h8(s0) != 0 {
+                (tables.map_a1d1d4[s0] * 63 + (s1 - adjust1) as u64) * 62 + (s2 - adjust2) as u64
+            } else if off_a1h8(s1) != 0 {
+                (6 * 63 + (s0 / 8) as u64 * 28 + tables.map_b1h1h7[s1]) * 62 + (s2 - adjust2) as u64
+            } else if off_a1h8(s2) != 0 {
+                6 * 63 * 62
+                    + 4 * 28 * 62
+                    + (s0 / 8) as u64 * 7 * 28
+                    + (s1 / 8 - adjust1) as u64 * 28
+                    + tables.map_b1h1h7[s2]
+            } else {
+                6 * 63 * 62
+                    + 4 * 28 * 62
+                    + 4 * 7 * 28
+                    + (s0 / 8) as u64 * 7 * 6
+                    + (s1 / 8 - adjust1) as u64 * 6
+                    + (s2 / 8 - adjust2) as u64
+            }
+        } else {
+            tables.map_kk[tables.map_a1d1d4[squares[0]] as usize][squares[1]]
+        };
+    }
+
+    // The other groups are indexed as combinations of the squares left free by the
+    // previous groups (non-leading pawns only use the 48 pawn squares)
+    idx *= data.group_idx[0];
+    let mut group_start = data.group_len[0];
+    let mut remaining_pawns = info.has_pawns && info.pawn_count[1] > 0;
+    let mut next = 1;
+    while data.group_len[next] != 0 {
+        let group_end = group_start + data.group_len[next];
+        squares[group_start..group_end].sort_unstable();
+        let mut n = 0;
+        for i in group_start..group_end {
+            let adjust = squares[..group_start]
+                .iter()
+                .filter(|square| **square < squares[i])
+                .count();
+            let free_square = squares[i]
+                .checked_sub(adjust)?
+                .checked_sub(if remaining_pawns { 8 } else { 0 })?;
+            n += tables.binomial[i - group_start + 1][free_square];
+        }
+        remaining_pawns = false;
+        idx += n * data.group_idx[next];
+        group_start = group_end;
+        next += 1;
+    }
+
+    Some(idx)
+}
@@ -0,0 +1,838 @@
+#![allow(dead_code)]
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::table::{
+    DTZ_MAGIC, HAS_PAWNS_FLAG, SINGLE_VALUE_FLAG, SPLIT_FLAG, WDL_MAGIC, WIN_PLIES_FLAG,
+};
+
+/*****************
+* ENDGAME SOLVER
+******************/
+
+// The tests of the tablebase module need table files, which are generated here : endgames of
+// a king and a piece or a pawn against a king are solved by retrograde analysis, and written
+// in the Syzygy format. The solver and the indexing of the tables work on raw
+// squares, independently of the rest of the engine and of the prober, so that the tests
+// catch the bugs of the prober instead of reproducing them.
+
+// In this file, squares are indexed from 0 (a1) to 63 (h8) in row-major ordering, and
+// positions by side to move (white, then black), white king, white piece and black king.
+
+/// Number of positions of an endgame with three pieces, for both sides to move
+pub const POSITIONS: usize = 2 * 64 * 64 * 64;
+
+/// Outcome of a position of the endgame
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Illegal,
+    Draw,
+    /// White wins : number of plies before the next zeroing move (the mate of black, or a
+    /// move of the pawn)
+    Win(u8),
+}
+
+/// Outcome of all the positions of an endgame of a king and a piece or a pawn against a king
+pub struct EndgameSolution {
+    /// 'Q', 'R', 'B', 'N' or 'P'
+    pub piece: char,
+    pub outcomes: Vec<Outcome>,
+}
+
+/// Decompose a position index into the side to move (true for white) and its squares
+pub fn get_position(index: usize) -> (bool, usize, usize, usize) {
+    (
+        index < POSITIONS / 2,
+        index / 4096 % 64,
+        index / 64 % 64,
+        index % 64,
+    )
+}
+
+fn get_position_index(
+    white_to_move: bool,
+    white_king: usize,
+    piece: usize,
+    black_king: usize,
+) -> usize {
+    (!white_to_move as usize) * POSITIONS / 2 + white_king * 4096 + piece * 64 + black_king
+}
+
+fn are_adjacent(square_a: usize, square_b: usize) -> bool {
+    (square_a / 8).abs_diff(square_b / 8) <= 1 && (square_a % 8).abs_diff(square_b % 8) <= 1
+}
+
+fn get_directions(piece: char) -> &'static [(i32, i32)] {
+    const QUEEN: [(i32, i32); 8] = [
+        (1, 0),
+        (-1, 0),
+        (0, 1),
+        (0, -1),
+        (1, 1),
+        (1, -1),
+        (-1, 1),
+        (-1, -1),
+    ];
+    const KNIGHT: [(i32, i32); 8] = [
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ];
+    match piece {
+        'Q' => &QUEEN,
+        'R' => &QUEEN[..4],
+        'B' => &QUEEN[4..],
+        'N' => &KNIGHT,
+        _ => &[],
+    }
+}
+
+/// Call f on each square reached by a piece, stopping sliders before the blockers
+fn for_each_piece_target<F: FnMut(usize)>(piece: char, from: usize, blockers: &[usize], mut f: F) {
+    for (df, dr) in get_directions(piece) {
+        let (mut file, mut rank) = ((from % 8) as i32 + df, (from / 8) as i32 + dr);
+        while (0..8).contains(&file) && (0..8).contains(&rank) {
+            let square = (rank * 8 + file) as usize;
+            if blockers.contains(&square) {
+                break;
+            }
+            f(square);
+            if piece == 'N' {
+                break;
+            }
+            file += df;
+            rank += dr;
+        }
+    }
+}
+
+/// Check whether the white piece attacks the target square. Sliders can be blocked by the
+/// blocker.
+fn is_attacked(piece: char, from: usize, target: usize, blocker: usize) -> bool {
+    let (df, dr) = (
+        (target % 8) as i32 - (from % 8) as i32,
+        (target / 8) as i32 - (from / 8) as i32,
+    );
+    match piece {
+        'P' => return dr == 1 && df.abs() == 1,
+        'N' => return df.abs() * dr.abs() == 2,
+        _ => (),
+    }
+    let aligned = ((df == 0) != (dr == 0) && piece != 'B')
+        || (df.abs() == dr.abs() && df != 0 && piece != 'R');
+    if !aligned {
+        return false;
+    }
+    let step = (dr.signum() * 8 + df.signum()) as isize;
+    let mut square = from as isize + step;
+    while square != target as isize {
+        if square == blocker as isize {
+            return false;
+        }
+        square += step;
+    }
+    true
+}
+
+fn get_king_targets(from: usize) -> impl Iterator<Item = usize> {
+    let (file, rank) = ((from % 8) as i32, (from / 8) as i32);
+    [
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, -1),
+        (0, 1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+    ]
+    .into_iter()
+    .map(move |(df, dr)| (file + df, rank + dr))
+    .filter(|(file, rank)| (0..8).contains(file) && (0..8).contains(rank))
+    .map(|(file, rank)| (rank * 8 + file) as usize)
+}
+
+fn is_legal(
+    white_to_move: bool,
+    white_king: usize,
+    piece: usize,
+    black_king: usize,
+    kind: char,
+) -> bool {
+    white_king != piece
+        && piece != black_king
+        && !are_adjacent(white_king, black_king)
+        && (kind != 'P' || (8..56).contains(&piece))
+        && !(white_to_move && is_attacked(kind, piece, black_king, white_king))
+}
+
+/// Moves of the endgame which don't zero the fifty-move counter (the moves of the kings and
+/// of the piece), from each position and in reverse
+struct MoveGraph {
+    piece: char,
+    successors_start: Vec<usize>,
+    successors: Vec<usize>,
+    predecessors_start: Vec<usize>,
+    predecessors: Vec<usize>,
+    /// Number of successors of each position not known to be won by white yet
+    remaining: Vec<usize>,
+    /// Black positions where the white piece can be captured, which can always escape to a
+    /// draw
+    can_escape: Vec<bool>,
+}
+
+impl MoveGraph {
+    fn new(piece: char) -> MoveGraph {
+        let mut successors = Vec::new();
+        let mut successors_start = vec![0usize; POSITIONS + 1];
+        let mut can_escape = vec![false; POSITIONS];
+        for index in 0..POSITIONS {
+            successors_start[index] = successors.len();
+            let (white_to_move, white_king, square, black_king) = get_position(index);
+            if !is_legal(white_to_move, white_king, square, black_king, piece) {
+                continue;
+            }
+
+            if white_to_move {
+                for target in get_king_targets(white_king) {
+                    if target != square && !are_adjacent(target, black_king) {
+                        successors.push(get_position_index(false, target, square, black_king));
+                    }
+                }
+                for_each_piece_target(piece, square, &[white_king, black_king], |target| {
+                    successors.push(get_position_index(false, white_king, target, black_king))
+                });
+            } else {
+                for target in get_king_targets(black_king) {
+                    if are_adjacent(target, white_king) {
+                        continue;
+                    }
+                    if target == square {
+                        can_escape[index] = true;
+                    } else if !is_attacked(piece, square, target, white_king) {
+                        successors.push(get_position_index(true, white_king, square, target));
+                    }
+                }
+            }
+        }
+        successors_start[POSITIONS] = successors.len();
+
+        let mut predecessors_start = vec![0usize; POSITIONS + 1];
+        for successor in &successors {
+            predecessors_start[successor + 1] += 1;
+        }
+        for index in 0..POSITIONS {
+            predecessors_start[index + 1] += predecessors_start[index];
+        }
+        let mut predecessors = vec![0; successors.len()];
+        let mut next = predecessors_start.clone();
+        for index in 0..POSITIONS {
+            for successor in &successors[successors_start[index]..successors_start[index + 1]] {
+                predecessors[next[*successor]] = index;
+                next[*successor] += 1;
+            }
+        }
+
+        let remaining = (0..POSITIONS)
+            .map(|index| successors_start[index + 1] - successors_start[index])
+            .collect();
+        MoveGraph {
+            piece,
+            successors_start,
+            successors,
+            predecessors_start,
+            predecessors,
+            remaining,
+            can_escape,
+        }
+    }
+
+    /// Check whether black is mated in a legal position. Must be called before the
+    /// propagation reaches the position.
+    fn is_mate(&self, index: usize) -> bool {
+        let (white_to_move, white_king, square, black_king) = get_position(index);
+        !white_to_move
+            && is_legal(white_to_move, white_king, square, black_king, self.piece)
+            && self.remaining[index] == 0
+            && !self.can_escape[index]
+            && is_attacked(self.piece, square, black_king, white_king)
+    }
+
+    /// Propagate the wins backwards from the positions of the queue, sorted by number of
+    /// plies : a white position is won as soon as one of its successors is lost, a black
+    /// position is lost once all its successors are won
+    fn propagate(&mut self, outcomes: &mut [Outcome], mut queue: VecDeque<usize>) {
+        while let Some(index) = queue.pop_front() {
+            let Outcome::Win(plies) = outcomes[index] else {
+                continue;
+            };
+            for &predecessor in &self.predecessors
+                [self.predecessors_start[index]..self.predecessors_start[index + 1]]
+            {
+                if outcomes[predecessor] != Outcome::Draw {
+                    continue;
+                }
+                let is_white = predecessor < POSITIONS / 2;
+                if !is_white {
+                    self.remaining[predecessor] -= 1;
+                }
+                if is_white || (self.remaining[predecessor] == 0 && !self.can_escape[predecessor]) {
+                    outcomes[predecessor] = Outcome::Win(plies + 1);
+                    queue.push_back(predecessor);
+                }
+            }
+        }
+    }
+}
+
+impl EndgameSolution {
+    /// Legal positions of the endgame, all drawn
+    fn get_drawn_solution(piece: char) -> EndgameSolution {
+        let outcomes = (0..POSITIONS)
+            .map(|index| {
+                let (white_to_move, white_king, square, black_king) = get_position(index);
+                if is_legal(white_to_move, white_king, square, black_king, piece) {
+                    Outcome::Draw
+                } else {
+                    Outcome::Illegal
+                }
+            })
+            .collect();
+        EndgameSolution { piece, outcomes }
+    }
+
+    /// Solve the endgame of a king and the given piece ('Q', 'R', 'B' or 'N') against a king
+    pub fn new(piece: char) -> EndgameSolution {
+        let mut solution = EndgameSolution::get_drawn_solution(piece);
+        let mut graph = MoveGraph::new(piece);
+        let mut mates = VecDeque::new();
+        for index in (POSITIONS / 2..POSITIONS).filter(|index| graph.is_mate(*index)) {
+            solution.outcomes[index] = Outcome::Win(0);
+            mates.push_back(index);
+        }
+        graph.propagate(&mut solution.outcomes, mates);
+        solution
+    }
+
+    /// Solve the endgame of a king and a pawn against a king. Promotions lead to the given
+    /// solutions of the queen and rook endgames (minor pieces can't win).
+    pub fn new_pawn(queen: &EndgameSolution, rook: &EndgameSolution) -> EndgameSolution {
+        let mut solution = EndgameSolution::get_drawn_solution('P');
+        let mut graph = MoveGraph::new('P');
+
+        // Pawn moves lead to positions with the pawn further up, which are solved first. The
+        // distances start again from the positions where a pawn move wins.
+        for rank in (1..7).rev() {
+            let mut queue = VecDeque::new();
+            for index in 0..POSITIONS {
+                let (white_to_move, white_king, square, black_king) = get_position(index);
+                if square / 8 != rank || solution.outcomes[index] == Outcome::Illegal {
+                    continue;
+                }
+                if graph.is_mate(index) {
+                    solution.outcomes[index] = Outcome::Win(0);
+                    queue.push_front(index);
+                } else if white_to_move
+                    && solution.has_winning_pawn_move([queen, rook], white_king, square, black_king)
+                {
+                    solution.outcomes[index] = Outcome::Win(1);
+                    queue.push_back(index);
+                }
+            }
+            graph.propagate(&mut solution.outcomes, queue);
+        }
+        solution
+    }
+
+    /// Check whether white wins by moving the pawn, once the positions with the pawn further
+    /// up are solved
+    fn has_winning_pawn_move(
+        &self,
+        promotions: [&EndgameSolution; 2],
+        white_king: usize,
+        pawn: usize,
+        black_king: usize,
+    ) -> bool {
+        let is_free = |square: usize| square != white_king && square != black_king;
+        let is_lost = |solution: &EndgameSolution, square: usize| {
+            let index = get_position_index(false, white_king, square, black_king);
+            matches!(solution.outcomes[index], Outcome::Win(_))
+        };
+
+        let push = pawn + 8;
+        if !is_free(push) {
+            return false;
+        }
+        if push >= 56 {
+            return promotions.iter().any(|solution| is_lost(solution, push));
+        }
+        is_lost(self, push) || (pawn < 16 && is_free(push + 8) && is_lost(self, push + 8))
+    }
+
+    /// Name of the table of the endgame
+    pub fn get_name(&self) -> String {
+        format!("K{}vK", self.piece)
+    }
+
+    /// FEN of a position
+    pub fn get_fen(&self, index: usize) -> String {
+        let (white_to_move, white_king, square, black_king) = get_position(index);
+        let mut board = ['1'; 64];
+        board[white_king] = 'K';
+        board[square] = self.piece;
+        board[black_king] = 'k';
+
+        let ranks: Vec<String> = (0..8)
+            .rev()
+            .map(|rank| board[rank * 8..rank * 8 + 8].iter().collect())
+            .collect();
+        let side = if white_to_move { 'w' } else { 'b' };
+        format!("{} {} - - 0 1", ranks.join("/"), side)
+    }
+
+    /// Write the WDL and DTZ tables of the endgame in the directory
+    pub fn write_tables(&self, directory: &Path) -> io::Result<()> {
+        // Pieces of the table in the order they are indexed (with the codes of the table
+        // files), and position of the index of the first group among the groups. The pawn
+        // table uses another order than the natural one, which the prober must follow.
+        let has_pawns = self.piece == 'P';
+        let (pieces, order, files, size) = match self.piece {
+            'Q' => ([6, 5, 14], 0, 1, PIECES_TABLE_SIZE),
+            'R' => ([6, 4, 14], 0, 1, PIECES_TABLE_SIZE),
+            'B' => ([6, 3, 14], 0, 1, PIECES_TABLE_SIZE),
+            'N' => ([6, 2, 14], 0, 1, PIECES_TABLE_SIZE),
+            _ => ([1, 14, 6], 1, 4, PAWN_TABLE_SIZE),
+        };
+
+        // Positions equivalent by symmetry share the same index. Indices of illegal
+        // positions are left to 0. WDL sub-tables are indexed by file * 2 + side to move.
+        let mut wdl = vec![vec![None; size]; 2 * files];
+        let mut dtz = vec![vec![0; size]; files];
+        for (index, outcome) in self.outcomes.iter().enumerate() {
+            let (white_to_move, white_king, square, black_king) = get_position(index);
+            let side = !white_to_move as usize;
+            let (wdl_value, dtz_value) = match (outcome, white_to_move) {
+                (Outcome::Illegal, _) => continue,
+                (Outcome::Draw, _) => (2, 0),
+                (Outcome::Win(plies), true) => (4, *plies as u16 - 1),
+                (Outcome::Win(_), false) => (0, 0),
+            };
+            let (file, idx) = if has_pawns {
+                let squares = get_canonical_squares([square, black_king, white_king], true);
+                (squares[0] % 8, get_pawn_index(squares, order))
+            } else {
+                let squares = get_canonical_squares([white_king, square, black_king], false);
+                (0, get_pieces_index(squares))
+            };
+            assert!(
+                wdl[file * 2 + side][idx].is_none_or(|value| value == wdl_value),
+                "Symmetric positions with different outcomes"
+            );
+            wdl[file * 2 + side][idx] = Some(wdl_value);
+            if white_to_move {
+                dtz[file][idx] = dtz_value;
+            }
+        }
+
+        let name = self.get_name();
+        let flags = if has_pawns {
+            SPLIT_FLAG | HAS_PAWNS_FLAG
+        } else {
+            SPLIT_FLAG
+        };
+        let wdl: Vec<(u8, Vec<u16>)> = wdl
+            .into_iter()
+            .map(|values| {
+                (
+                    0,
+                    values.into_iter().map(|value| value.unwrap_or(0)).collect(),
+                )
+            })
+            .collect();
+        write_table(
+            &directory.join(format!("{}.rtbw", name)),
+            WDL_MAGIC,
+            flags,
+            &pieces,
+            order as u8,
+            &wdl,
+        )?;
+        // Only white to move is stored in the DTZ table, with distances in plies
+        let dtz: Vec<(u8, Vec<u16>)> = dtz
+            .into_iter()
+            .map(|values| (WIN_PLIES_FLAG, values))
+            .collect();
+        write_table(
+            &directory.join(format!("{}.rtbz", name)),
+            DTZ_MAGIC,
+            flags,
+            &pieces,
+            order as u8,
+            &dtz,
+        )
+    }
+}
+
+/*********
+* INDEXING
+**********/
+
+// Tables are indexed as described by the Syzygy format. Positions are first brought to a
+// canonical form with the symmetries of the board : the first piece (the white king, or the
+// pawn) on the a-d files, and for pawnless tables on ranks 1-4, with the first piece off the
+// a1-h8 diagonal under it. The three pieces of pawnless tables are then indexed together,
+// depending on how many of them are on the diagonal. The pawn is indexed by its rank in the
+// sub-table of its file, and the kings by their square among the squares left free.
+
+/// Squares of the a1-d1-d4 triangle, in the order of their index : under the diagonal first
+const TRIANGLE: [usize; 10] = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+
+/// Number of positions of each sub-table of a pawnless table with three different pieces
+pub const PIECES_TABLE_SIZE: usize = 6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + 4 * 7 * 6;
+/// Number of positions of each sub-table of a table with a pawn and two kings
+pub const PAWN_TABLE_SIZE: usize = 6 * 63 * 62;
+
+fn is_on_diagonal(square: usize) -> bool {
+    square / 8 == square % 8
+}
+
+/// Index of a square among the 28 squares under the a1-h8 diagonal
+fn get_under_diagonal_index(square: usize) -> usize {
+    (0..square).filter(|square| square / 8 < square % 8).count()
+}
+
+/// Bring the squares of a position (in the order of the table) to their canonical form
+pub fn get_canonical_squares(squares: [usize; 3], has_pawns: bool) -> [usize; 3] {
+    let mut squares = squares;
+    if squares[0] % 8 > 3 {
+        squares = squares.map(|square| square ^ 7);
+    }
+    if has_pawns {
+        return squares;
+    }
+    if squares[0] / 8 > 3 {
+        squares = squares.map(|square| square ^ 56);
+    }
+    let first_off_diagonal = squares.iter().find(|square| !is_on_diagonal(**square));
+    if first_off_diagonal.is_some_and(|square| square / 8 > square % 8) {
+        squares = squares.map(|square| square % 8 * 8 + square / 8);
+    }
+    squares
+}
+
+/// Index of a canonical position of a pawnless table with three different pieces
+pub fn get_pieces_index(squares: [usize; 3]) -> usize {
+    let [first, second, third] = squares;
+    let triangle = TRIANGLE.iter().position(|square| *square == first).unwrap();
+    // Squares and ranks on the diagonal are counted without the ones of the previous pieces
+    let (second_below, third_below) = (
+        (second > first) as usize,
+        (third > first) as usize + (third > second) as usize,
+    );
+
+    if !is_on_diagonal(first) {
+        (triangle * 63 + second - second_below) * 62 + third - third_below
+    } else if !is_on_diagonal(second) {
+        6 * 63 * 62 + (first / 8 * 28 + get_under_diagonal_index(second)) * 62 + third - third_below
+    } else if !is_on_diagonal(third) {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + (first / 8 * 7 + second / 8 - second_below) * 28
+            + get_under_diagonal_index(third)
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + (first / 8 * 7 + second / 8 - second_below) * 6
+            + third / 8
+            - third_below
+    }
+}
+
+/// Index of a canonical position of a table with a pawn (first) and two kings, in the
+/// sub-table of the file of the pawn. The index of the pawn is the order-th digit of the
+/// index, the kings come after it.
+pub fn get_pawn_index(squares: [usize; 3], order: usize) -> usize {
+    let [pawn, first, second] = squares;
+    let digits = [
+        (pawn / 8 - 1, 6),
+        (first - (first > pawn) as usize, 63),
+        (
+            second - (second > pawn) as usize - (second > first) as usize,
+            62,
+        ),
+    ];
+    let mut groups = vec![1, 2];
+    groups.insert(order, 0);
+
+    let (mut idx, mut multiplier) = (0, 1);
+    for group in groups {
+        let (digit, base) = digits[group];
+        idx += digit * multiplier;
+        multiplier *= base;
+    }
+    idx
+}
+
+/*************
+* TABLE WRITER
+**************/
+
+/// Size of the compressed blocks (as a power of two). Small blocks make the tests go
+/// through the sparse index.
+const BLOCK_SIZE_LOG: u8 = 6;
+/// Number of values between two entries of the sparse index (as a power of two)
+const SPAN_LOG: u8 = 6;
+/// Maximum number of values of a symbol
+const MAX_SYMBOL_VALUES: usize = 256;
+/// Symbols are stored on 12 bits, and 0xFFF marks values in the symbol tree
+const MAX_SYMBOLS: usize = 0xFFF;
+/// Number of pairs created by Re-Pair. The real tables use as many as they can, but a few
+/// are enough for the tests and keep the compression fast.
+const MAX_PAIRS: usize = 64;
+
+/// Compressed sub-table
+struct CompressedTable {
+    /// Compression parameters, as read by the prober
+    sizes: Vec<u8>,
+    sparse_index: Vec<u8>,
+    block_length: Vec<u8>,
+    data: Vec<u8>,
+}
+
+/// Write a table file, with one sub-table per file of the pawn (once for pawnless tables)
+/// and side to move stored (each with its flags and its values). Both sides use the same
+/// pieces order, and the same position of the first group among the groups.
+fn write_table(
+    path: &Path,
+    magic: [u8; 4],
+    flags: u8,
+    pieces: &[u8],
+    order: u8,
+    sub_tables: &[(u8, Vec<u16>)],
+) -> io::Result<()> {
+    let mut bytes = magic.to_vec();
+    bytes.push(flags);
+    let files = if flags & HAS_PAWNS_FLAG != 0 { 4 } else { 1 };
+    for _ in 0..files {
+        bytes.push(order | order << 4);
+        bytes.extend(pieces.iter().map(|piece| piece | piece << 4));
+    }
+    if bytes.len() % 2 == 1 {
+        bytes.push(0);
+    }
+
+    let tables: Vec<CompressedTable> = sub_tables
+        .iter()
+        .map(|(flags, values)| compress(*flags, values))
+        .collect();
+    for table in &tables {
+        bytes.extend(&table.sizes);
+    }
+    for table in &tables {
+        bytes.extend(&table.sparse_index);
+    }
+    for table in &tables {
+        bytes.extend(&table.block_length);
+    }
+    for table in &tables {
+        bytes.resize(bytes.len().div_ceil(64) * 64, 0);
+        bytes.extend(&table.data);
+    }
+    bytes.resize(bytes.len().div_ceil(64) * 64 + 16, 0);
+
+    fs::write(path, bytes)
+}
+
+/// Compress values with Re-Pair and canonical Huffman codes
+fn compress(flags: u8, values: &[u16]) -> CompressedTable {
+    if values.iter().all(|value| *value == values[0]) {
+        return CompressedTable {
+            sizes: vec![flags | SINGLE_VALUE_FLAG, values[0] as u8],
+            sparse_index: Vec::new(),
+            block_length: Vec::new(),
+            data: Vec::new(),
+        };
+    }
+
+    // Re-Pair : the most frequent pair of adjacent symbols is replaced by a new symbol,
+    // until no pair is frequent enough. Values are the first symbols.
+    let mut pairs: Vec<Option<(usize, usize)>> =
+        vec![None; *values.iter().max().unwrap() as usize + 1];
+    let mut symbol_values: Vec<usize> = vec![1; pairs.len()];
+    let mut sequence: Vec<usize> = values.iter().map(|value| *value as usize).collect();
+    let values_count = pairs.len();
+    while pairs.len() < (values_count + MAX_PAIRS).min(MAX_SYMBOLS) {
+        let mut counts: HashMap<(usize, usize), usize> = HashMap::new();
+        for pair in sequence.windows(2) {
+            if symbol_values[pair[0]] + symbol_values[pair[1]] <= MAX_SYMBOL_VALUES {
+                *counts.entry((pair[0], pair[1])).or_default() += 1;
+            }
+        }
+        let Some((pair, count)) = counts
+            .into_iter()
+            .max_by_key(|(pair, count)| (*count, Reverse(*pair)))
+        else {
+            break;
+        };
+        if count < 4 {
+            break;
+        }
+
+        let symbol = pairs.len();
+        pairs.push(Some(pair));
+        symbol_values.push(symbol_values[pair.0] + symbol_values[pair.1]);
+        let mut replaced = Vec::with_capacity(sequence.len());
+        let mut i = 0;
+        while i < sequence.len() {
+            if i + 1 < sequence.len() && (sequence[i], sequence[i + 1]) == pair {
+                replaced.push(symbol);
+                i += 2;
+            } else {
+                replaced.push(sequence[i]);
+                i += 1;
+            }
+        }
+        sequence = replaced;
+    }
+
+    // Huffman code lengths of the symbols of the sequence
+    let mut frequencies = vec![0usize; pairs.len()];
+    for symbol in &sequence {
+        frequencies[*symbol] += 1;
+    }
+    let mut code_lengths = vec![0usize; pairs.len()];
+    let mut heap = BinaryHeap::new();
+    let mut children: Vec<Vec<usize>> = Vec::new();
+    for (symbol, frequency) in frequencies.iter().enumerate().filter(|(_, f)| **f > 0) {
+        heap.push(Reverse((*frequency, children.len())));
+        children.push(vec![symbol]);
+    }
+    while heap.len() > 1 {
+        let Reverse((frequency_a, node_a)) = heap.pop().unwrap();
+        let Reverse((frequency_b, node_b)) = heap.pop().unwrap();
+        let mut symbols = children[node_a].clone();
+        symbols.extend(&children[node_b]);
+        for symbol in &symbols {
+            code_lengths[*symbol] += 1;
+        }
+        heap.push(Reverse((frequency_a + frequency_b, children.len())));
+        children.push(symbols);
+    }
+    for (symbol, length) in code_lengths.iter_mut().enumerate() {
+        if frequencies[symbol] > 0 && *length == 0 {
+            *length = 1;
+        }
+    }
+
+    // Symbols are renumbered : coded symbols first, from the longest codes to the shortest,
+    // then the symbols only used in pairs
+    let mut order: Vec<usize> = (0..pairs.len()).collect();
+    order.sort_by_key(|symbol| (code_lengths[*symbol] == 0, Reverse(code_lengths[*symbol])));
+    let mut ids = vec![0; pairs.len()];
+    for (id, symbol) in order.iter().enumerate() {
+        ids[*symbol] = id;
+    }
+    let max_len = *code_lengths.iter().max().unwrap();
+    let min_len = *code_lengths
+        .iter()
+        .filter(|length| **length > 0)
+        .min()
+        .unwrap();
+    let mut counts = vec![0; max_len + 1];
+    for length in code_lengths.iter().filter(|length| **length > 0) {
+        counts[*length] += 1;
+    }
+    let mut lowest_sym = vec![0; max_len + 2];
+    let mut base = vec![0u64; max_len + 2];
+    for length in (min_len..max_len).rev() {
+        lowest_sym[length] = lowest_sym[length + 1] + counts[length + 1];
+        base[length] = (base[length + 1] + counts[length + 1] as u64) / 2;
+    }
+
+    let mut sizes = vec![
+        flags,
+        BLOCK_SIZE_LOG,
+        SPAN_LOG,
+        1, // Padding block, for the sparse index entries past the last value
+    ];
+    let mut btree = Vec::new();
+    for symbol in order {
+        let (left, right) = match pairs[symbol] {
+            Some((left, right)) => (ids[left], ids[right]),
+            None => (symbol, 0xFFF),
+        };
+        btree.extend([
+            left as u8,
+            (left >> 8 | (right & 0xF) << 4) as u8,
+            (right >> 4) as u8,
+        ]);
+    }
+
+    // Blocks are filled with whole symbols
+    let block_bits = 8 << BLOCK_SIZE_LOG;
+    let mut blocks: Vec<(Vec<u8>, usize)> = Vec::new();
+    let (mut block, mut bits, mut block_values) = (vec![0u8; block_bits / 8], 0, 0);
+    for symbol in &sequence {
+        let length = code_lengths[*symbol];
+        let id = ids[*symbol];
+        if bits + length > block_bits || block_values + symbol_values[*symbol] > 65536 {
+            blocks.push((block, block_values));
+            (block, bits, block_values) = (vec![0u8; block_bits / 8], 0, 0);
+        }
+        let code = base[length] + (id - lowest_sym[length]) as u64;
+        for bit in (0..length).rev() {
+            if code >> bit & 1 != 0 {
+                block[bits / 8] |= 0x80 >> (bits % 8);
+            }
+            bits += 1;
+        }
+        block_values += symbol_values[*symbol];
+    }
+    blocks.push((block, block_values));
+
+    sizes.extend((blocks.len() as u32).to_le_bytes());
+    sizes.extend([max_len as u8, min_len as u8]);
+    for lowest in &lowest_sym[min_len..=max_len] {
+        sizes.extend((*lowest as u16).to_le_bytes());
+    }
+    sizes.extend((pairs.len() as u16).to_le_bytes());
+    sizes.extend(btree);
+    if pairs.len() % 2 == 1 {
+        sizes.push(0);
+    }
+
+    // The sparse index points to the value at the middle of each span
+    let span = 1 << SPAN_LOG;
+    let mut sparse_index = Vec::new();
+    let (mut block, mut block_start) = (0, 0);
+    for middle in (0..values.len().div_ceil(span)).map(|k| k * span + span / 2) {
+        while block < blocks.len() && middle >= block_start + blocks[block].1 {
+            block_start += blocks[block].1;
+            block += 1;
+        }
+        sparse_index.extend((block as u32).to_le_bytes());
+        sparse_index.extend(((middle - block_start) as u16).to_le_bytes());
+    }
+    let mut block_length = Vec::new();
+    for (_, block_values) in &blocks {
+        block_length.extend((*block_values as u16 - 1).to_le_bytes());
+    }
+    block_length.extend(u16::MAX.to_le_bytes());
+
+    CompressedTable {
+        sizes,
+        sparse_index,
+        block_length,
+        data: blocks.into_iter().flat_map(|(block, _)| block).collect(),
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * The tuning module contains the tools used to fit the weights of the handcrafted
+ * evaluation to game results, with Texel's tuning method.
+ *
+ * It is run with "krabnik tune <dataset>", which loads a set of positions labeled with the
+ * result of their game, resolves them to quiet positions with the quiescence search, fits
+ * the scaling constant K of the sigmoid, then optimizes the weights (gradient descent or
+ * local search) and writes them as Rust source, to be pasted in the evaluation module.
+ */
+
+pub mod texel;
+
+pub use texel::*;
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_texel_tuning() {
+    use crate::board_representation::*;
+    use crate::evaluation::*;
+
+    // Dataset formats
+    let dataset = "\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1 0-1
+4k3/pppp4/8/8/8/8/PPPPP3/4K3 w - - 0 1 [1.0]
+
+4k3/pppp4/8/8/8/8/PPPP4/4K3 b - - 0 40 \"1/2-1/2\"
+4k3/ppppp3/8/8/8/8/PPPP4/4K3 w - - c9 \"0-1\";
+4k3/pppp4/8/8/8/8/PPPPP3/4K3 b - - 0 1 | 95 | 1.0
+";
+    let positions = read_dataset(dataset).unwrap();
+    assert!(positions.len() == 5, "Failed at assert 0");
+    let results: Vec<f64> = positions.iter().map(|position| position.result).collect();
+    assert!(results == [0.0, 1.0, 0.5, 0.0, 1.0], "Failed at assert 1");
+    assert!(
+        positions[2].position.full_move_number == 40,
+        "Failed at assert 2"
+    );
+    assert!(
+        matches!(
+            read_dataset("8/8/8/8/8/8/8/8 w - - 0 1 2-0"),
+            Err(TuningError::InvalidRecord(1, _))
+        ),
+        "Failed at assert 3"
+    );
+
+    // The coefficients reproduce the handcrafted evaluation
+    let weights: Vec<f64> = DEFAULT_PARAMETERS
+        .to_vector()
+        .iter()
+        .map(|weight| *weight as f64)
+        .collect();
+    for labeled_position in &positions {
+        let position = &labeled_position.position;
+        let coefficients = get_parameter_coefficients(position).unwrap();
+        let evaluation: f64 = coefficients
+            .iter()
+            .map(|(index, coefficient)| coefficient * weights[*index])
+            .sum();
+        let expected = match position.current_turn {
+            Player::White => evaluate_handcrafted(position),
+            Player::Black => -evaluate_handcrafted(position),
+        };
+        assert!(
+            (evaluation - expected as f64).abs() <= 1.0,
+            "Failed at assert 4"
+        );
+    }
+
+    // Parameter vector round trip
+    let vector = DEFAULT_PARAMETERS.to_vector();
+    assert!(vector.len() == PARAMETER_COUNT, "Failed at assert 5");
+    assert!(
+        EvaluationParameters::from_vector(&vector) == DEFAULT_PARAMETERS,
+        "Failed at assert 6"
+    );
+    assert!(
+        EvaluationParameters::default()
+            .to_rust_source()
+            .starts_with("pub static DEFAULT_PARAMETERS: EvaluationParameters"),
+        "Failed at assert 7"
+    );
+
+    // Both methods lower the error
+    let mut tuner = Tuner::new(&positions, true, 2);
+    let k = tuner.fit_k(&weights);
+    assert!(k > 0.0 && k < 10.0, "Failed at assert 8");
+    let initial_error = tuner.error(&weights, k);
+
+    let mut tuned_weights = weights.clone();
+    tuner.tune_gradient_descent(&mut tuned_weights, 20, 1.0, |_, _| {});
+    assert!(
+        tuner.error(&tuned_weights, k) < initial_error,
+        "Failed at assert 9"
+    );
+
+    let mut tuned_weights = weights.clone();
+    tuner.tune_local_search(&mut tuned_weights, 2, |_, _| {});
+    assert!(
+        tuner.error(&tuned_weights, k) < initial_error,
+        "Failed at assert 10"
+    );
+
+    // Command line arguments
+    let args: Vec<String> = ["data.txt", "--method", "local", "--iterations", "5"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let options = TuningOptions::from_args(&args).unwrap();
+    assert!(options.dataset == "data.txt", "Failed at assert 11");
+    assert!(
+        options.method == TuningMethod::LocalSearch,
+        "Failed at assert 12"
+    );
+    assert!(options.iterations == 5, "Failed at assert 13");
+    assert!(
+        TuningOptions::from_args(&["--threads".to_string()]).is_err(),
+        "Failed at assert 14"
+    );
+}
@@ -0,0 +1,532 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::fs;
+use std::thread;
+
+use crate::board_representation::*;
+use crate::datagen::*;
+use crate::evaluation::*;
+use crate::pgn::*;
+use crate::search::*;
+
+/**************
+* TEXEL TUNING
+***************/
+
+// Texel's tuning method fits the evaluation weights to game results : the evaluation of each
+// position (from White's point of view) is mapped to an expected score with a sigmoid,
+// and the mean squared error with the actual results is minimized.
+// The handcrafted evaluation is linear in its weights, so each position is reduced to the
+// coefficients of the weights in its evaluation, which makes both the error and its gradient
+// cheap to compute.
+// See : <https://www.chessprogramming.org/Texel%27s_Tuning_Method>
+//
+// Datasets contain one position per line, either as a FEN followed by the result ("1-0",
+// "0-1", "1/2-1/2", or a score such as "[0.5]"), or as an EPD record with the result in
+// the c9 operation (c9 "1-0";), or as a record of the datagen text format.
+
+/// Errors that can occur while running the tuner
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TuningError {
+    /// A file can't be read or written
+    Io(String),
+    /// The command line arguments are invalid
+    InvalidArgument(String),
+    /// A line of the dataset (starting at 1) can't be parsed
+    InvalidRecord(usize, String),
+    /// The dataset doesn't contain any position whose evaluation depends on the weights
+    EmptyDataset,
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TuningError::Io(error) => write!(f, "{}", error),
+            TuningError::InvalidArgument(argument) => write!(f, "invalid argument: {}", argument),
+            TuningError::InvalidRecord(line, record) => {
+                write!(f, "line {}: invalid record: {}", line, record)
+            }
+            TuningError::EmptyDataset => write!(f, "no usable position in the dataset"),
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
+
+/// A position, along with the result of the game it was taken from (1 for a White win,
+/// 0.5 for a draw and 0 for a Black win)
+#[derive(Clone, Debug)]
+pub struct LabeledPosition {
+    pub position: Position,
+    pub result: f64,
+}
+
+/// Optimization algorithm used by the tuner
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TuningMethod {
+    /// Full-batch gradient descent with the Adam optimizer
+    GradientDescent,
+    /// Texel's original local search, changing weights one unit at a time
+    LocalSearch,
+}
+
+/// Settings of the tune command
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuningOptions {
+    pub dataset: String,
+    /// Path of the Rust source file the tuned weights are written to
+    pub output: String,
+    pub method: TuningMethod,
+    /// Number of epochs (gradient descent) or passes over the weights (local search)
+    pub iterations: usize,
+    pub learning_rate: f64,
+    pub threads: usize,
+    /// Replace positions with the quiet position found by the quiescence search
+    pub quiesce: bool,
+}
+
+impl Default for TuningOptions {
+    fn default() -> TuningOptions {
+        TuningOptions {
+            dataset: String::new(),
+            output: "tuned_parameters.rs".to_string(),
+            method: TuningMethod::GradientDescent,
+            iterations: 1000,
+            learning_rate: 1.0,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            quiesce: true,
+        }
+    }
+}
+
+/// Coefficients of the weights in the evaluation of a dataset position
+#[derive(Clone, Debug)]
+struct TuningEntry {
+    coefficients: Vec<(usize, f64)>,
+    result: f64,
+}
+
+/// Dataset reduced to weight coefficients, ready to be optimized
+#[derive(Clone, Debug)]
+pub struct Tuner {
+    entries: Vec<TuningEntry>,
+    /// Scaling constant of the sigmoid
+    pub k: f64,
+    pub threads: usize,
+}
+
+/****************
+* DATASET PARSING
+*****************/
+
+/// Parse a game result, either as a result token or as a score between 0 and 1. Quotes,
+/// brackets and semicolons around it are ignored.
+fn parse_result(token: &str) -> Option<f64> {
+    match token.trim_matches(['"', '[', ']', ';']) {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        score => score
+            .parse()
+            .ok()
+            .filter(|score| (0.0..=1.0).contains(score)),
+    }
+}
+
+/// Parse a dataset line (FEN followed by the result, EPD record with a c9 operation, or
+/// record of the datagen text format)
+pub fn parse_labeled_position(line: &str) -> Option<LabeledPosition> {
+    if line.contains('|') {
+        let record = DataRecord::from_text(line).ok()?;
+        return Some(LabeledPosition {
+            position: record.position,
+            result: match record.result {
+                GameResult::WhiteWins => 1.0,
+                GameResult::BlackWins => 0.0,
+                _ => 0.5,
+            },
+        });
+    }
+    if line.contains(';') {
+        let record = EpdRecord::from_epd(line).ok()?;
+        let result = parse_result(record.comment(9)?)?;
+        return Some(LabeledPosition {
+            position: record.position,
+            result,
+        });
+    }
+
+    let (fen, result) = line.trim().rsplit_once(char::is_whitespace)?;
+    Some(LabeledPosition {
+        position: Position::from_fen(fen).ok()?,
+        result: parse_result(result)?,
+    })
+}
+
+/// Read all the positions of a dataset. Empty lines are ignored.
+pub fn read_dataset(dataset: &str) -> Result<Vec<LabeledPosition>, TuningError> {
+    dataset
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            parse_labeled_position(line)
+                .ok_or_else(|| TuningError::InvalidRecord(i + 1, line.to_string()))
+        })
+        .collect()
+}
+
+/*******
+* TUNER
+********/
+
+/// Expected score of White given an evaluation (in centipawns, from White's point of view)
+#[inline(always)]
+fn sigmoid(k: f64, evaluation: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * evaluation / 400.0))
+}
+
+/// Run a function on chunks of the entries in parallel, and sum the results
+fn parallel_sum<T, F>(entries: &[TuningEntry], threads: usize, zero: T, f: F) -> T
+where
+    T: Send + std::ops::AddAssign,
+    F: Fn(&[TuningEntry]) -> T + Sync,
+{
+    let chunk_size = entries.len().div_ceil(threads.max(1)).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = entries
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| f(chunk)))
+            .collect();
+
+        let mut sum = zero;
+        for handle in handles {
+            sum += handle.join().expect("Tuning thread panicked");
+        }
+        sum
+    })
+}
+
+impl TuningEntry {
+    fn evaluate(&self, weights: &[f64]) -> f64 {
+        self.coefficients
+            .iter()
+            .map(|(index, coefficient)| coefficient * weights[*index])
+            .sum()
+    }
+}
+
+impl Tuner {
+    /// Reduce the positions to the coefficients of their evaluation. With quiesce, each
+    /// position is first replaced by the quiet position reached by the quiescence search.
+    /// Positions whose evaluation doesn't depend on the weights (known endgames) are
+    /// skipped.
+    pub fn new(positions: &[LabeledPosition], quiesce: bool, threads: usize) -> Tuner {
+        let mut evaluator = Evaluator::new();
+        let entries = positions
+            .iter()
+            .filter_map(|labeled_position| {
+                let quiet_position;
+                let position = if quiesce {
+                    quiet_position = get_quiet_position(&labeled_position.position, &mut evaluator);
+                    &quiet_position
+                } else {
+                    &labeled_position.position
+                };
+
+                Some(TuningEntry {
+                    coefficients: get_parameter_coefficients(position)?,
+                    result: labeled_position.result,
+                })
+            })
+            .collect();
+
+        Tuner {
+            entries,
+            k: 1.0,
+            threads,
+        }
+    }
+
+    /// Number of positions used by the tuner
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Mean squared error between the results and the expected scores
+    pub fn error(&self, weights: &[f64], k: f64) -> f64 {
+        let sum = parallel_sum(&self.entries, self.threads, 0.0, |entries| {
+            entries
+                .iter()
+                .map(|entry| (entry.result - sigmoid(k, entry.evaluate(weights))).powi(2))
+                .sum::<f64>()
+        });
+        sum / self.entries.len() as f64
+    }
+
+    /// Gradient of the error with respect to the weights
+    fn gradient(&self, weights: &[f64]) -> Vec<f64> {
+        let k = self.k;
+        let mut gradient = parallel_sum(
+            &self.entries,
+            self.threads,
+            GradientSum(vec![0.0; weights.len()]),
+            |entries| {
+                let mut gradient = vec![0.0; weights.len()];
+                for entry in entries {
+                    let expected = sigmoid(k, entry.evaluate(weights));
+                    let factor = (expected - entry.result) * expected * (1.0 - expected);
+                    for (index, coefficient) in &entry.coefficients {
+                        gradient[*index] += factor * coefficient;
+                    }
+                }
+                GradientSum(gradient)
+            },
+        )
+        .0;
+
+        let scale = 2.0 * k * 10f64.ln() / 400.0 / self.entries.len() as f64;
+        gradient.iter_mut().for_each(|value| *value *= scale);
+        gradient
+    }
+
+    /// Find the sigmoid scaling constant minimizing the error with the given weights
+    /// (golden-section search), and use it for the tuning
+    pub fn fit_k(&mut self, weights: &[f64]) -> f64 {
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut low, mut high) = (0.0, 10.0);
+
+        while high - low > 1e-4 {
+            let k1 = high - ratio * (high - low);
+            let k2 = low + ratio * (high - low);
+            if self.error(weights, k1) < self.error(weights, k2) {
+                high = k2;
+            } else {
+                low = k1;
+            }
+        }
+
+        self.k = (low + high) / 2.0;
+        self.k
+    }
+
+    /// Optimize the weights with gradient descent (Adam). The callback is called after each
+    /// epoch with the epoch number and the current weights.
+    pub fn tune_gradient_descent<F: FnMut(usize, &[f64])>(
+        &self,
+        weights: &mut [f64],
+        epochs: usize,
+        learning_rate: f64,
+        mut callback: F,
+    ) {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        const EPSILON: f64 = 1e-8;
+
+        let mut momentum = vec![0.0; weights.len()];
+        let mut velocity = vec![0.0; weights.len()];
+
+        for epoch in 1..=epochs {
+            let gradient = self.gradient(weights);
+            for i in 0..weights.len() {
+                momentum[i] = BETA1 * momentum[i] + (1.0 - BETA1) * gradient[i];
+                velocity[i] = BETA2 * velocity[i] + (1.0 - BETA2) * gradient[i].powi(2);
+                let momentum_hat = momentum[i] / (1.0 - BETA1.powi(epoch as i32));
+                let velocity_hat = velocity[i] / (1.0 - BETA2.powi(epoch as i32));
+                weights[i] -= learning_rate * momentum_hat / (velocity_hat.sqrt() + EPSILON);
+            }
+            callback(epoch, weights);
+        }
+    }
+
+    /// Optimize the weights with Texel's local search : each weight is changed by one unit
+    /// in both directions, and the change is kept if it lowers the error. This is repeated
+    /// until no weight changes, or for the given number of passes. The callback is called
+    /// after each pass with the pass number and the current weights.
+    pub fn tune_local_search<F: FnMut(usize, &[f64])>(
+        &self,
+        weights: &mut [f64],
+        passes: usize,
+        mut callback: F,
+    ) {
+        // Entries (and coefficients) affected by each weight, so only their part of the
+        // error has to be recomputed when a weight changes
+        let mut affected_entries: Vec<Vec<(usize, f64)>> = vec![Vec::new(); weights.len()];
+        for (i, entry) in self.entries.iter().enumerate() {
+            for (index, coefficient) in &entry.coefficients {
+                affected_entries[*index].push((i, *coefficient));
+            }
+        }
+        let mut evaluations: Vec<f64> = self
+            .entries
+            .iter()
+            .map(|entry| entry.evaluate(weights))
+            .collect();
+
+        let k = self.k;
+        let squared_error =
+            |i: usize, evaluation: f64| (self.entries[i].result - sigmoid(k, evaluation)).powi(2);
+
+        for pass in 1..=passes {
+            let mut improved = false;
+
+            for (index, affected) in affected_entries.iter().enumerate() {
+                if affected.is_empty() {
+                    continue;
+                }
+                let current_error: f64 = affected
+                    .iter()
+                    .map(|(i, _)| squared_error(*i, evaluations[*i]))
+                    .sum();
+
+                for delta in [1.0, -1.0] {
+                    let new_error: f64 = affected
+                        .iter()
+                        .map(|(i, coefficient)| {
+                            squared_error(*i, evaluations[*i] + delta * coefficient)
+                        })
+                        .sum();
+
+                    if new_error < current_error {
+                        weights[index] += delta;
+                        for (i, coefficient) in affected {
+                            evaluations[*i] += delta * coefficient;
+                        }
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+
+            callback(pass, weights);
+            if !improved {
+                break;
+            }
+        }
+    }
+}
+
+/// Vector wrapper, so gradients of the different threads can be summed
+struct GradientSum(Vec<f64>);
+
+impl std::ops::AddAssign for GradientSum {
+    fn add_assign(&mut self, other: GradientSum) {
+        for (value, other_value) in self.0.iter_mut().zip(other.0) {
+            *value += other_value;
+        }
+    }
+}
+
+/*************
+* TUNE COMMAND
+**************/
+
+impl TuningOptions {
+    /// Parse the arguments of the tune command :
+    /// krabnik tune <dataset> [--output <file>] [--method gradient|local]
+    /// [--iterations <n>] [--learning-rate <x>] [--threads <n>] [--no-quiesce]
+    pub fn from_args(args: &[String]) -> Result<TuningOptions, TuningError> {
+        let mut options = TuningOptions::default();
+        let mut args = args.iter();
+        let invalid = |arg: &str| TuningError::InvalidArgument(arg.to_string());
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| invalid(arg));
+            match arg.as_str() {
+                "--output" => options.output = value()?.clone(),
+                "--method" => {
+                    options.method = match value()?.as_str() {
+                        "gradient" => TuningMethod::GradientDescent,
+                        "local" => TuningMethod::LocalSearch,
+                        method => return Err(invalid(method)),
+                    }
+                }
+                "--iterations" => {
+                    let value = value()?;
+                    options.iterations = value.parse().map_err(|_| invalid(value))?;
+                }
+                "--learning-rate" => {
+                    let value = value()?;
+                    options.learning_rate = value.parse().map_err(|_| invalid(value))?;
+                }
+                "--threads" => {
+                    let value = value()?;
+                    options.threads = value
+                        .parse()
+                        .ok()
+                        .filter(|threads| *threads > 0)
+                        .ok_or_else(|| invalid(value))?;
+                }
+                "--no-quiesce" => options.quiesce = false,
+                _ if arg.starts_with("--") || !options.dataset.is_empty() => {
+                    return Err(invalid(arg))
+                }
+                _ => options.dataset = arg.clone(),
+            }
+        }
+
+        if options.dataset.is_empty() {
+            return Err(TuningError::InvalidArgument(
+                "missing dataset path".to_string(),
+            ));
+        }
+        Ok(options)
+    }
+}
+
+/// Run the tune command : load the dataset, fit K, optimize the weights and write them as
+/// Rust source
+pub fn run_tune_command(args: &[String]) -> Result<(), TuningError> {
+    let options = TuningOptions::from_args(args)?;
+
+    let dataset = fs::read_to_string(&options.dataset)
+        .map_err(|error| TuningError::Io(format!("{}: {}", options.dataset, error)))?;
+    let positions = read_dataset(&dataset)?;
+    let mut tuner = Tuner::new(&positions, options.quiesce, options.threads);
+    if tuner.is_empty() {
+        return Err(TuningError::EmptyDataset);
+    }
+    println!("Loaded {} positions", tuner.len());
+
+    let mut weights: Vec<f64> = DEFAULT_PARAMETERS
+        .to_vector()
+        .iter()
+        .map(|weight| *weight as f64)
+        .collect();
+    let k = tuner.fit_k(&weights);
+    println!("K = {:.4}, error = {:.6}", k, tuner.error(&weights, k));
+
+    let log = |iteration: usize, weights: &[f64]| {
+        if iteration.is_multiple_of(50) {
+            println!(
+                "Iteration {}, error = {:.6}",
+                iteration,
+                tuner.error(weights, k)
+            );
+        }
+    };
+    match options.method {
+        TuningMethod::GradientDescent => tuner.tune_gradient_descent(
+            &mut weights,
+            options.iterations,
+            options.learning_rate,
+            log,
+        ),
+        TuningMethod::LocalSearch => tuner.tune_local_search(&mut weights, options.iterations, log),
+    }
+    println!("Final error = {:.6}", tuner.error(&weights, k));
+
+    let weights: Vec<i32> = weights.iter().map(|weight| weight.round() as i32).collect();
+    let source = EvaluationParameters::from_vector(&weights).to_rust_source();
+    fs::write(&options.output, source)
+        .map_err(|error| TuningError::Io(format!("{}: {}", options.output, error)))?;
+    println!("Tuned weights written to {}", options.output);
+
+    Ok(())
+}
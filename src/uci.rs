@@ -0,0 +1,479 @@
+/*
+ * The uci module implements the Universal Chess Interface, the text protocol used by chess
+ * GUIs (and match runners) to drive the engine through its standard input and output.
+ *
+ * Commands are read line by line. Searches run on their own thread, so that commands such
+ * as "stop" or "isready" are still answered while the engine is thinking.
+ * See : <https://www.shredderchess.com/download/div/uci.zip>
+ */
+
+#![allow(dead_code)]
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::board_representation::*;
+use crate::evaluation::*;
+use crate::move_generation::*;
+use crate::search::*;
+use crate::tablebase::*;
+
+/*********
+* SESSION
+**********/
+
+pub const ENGINE_NAME: &str = "Krabnik";
+pub const ENGINE_AUTHOR: &str = "Aurélien Delval";
+
+/// Name of the UCI option enabling Chess960 castling notation
+pub const CHESS960_OPTION: &str = "UCI_Chess960";
+
+/// Name of the UCI option setting the size of the transposition table, in MiB
+pub const HASH_OPTION: &str = "Hash";
+
+/// Name of the UCI option setting the number of search threads
+pub const THREADS_OPTION: &str = "Threads";
+
+/// Name of the UCI option setting the directories of the Syzygy tables
+pub const SYZYGY_PATH_OPTION: &str = "SyzygyPath";
+
+/// State of the engine between UCI commands. Output lines are written to a writer shared
+/// with the search thread.
+pub struct UciSession<W: Write + Send + 'static> {
+    output: Arc<Mutex<W>>,
+    position: Position,
+    /// Hashes of the positions played before the current one, for repetition detection
+    game_hashes: Vec<u64>,
+    chess960: bool,
+    tt: Arc<TranspositionTable>,
+    threads: usize,
+    tablebase: Option<Arc<SyzygyTablebase>>,
+    /// Moved to the search thread while a search is running
+    evaluator: Option<Evaluator>,
+    search_thread: Option<JoinHandle<Evaluator>>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Write a line to the shared output, and flush it so the GUI receives it immediately
+pub(crate) fn send_line<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap();
+    // A closed output can't be reported anywhere
+    let _ = writeln!(output, "{}", line).and_then(|_| output.flush());
+}
+
+/// Format a search score for the info command : "cp <centipawns>" or "mate <moves>" (negative
+/// if the engine is getting mated)
+pub fn get_uci_score(score: i32) -> String {
+    if is_mate_score(score) {
+        let plies = MATE_SCORE - score.abs();
+        let moves = (plies + 1) / 2;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+/// Write a principal variation in UCI notation, starting from the given position
+pub fn get_pv_uci(position: &Position, pv: &[Move]) -> String {
+    let mut position = position.clone();
+    let mut moves = Vec::new();
+    for mov in pv {
+        moves.push(get_move_uci(&position, *mov));
+        position.make_move(*mov);
+    }
+    moves.join(" ")
+}
+
+/// Parse the arguments of the go command. Returns the search limits, and whether the
+/// search is infinite (in which case the best move must only be sent after "stop").
+pub fn parse_go_arguments(args: &[&str], turn: Player) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::default();
+    let mut infinite = false;
+    let (mut time, mut increment, mut moves_to_go) = (None, Duration::ZERO, None);
+    let (time_option, increment_option) = match turn {
+        Player::White => ("wtime", "winc"),
+        Player::Black => ("btime", "binc"),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().and_then(|value| value.parse::<i64>().ok());
+        let millis = |value: i64| Duration::from_millis(value.max(0) as u64);
+        match *arg {
+            "depth" => limits.depth = value().map(|depth| depth.clamp(1, MAX_DEPTH as i64) as u8),
+            "nodes" => limits.nodes = value().map(|nodes| nodes.max(1) as u64),
+            "movetime" => limits.time = value().map(millis),
+            "movestogo" => moves_to_go = value().map(|moves| moves.max(1) as u32),
+            "infinite" => infinite = true,
+            _ if *arg == time_option => time = value().map(millis),
+            _ if *arg == increment_option => increment = value().map_or(Duration::ZERO, millis),
+            _ => {}
+        }
+    }
+
+    if let (None, Some(time)) = (limits.time, time) {
+        limits.time = Some(get_time_limit(time, increment, moves_to_go));
+    }
+    (limits, infinite)
+}
+
+impl<W: Write + Send + 'static> UciSession<W> {
+    pub fn new(output: W) -> UciSession<W> {
+        UciSession {
+            output: Arc::new(Mutex::new(output)),
+            position: Position::new(),
+            game_hashes: Vec::new(),
+            chess960: false,
+            tt: Arc::new(TranspositionTable::new(DEFAULT_HASH_SIZE)),
+            threads: 1,
+            tablebase: None,
+            evaluator: Some(Evaluator::new()),
+            search_thread: None,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Return the writer the session outputs to
+    pub fn get_output(&self) -> &Arc<Mutex<W>> {
+        &self.output
+    }
+
+    fn send(&self, line: &str) {
+        send_line(&self.output, line);
+    }
+
+    /// Wait for the running search (if any) to finish, and get the evaluator back
+    pub fn wait_for_search(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            self.evaluator = Some(search_thread.join().expect("Search thread panicked"));
+        }
+    }
+
+    /// Stop the running search (if any) and wait for its best move
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+
+    /// Handle a line of input. Returns false once the engine must quit.
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match *command {
+            "uci" => {
+                self.send(&format!(
+                    "id name {} {}",
+                    ENGINE_NAME,
+                    env!("CARGO_PKG_VERSION")
+                ));
+                self.send(&format!("id author {}", ENGINE_AUTHOR));
+                self.send(&format!(
+                    "option name {} type check default false",
+                    CHESS960_OPTION
+                ));
+                self.send(&format!(
+                    "option name {} type spin default {} min 1 max {}",
+                    HASH_OPTION, DEFAULT_HASH_SIZE, MAX_HASH_SIZE
+                ));
+                self.send(&format!(
+                    "option name {} type spin default 1 min 1 max {}",
+                    THREADS_OPTION, MAX_THREADS
+                ));
+                self.send(&format!(
+                    "option name {} type string default <empty>",
+                    EVAL_FILE_OPTION
+                ));
+                self.send(&format!(
+                    "option name {} type check default true",
+                    USE_NNUE_OPTION
+                ));
+                self.send(&format!(
+                    "option name {} type string default <empty>",
+                    SYZYGY_PATH_OPTION
+                ));
+                self.send("uciok");
+            }
+            "isready" => self.send("readyok"),
+            "setoption" => {
+                self.stop_search();
+                self.set_option(args);
+            }
+            "ucinewgame" => {
+                self.stop_search();
+                self.position = Position::new();
+                self.position.chess960 = self.chess960;
+                self.game_hashes.clear();
+                self.tt.clear();
+            }
+            "position" => {
+                self.stop_search();
+                self.set_position(args);
+            }
+            "go" => {
+                self.stop_search();
+                self.go(args);
+            }
+            "stop" => self.stop_search(),
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
+            // "debug", "register", "ponderhit" and unknown commands are ignored
+            _ => {}
+        }
+        true
+    }
+
+    /// setoption name <name> [value <value>]. Names and values may contain spaces.
+    fn set_option(&mut self, args: &[&str]) {
+        let line = args.join(" ");
+        let Some(line) = line.strip_prefix("name ") else {
+            return;
+        };
+        let (name, value) = match line.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (line.trim(), ""),
+        };
+        let value = if value == "<empty>" { "" } else { value };
+
+        if name == CHESS960_OPTION {
+            self.chess960 = value == "true";
+            self.position.chess960 = self.chess960;
+            return;
+        }
+        if name == HASH_OPTION || name == THREADS_OPTION {
+            let Ok(value) = value.parse::<usize>() else {
+                self.send(&format!("info string {}: invalid value {}", name, value));
+                return;
+            };
+            if name == HASH_OPTION {
+                self.tt = Arc::new(TranspositionTable::new(value.clamp(1, MAX_HASH_SIZE)));
+            } else {
+                self.threads = value.clamp(1, MAX_THREADS);
+            }
+            return;
+        }
+        if name == SYZYGY_PATH_OPTION {
+            let tablebase = SyzygyTablebase::new(value);
+            let count = tablebase.wdl_tables.len() + tablebase.dtz_tables.len();
+            self.send(&format!("info string found {} Syzygy tables", count));
+            self.tablebase = (count > 0).then(|| Arc::new(tablebase));
+            return;
+        }
+        let evaluator = self
+            .evaluator
+            .as_mut()
+            .expect("No search should be running");
+        match evaluator.set_option(name, value) {
+            Ok(true) => {}
+            Ok(false) => self.send(&format!("info string unknown option {}", name)),
+            Err(error) => self.send(&format!("info string {}: {}", name, error)),
+        }
+    }
+
+    /// position (startpos | fen <fen>) [moves <move> ...]
+    fn set_position(&mut self, args: &[&str]) {
+        let moves_index = args.iter().position(|arg| *arg == "moves");
+        let (setup, moves) = match moves_index {
+            Some(index) => (&args[..index], &args[index + 1..]),
+            None => (args, &[][..]),
+        };
+
+        let position = match setup.split_first() {
+            Some((&"startpos", _)) => Ok(Position::new()),
+            Some((&"fen", fen)) => Position::from_fen(&fen.join(" ")),
+            _ => return,
+        };
+        let mut position = match position {
+            Ok(position) => position,
+            Err(error) => {
+                self.send(&format!("info string invalid position: {}", error));
+                return;
+            }
+        };
+        position.chess960 |= self.chess960;
+
+        self.game_hashes.clear();
+        for uci in moves {
+            match parse_uci_move(&mut position, uci) {
+                Some(mov) => {
+                    self.game_hashes.push(position.get_zobrist_hash());
+                    position.make_move(mov);
+                }
+                None => {
+                    self.send(&format!("info string illegal move {}", uci));
+                    break;
+                }
+            }
+        }
+        self.position = position;
+    }
+
+    /// Start a search on its own thread. Its progress and best move are sent to the output.
+    fn go(&mut self, args: &[&str]) {
+        let (limits, infinite) = parse_go_arguments(args, self.position.current_turn);
+        let output = self.output.clone();
+        let stop = self.stop.clone();
+        let root = self.position.clone();
+        let game_hashes = self.game_hashes.clone();
+        let tt = self.tt.clone();
+        let threads = self.threads;
+        let tablebase = self.tablebase.clone();
+        let mut evaluator = self.evaluator.take().expect("No search should be running");
+        stop.store(false, Ordering::Relaxed);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let start = Instant::now();
+            let mut position = root.clone();
+            let context = SearchContext {
+                tt: &tt,
+                stop: &stop,
+                threads,
+                game_hashes: &game_hashes,
+                tablebase: tablebase.as_deref(),
+            };
+            let result = search_with_callback(
+                &mut position,
+                &mut evaluator,
+                &limits,
+                &context,
+                |result| {
+                    let elapsed = start.elapsed();
+                    let nps = result.nodes as u128 * 1000 / elapsed.as_millis().max(1);
+                    send_line(
+                        &output,
+                        &format!(
+                            "info depth {} score {} nodes {} nps {} hashfull {} tbhits {} time {} pv {}",
+                            result.depth,
+                            get_uci_score(result.score),
+                            result.nodes,
+                            nps,
+                            tt.get_hashfull(),
+                            result.tb_hits,
+                            elapsed.as_millis(),
+                            get_pv_uci(&root, &result.pv)
+                        ),
+                    );
+                },
+            );
+
+            // The best move of an infinite search is only sent once it is stopped
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            let best_move = result
+                .best_move
+                .map_or("0000".to_string(), |mov| get_move_uci(&root, mov));
+            send_line(&output, &format!("bestmove {}", best_move));
+            evaluator
+        }));
+    }
+}
+
+/// Run the UCI loop on the standard input and output, until "quit" or the end of the input.
+/// The first line may already have been read (for instance to detect the protocol).
+pub fn run_uci(first_line: Option<&str>) {
+    let mut session = UciSession::new(io::stdout());
+    if first_line.is_some_and(|line| !session.handle_command(line)) {
+        return;
+    }
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !session.handle_command(&line) {
+            return;
+        }
+    }
+    session.wait_for_search();
+}
+
+/******
+* TESTS
+*******/
+
+#[test]
+fn test_uci() {
+    let mut session = UciSession::new(Vec::new());
+    let output = session.get_output().clone();
+    let take_output = || String::from_utf8(std::mem::take(&mut *output.lock().unwrap())).unwrap();
+
+    // Handshake
+    assert!(session.handle_command("uci"), "Failed at assert 0");
+    let lines = take_output();
+    assert!(lines.starts_with("id name Krabnik"), "Failed at assert 1");
+    assert!(lines.ends_with("uciok\n"), "Failed at assert 2");
+    session.handle_command("isready");
+    assert!(take_output() == "readyok\n", "Failed at assert 3");
+
+    // Positions
+    session.handle_command("position startpos moves e2e4 e7e5 g1f3");
+    assert!(
+        session.position.to_fen()
+            == "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        "Failed at assert 4"
+    );
+    session.handle_command("position fen 4k3/8/8/8/8/8/8/R3K3 w Q - 0 1 moves e1c1");
+    assert!(
+        session.position.to_fen() == "4k3/8/8/8/8/8/8/2KR4 b - - 1 1",
+        "Failed at assert 5"
+    );
+    session.handle_command("position startpos moves e2e5");
+    assert!(
+        take_output().starts_with("info string illegal move"),
+        "Failed at assert 6"
+    );
+
+    // Searches
+    session.handle_command("position fen 6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1");
+    session.handle_command("go depth 3");
+    session.wait_for_search();
+    let lines = take_output();
+    assert!(lines.contains("score mate 1"), "Failed at assert 7");
+    assert!(lines.ends_with("bestmove a1a8\n"), "Failed at assert 8");
+    session.handle_command("position startpos");
+    session.handle_command("go infinite");
+    session.handle_command("stop");
+    assert!(take_output().contains("bestmove "), "Failed at assert 9");
+
+    // Options
+    session.handle_command("setoption name UCI_Chess960 value true");
+    session.handle_command("position fen 4k3/8/8/8/8/8/8/R3K3 w Q - 0 1 moves e1a1");
+    assert!(
+        session.position.to_fen() == "4k3/8/8/8/8/8/8/2KR4 b - - 1 1",
+        "Failed at assert 10"
+    );
+    session.handle_command("setoption name Use NNUE value maybe");
+    assert!(
+        take_output().starts_with("info string Use NNUE"),
+        "Failed at assert 11"
+    );
+    assert!(!session.handle_command("quit"), "Failed at assert 12");
+
+    // Time management
+    let (limits, infinite) = parse_go_arguments(
+        &["wtime", "60000", "btime", "1000", "winc", "1000"],
+        Player::Black,
+    );
+    assert!(!infinite, "Failed at assert 13");
+    assert!(
+        limits.time
+            == Some(get_time_limit(
+                Duration::from_millis(1000),
+                Duration::ZERO,
+                None
+            )),
+        "Failed at assert 14"
+    );
+    assert!(
+        get_uci_score(-MATE_SCORE + 4) == "mate -2",
+        "Failed at assert 15"
+    );
+}